@@ -22,6 +22,10 @@ final class EditorState: ObservableObject {
     @Published var tabs: [Tab] = []
     @Published var activeTabId: UInt64 = 0
 
+    /// Tab and revision of the last content read. Revisions count per
+    /// document, so a tab switch needs a fresh read too.
+    private var syncedRevision: (tab: UInt64, revision: UInt64)?
+
     init() {
         editor = RMDEEditor()
         syncFromRust()
@@ -188,11 +192,15 @@ final class EditorState: ObservableObject {
 
     /// Sync Swift state from Rust editor
     private func syncFromRust() {
-        content = editor.get_content().toString()
+        activeTabId = editor.get_active_tab_id()
+        let revision = editor.get_content_revision()
+        if syncedRevision?.tab != activeTabId || syncedRevision?.revision != revision {
+            content = readContent()
+            syncedRevision = (activeTabId, revision)
+        }
         cursorPosition = editor.get_cursor_position()
         isDirty = editor.is_dirty()
         title = editor.get_title().toString()
-        activeTabId = editor.get_active_tab_id()
 
         // Initialize tabs if empty (first run)
         if tabs.isEmpty {
@@ -206,6 +214,21 @@ final class EditorState: ObservableObject {
         }
     }
 
+    /// Read the active document in chunks, which end on character boundaries
+    private func readContent() -> String {
+        var text = ""
+        var offset: UInt = 0
+        while true {
+            let chunk = editor.get_content_chunk(offset, 64 * 1024).toString()
+            if chunk.isEmpty {
+                break
+            }
+            offset += UInt(chunk.utf8.count)
+            text += chunk
+        }
+        return text
+    }
+
     /// Update content from external source (e.g., NSTextView)
     func updateContent(_ newContent: String, cursorPos: UInt) {
         // Calculate diff and apply to Rust editor
@@ -0,0 +1,362 @@
+//! Heading anchor completion and heading renames that keep links working
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::edit::TextEdit;
+use crate::links::{self, LinkTarget};
+use crate::outline::{Heading, outline};
+use crate::slug::{heading_slugs, slugify};
+use crate::syntax::MarkdownTree;
+use crate::text::{line_of, line_starts, line_text};
+use crate::wiki::WikiLink;
+use crate::workspace;
+
+/// A heading offered as a link anchor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnchorCompletion {
+    /// Anchor slug to insert after `#`
+    pub slug: String,
+    /// Heading text, for display
+    pub heading: String,
+    pub level: u8,
+}
+
+/// An anchor being typed in a link destination (`](#go` or `](notes.md#go`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct AnchorContext {
+    /// File part of the destination; empty for the same document
+    pub path: String,
+    /// Anchor text typed so far
+    pub prefix: String,
+    /// Byte range of the typed anchor text, replaced by a completion
+    pub range: Range<usize>,
+}
+
+/// Find an anchor being typed in an inline link destination before a cursor
+pub(crate) fn anchor_context(text: &str, pos: usize) -> Option<AnchorContext> {
+    let line_start = text[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let before = &text[line_start..pos];
+    let open = before.rfind("](")? + 2;
+    let destination = &before[open..];
+    let destination = destination.strip_prefix('<').unwrap_or(destination);
+    if destination.contains(|c: char| c.is_whitespace() || matches!(c, ')' | '>')) {
+        return None;
+    }
+    let (path, prefix) = destination.split_once('#')?;
+    if !path.is_empty() && !workspace::is_markdown_file(Path::new(&links::percent_decode(path))) {
+        return None;
+    }
+    Some(AnchorContext {
+        path: path.to_string(),
+        prefix: prefix.to_string(),
+        range: pos - prefix.len()..pos,
+    })
+}
+
+/// Headings of a Markdown text whose anchors start with (or else contain)
+/// a prefix, in document order
+pub fn anchor_completions(text: &str, prefix: &str) -> Vec<AnchorCompletion> {
+    let headings = outline(&MarkdownTree::parse(text), text);
+    let slugs = heading_slugs(&headings);
+    let prefix = prefix.to_lowercase();
+
+    let mut starts = Vec::new();
+    let mut contains = Vec::new();
+    for (heading, slug) in headings.into_iter().zip(slugs) {
+        let completion = AnchorCompletion {
+            slug,
+            heading: heading.text,
+            level: heading.level,
+        };
+        if completion.slug.starts_with(&prefix) {
+            starts.push(completion);
+        } else if completion.slug.contains(&prefix) {
+            contains.push(completion);
+        }
+    }
+    starts.extend(contains);
+    starts
+}
+
+/// A link that has to change when a heading is renamed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkUpdate {
+    /// File containing the link; `None` for an unsaved document
+    pub path: Option<PathBuf>,
+    /// Line of the link (0-indexed)
+    pub line: usize,
+    /// Text of the line containing the link
+    pub context: String,
+    pub edit: TextEdit,
+}
+
+/// The changes needed to rename a heading without breaking links to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadingRename {
+    /// Heading being renamed
+    pub heading: Heading,
+    /// Anchor of the heading before the rename
+    pub slug: String,
+    pub new_text: String,
+    /// Edit replacing the heading text
+    pub edit: TextEdit,
+    /// Old and new slug of every heading whose anchor changes. Renaming
+    /// one of several same-named headings can shift the others' suffixes.
+    pub slug_changes: Vec<(String, String)>,
+    /// Links pointing at a changed anchor
+    pub link_updates: Vec<LinkUpdate>,
+}
+
+/// Plan renaming the heading on a line. Returns `None` if there is no
+/// heading on that line.
+pub(crate) fn plan_rename(text: &str, line: usize, new_text: &str) -> Option<HeadingRename> {
+    let new_text = new_text.trim();
+    let headings = outline(&MarkdownTree::parse(text), text);
+    let index = headings.iter().position(|h| h.line == line)?;
+    let heading = headings[index].clone();
+    let edit = TextEdit::new(heading_text_range(text, &heading), new_text);
+    let slugs = heading_slugs(&headings);
+    let slug = slugs[index].clone();
+
+    let mut renamed = text.to_string();
+    renamed.replace_range(edit.range.clone(), new_text);
+    let new_headings = outline(&MarkdownTree::parse(&renamed), &renamed);
+
+    // Changing only the text keeps the heading count, so slugs pair up
+    let slug_changes = slugs
+        .into_iter()
+        .zip(heading_slugs(&new_headings))
+        .filter(|(old, new)| old != new)
+        .collect();
+
+    Some(HeadingRename {
+        heading,
+        slug,
+        new_text: new_text.to_string(),
+        edit,
+        slug_changes,
+        link_updates: Vec::new(),
+    })
+}
+
+/// Byte range of a heading's text, excluding `#` markers, the closing
+/// sequence and a setext underline
+fn heading_text_range(text: &str, heading: &Heading) -> Range<usize> {
+    let source = &text[heading.range.clone()];
+    let start = heading.range.start;
+    let trimmed = source.trim_start();
+    let offset = source.len() - trimmed.len();
+
+    if trimmed.starts_with('#') {
+        let line = trimmed.lines().next().unwrap_or("");
+        let after_marker = line.trim_start_matches('#');
+        let content_start = offset + (line.len() - after_marker.len());
+        let content = after_marker.trim_end();
+        // Drop a closing `#` sequence preceded by a space
+        let without_closing = content.trim_end_matches('#');
+        let content = if without_closing.is_empty() || without_closing.ends_with([' ', '\t']) {
+            without_closing.trim_end()
+        } else {
+            content
+        };
+        let leading = content.len() - content.trim_start().len();
+        let content = content.trim_start();
+        let begin = start + content_start + leading;
+        return begin..begin + content.len();
+    }
+
+    // Setext: every line before the underline
+    let body = source.trim_end_matches(['\n', '\r']);
+    let underline = body.rfind('\n').unwrap_or(body.len());
+    let content = body[..underline].trim_end();
+    let begin = start + offset;
+    begin..start + content.len()
+}
+
+/// Links in `text` (located at `base`) pointing at an anchor the rename
+/// changes in the document at `target`. Same-document anchors (`#slug`)
+/// count when `base` is `target`. `wiki_targets` tells whether a
+/// wiki-link's note is `target`; wiki-links name the heading text and go to
+/// the first heading with that anchor, so a wiki-link to one of several
+/// same-named headings is left alone unless it is the first.
+pub(crate) fn link_updates(
+    text: &str,
+    base: Option<&Path>,
+    root: Option<&Path>,
+    rename: &HeadingRename,
+    target: Option<&Path>,
+    wiki_targets: &dyn Fn(&WikiLink) -> bool,
+) -> Vec<LinkUpdate> {
+    let new_slug = |anchor: &str| {
+        rename
+            .slug_changes
+            .iter()
+            .find(|(old, _)| old.eq_ignore_ascii_case(anchor))
+            .map(|(_, new)| new.clone())
+    };
+
+    let tree = MarkdownTree::parse(text);
+    let mut edits = Vec::new();
+    for link in links::links(&tree, text) {
+        let anchor = match links::resolve(&link.destination, base, root) {
+            Some(LinkTarget::Anchor(anchor)) if base == target => anchor,
+            Some(LinkTarget::File {
+                path,
+                anchor: Some(anchor),
+            }) if Some(path.as_path()) == target => anchor,
+            _ => continue,
+        };
+        let Some(slug) = new_slug(&anchor) else {
+            continue;
+        };
+        let raw = &text[link.destination_range.clone()];
+        let Some(hash) = raw.find('#') else { continue };
+        let end = link.destination_range.end - usize::from(raw.starts_with('<'));
+        let start = link.destination_range.start + hash + 1;
+        edits.push(TextEdit::new(start..end, slug));
+    }
+
+    for link in crate::wiki::parse_wiki_links(text) {
+        let Some(heading) = &link.heading else {
+            continue;
+        };
+        let same_document = link.target.is_empty() && base == target;
+        if slugify(heading) != rename.slug || !(same_document || wiki_targets(&link)) {
+            continue;
+        }
+        let inner = &text[link.range.clone()];
+        let Some(hash) = inner.find('#') else {
+            continue;
+        };
+        let heading_start = link.range.start + hash + 1;
+        let heading_len = inner[hash + 1..]
+            .find(['|', ']'])
+            .unwrap_or(inner.len() - hash - 1);
+        edits.push(TextEdit::new(
+            heading_start..heading_start + heading_len,
+            rename.new_text.as_str(),
+        ));
+    }
+
+    let starts = line_starts(text);
+    edits.sort_by_key(|e| e.range.start);
+    edits
+        .into_iter()
+        .map(|edit| {
+            let line = line_of(&starts, edit.range.start);
+            LinkUpdate {
+                path: base.map(Path::to_path_buf),
+                line,
+                context: line_text(text, &starts, line).trim().to_string(),
+                edit,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anchor_context() {
+        let text = "See [goals](#go";
+        let ctx = anchor_context(text, text.len()).unwrap();
+        assert_eq!(ctx.path, "");
+        assert_eq!(ctx.prefix, "go");
+        assert_eq!(ctx.range, 13..15);
+
+        let text = "See [spec](../spec.md#";
+        let ctx = anchor_context(text, text.len()).unwrap();
+        assert_eq!(ctx.path, "../spec.md");
+        assert_eq!(ctx.prefix, "");
+
+        assert_eq!(anchor_context("[img](photo.png#", 16), None);
+        assert_eq!(anchor_context("[a](#b) more", 12), None);
+    }
+
+    #[test]
+    fn test_anchor_completions() {
+        let text = "# Intro\n\n## Goals\n\n## Intro\n";
+        let slugs: Vec<_> = anchor_completions(text, "in")
+            .into_iter()
+            .map(|c| c.slug)
+            .collect();
+        assert_eq!(slugs, vec!["intro", "intro-1"]);
+        assert_eq!(anchor_completions(text, "oal")[0].heading, "Goals");
+    }
+
+    #[test]
+    fn test_heading_text_range() {
+        let text = "## Old name ##\n\nSetext title\n---\n";
+        let headings = outline(&MarkdownTree::parse(text), text);
+        assert_eq!(&text[heading_text_range(text, &headings[0])], "Old name");
+        assert_eq!(
+            &text[heading_text_range(text, &headings[1])],
+            "Setext title"
+        );
+    }
+
+    #[test]
+    fn test_plan_rename_shifts_duplicate_suffixes() {
+        let text = "# Notes\n\n# Notes\n";
+        let plan = plan_rename(text, 0, "Summary").unwrap();
+        assert_eq!(plan.edit, TextEdit::new(2..7, "Summary"));
+        assert_eq!(
+            plan.slug_changes,
+            vec![
+                ("notes".to_string(), "summary".to_string()),
+                ("notes-1".to_string(), "notes".to_string()),
+            ]
+        );
+        assert!(plan_rename(text, 1, "x").is_none());
+    }
+
+    #[test]
+    fn test_wiki_link_to_duplicate_heading() {
+        let text = "# Notes
+
+# Notes
+
+[[#Notes]] [a](#notes-1)
+";
+        let wiki = |_: &WikiLink| false;
+
+        // Wiki-links go to the first "Notes", so renaming the second only
+        // changes the anchor link
+        let rename = plan_rename(text, 2, "Later").unwrap();
+        let updates = link_updates(text, None, None, &rename, None, &wiki);
+        let edits: Vec<_> = updates.into_iter().map(|u| u.edit).collect();
+        assert_eq!(edits, vec![TextEdit::new(34..41, "later")]);
+
+        let rename = plan_rename(text, 0, "Earlier").unwrap();
+        let updates = link_updates(text, None, None, &rename, None, &wiki);
+        assert_eq!(updates[0].edit, TextEdit::new(21..26, "Earlier"));
+    }
+
+    #[test]
+    fn test_link_updates() {
+        let text = "[a](#old) [b](<spec.md#old>) [c](other.md#old) [[spec#Old|d]]\n";
+        let spec = Path::new("/ws/spec.md");
+        let rename = plan_rename("# Old\n", 0, "New name").unwrap();
+        let wiki = |link: &WikiLink| link.target == "spec";
+
+        // From another note: only links into spec.md change
+        let base = Path::new("/ws/a.md");
+        let updates = link_updates(text, Some(base), None, &rename, Some(spec), &wiki);
+        let edits: Vec<_> = updates.into_iter().map(|u| u.edit).collect();
+        assert_eq!(
+            edits,
+            vec![
+                TextEdit::new(23..26, "new-name"),
+                TextEdit::new(54..57, "New name"),
+            ]
+        );
+
+        // Within spec.md itself the bare anchor changes too
+        let updates = link_updates(text, Some(spec), None, &rename, Some(spec), &wiki);
+        assert_eq!(updates.len(), 3);
+        assert_eq!(updates[0].edit, TextEdit::new(5..8, "new-name"));
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A calendar date (proleptic Gregorian), as written in `YYYY-MM-DD` form
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    /// Create a date, returning `None` if it doesn't exist
+    pub fn new(year: i32, month: u8, day: u8) -> Option<Self> {
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+        Some(Self { year, month, day })
+    }
+
+    /// Parse a `YYYY-MM-DD` date
+    pub fn parse(s: &str) -> Option<Self> {
+        let bytes = s.as_bytes();
+        if bytes.len() != 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+            return None;
+        }
+        let year = s[0..4].parse().ok()?;
+        let month = s[5..7].parse().ok()?;
+        let day = s[8..10].parse().ok()?;
+        Self::new(year, month, day)
+    }
+
+    /// Today's date in the local time zone
+    pub fn today() -> Self {
+        Self::local(SystemTime::now())
+    }
+
+    /// The UTC date of a point in time
+    pub fn from_system_time(time: SystemTime) -> Self {
+        Self::from_days_since_epoch(unix_seconds(time).div_euclid(86_400))
+    }
+
+    /// The date of a point in time in the local time zone
+    pub fn local(time: SystemTime) -> Self {
+        let seconds = unix_seconds(time) + utc_offset(time);
+        Self::from_days_since_epoch(seconds.div_euclid(86_400))
+    }
+
+    /// Date a number of days after 1970-01-01
+    fn from_days_since_epoch(days: i64) -> Self {
+        // Civil-from-days over 400-year eras starting on March 1st
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let day_of_era = z.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u8;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        Self {
+            year: year as i32,
+            month,
+            day,
+        }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(before) => -(before.duration().as_secs() as i64) - 1,
+    }
+}
+
+/// Seconds the local time zone is ahead of UTC at a point in time
+#[cfg(unix)]
+fn utc_offset(time: SystemTime) -> i64 {
+    let seconds = unix_seconds(time) as libc::time_t;
+    // SAFETY: localtime_r only reads `seconds` and writes to `tm`, and an
+    // all-zero tm is a valid value
+    unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&seconds, &mut tm).is_null() {
+            return 0;
+        }
+        tm.tm_gmtoff as i64
+    }
+}
+
+#[cfg(not(unix))]
+fn utc_offset(_time: SystemTime) -> i64 {
+    0
+}
+
+fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        let date = Date::parse("2024-02-29").unwrap();
+        assert_eq!(date, Date::new(2024, 2, 29).unwrap());
+        assert_eq!(date.to_string(), "2024-02-29");
+        assert_eq!(Date::parse("2023-02-29"), None);
+        assert_eq!(Date::parse("2024-2-1"), None);
+    }
+
+    #[test]
+    fn test_from_system_time() {
+        use std::time::Duration;
+
+        let at = |secs: u64| Date::from_system_time(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(at(0).to_string(), "1970-01-01");
+        assert_eq!(at(951_782_400).to_string(), "2000-02-29");
+        assert_eq!(at(1_735_689_599).to_string(), "2024-12-31");
+    }
+
+    #[test]
+    fn test_local() {
+        use std::time::Duration;
+
+        // Time zones are at most 14 hours from UTC
+        let noon = UNIX_EPOCH + Duration::from_secs(1_709_294_400);
+        let hours = Duration::from_secs(14 * 3600);
+        let earliest = Date::from_system_time(noon - hours);
+        let latest = Date::from_system_time(noon + hours);
+        assert!((earliest..=latest).contains(&Date::local(noon)));
+    }
+
+    #[test]
+    fn test_ordering() {
+        assert!(Date::parse("2024-01-31").unwrap() < Date::parse("2024-02-01").unwrap());
+    }
+}
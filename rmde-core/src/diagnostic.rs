@@ -0,0 +1,39 @@
+use std::ops::Range;
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum Severity {
+    Error = 1,
+    Warning = 2,
+    Info = 3,
+}
+
+/// A problem found in a document, anchored to a byte range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Byte range the diagnostic applies to
+    pub range: Range<usize>,
+    pub severity: Severity,
+    /// Stable identifier of the check that produced it (e.g. `broken-link`)
+    pub code: &'static str,
+    /// Human-readable description
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Create a diagnostic
+    pub fn new(
+        range: Range<usize>,
+        severity: Severity,
+        code: &'static str,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            range,
+            severity,
+            code,
+            message: message.into(),
+        }
+    }
+}
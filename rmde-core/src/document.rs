@@ -1,9 +1,38 @@
-use std::path::PathBuf;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 use ropey::Rope;
 
+use crate::diagnostic::Diagnostic;
+use crate::docx;
+use crate::edit::{self, TextEdit};
+use crate::encoding::{self, Encoding};
+use crate::epub::{self, EpubOptions, EpubSource};
 use crate::error::{Error, Result};
+use crate::footnotes;
+use crate::front_matter::{self, FrontMatter, FrontMatterValue};
+use crate::history::{History, Snapshot};
+use crate::html::{self, HtmlOptions};
+use crate::indent::{self, IndentSettings};
+use crate::line_ending::{self, LineEnding, LineEndingCounts};
+use crate::links::{self, Link, LinkTarget};
+use crate::outline::{self, Heading};
+use crate::prose::{self, ProseRules};
+use crate::references;
+use crate::reflow::{self, WrapSettings};
+use crate::save::write_atomic;
 use crate::selection::Selection;
+use crate::slides::{self, SlideOptions};
+use crate::spell::{self, Misspelling, SpellCache, SpellChecker};
+use crate::stats::{DocumentStats, StatsCache, StatsOptions, TextStats};
+use crate::syntax::MarkdownTree;
+use crate::tasks::{self, Task};
+use crate::toc::{self, TocEntry};
+use crate::unicode::{self, InvisibleChar, NormalizationForm};
+use crate::writing::{self, GoalProgress, GoalStorage, WordActivity};
 use crate::DocumentId;
 
 /// A single document with its content and metadata
@@ -18,6 +47,26 @@ pub struct Document {
     path: Option<PathBuf>,
     /// Whether the document has unsaved changes
     dirty: bool,
+    /// Encoding the file is read and written in
+    encoding: Encoding,
+    /// Line break used for typed newlines and written on save
+    line_ending: LineEnding,
+    /// Indentation used by indent/outdent and the Tab key
+    indent: IndentSettings,
+    /// Column width for reflow and wrap as you type
+    wrap: WrapSettings,
+    /// Undo/redo stacks
+    history: History,
+    /// Incremented on every content change
+    revision: u64,
+    /// Word counts of the last revision they were requested for
+    stats: StatsCache,
+    /// Words added and removed by changes not yet collected by the editor
+    activity: Vec<WordActivity>,
+    /// Time source for the activity log
+    clock: fn() -> SystemTime,
+    /// Misspellings of each paragraph last checked
+    spell: SpellCache,
 }
 
 impl Document {
@@ -29,45 +78,169 @@ impl Document {
             selections: vec![Selection::default()],
             path: None,
             dirty: false,
+            encoding: Encoding::default(),
+            line_ending: LineEnding::default(),
+            indent: IndentSettings::default(),
+            wrap: WrapSettings::default(),
+            history: History::default(),
+            revision: 0,
+            stats: StatsCache::default(),
+            activity: Vec::new(),
+            clock: SystemTime::now,
+            spell: SpellCache::default(),
         }
+        .saved()
     }
 
-    /// Open a document from a file path
+    /// Open a document from a file path, detecting its encoding
     pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
         let path = path.into();
-        let content = std::fs::read_to_string(&path)?;
-        let rope = Rope::from_str(&content);
+        let (rope, encoding) = encoding::read_file(&path)?;
+        let mut doc = Self::from_rope(rope);
+        doc.path = Some(path);
+        doc.encoding = encoding;
+        Ok(doc.saved())
+    }
+
+    /// Open a document from a file path in a given encoding
+    pub fn open_with_encoding(path: impl Into<PathBuf>, encoding: Encoding) -> Result<Self> {
+        let path = path.into();
+        let mut doc = Self::from_rope(encoding::read_file_as(&path, encoding)?);
+        doc.path = Some(path);
+        doc.encoding = encoding;
+        Ok(doc.saved())
+    }
+
+    /// Read the file again in another encoding, discarding unsaved changes
+    /// and undo history
+    pub fn reopen_with_encoding(&mut self, encoding: Encoding) -> Result<()> {
+        let path = self.path.clone().ok_or(Error::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "No file path set",
+        )))?;
+        let mut doc = Self::open_with_encoding(path, encoding)?;
+        doc.id = self.id;
+        doc.wrap = self.wrap;
+        *self = doc;
+        Ok(())
+    }
+
+    /// Create an untitled document holding `text`, such as imported
+    /// content; it is dirty until saved
+    pub fn from_text(text: &str) -> Self {
+        Self::from_rope(Rope::from_str(text))
+    }
 
-        Ok(Self {
+    fn from_rope(rope: Rope) -> Self {
+        let indent = IndentSettings::detect_lines(rope.lines().map(Cow::from)).unwrap_or_default();
+        let dirty = rope.len_bytes() > 0;
+        let line_ending = LineEndingCounts::count(rope.chunks())
+            .dominant()
+            .unwrap_or_default();
+
+        let doc = Self {
             id: DocumentId::new(),
             content: rope,
             selections: vec![Selection::default()],
-            path: Some(path),
-            dirty: false,
-        })
+            path: None,
+            dirty,
+            encoding: Encoding::default(),
+            line_ending,
+            indent,
+            wrap: WrapSettings::default(),
+            history: History::default(),
+            revision: 0,
+            stats: StatsCache::default(),
+            activity: Vec::new(),
+            clock: SystemTime::now,
+            spell: SpellCache::default(),
+        };
+        if dirty { doc } else { doc.saved() }
     }
 
-    /// Save the document to its file path
+    /// The document marked as saved in its current state
+    fn saved(mut self) -> Self {
+        self.mark_saved();
+        self
+    }
+
+    /// Save the document to its file path, regenerating tables of contents
     pub fn save(&mut self) -> Result<()> {
-        let path = self.path.as_ref().ok_or(Error::Io(std::io::Error::new(
+        let path = self.path.clone().ok_or(Error::Io(std::io::Error::new(
             std::io::ErrorKind::NotFound,
             "No file path set",
         )))?;
 
-        std::fs::write(path, self.content.to_string())?;
-        self.dirty = false;
+        self.update_toc();
+        self.write(&path)?;
+        self.mark_saved();
         Ok(())
     }
 
+    /// Save the document to its file path in another encoding, which is
+    /// kept for later saves
+    pub fn save_with_encoding(&mut self, encoding: Encoding) -> Result<()> {
+        let previous = std::mem::replace(&mut self.encoding, encoding);
+        let result = self.save();
+        if result.is_err() {
+            self.encoding = previous;
+        }
+        result
+    }
+
     /// Save the document to a new path
     pub fn save_as(&mut self, path: impl Into<PathBuf>) -> Result<()> {
         let path = path.into();
-        std::fs::write(&path, self.content.to_string())?;
+        self.update_toc();
+        self.write(&path)?;
         self.path = Some(path);
-        self.dirty = false;
+        self.mark_saved();
         Ok(())
     }
 
+    /// Write the content in the document's encoding, refusing characters
+    /// it can't represent
+    fn write(&self, path: &Path) -> Result<()> {
+        let encoding = self.encoding;
+        if let Some(character) = encoding.unencodable(self.content.chunks()) {
+            return Err(Error::Unencodable {
+                encoding,
+                character,
+            });
+        }
+        write_atomic(path, |out| encoding.write(self.content.chunks(), out))
+    }
+
+    /// Encoding the file is read and written in
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Line ending used for new lines and on save; the most common one
+    /// when the file was opened
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    /// How often each line ending occurs, to spot mixed line endings
+    pub fn line_ending_counts(&self) -> LineEndingCounts {
+        LineEndingCounts::count(self.content.chunks())
+    }
+
+    /// Convert every line break to one style, as one undo step, and use it
+    /// from now on
+    pub fn convert_line_endings(&mut self, ending: LineEnding) {
+        let edits = line_ending::convert_edits(&self.content(), ending);
+        if edits.is_empty() {
+            self.line_ending = ending;
+            return;
+        }
+        // The undo step keeps the old line ending
+        self.checkpoint();
+        self.line_ending = ending;
+        self.replace(&edits);
+    }
+
     /// Get the document's ID
     pub fn id(&self) -> DocumentId {
         self.id
@@ -83,6 +256,11 @@ impl Document {
         self.dirty
     }
 
+    /// Get a counter that changes whenever the content changes
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
     /// Get the document title (filename or "Untitled")
     pub fn title(&self) -> String {
         self.path
@@ -103,6 +281,44 @@ impl Document {
         self.content.len_bytes()
     }
 
+    /// Text from a byte offset, at most `max_bytes` long and cut at
+    /// character boundaries, for reading a large document piece by piece.
+    /// An offset inside a character starts at that character, and at least
+    /// one character is returned before the end.
+    pub fn content_chunk(&self, start: usize, max_bytes: usize) -> String {
+        let start_char = self.content.byte_to_char(start.min(self.len()));
+        let start = self.content.char_to_byte(start_char);
+        let end = start.saturating_add(max_bytes).min(self.len());
+        let mut end_char = self.content.byte_to_char(end);
+        if end_char == start_char && start_char < self.content.len_chars() {
+            end_char += 1;
+        }
+        self.content.slice(start_char..end_char).to_string()
+    }
+
+    /// Whether the content contains `needle`, searched chunk by chunk
+    fn contains(&self, needle: &str) -> bool {
+        let mut window = String::new();
+        for chunk in self.content.chunks() {
+            window.push_str(chunk);
+            if window.contains(needle) {
+                return true;
+            }
+            // Keep enough of the tail to find a match across chunks
+            let mut keep = window.len().saturating_sub(needle.len());
+            while !window.is_char_boundary(keep) {
+                keep += 1;
+            }
+            window.drain(..keep);
+        }
+        false
+    }
+
+    /// Content as the rope's internal chunks, in order, without copying
+    pub fn chunks(&self) -> impl Iterator<Item = &str> {
+        self.content.chunks()
+    }
+
     /// Check if document is empty
     pub fn is_empty(&self) -> bool {
         self.content.len_bytes() == 0
@@ -139,29 +355,44 @@ impl Document {
 
     /// Set a single cursor position
     pub fn set_cursor(&mut self, pos: usize) {
-        let pos = pos.min(self.len());
+        let pos = self.snap_to_char(pos);
         self.selections = vec![Selection::cursor(pos)];
     }
 
     /// Add a new cursor at position
     pub fn add_cursor(&mut self, pos: usize) {
-        let pos = pos.min(self.len());
+        let pos = self.snap_to_char(pos);
         // Don't add duplicate cursors
         if !self.selections.iter().any(|s| s.head == pos && s.is_cursor()) {
             self.selections.push(Selection::cursor(pos));
         }
     }
 
-    /// Insert text at all cursor positions
+    /// Insert text at all cursor positions. Line breaks in the text are
+    /// converted to the document's line ending.
     pub fn insert(&mut self, text: &str) {
         if text.is_empty() {
             return;
         }
+        let text = line_ending::normalize(text, self.line_ending);
+        let text = text.as_ref();
+        // Typing a word is one undo step
+        if !self.history.continues_typing(&self.selections, text) {
+            self.checkpoint();
+        }
 
         let text_len = text.len();
+        let before = self.content.clone();
+        let mut edits: Vec<_> = self
+            .selections
+            .iter()
+            .map(|sel| TextEdit::new(sel.start()..sel.end(), text))
+            .collect();
+        edits.sort_by_key(|e| e.range.start);
 
         // Sort selections by position (descending) to avoid offset issues
-        self.selections.sort_by(|a, b| b.start().cmp(&a.start()));
+        self.selections
+            .sort_by_key(|s| std::cmp::Reverse(s.start()));
 
         for sel in &mut self.selections {
             // Delete any selected text first
@@ -186,80 +417,644 @@ impl Document {
 
         // Re-sort selections ascending and adjust for insertions
         self.normalize_selections();
-        self.dirty = true;
+        self.mark_changed();
+        self.log_words(&before, &edits);
+
+        // Wrapping is part of the same undo step as the typing
+        if self.wrap.wrap_as_you_type {
+            let edits = reflow::wrap_edits(
+                &self.content(),
+                &self.selections,
+                self.wrap.width,
+                self.indent.tab_width(),
+            );
+            let edits = self.prepare_edits(edits);
+            if !edits.is_empty() {
+                self.replace(&edits);
+            }
+        }
+        self.history.typed(&self.selections, text);
     }
 
     /// Delete character before cursor (backspace)
     pub fn delete_backward(&mut self) {
-        self.selections.sort_by(|a, b| b.start().cmp(&a.start()));
+        let ranges = self
+            .selections
+            .iter()
+            .map(|sel| {
+                if sel.is_cursor() {
+                    self.prev_char_start(sel.head)..sel.head
+                } else {
+                    sel.start()..sel.end()
+                }
+            })
+            .collect();
+        self.delete_ranges(ranges);
+    }
 
-        for sel in &mut self.selections {
-            if sel.is_cursor() {
-                if sel.head > 0 {
-                    let char_idx = self.content.byte_to_char(sel.head);
-                    if char_idx > 0 {
-                        self.content.remove((char_idx - 1)..char_idx);
-                        sel.head = self.content.char_to_byte(char_idx - 1);
-                        sel.anchor = sel.head;
-                    }
+    /// Delete character after cursor (delete key)
+    pub fn delete_forward(&mut self) {
+        let ranges = self
+            .selections
+            .iter()
+            .map(|sel| {
+                if sel.is_cursor() {
+                    sel.head..self.next_char_end(sel.head)
+                } else {
+                    sel.start()..sel.end()
                 }
-            } else {
-                // Delete selection
-                let start = sel.start();
-                let end = sel.end();
-                let start_char = self.content.byte_to_char(start);
-                let end_char = self.content.byte_to_char(end);
-                self.content.remove(start_char..end_char);
-                sel.head = start;
-                sel.anchor = start;
+            })
+            .collect();
+        self.delete_ranges(ranges);
+    }
+
+    /// Delete byte ranges as one change, merging any that overlap
+    fn delete_ranges(&mut self, mut ranges: Vec<Range<usize>>) {
+        ranges.sort_by_key(|r| r.start);
+        let mut edits: Vec<TextEdit> = Vec::new();
+        for range in ranges.into_iter().filter(|r| !r.is_empty()) {
+            match edits.last_mut() {
+                Some(last) if range.start <= last.range.end => {
+                    last.range.end = last.range.end.max(range.end);
+                }
+                _ => edits.push(TextEdit::delete(range)),
             }
         }
+        if edits.is_empty() {
+            return;
+        }
+        self.checkpoint();
+        self.replace(&edits);
+    }
+
+    /// Start of the character before a position; a CRLF pair counts as one
+    fn prev_char_start(&self, pos: usize) -> usize {
+        let char_idx = self.content.byte_to_char(pos);
+        let crlf = char_idx > 1
+            && self.content.char(char_idx - 1) == '\n'
+            && self.content.char(char_idx - 2) == '\r';
+        let start = char_idx.saturating_sub(if crlf { 2 } else { 1 });
+        self.content.char_to_byte(start)
+    }
+
+    /// End of the character after a position; a CRLF pair counts as one
+    fn next_char_end(&self, pos: usize) -> usize {
+        let char_idx = self.content.byte_to_char(pos);
+        let len = self.content.len_chars();
+        let crlf = char_idx + 1 < len
+            && self.content.char(char_idx) == '\r'
+            && self.content.char(char_idx + 1) == '\n';
+        let end = (char_idx + if crlf { 2 } else { 1 }).min(len);
+        self.content.char_to_byte(end)
+    }
+
+    /// Round a position down to a character boundary and move it off the
+    /// middle of a CRLF pair, to the line's end. Positions from the app may
+    /// be UTF-16 offsets that land inside a character.
+    fn snap_to_char(&self, pos: usize) -> usize {
+        let pos = pos.min(self.len());
+        let pos = self.content.char_to_byte(self.content.byte_to_char(pos));
+        if pos > 0
+            && pos < self.len()
+            && self.content.byte(pos - 1) == b'\r'
+            && self.content.byte(pos) == b'\n'
+        {
+            pos - 1
+        } else {
+            pos
+        }
+    }
+
+    /// Apply a batch of non-overlapping edits as one change, mapping all
+    /// selections through it
+    pub fn apply_edits(&mut self, edits: Vec<TextEdit>) {
+        let edits = self.prepare_edits(edits);
+        if edits.is_empty() {
+            return;
+        }
+        self.checkpoint();
+        self.replace(&edits);
+    }
+
+    /// Snap edit ranges to characters, sort edits, drop overlapping ones
+    /// and convert the line breaks they insert to the document's line
+    /// ending
+    fn prepare_edits(&self, mut edits: Vec<TextEdit>) -> Vec<TextEdit> {
+        for edit in &mut edits {
+            let start = self.snap_to_char(edit.range.start);
+            edit.range = start..self.snap_to_char(edit.range.end).max(start);
+        }
+        let mut edits = edit::normalize_edits(edits);
+        for edit in &mut edits {
+            if let Cow::Owned(text) = line_ending::normalize(&edit.text, self.line_ending) {
+                edit.text = text;
+            }
+        }
+        edits
+    }
+
+    /// Apply sorted, non-overlapping edits and map selections through them,
+    /// without recording an undo step
+    fn replace(&mut self, edits: &[TextEdit]) {
+        let before = self.content.clone();
+        for e in edits.iter().rev() {
+            let start_char = self.content.byte_to_char(e.range.start);
+            let end_char = self.content.byte_to_char(e.range.end);
+            self.content.remove(start_char..end_char);
+            self.content.insert(start_char, &e.text);
+        }
+
+        for sel in &mut self.selections {
+            sel.anchor = edit::map_position(sel.anchor, edits);
+            sel.head = edit::map_position(sel.head, edits);
+        }
 
         self.normalize_selections();
+        self.mark_changed();
+        self.log_words(&before, edits);
+    }
+
+    /// Mark the content as the saved state
+    fn mark_saved(&mut self) {
+        self.dirty = false;
+        self.history.mark_saved();
+    }
+
+    /// Mark the content as changed since the last save
+    fn mark_changed(&mut self) {
         self.dirty = true;
+        self.revision += 1;
     }
 
-    /// Delete character after cursor (delete key)
-    pub fn delete_forward(&mut self) {
-        self.selections.sort_by(|a, b| b.start().cmp(&a.start()));
+    /// Record the words an edit added and removed. Undo and redo don't
+    /// call this, so they don't count as writing.
+    fn log_words(&mut self, before: &Rope, edits: &[TextEdit]) {
+        let changes = writing::word_changes(before, &self.content, edits);
+        writing::log_activity(&mut self.activity, (self.clock)(), changes);
+    }
 
-        for sel in &mut self.selections {
-            if sel.is_cursor() {
-                let char_idx = self.content.byte_to_char(sel.head);
-                if char_idx < self.content.len_chars() {
-                    self.content.remove(char_idx..(char_idx + 1));
+    /// Record the current state as an undo step before an edit
+    fn checkpoint(&mut self) {
+        self.history.record(self.snapshot());
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            content: self.content.clone(),
+            selections: self.selections.clone(),
+            line_ending: self.line_ending,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.content = snapshot.content;
+        self.selections = snapshot.selections;
+        self.line_ending = snapshot.line_ending;
+        self.mark_changed();
+        self.dirty = !self.history.is_saved();
+    }
+
+    /// Undo the last edit. Returns false if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.history.undo(self.snapshot()) {
+            Some(previous) => {
+                self.restore(previous);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Redo the last undone edit. Returns false if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        match self.history.redo(self.snapshot()) {
+            Some(next) => {
+                self.restore(next);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Check if there is an edit to undo
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    /// Check if there is an undone edit to redo
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    /// Get the indentation settings
+    pub fn indent_settings(&self) -> IndentSettings {
+        self.indent
+    }
+
+    /// Set the indentation settings
+    pub fn set_indent_settings(&mut self, settings: IndentSettings) {
+        self.indent = settings;
+    }
+
+    /// Get the hard wrap settings
+    pub fn wrap_settings(&self) -> WrapSettings {
+        self.wrap
+    }
+
+    /// Set the hard wrap settings
+    pub fn set_wrap_settings(&mut self, settings: WrapSettings) {
+        self.wrap = settings;
+    }
+
+    /// Rewrap the paragraphs touched by selections to the wrap width
+    pub fn reflow(&mut self) {
+        let edits = reflow::reflow_edits(
+            &self.content(),
+            &self.selections,
+            self.wrap.width,
+            self.indent.tab_width(),
+        );
+        self.apply_edits(edits);
+    }
+
+    /// Indent every line touched by a selection
+    pub fn indent(&mut self) {
+        let edits = indent::indent_edits(&self.content(), &self.selections, self.indent);
+        self.apply_edits(edits);
+    }
+
+    /// Outdent every line touched by a selection
+    pub fn outdent(&mut self) {
+        let edits = indent::outdent_edits(&self.content(), &self.selections, self.indent);
+        self.apply_edits(edits);
+    }
+
+    /// Handle the Tab key: indent lines for multi-line selections and list
+    /// items, otherwise insert indentation at each cursor
+    pub fn insert_tab(&mut self) {
+        let edits = indent::tab_edits(&self.content(), &self.selections, self.indent);
+        self.apply_edits(edits);
+    }
+
+    /// Get the headings of the document in order
+    pub fn outline(&self) -> Vec<Heading> {
+        let text = self.content();
+        outline::outline(&MarkdownTree::parse(&text), &text)
+    }
+
+    /// Headings up to a level as table of contents entries
+    pub fn toc(&self, max_level: u8) -> Vec<TocEntry> {
+        toc::toc_entries(&self.content(), max_level)
+    }
+
+    /// Insert a table of contents listing headings up to a level at the
+    /// primary cursor
+    pub fn insert_toc(&mut self, max_level: u8) {
+        let pos = self.primary_selection().head;
+        let edit = toc::insert_edit(&self.content(), pos, max_level);
+        self.apply_edits(vec![edit]);
+    }
+
+    /// Regenerate every table of contents between its markers. Returns
+    /// true if any changed.
+    pub fn update_toc(&mut self) -> bool {
+        // Saves call this, so skip copying the text when there is no TOC
+        if !self.contains("<!-- tocstop -->") {
+            return false;
+        }
+        let edits = toc::update_edits(&self.content());
+        let changed = !edits.is_empty();
+        self.apply_edits(edits);
+        changed
+    }
+
+    /// Get all task list items in the document
+    pub fn tasks(&self) -> Vec<Task> {
+        tasks::parse_tasks(&self.content())
+    }
+
+    /// Toggle `[ ]` / `[x]` on every task item touched by a selection
+    pub fn toggle_tasks(&mut self) {
+        let edits = tasks::toggle_edits(&self.content(), &self.selections);
+        self.apply_edits(edits);
+    }
+
+    /// Get all links, images and reference definitions with destinations
+    pub fn links(&self) -> Vec<Link> {
+        let text = self.content();
+        links::links(&MarkdownTree::parse(&text), &text)
+    }
+
+    /// Resolve the link at a byte offset relative to this document's path
+    pub fn link_target_at(&self, pos: usize, root: Option<&Path>) -> Option<LinkTarget> {
+        let links = self.links();
+        let link = links::link_at(&links, pos)?;
+        links::resolve(&link.destination, self.path.as_deref(), root)
+    }
+
+    /// Move the cursor to the heading with the given anchor slug.
+    /// Returns false if no heading matches.
+    pub fn jump_to_anchor(&mut self, anchor: &str) -> bool {
+        match links::anchor_position(&self.content(), anchor) {
+            Some(pos) => {
+                self.set_cursor(pos);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Report broken links and missing anchors, reading targets from disk
+    pub fn link_diagnostics(&self, root: Option<&Path>) -> Vec<Diagnostic> {
+        let read = |path: &Path| {
+            encoding::read_file(path)
+                .ok()
+                .map(|(text, _)| text.to_string())
+        };
+        links::validate(&self.content(), self.path.as_deref(), root, &read)
+    }
+
+    /// Convert inline links and images touched by selections to reference
+    /// style, appending their definitions at the end of the document
+    pub fn links_to_reference(&mut self) {
+        let edits = references::to_reference_edits(&self.content(), &self.selections);
+        self.apply_edits(edits);
+    }
+
+    /// Convert reference links and images touched by selections to inline
+    pub fn links_to_inline(&mut self) {
+        let edits = references::to_inline_edits(&self.content(), &self.selections);
+        self.apply_edits(edits);
+    }
+
+    /// Move all link reference definitions to the end of the document
+    pub fn collect_link_definitions(&mut self) {
+        let edits = references::collect_definitions_edits(&self.content());
+        self.apply_edits(edits);
+    }
+
+    /// Remove link reference definitions that nothing refers to
+    pub fn remove_unused_link_definitions(&mut self) {
+        let edits = references::remove_unused_edits(&self.content());
+        self.apply_edits(edits);
+    }
+
+    /// Report duplicate definitions and undefined reference labels
+    pub fn reference_diagnostics(&self) -> Vec<Diagnostic> {
+        references::diagnostics(&self.content())
+    }
+
+    /// Insert a new numbered footnote reference at each cursor, appending
+    /// empty definitions after the existing footnotes
+    pub fn insert_footnote(&mut self) {
+        let (edits, cursors) = footnotes::insert_edits(&self.content(), &self.selections);
+        self.apply_edits(edits);
+        self.selections = cursors.into_iter().map(Selection::cursor).collect();
+    }
+
+    /// Renumber numeric footnotes in order of first reference
+    pub fn renumber_footnotes(&mut self) {
+        let edits = footnotes::renumber_edits(&self.content());
+        self.apply_edits(edits);
+    }
+
+    /// Move the cursor from a footnote reference to its definition, or from
+    /// a definition to its first reference. Returns false if there is no
+    /// footnote at the cursor or its counterpart is missing.
+    pub fn jump_to_footnote(&mut self) -> bool {
+        match footnotes::jump_target(&self.content(), self.primary_selection().head) {
+            Some(pos) => {
+                self.set_cursor(pos);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove footnote definitions that nothing refers to
+    pub fn remove_unused_footnotes(&mut self) {
+        let edits = footnotes::remove_unused_edits(&self.content());
+        self.apply_edits(edits);
+    }
+
+    /// Report undefined, unreferenced and duplicate footnotes
+    pub fn footnote_diagnostics(&self) -> Vec<Diagnostic> {
+        footnotes::diagnostics(&self.content())
+    }
+
+    /// Zero-width spaces, bidi controls and other invisible characters
+    pub fn invisible_chars(&self) -> Vec<InvisibleChar> {
+        unicode::invisible_chars(&self.content())
+    }
+
+    /// Report invisible characters and text not in NFC form
+    pub fn unicode_diagnostics(&self) -> Vec<Diagnostic> {
+        unicode::diagnostics(&self.content())
+    }
+
+    /// Normalize the selected text, or the whole document if nothing is
+    /// selected, to a Unicode normalization form
+    pub fn normalize_unicode(&mut self, form: NormalizationForm) {
+        let text = self.content();
+        let ranges = unicode::selected_ranges(&text, &self.selections);
+        self.apply_edits(unicode::normalize_edits(&text, &ranges, form));
+    }
+
+    /// Remove invisible characters from the selected text, or the whole
+    /// document if nothing is selected. No-break and other space-like
+    /// characters become ordinary spaces, so words stay apart.
+    pub fn strip_invisible_chars(&mut self) {
+        let text = self.content();
+        let ranges = unicode::selected_ranges(&text, &self.selections);
+        self.apply_edits(unicode::invisible_edits(&text, &ranges));
+    }
+
+    /// Word, character, sentence and paragraph counts for the document and
+    /// each outline section. Edits only recount the blocks they changed.
+    pub fn stats(&mut self, options: StatsOptions) -> &DocumentStats {
+        let text = self.content();
+        self.stats.get(&text, self.revision, options)
+    }
+
+    /// Counts for the text under all selections
+    pub fn selection_stats(&mut self, options: StatsOptions) -> TextStats {
+        let text = self.content();
+        self.stats.get(&text, self.revision, options);
+        self.stats.selection_stats(&text, &self.selections)
+    }
+
+    /// Words the spell checker doesn't know. Words listed under
+    /// `spell_ignore` in the front matter are left out, and
+    /// only paragraphs changed since the last check are checked again.
+    pub fn misspellings(&mut self, checker: &SpellChecker) -> Vec<Misspelling> {
+        let text = self.content();
+        let ignore: HashSet<String> = spell::front_matter_ignores(&text).into_iter().collect();
+        spell::check(&text, checker, &ignore, &mut self.spell)
+    }
+
+    /// Accept a word in this document without adding it to the dictionary,
+    /// by listing it under `spell_ignore` in the front matter so it's
+    /// saved with the document
+    pub fn ignore_word(&mut self, word: &str) -> Result<()> {
+        let mut words = self.ignored_words();
+        if words.iter().any(|w| w == word) {
+            return Ok(());
+        }
+        words.push(word.to_string());
+        let value =
+            FrontMatterValue::List(words.into_iter().map(FrontMatterValue::String).collect());
+        self.set_front_matter(spell::IGNORE_KEY, &value)
+    }
+
+    /// Words listed under `spell_ignore` in the front matter
+    pub fn ignored_words(&self) -> Vec<String> {
+        spell::front_matter_ignores(&self.content())
+    }
+
+    /// Word goal from the front matter, or else from the sidecar file next
+    /// to the document
+    pub fn word_goal(&self) -> Result<Option<usize>> {
+        if let Some(goal) = writing::front_matter_goal(&self.content()) {
+            return Ok(Some(goal));
+        }
+        match &self.path {
+            Some(path) => writing::sidecar_goal(path),
+            None => Ok(None),
+        }
+    }
+
+    /// Set or clear the word goal. Front matter goals are an undoable edit;
+    /// sidecar goals are written straight to disk and need a saved document.
+    pub fn set_word_goal(&mut self, goal: Option<usize>, storage: GoalStorage) -> Result<()> {
+        match storage {
+            GoalStorage::FrontMatter => {
+                match goal {
+                    Some(goal) => {
+                        let value = FrontMatterValue::Integer(goal as i64);
+                        self.set_front_matter(writing::GOAL_KEY, &value)?;
+                    }
+                    None => {
+                        self.remove_front_matter(writing::GOAL_KEY)?;
+                    }
                 }
-            } else {
-                // Delete selection
-                let start = sel.start();
-                let end = sel.end();
-                let start_char = self.content.byte_to_char(start);
-                let end_char = self.content.byte_to_char(end);
-                self.content.remove(start_char..end_char);
-                sel.head = start;
-                sel.anchor = start;
+                Ok(())
+            }
+            GoalStorage::Sidecar => {
+                let path = self.path.as_ref().ok_or(Error::Io(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "No file path set",
+                )))?;
+                writing::set_sidecar_goal(path, goal)
             }
         }
+    }
 
-        self.normalize_selections();
-        self.dirty = true;
+    /// Words written towards the goal, counted like `stats`
+    pub fn goal_progress(&mut self, options: StatsOptions) -> Result<Option<GoalProgress>> {
+        let Some(goal) = self.word_goal()? else {
+            return Ok(None);
+        };
+        let words = self.stats(options).total.words;
+        Ok(Some(GoalProgress { goal, words }))
+    }
+
+    /// Take the word changes recorded since the last call
+    pub(crate) fn take_activity(&mut self) -> Vec<WordActivity> {
+        std::mem::take(&mut self.activity)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn set_clock(&mut self, clock: fn() -> SystemTime) {
+        self.clock = clock;
+    }
+
+    /// Parse the front matter block, if the document has one
+    pub fn front_matter(&self) -> Result<Option<FrontMatter>> {
+        front_matter::parse_front_matter(&self.content())
+    }
+
+    /// Set a front matter value by dot-separated key path (`author.name`),
+    /// rewriting only that key. Creates a YAML block if there is none.
+    pub fn set_front_matter(&mut self, path: &str, value: &FrontMatterValue) -> Result<()> {
+        let edits = front_matter::set_edits(&self.content(), path, value)?;
+        self.apply_edits(edits);
+        Ok(())
+    }
+
+    /// Remove a front matter key. Returns false if it was not present.
+    pub fn remove_front_matter(&mut self, path: &str) -> Result<bool> {
+        let edits = front_matter::remove_edits(&self.content(), path)?;
+        let removed = !edits.is_empty();
+        self.apply_edits(edits);
+        Ok(removed)
+    }
+
+    /// Report malformed front matter
+    pub fn front_matter_diagnostics(&self) -> Vec<Diagnostic> {
+        front_matter::diagnostics(&self.content())
+    }
+
+    /// Report style problems in the prose
+    pub fn prose_diagnostics(&self, rules: &ProseRules) -> Vec<Diagnostic> {
+        prose::lint(&self.content(), rules)
+    }
+
+    /// Render the document as HTML
+    pub fn to_html(&self, options: &HtmlOptions) -> String {
+        html::render_html(&self.content(), options)
+    }
+
+    /// Export the document as a `.docx` package, embedding images that
+    /// are relative to its folder
+    pub fn to_docx(&self) -> Result<Vec<u8>> {
+        let base = self.path.as_deref().and_then(Path::parent);
+        docx::render_docx(&self.content(), base)
+    }
+
+    /// Render the document as an EPUB book with one chapter per top-level
+    /// heading
+    pub fn to_epub(&self, options: &EpubOptions) -> Result<Vec<u8>> {
+        let source = EpubSource {
+            text: self.content(),
+            path: self.path.clone(),
+        };
+        epub::render_epub(&[source], options)
+    }
+
+    /// Render the document as an HTML slide deck
+    pub fn to_slides(&self, options: &SlideOptions) -> String {
+        slides::render_slides(&self.content(), options)
     }
 
     /// Move all cursors by delta
     pub fn move_cursors(&mut self, delta: isize, extend: bool) {
         let max = self.len();
-        for sel in &mut self.selections {
+        let step = if delta > 0 { 1 } else { -1 };
+        for i in 0..self.selections.len() {
+            let mut sel = self.selections[i];
             sel.move_by(delta, max, extend);
+            // Never stop inside a character or between the two bytes of
+            // a CRLF
+            while self.snap_to_char(sel.head) != sel.head {
+                sel.move_by(step, max, extend);
+            }
+            self.selections[i] = sel;
         }
         self.normalize_selections();
     }
 
-    /// Normalize selections: sort, merge overlapping, ensure at least one
+    /// Normalize selections: snap to characters, sort, merge overlapping,
+    /// ensure at least one
     fn normalize_selections(&mut self) {
         if self.selections.is_empty() {
             self.selections.push(Selection::default());
             return;
         }
+        self.selections = self
+            .selections
+            .iter()
+            .map(|s| Selection::new(self.snap_to_char(s.anchor), self.snap_to_char(s.head)))
+            .collect();
 
         // Sort by start position
         self.selections.sort_by_key(|s| s.start());
@@ -267,13 +1062,13 @@ impl Document {
         // Merge overlapping selections
         let mut merged: Vec<Selection> = Vec::with_capacity(self.selections.len());
         for sel in self.selections.drain(..) {
-            if let Some(last) = merged.last_mut() {
-                if sel.start() <= last.end() {
-                    // Overlapping - merge
-                    last.head = last.end().max(sel.end());
-                    last.anchor = last.start().min(sel.start());
-                    continue;
-                }
+            if let Some(last) = merged.last_mut()
+                && sel.start() <= last.end()
+            {
+                // Overlapping - merge
+                last.head = last.end().max(sel.end());
+                last.anchor = last.start().min(sel.start());
+                continue;
             }
             merged.push(sel);
         }
@@ -377,4 +1172,360 @@ mod tests {
         doc.select_all();
         assert_eq!(doc.selected_text(), Some("Hello World".to_string()));
     }
+
+    #[test]
+    fn test_apply_edits_maps_selections() {
+        let mut doc = Document::new();
+        doc.insert("one two");
+        doc.set_cursor(4);
+        doc.apply_edits(vec![TextEdit::insert(0, ">> "), TextEdit::delete(3..4)]);
+        assert_eq!(doc.content(), ">> onetwo");
+        assert_eq!(doc.primary_selection().head, 6);
+    }
+
+    #[test]
+    fn test_indent_and_outdent() {
+        let mut doc = Document::new();
+        doc.set_indent_settings(IndentSettings::spaces(2));
+        doc.insert("- a\n- b\n");
+        doc.set_cursor(6);
+        doc.indent();
+        assert_eq!(doc.content(), "- a\n  - b\n");
+        assert_eq!(doc.primary_selection().head, 8);
+        doc.outdent();
+        assert_eq!(doc.content(), "- a\n- b\n");
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut doc = Document::new();
+        doc.insert("Hello");
+        doc.insert(" World");
+        doc.delete_backward();
+        assert!(doc.undo());
+        assert_eq!(doc.content(), "Hello World");
+        assert!(doc.undo());
+        assert_eq!(doc.content(), "Hello");
+        assert!(doc.redo());
+        assert_eq!(doc.content(), "Hello World");
+        assert_eq!(doc.primary_selection().head, 11);
+    }
+
+    #[test]
+    fn test_typing_is_undone_by_word() {
+        let mut doc = Document::new();
+        for c in "one two\nthree".chars() {
+            doc.insert(&c.to_string());
+        }
+        doc.undo();
+        assert_eq!(doc.content(), "one two\n");
+        doc.undo();
+        assert_eq!(doc.content(), "one two");
+        doc.undo();
+        assert_eq!(doc.content(), "one ");
+        assert!(doc.undo());
+        assert_eq!(doc.content(), "");
+        assert!(!doc.can_undo());
+
+        // Moving the cursor starts a new step
+        doc.insert("a");
+        doc.set_cursor(0);
+        doc.insert("b");
+        doc.undo();
+        assert_eq!(doc.content(), "a");
+    }
+
+    #[test]
+    fn test_undo_to_saved_state_is_clean() {
+        let path = std::env::temp_dir().join(format!("rmde-saved-{}.md", std::process::id()));
+        let mut doc = Document::new();
+        doc.insert("Draft");
+        doc.save_as(&path).unwrap();
+        doc.insert("s");
+        assert!(doc.is_dirty());
+        doc.undo();
+        assert!(!doc.is_dirty());
+        doc.undo();
+        assert!(doc.is_dirty());
+        doc.redo();
+        assert!(!doc.is_dirty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_positions_inside_characters() {
+        let text = "# Café ✓\n\n- 😀 done\n";
+        for pos in 0..=text.len() {
+            let mut doc = Document::from_text(text);
+            doc.set_cursor(pos);
+            assert!(text.is_char_boundary(doc.primary_selection().head));
+            doc.delete_backward();
+            doc.set_cursor(pos);
+            doc.insert_tab();
+            doc.set_cursor(pos);
+            doc.insert_toc(2);
+            doc.insert("x");
+        }
+
+        let mut doc = Document::from_text("éa");
+        doc.apply_edits(vec![TextEdit::insert(1, "x")]);
+        assert_eq!(doc.content(), "xéa");
+        doc.set_cursor(0);
+        doc.move_cursors(2, false);
+        assert_eq!(doc.primary_selection().head, 3);
+    }
+
+    #[test]
+    fn test_noop_delete_is_not_an_undo_step() {
+        let mut doc = Document::new();
+        doc.delete_backward();
+        assert!(!doc.can_undo());
+        assert!(!doc.is_dirty());
+    }
+
+    #[test]
+    fn test_reference_conversion_is_one_undo_step() {
+        let mut doc = Document::new();
+        doc.insert("[a](x.md) [b](y.md)\n");
+        doc.select_all();
+        doc.links_to_reference();
+        assert_eq!(doc.content(), "[a][1] [b][2]\n\n[1]: x.md\n[2]: y.md\n");
+        doc.undo();
+        assert_eq!(doc.content(), "[a](x.md) [b](y.md)\n");
+    }
+
+    #[test]
+    fn test_insert_and_jump_to_footnote() {
+        let mut doc = Document::new();
+        doc.insert("Claim.");
+        doc.insert_footnote();
+        assert_eq!(doc.content(), "Claim.[^1]\n\n[^1]: \n");
+        assert_eq!(doc.primary_selection().head, 10);
+
+        doc.set_cursor(7);
+        assert!(doc.jump_to_footnote());
+        assert_eq!(doc.primary_selection().head, 18);
+        assert!(doc.jump_to_footnote());
+        assert_eq!(doc.primary_selection().head, 6);
+    }
+
+    #[test]
+    fn test_wrap_as_you_type() {
+        let mut doc = Document::new();
+        doc.set_wrap_settings(WrapSettings {
+            width: 12,
+            wrap_as_you_type: true,
+        });
+        doc.insert("- one two th");
+        assert_eq!(doc.content(), "- one two th");
+        doc.insert("r");
+        assert_eq!(doc.content(), "- one two\n  thr");
+        assert_eq!(doc.primary_selection().head, doc.len());
+
+        doc.undo();
+        assert_eq!(doc.content(), "- one two th");
+    }
+
+    #[test]
+    fn test_set_front_matter() {
+        let mut doc = Document::new();
+        doc.insert("---\ntitle: Draft # working title\n---\nText\n");
+        doc.set_front_matter("title", &"Final".into()).unwrap();
+        assert_eq!(
+            doc.content(),
+            "---\ntitle: Final # working title\n---\nText\n"
+        );
+        assert!(doc.remove_front_matter("title").unwrap());
+        assert_eq!(doc.content(), "---\n---\nText\n");
+        doc.undo();
+        doc.undo();
+        assert_eq!(
+            doc.content(),
+            "---\ntitle: Draft # working title\n---\nText\n"
+        );
+    }
+
+    #[test]
+    fn test_jump_to_anchor() {
+        let mut doc = Document::new();
+        doc.insert("# Intro\n\n## Next Steps\n");
+        assert!(doc.jump_to_anchor("next-steps"));
+        assert_eq!(doc.primary_selection().head, 9);
+        assert!(!doc.jump_to_anchor("missing"));
+    }
+
+    #[test]
+    fn test_toggle_tasks() {
+        let mut doc = Document::new();
+        doc.insert("- [ ] a\n- [x] b\n");
+        doc.select_all();
+        doc.toggle_tasks();
+        assert_eq!(doc.content(), "- [x] a\n- [ ] b\n");
+    }
+
+    #[test]
+    fn test_toc_regenerated_on_save() {
+        let path = std::env::temp_dir().join(format!("rmde-toc-{}.md", std::process::id()));
+        let mut doc = Document::new();
+        doc.insert("# Title\n\n");
+        doc.insert_toc(2);
+        doc.insert("## Added\n");
+        doc.save_as(&path).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            saved,
+            "# Title\n\n<!-- toc depth=2 -->\n\n- [Title](#title)\n  - [Added](#added)\n\n\
+             <!-- tocstop -->\n## Added\n"
+        );
+        assert_eq!(saved, doc.content());
+
+        // Markers are found wherever rope chunks split them
+        for pad in 1000..1040 {
+            let doc = Document::from_text(&format!("{}<!-- tocstop -->", "é".repeat(pad)));
+            assert!(doc.contains("<!-- tocstop -->"));
+            assert!(!doc.contains("<!-- toc -->"));
+        }
+    }
+
+    #[test]
+    fn test_content_chunks() {
+        let mut doc = Document::new();
+        doc.insert("añb€c");
+        assert_eq!(doc.content_chunk(0, 2), "a");
+        assert_eq!(doc.content_chunk(1, 2), "ñ");
+        assert_eq!(doc.content_chunk(2, 3), "ñb");
+        assert_eq!(doc.content_chunk(4, 1), "€");
+        assert_eq!(doc.content_chunk(7, 100), "c");
+        assert_eq!(doc.content_chunk(8, 100), "");
+
+        let mut read = String::new();
+        while read.len() < doc.len() {
+            read.push_str(&doc.content_chunk(read.len(), 3));
+        }
+        assert_eq!(read, doc.content());
+    }
+
+    #[test]
+    fn test_open_and_save_large_file() {
+        let path = std::env::temp_dir().join(format!("rmde-large-{}.md", std::process::id()));
+        let text = "- item\n  - nested ü\n".repeat(20_000);
+        std::fs::write(&path, &text).unwrap();
+        let mut doc = Document::open(&path).unwrap();
+        assert!(!doc.is_dirty());
+        assert_eq!(doc.indent_settings(), IndentSettings::spaces(2));
+        assert_eq!(doc.chunks().map(str::len).sum::<usize>(), text.len());
+        doc.insert("# Top\n");
+        doc.save().unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(saved.len(), text.len() + 6);
+        assert!(saved.starts_with("# Top\n- item\n"));
+    }
+
+    #[test]
+    fn test_encoding_preserved_on_save() {
+        let path = std::env::temp_dir().join(format!("rmde-encoding-{}.md", std::process::id()));
+        std::fs::write(&path, b"caf\xE9 \x93quoted\x94\n").unwrap();
+        let mut doc = Document::open(&path).unwrap();
+        assert_eq!(doc.encoding(), Encoding::Windows1252);
+        assert_eq!(doc.content(), "caf\u{e9} \u{201c}quoted\u{201d}\n");
+
+        doc.insert("\u{20ac} ");
+        doc.save().unwrap();
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            b"\x80 caf\xE9 \x93quoted\x94\n"
+        );
+
+        doc.insert("\u{3b1}");
+        assert!(matches!(
+            doc.save(),
+            Err(Error::Unencodable {
+                character: '\u{3b1}',
+                ..
+            })
+        ));
+        doc.save_with_encoding(Encoding::Utf16Le).unwrap();
+        assert!(
+            std::fs::read(&path)
+                .unwrap()
+                .starts_with(b"\xFF\xFE\xac\x20")
+        );
+
+        doc.reopen_with_encoding(Encoding::Latin1).unwrap();
+        assert_eq!(doc.encoding(), Encoding::Latin1);
+        assert!(doc.content().starts_with("\u{ff}\u{fe}"));
+        assert!(matches!(
+            doc.reopen_with_encoding(Encoding::Utf8),
+            Err(Error::Decode { offset: 0, .. })
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_crlf_preserved() {
+        let path = std::env::temp_dir().join(format!("rmde-crlf-{}.md", std::process::id()));
+        std::fs::write(&path, "# A\r\n\r\nText\r\nmore\n").unwrap();
+        let mut doc = Document::open(&path).unwrap();
+        assert_eq!(doc.line_ending(), LineEnding::Crlf);
+        assert!(doc.line_ending_counts().is_mixed());
+        assert_eq!(doc.line_count(), 5);
+
+        doc.set_cursor(3);
+        doc.insert("\nB");
+        assert_eq!(doc.content(), "# A\r\nB\r\n\r\nText\r\nmore\n");
+        doc.move_cursors(1, false);
+        assert_eq!(doc.primary_selection().head, 8);
+        doc.delete_backward();
+        doc.delete_forward();
+        assert_eq!(doc.content(), "# A\r\nBText\r\nmore\n");
+
+        // Saving keeps stray line endings; only converting changes them
+        doc.save().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "# A\r\nBText\r\nmore\n"
+        );
+        assert!(!doc.can_redo() && doc.can_undo());
+        doc.convert_line_endings(LineEnding::Lf);
+        assert_eq!(doc.content(), "# A\nBText\nmore\n");
+        assert!(!doc.line_ending_counts().is_mixed());
+        doc.undo();
+        assert_eq!(doc.content(), "# A\r\nBText\r\nmore\n");
+        assert_eq!(doc.line_ending(), LineEnding::Crlf);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_crlf_multi_cursor_delete() {
+        let mut doc = Document::from_text("\r\n");
+        doc.set_cursor(1);
+        assert_eq!(doc.primary_selection().head, 0);
+        doc.add_cursor(2);
+        doc.delete_backward();
+        assert_eq!(doc.content(), "");
+
+        let mut doc = Document::from_text("a\r\nb\r\nc");
+        doc.set_cursor(1);
+        doc.add_cursor(4);
+        doc.delete_forward();
+        assert_eq!(doc.content(), "abc");
+        let heads: Vec<_> = doc.selections().iter().map(|s| s.head).collect();
+        assert_eq!(heads, vec![1, 2]);
+        doc.undo();
+        assert_eq!(doc.content(), "a\r\nb\r\nc");
+    }
+
+    #[test]
+    fn test_edits_use_line_ending() {
+        let mut doc = Document::from_text("one two three four\r\nfive six\r\n");
+        doc.set_wrap_settings(WrapSettings {
+            width: 10,
+            ..WrapSettings::default()
+        });
+        doc.reflow();
+        assert_eq!(doc.content(), "one two\r\nthree four\r\nfive six\r\n");
+        assert!(!doc.line_ending_counts().is_mixed());
+    }
 }
@@ -0,0 +1,2004 @@
+//! DOCX export and import: Markdown written as and read from a
+//! WordprocessingML package
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{Read as _, Write as _};
+use std::path::Path;
+
+use tree_sitter::Node;
+use zip::CompressionMethod;
+use zip::write::SimpleFileOptions;
+
+use crate::error::{Error, Result};
+use crate::footnotes::{self, FootnoteDefinition};
+use crate::html::{
+    code_block, decode_entity, dedent_continuation, escape, find_url, item_blocks, trim_url,
+    unescape, without,
+};
+use crate::import::{Align, Block, Inline, ListItem, clean, flatten, markdown, plain};
+use crate::links::percent_decode;
+use crate::outline::outline;
+use crate::references::{definitions, normalize_label, strip_brackets};
+use crate::slug::{Slugger, heading_slugs};
+use crate::syntax::{MarkdownTree, child_of_kind, plain_text};
+
+const NAMESPACES: &str = "xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\" \
+     xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\" \
+     xmlns:wp=\"http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing\" \
+     xmlns:a=\"http://schemas.openxmlformats.org/drawingml/2006/main\" \
+     xmlns:pic=\"http://schemas.openxmlformats.org/drawingml/2006/picture\"";
+
+const RELATIONSHIPS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const HYPERLINK: &str = "hyperlink";
+const IMAGE: &str = "image";
+
+const CONTENT_TYPES: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>
+<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">
+<Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>
+<Default Extension=\"xml\" ContentType=\"application/xml\"/>
+<Default Extension=\"png\" ContentType=\"image/png\"/>
+<Default Extension=\"jpg\" ContentType=\"image/jpeg\"/>
+<Default Extension=\"jpeg\" ContentType=\"image/jpeg\"/>
+<Default Extension=\"gif\" ContentType=\"image/gif\"/>
+<Override PartName=\"/word/document.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml\"/>
+<Override PartName=\"/word/styles.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml\"/>
+<Override PartName=\"/word/numbering.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.numbering+xml\"/>
+<Override PartName=\"/word/footnotes.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.footnotes+xml\"/>
+</Types>
+";
+
+const PACKAGE_RELATIONSHIPS: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>
+<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">
+<Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" Target=\"word/document.xml\"/>
+</Relationships>
+";
+
+const STYLES: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>
+<w:styles xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\">
+<w:docDefaults><w:rPrDefault><w:rPr><w:rFonts w:ascii=\"Calibri\" w:hAnsi=\"Calibri\" w:eastAsia=\"Calibri\" w:cs=\"Calibri\"/><w:sz w:val=\"22\"/></w:rPr></w:rPrDefault>\
+<w:pPrDefault><w:pPr><w:spacing w:after=\"160\" w:line=\"264\" w:lineRule=\"auto\"/></w:pPr></w:pPrDefault></w:docDefaults>
+<w:style w:type=\"paragraph\" w:default=\"1\" w:styleId=\"Normal\"><w:name w:val=\"Normal\"/><w:qFormat/></w:style>
+<w:style w:type=\"paragraph\" w:styleId=\"Heading1\"><w:name w:val=\"heading 1\"/><w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before=\"360\" w:after=\"120\"/><w:outlineLvl w:val=\"0\"/></w:pPr><w:rPr><w:b/><w:sz w:val=\"36\"/></w:rPr></w:style>
+<w:style w:type=\"paragraph\" w:styleId=\"Heading2\"><w:name w:val=\"heading 2\"/><w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before=\"320\" w:after=\"120\"/><w:outlineLvl w:val=\"1\"/></w:pPr><w:rPr><w:b/><w:sz w:val=\"30\"/></w:rPr></w:style>
+<w:style w:type=\"paragraph\" w:styleId=\"Heading3\"><w:name w:val=\"heading 3\"/><w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before=\"280\" w:after=\"80\"/><w:outlineLvl w:val=\"2\"/></w:pPr><w:rPr><w:b/><w:sz w:val=\"26\"/></w:rPr></w:style>
+<w:style w:type=\"paragraph\" w:styleId=\"Heading4\"><w:name w:val=\"heading 4\"/><w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before=\"240\" w:after=\"80\"/><w:outlineLvl w:val=\"3\"/></w:pPr><w:rPr><w:b/><w:sz w:val=\"24\"/></w:rPr></w:style>
+<w:style w:type=\"paragraph\" w:styleId=\"Heading5\"><w:name w:val=\"heading 5\"/><w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before=\"240\" w:after=\"80\"/><w:outlineLvl w:val=\"4\"/></w:pPr><w:rPr><w:b/><w:i/><w:sz w:val=\"22\"/></w:rPr></w:style>
+<w:style w:type=\"paragraph\" w:styleId=\"Heading6\"><w:name w:val=\"heading 6\"/><w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before=\"240\" w:after=\"80\"/><w:outlineLvl w:val=\"5\"/></w:pPr><w:rPr><w:i/><w:sz w:val=\"22\"/></w:rPr></w:style>
+<w:style w:type=\"paragraph\" w:styleId=\"Quote\"><w:name w:val=\"Quote\"/><w:basedOn w:val=\"Normal\"/><w:qFormat/><w:pPr><w:pBdr><w:left w:val=\"single\" w:sz=\"18\" w:space=\"8\" w:color=\"D0D7DE\"/></w:pBdr><w:ind w:left=\"480\"/></w:pPr><w:rPr><w:color w:val=\"59636E\"/></w:rPr></w:style>
+<w:style w:type=\"paragraph\" w:styleId=\"ListParagraph\"><w:name w:val=\"List Paragraph\"/><w:basedOn w:val=\"Normal\"/><w:qFormat/><w:pPr><w:spacing w:after=\"60\"/><w:ind w:left=\"720\"/></w:pPr></w:style>
+<w:style w:type=\"paragraph\" w:styleId=\"SourceCode\"><w:name w:val=\"Source Code\"/><w:basedOn w:val=\"Normal\"/><w:pPr><w:shd w:val=\"clear\" w:color=\"auto\" w:fill=\"F6F8FA\"/><w:spacing w:after=\"160\" w:line=\"240\" w:lineRule=\"auto\"/></w:pPr><w:rPr><w:rFonts w:ascii=\"Consolas\" w:hAnsi=\"Consolas\" w:cs=\"Consolas\"/><w:sz w:val=\"19\"/></w:rPr></w:style>
+<w:style w:type=\"paragraph\" w:styleId=\"FootnoteText\"><w:name w:val=\"footnote text\"/><w:basedOn w:val=\"Normal\"/><w:pPr><w:spacing w:after=\"0\"/></w:pPr><w:rPr><w:sz w:val=\"20\"/></w:rPr></w:style>
+<w:style w:type=\"character\" w:styleId=\"VerbatimChar\"><w:name w:val=\"Verbatim Char\"/><w:rPr><w:rFonts w:ascii=\"Consolas\" w:hAnsi=\"Consolas\" w:cs=\"Consolas\"/><w:sz w:val=\"20\"/><w:shd w:val=\"clear\" w:color=\"auto\" w:fill=\"EFF1F3\"/></w:rPr></w:style>
+<w:style w:type=\"character\" w:styleId=\"Hyperlink\"><w:name w:val=\"Hyperlink\"/><w:rPr><w:color w:val=\"0563C1\"/><w:u w:val=\"single\"/></w:rPr></w:style>
+<w:style w:type=\"character\" w:styleId=\"FootnoteReference\"><w:name w:val=\"footnote reference\"/><w:rPr><w:vertAlign w:val=\"superscript\"/></w:rPr></w:style>
+<w:style w:type=\"table\" w:styleId=\"TableGrid\"><w:name w:val=\"Table Grid\"/><w:pPr><w:spacing w:after=\"0\"/></w:pPr><w:tblPr><w:tblBorders>\
+<w:top w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"D0D7DE\"/><w:left w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"D0D7DE\"/>\
+<w:bottom w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"D0D7DE\"/><w:right w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"D0D7DE\"/>\
+<w:insideH w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"D0D7DE\"/><w:insideV w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"D0D7DE\"/>\
+</w:tblBorders><w:tblCellMar><w:left w:w=\"108\" w:type=\"dxa\"/><w:right w:w=\"108\" w:type=\"dxa\"/></w:tblCellMar></w:tblPr></w:style>
+</w:styles>
+";
+
+const SECTION: &str = "<w:sectPr><w:pgSz w:w=\"12240\" w:h=\"15840\"/><w:pgMar w:top=\"1440\" w:right=\"1440\" \
+     w:bottom=\"1440\" w:left=\"1440\" w:header=\"720\" w:footer=\"720\" w:gutter=\"0\"/></w:sectPr>";
+
+/// List indentation per level, in twentieths of a point
+const INDENT: usize = 720;
+/// Images are scaled down to the text width (6 inches) in EMUs
+const MAX_IMAGE_WIDTH: u64 = 5_486_400;
+const EMU_PER_PIXEL: u64 = 9525;
+
+/// Write Markdown as a `.docx` package. Relative image paths resolve
+/// against `base`, the folder of the document.
+pub fn render_docx(text: &str, base: Option<&Path>) -> Result<Vec<u8>> {
+    let tree = MarkdownTree::parse(text);
+    let mut package = Package::default();
+    let definitions = footnotes::footnotes(text).0;
+
+    let mut writer = Writer::new(text, &tree, base, &mut package);
+    let headings = outline(&tree, text);
+    writer.slugs = headings
+        .iter()
+        .map(|h| h.range.start)
+        .zip(heading_slugs(&headings))
+        .collect();
+    writer.footnotes = Some((definitions.clone(), Vec::new()));
+    writer.block(tree.root());
+    let body = std::mem::take(&mut writer.out);
+    let links = writer.links.clone();
+    let order = writer
+        .footnotes
+        .take()
+        .map(|(_, order)| order)
+        .unwrap_or_default();
+
+    // Footnotes are rendered by writers of their own, in reference order
+    let mut notes = String::new();
+    for (i, label) in order.iter().enumerate() {
+        let Some(definition) = definitions.iter().find(|d| &d.label == label) else {
+            continue;
+        };
+        let source = dedent_continuation(&text[definition.content_start..definition.range.end]);
+        let note_tree = MarkdownTree::parse(&source);
+        let mut writer = Writer::new(&source, &note_tree, base, &mut package);
+        writer.links = links.clone();
+        writer.in_footnote = true;
+        writer.prefix =
+            "<w:r><w:rPr><w:rStyle w:val=\"FootnoteReference\"/></w:rPr><w:footnoteRef/></w:r>\
+                         <w:r><w:t xml:space=\"preserve\"> </w:t></w:r>"
+                .to_string();
+        writer.block(note_tree.root());
+        let _ = write!(
+            notes,
+            "<w:footnote w:id=\"{}\">{}</w:footnote>",
+            i + 1,
+            writer.out
+        );
+    }
+
+    let document = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <w:document {NAMESPACES}><w:body>{body}{SECTION}</w:body></w:document>\n"
+    );
+    let footnotes = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <w:footnotes {NAMESPACES}>\
+         <w:footnote w:type=\"separator\" w:id=\"-1\"><w:p><w:r><w:separator/></w:r></w:p></w:footnote>\
+         <w:footnote w:type=\"continuationSeparator\" w:id=\"0\"><w:p><w:r><w:continuationSeparator/></w:r></w:p></w:footnote>\
+         {notes}</w:footnotes>\n"
+    );
+
+    let relationships = package.relationships_xml();
+    let numbering = package.numbering_xml();
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let parts = [
+        ("[Content_Types].xml", CONTENT_TYPES.as_bytes()),
+        ("_rels/.rels", PACKAGE_RELATIONSHIPS.as_bytes()),
+        ("word/document.xml", document.as_bytes()),
+        ("word/_rels/document.xml.rels", relationships.as_bytes()),
+        ("word/styles.xml", STYLES.as_bytes()),
+        ("word/numbering.xml", numbering.as_bytes()),
+        ("word/footnotes.xml", footnotes.as_bytes()),
+    ];
+    for (name, data) in parts {
+        zip.start_file(name, options)?;
+        zip.write_all(data)?;
+    }
+    for (name, data) in &package.media {
+        zip.start_file(format!("word/media/{name}"), options)?;
+        zip.write_all(data)?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Parts shared by the document and its footnotes
+#[derive(Default)]
+struct Package {
+    /// Relationship type and target; ids follow the three fixed ones
+    relationships: Vec<(&'static str, String)>,
+    /// Image files by name in `word/media`
+    media: Vec<(String, Vec<u8>)>,
+    /// Numbering instance per list: ordered, and the first number
+    lists: Vec<(bool, u64)>,
+    drawings: usize,
+    bookmarks: usize,
+}
+
+impl Package {
+    /// Relationship id for a target, reusing an earlier one
+    fn relationship(&mut self, kind: &'static str, target: &str) -> String {
+        let index = match self
+            .relationships
+            .iter()
+            .position(|(k, t)| *k == kind && t == target)
+        {
+            Some(index) => index,
+            None => {
+                self.relationships.push((kind, target.to_string()));
+                self.relationships.len() - 1
+            }
+        };
+        format!("rId{}", index + 4)
+    }
+
+    fn relationships_xml(&self) -> String {
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+             <Relationship Id=\"rId1\" Type=\"{RELATIONSHIPS}/styles\" Target=\"styles.xml\"/>\
+             <Relationship Id=\"rId2\" Type=\"{RELATIONSHIPS}/numbering\" Target=\"numbering.xml\"/>\
+             <Relationship Id=\"rId3\" Type=\"{RELATIONSHIPS}/footnotes\" Target=\"footnotes.xml\"/>"
+        );
+        for (i, (kind, target)) in self.relationships.iter().enumerate() {
+            let mode = if *kind == HYPERLINK {
+                " TargetMode=\"External\""
+            } else {
+                ""
+            };
+            let _ = write!(
+                xml,
+                "<Relationship Id=\"rId{}\" Type=\"{RELATIONSHIPS}/{kind}\" Target=\"{}\"{mode}/>",
+                i + 4,
+                xml_escape(target)
+            );
+        }
+        xml.push_str("</Relationships>\n");
+        xml
+    }
+
+    /// Bullet and decimal list definitions, and a numbering instance per
+    /// list so each ordered list starts from its own first number
+    fn numbering_xml(&self) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <w:numbering xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\">",
+        );
+        for (id, ordered) in [(0, false), (1, true)] {
+            let _ = write!(
+                xml,
+                "<w:abstractNum w:abstractNumId=\"{id}\"><w:multiLevelType w:val=\"hybridMultilevel\"/>"
+            );
+            for level in 0..9 {
+                let (format, text) = if ordered {
+                    ("decimal", format!("%{}.", level + 1))
+                } else {
+                    ("bullet", ["•", "◦", "▪"][level % 3].to_string())
+                };
+                let _ = write!(
+                    xml,
+                    "<w:lvl w:ilvl=\"{level}\"><w:start w:val=\"1\"/><w:numFmt w:val=\"{format}\"/>\
+                     <w:lvlText w:val=\"{text}\"/><w:lvlJc w:val=\"left\"/>\
+                     <w:pPr><w:ind w:left=\"{}\" w:hanging=\"360\"/></w:pPr></w:lvl>",
+                    INDENT * (level + 1)
+                );
+            }
+            xml.push_str("</w:abstractNum>");
+        }
+        for (i, (ordered, start)) in self.lists.iter().enumerate() {
+            let _ = write!(
+                xml,
+                "<w:num w:numId=\"{}\"><w:abstractNumId w:val=\"{}\"/>",
+                i + 1,
+                u8::from(*ordered)
+            );
+            if *ordered {
+                for level in 0..9 {
+                    let _ = write!(
+                        xml,
+                        "<w:lvlOverride w:ilvl=\"{level}\"><w:startOverride w:val=\"{start}\"/></w:lvlOverride>"
+                    );
+                }
+            }
+            xml.push_str("</w:num>");
+        }
+        xml.push_str("</w:numbering>\n");
+        xml
+    }
+}
+
+/// Character formatting of a run
+#[derive(Debug, Clone, Copy, Default)]
+struct Format {
+    bold: bool,
+    italic: bool,
+    strike: bool,
+    code: bool,
+    link: bool,
+}
+
+/// The list an item paragraph belongs to
+struct ListLevel {
+    num_id: usize,
+    depth: usize,
+    /// The item's first paragraph, which carries the number or bullet,
+    /// has not been written yet
+    numbered: bool,
+}
+
+struct Writer<'a> {
+    text: &'a str,
+    tree: &'a MarkdownTree,
+    base: Option<&'a Path>,
+    package: &'a mut Package,
+    links: HashMap<String, (String, Option<String>)>,
+    /// Heading bookmarks by heading start
+    slugs: HashMap<usize, String>,
+    /// Footnote definitions and the labels referenced so far; `None` when
+    /// writing a footnote
+    footnotes: Option<(Vec<FootnoteDefinition>, Vec<String>)>,
+    /// Footnotes cannot hold hyperlinks or images of their own
+    in_footnote: bool,
+    lists: Vec<ListLevel>,
+    quote_depth: usize,
+    /// Runs to put at the start of the next paragraph
+    prefix: String,
+    /// Block continuations inside the inline content being written
+    skip: Vec<std::ops::Range<usize>>,
+    /// Bounds of the inline content: surrounding whitespace is dropped and
+    /// a heading's closing `#`s are cut off
+    bounds: std::ops::Range<usize>,
+    out: String,
+}
+
+impl<'a> Writer<'a> {
+    fn new(
+        text: &'a str,
+        tree: &'a MarkdownTree,
+        base: Option<&'a Path>,
+        package: &'a mut Package,
+    ) -> Self {
+        let mut links = HashMap::new();
+        for definition in definitions(tree, text) {
+            links
+                .entry(normalize_label(&definition.label))
+                .or_insert((definition.destination, definition.title));
+        }
+        Self {
+            text,
+            tree,
+            base,
+            package,
+            links,
+            slugs: HashMap::new(),
+            footnotes: None,
+            in_footnote: false,
+            lists: Vec::new(),
+            quote_depth: 0,
+            prefix: String::new(),
+            skip: Vec::new(),
+            bounds: 0..0,
+            out: String::new(),
+        }
+    }
+
+    // --- Blocks ---
+
+    fn block(&mut self, node: Node) {
+        if !matches!(node.kind(), "document" | "section") && self.is_footnote_definition(node) {
+            return;
+        }
+        match node.kind() {
+            "document" | "section" => self.blocks(node),
+            "atx_heading" | "setext_heading" => self.heading(node),
+            "paragraph" => {
+                let runs = child_of_kind(node, "inline")
+                    .map(|inline| self.inline_runs(inline, inline.end_byte()))
+                    .unwrap_or_default();
+                self.paragraph(None, "", &runs);
+            }
+            "block_quote" => {
+                self.quote_depth += 1;
+                self.blocks(node);
+                self.quote_depth -= 1;
+            }
+            "list" => self.list(node),
+            "thematic_break" => self.paragraph(
+                None,
+                "<w:pBdr><w:bottom w:val=\"single\" w:sz=\"6\" w:space=\"1\" w:color=\"auto\"/></w:pBdr>",
+                "",
+            ),
+            "indented_code_block" | "fenced_code_block" => {
+                let (lines, _) = code_block(self.text, node);
+                let runs: Vec<String> = lines.iter().map(|line| run(line, Format::default())).collect();
+                self.paragraph(Some("SourceCode"), "", &runs.join("<w:r><w:br/></w:r>"));
+            }
+            "pipe_table" => self.table(node),
+            // Markers, continuations, HTML, link reference definitions and
+            // front matter
+            _ => {}
+        }
+    }
+
+    fn blocks(&mut self, node: Node) {
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.named_children(&mut cursor).collect();
+        for child in children {
+            self.block(child);
+        }
+    }
+
+    fn is_footnote_definition(&self, node: Node) -> bool {
+        self.footnotes.as_ref().is_some_and(|(definitions, _)| {
+            definitions
+                .iter()
+                .any(|d| d.range.contains(&node.start_byte()))
+        })
+    }
+
+    /// Write a paragraph; `properties` go after the style and numbering
+    fn paragraph(&mut self, style: Option<&str>, properties: &str, runs: &str) {
+        let style = style.or(if self.in_footnote {
+            Some("FootnoteText")
+        } else if self.quote_depth > 0 {
+            Some("Quote")
+        } else if !self.lists.is_empty() {
+            Some("ListParagraph")
+        } else {
+            None
+        });
+        let mut ppr = String::new();
+        if let Some(style) = style {
+            let _ = write!(ppr, "<w:pStyle w:val=\"{style}\"/>");
+        }
+        let mut indent = None;
+        if let Some(list) = self.lists.last_mut() {
+            if list.numbered {
+                list.numbered = false;
+                let _ = write!(
+                    ppr,
+                    "<w:numPr><w:ilvl w:val=\"{}\"/><w:numId w:val=\"{}\"/></w:numPr>",
+                    list.depth.min(8),
+                    list.num_id
+                );
+            } else {
+                indent = Some(INDENT * (list.depth + 1));
+            }
+        }
+        ppr.push_str(properties);
+        if let Some(indent) = indent {
+            let _ = write!(ppr, "<w:ind w:left=\"{indent}\"/>");
+        }
+
+        self.out.push_str("<w:p>");
+        if !ppr.is_empty() {
+            let _ = write!(self.out, "<w:pPr>{ppr}</w:pPr>");
+        }
+        let prefix = std::mem::take(&mut self.prefix);
+        let _ = write!(self.out, "{prefix}{runs}</w:p>");
+    }
+
+    fn heading(&mut self, node: Node) {
+        let atx = node.kind() == "atx_heading";
+        let mut cursor = node.walk();
+        let level = node
+            .children(&mut cursor)
+            .find_map(|c| match c.kind() {
+                "setext_h1_underline" => Some(1),
+                "setext_h2_underline" => Some(2),
+                kind => kind
+                    .strip_prefix("atx_h")
+                    .and_then(|k| k.strip_suffix("_marker"))
+                    .and_then(|n| n.parse().ok()),
+            })
+            .unwrap_or(1);
+        let inline = if atx {
+            child_of_kind(node, "inline")
+        } else {
+            child_of_kind(node, "paragraph").and_then(|p| child_of_kind(p, "inline"))
+        };
+
+        let mut runs = String::new();
+        if let Some(slug) = self.slugs.get(&node.start_byte()) {
+            let id = self.package.bookmarks;
+            self.package.bookmarks += 1;
+            let _ = write!(
+                runs,
+                "<w:bookmarkStart w:id=\"{id}\" w:name=\"{}\"/><w:bookmarkEnd w:id=\"{id}\"/>",
+                xml_escape(&bookmark_name(slug))
+            );
+        }
+        if let Some(inline) = inline {
+            // Optional closing sequence: `## Title ##`
+            let source = &self.text[inline.byte_range()];
+            let trimmed = source.trim_end();
+            let without_closing = trimmed.trim_end_matches('#');
+            let end = if atx
+                && without_closing.len() < trimmed.len()
+                && (without_closing.is_empty() || without_closing.ends_with([' ', '\t']))
+            {
+                inline.start_byte() + without_closing.trim_end().len()
+            } else {
+                inline.end_byte()
+            };
+            runs.push_str(&self.inline_runs(inline, end));
+        }
+        // Headings inside lists and quotes keep their heading style
+        let lists = std::mem::take(&mut self.lists);
+        let quote_depth = std::mem::replace(&mut self.quote_depth, 0);
+        self.paragraph(Some(&format!("Heading{level}")), "", &runs);
+        self.lists = lists;
+        self.quote_depth = quote_depth;
+    }
+
+    fn list(&mut self, node: Node) {
+        let mut cursor = node.walk();
+        let items: Vec<Node> = node
+            .named_children(&mut cursor)
+            .filter(|c| c.kind() == "list_item")
+            .collect();
+        let Some(first) = items.first() else {
+            return;
+        };
+        let marker = first
+            .child(0)
+            .filter(|c| c.kind().starts_with("list_marker"))
+            .map(|c| self.text[c.byte_range()].trim())
+            .unwrap_or("-");
+        let start: Option<u64> = marker.strip_suffix(['.', ')']).and_then(|n| n.parse().ok());
+        self.package
+            .lists
+            .push((start.is_some(), start.unwrap_or(1)));
+        let num_id = self.package.lists.len();
+        let depth = self.lists.len();
+
+        for item in items {
+            self.lists.push(ListLevel {
+                num_id,
+                depth,
+                numbered: true,
+            });
+            if child_of_kind(item, "task_list_marker_checked").is_some() {
+                self.prefix.push_str(&run("☒ ", Format::default()));
+            } else if child_of_kind(item, "task_list_marker_unchecked").is_some() {
+                self.prefix.push_str(&run("☐ ", Format::default()));
+            }
+            let blocks = item_blocks(item);
+            if blocks.is_empty() {
+                self.paragraph(None, "", "");
+            }
+            for block in blocks {
+                self.block(block);
+            }
+            self.lists.pop();
+        }
+    }
+
+    fn table(&mut self, node: Node) {
+        let aligns: Vec<&str> = child_of_kind(node, "pipe_table_delimiter_row")
+            .map(|row| {
+                let mut cursor = row.walk();
+                row.named_children(&mut cursor)
+                    .filter(|c| c.kind() == "pipe_table_delimiter_cell")
+                    .map(|cell| {
+                        let left = child_of_kind(cell, "pipe_table_align_left").is_some();
+                        let right = child_of_kind(cell, "pipe_table_align_right").is_some();
+                        match (left, right) {
+                            (true, true) => "center",
+                            (false, true) => "right",
+                            _ => "left",
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        self.out.push_str(
+            "<w:tbl><w:tblPr><w:tblStyle w:val=\"TableGrid\"/><w:tblW w:w=\"5000\" w:type=\"pct\"/></w:tblPr><w:tblGrid>",
+        );
+        for _ in &aligns {
+            self.out.push_str("<w:gridCol/>");
+        }
+        self.out.push_str("</w:tblGrid>");
+        let mut cursor = node.walk();
+        let rows: Vec<Node> = node
+            .named_children(&mut cursor)
+            .filter(|c| matches!(c.kind(), "pipe_table_header" | "pipe_table_row"))
+            .collect();
+        for row in rows {
+            let header = row.kind() == "pipe_table_header";
+            let mut cursor = row.walk();
+            let cells: Vec<Node> = row
+                .named_children(&mut cursor)
+                .filter(|c| c.kind() == "pipe_table_cell")
+                .collect();
+            self.out.push_str("<w:tr>");
+            if header {
+                self.out.push_str("<w:trPr><w:tblHeader/></w:trPr>");
+            }
+            for (i, align) in aligns.iter().enumerate() {
+                let mut runs = cells
+                    .get(i)
+                    .map(|cell| self.inline_runs(*cell, cell.end_byte()))
+                    .unwrap_or_default();
+                if header {
+                    runs = runs
+                        .replace("<w:r>", "<w:r><w:rPr><w:b/></w:rPr>")
+                        .replace("<w:rPr><w:b/></w:rPr><w:rPr>", "<w:rPr><w:b/>");
+                }
+                let _ = write!(
+                    self.out,
+                    "<w:tc><w:tcPr><w:tcW w:w=\"0\" w:type=\"auto\"/></w:tcPr>\
+                     <w:p><w:pPr><w:jc w:val=\"{align}\"/></w:pPr>{runs}</w:p></w:tc>"
+                );
+            }
+            self.out.push_str("</w:tr>");
+        }
+        self.out.push_str("</w:tbl>");
+        // Word needs a paragraph between a table and what follows
+        self.out.push_str("<w:p/>");
+    }
+
+    // --- Inlines ---
+
+    /// Runs for an `inline` or `pipe_table_cell` node, up to `end`
+    fn inline_runs(&mut self, node: Node, end: usize) -> String {
+        let mut cursor = node.walk();
+        self.skip = node
+            .named_children(&mut cursor)
+            .filter(|c| c.kind() == "block_continuation")
+            .map(|c| c.byte_range())
+            .collect();
+        let mut out = String::new();
+        if let Some(root) = self.tree.inline_root(&node) {
+            self.bounds = root.start_byte()..end.min(root.end_byte());
+            self.children(root, Format::default(), &mut out);
+        }
+        self.skip.clear();
+        out
+    }
+
+    fn children(&mut self, node: Node, format: Format, out: &mut String) {
+        let mut pos = node.start_byte();
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.named_children(&mut cursor).collect();
+        for child in children {
+            if child.start_byte() >= self.bounds.end {
+                break;
+            }
+            self.text_run(pos..child.start_byte(), format, out);
+            if !matches!(
+                child.kind(),
+                "emphasis_delimiter" | "code_span_delimiter" | "latex_span_delimiter"
+            ) {
+                self.inline(child, format, out);
+            }
+            pos = child.end_byte();
+        }
+        self.text_run(pos..node.end_byte(), format, out);
+    }
+
+    fn inline(&mut self, node: Node, format: Format, out: &mut String) {
+        let source = &self.text[node.byte_range()];
+        match node.kind() {
+            "emphasis" => self.children(
+                node,
+                Format {
+                    italic: true,
+                    ..format
+                },
+                out,
+            ),
+            "strong_emphasis" => self.children(
+                node,
+                Format {
+                    bold: true,
+                    ..format
+                },
+                out,
+            ),
+            "strikethrough" => self.children(
+                node,
+                Format {
+                    strike: true,
+                    ..format
+                },
+                out,
+            ),
+            "code_span" => {
+                let mut cursor = node.walk();
+                let delimiters: Vec<Node> = node
+                    .children(&mut cursor)
+                    .filter(|c| c.kind() == "code_span_delimiter")
+                    .collect();
+                if let (Some(open), Some(close)) = (delimiters.first(), delimiters.last()) {
+                    let code = without(self.text, open.end_byte()..close.start_byte(), &self.skip)
+                        .replace('\n', " ")
+                        .replace("\\|", "|");
+                    let code = match code.strip_prefix(' ').and_then(|c| c.strip_suffix(' ')) {
+                        Some(inner) if !inner.trim().is_empty() => inner.to_string(),
+                        _ => code,
+                    };
+                    out.push_str(&run(
+                        &code,
+                        Format {
+                            code: true,
+                            ..format
+                        },
+                    ));
+                }
+            }
+            "inline_link" => {
+                let destination = child_of_kind(node, "link_destination")
+                    .map(|d| unescape(&self.text[d.byte_range()]))
+                    .unwrap_or_default();
+                self.hyperlink(node, &destination, format, out);
+            }
+            "full_reference_link" | "collapsed_reference_link" | "shortcut_link" => {
+                self.reference_link(node, format, out)
+            }
+            "image" => self.image(node, format, out),
+            "uri_autolink" | "email_autolink" => {
+                let target = source.trim_start_matches('<').trim_end_matches('>');
+                let url = match node.kind() {
+                    "email_autolink" => format!("mailto:{target}"),
+                    _ => target.to_string(),
+                };
+                self.url_link(&url, target, format, out);
+            }
+            "html_tag" => {
+                let tag = source.to_ascii_lowercase();
+                if tag.starts_with("<br") {
+                    out.push_str("<w:r><w:br/></w:r>");
+                }
+            }
+            "hard_line_break" => out.push_str("<w:r><w:br/></w:r>"),
+            "backslash_escape" => out.push_str(&run(&source[1..], format)),
+            "entity_reference" | "numeric_character_reference" => {
+                out.push_str(&run(&decode_entity(source), format))
+            }
+            _ => self.children(node, format, out),
+        }
+    }
+
+    fn hyperlink(&mut self, node: Node, destination: &str, format: Format, out: &mut String) {
+        let destination = destination
+            .strip_prefix('<')
+            .and_then(|d| d.strip_suffix('>'))
+            .unwrap_or(destination);
+        let Some(text) = child_of_kind(node, "link_text") else {
+            return;
+        };
+        let mut runs = String::new();
+        self.children(
+            text,
+            Format {
+                link: true,
+                ..format
+            },
+            &mut runs,
+        );
+        if self.in_footnote {
+            // Footnotes have no relationships part, so the URL is spelled out
+            out.push_str(&runs);
+            out.push_str(&run(&format!(" ({destination})"), format));
+        } else if let Some(anchor) = destination.strip_prefix('#') {
+            let _ = write!(
+                out,
+                "<w:hyperlink w:anchor=\"{}\">{runs}</w:hyperlink>",
+                xml_escape(&bookmark_name(&percent_decode(anchor)))
+            );
+        } else {
+            let id = self.package.relationship(HYPERLINK, destination);
+            let _ = write!(out, "<w:hyperlink r:id=\"{id}\">{runs}</w:hyperlink>");
+        }
+    }
+
+    fn url_link(&mut self, url: &str, text: &str, format: Format, out: &mut String) {
+        let runs = run(
+            text,
+            Format {
+                link: true,
+                ..format
+            },
+        );
+        if self.in_footnote {
+            out.push_str(&runs);
+        } else {
+            let id = self.package.relationship(HYPERLINK, url);
+            let _ = write!(out, "<w:hyperlink r:id=\"{id}\">{runs}</w:hyperlink>");
+        }
+    }
+
+    fn reference_link(&mut self, node: Node, format: Format, out: &mut String) {
+        let source = &self.text[node.byte_range()];
+        if node.kind() == "shortcut_link"
+            && let Some(label) = source.strip_prefix("[^").and_then(|l| l.strip_suffix(']'))
+            && let Some((definitions, order)) = &mut self.footnotes
+            && definitions.iter().any(|d| d.label == label)
+        {
+            // A footnote can be referenced once; later references repeat
+            // its number as plain superscript
+            match order.iter().position(|l| l == label) {
+                Some(index) => {
+                    let _ = write!(
+                        out,
+                        "<w:r><w:rPr><w:rStyle w:val=\"FootnoteReference\"/></w:rPr><w:t>{}</w:t></w:r>",
+                        index + 1
+                    );
+                }
+                None => {
+                    order.push(label.to_string());
+                    let _ = write!(
+                        out,
+                        "<w:r><w:rPr><w:rStyle w:val=\"FootnoteReference\"/></w:rPr>\
+                         <w:footnoteReference w:id=\"{}\"/></w:r>",
+                        order.len()
+                    );
+                }
+            }
+            return;
+        }
+
+        let text = child_of_kind(node, "link_text");
+        let label = match (node.kind(), child_of_kind(node, "link_label")) {
+            ("full_reference_link", Some(label)) => strip_brackets(&self.text[label.byte_range()]),
+            _ => text.map_or("", |t| &self.text[t.byte_range()]),
+        };
+        match self.links.get(&normalize_label(label)).cloned() {
+            Some((destination, _)) => self.hyperlink(node, &unescape(&destination), format, out),
+            None => {
+                out.push_str(&run("[", format));
+                if let Some(text) = text {
+                    self.children(text, format, out);
+                    self.text_run(text.end_byte()..node.end_byte(), format, out);
+                }
+            }
+        }
+    }
+
+    /// Embed a local image; remote and missing images leave their
+    /// description
+    fn image(&mut self, node: Node, format: Format, out: &mut String) {
+        let description = child_of_kind(node, "image_description");
+        let alt = description
+            .map(|d| plain_text(self.tree, d, self.text))
+            .unwrap_or_default();
+        let destination = match child_of_kind(node, "link_destination") {
+            Some(destination) => Some(unescape(&self.text[destination.byte_range()])),
+            None => {
+                let label = child_of_kind(node, "link_label")
+                    .map(|l| strip_brackets(&self.text[l.byte_range()]))
+                    .filter(|l| !l.is_empty())
+                    .or_else(|| description.map(|d| &self.text[d.byte_range()]))
+                    .unwrap_or("");
+                self.links
+                    .get(&normalize_label(label))
+                    .map(|(destination, _)| unescape(destination))
+            }
+        };
+        let embedded = destination.and_then(|destination| {
+            let destination = destination
+                .strip_prefix('<')
+                .and_then(|d| d.strip_suffix('>'))
+                .unwrap_or(&destination)
+                .to_string();
+            self.embed_image(&destination, &alt)
+        });
+        match embedded {
+            Some(drawing) => out.push_str(&drawing),
+            None => out.push_str(&run(&alt, format)),
+        }
+    }
+
+    fn embed_image(&mut self, destination: &str, alt: &str) -> Option<String> {
+        if self.in_footnote || destination.contains("://") {
+            return None;
+        }
+        let path = Path::new(&percent_decode(destination)).to_path_buf();
+        let path = match self.base {
+            Some(base) if path.is_relative() => base.join(path),
+            _ => path,
+        };
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        if !matches!(extension.as_str(), "png" | "jpg" | "jpeg" | "gif") {
+            return None;
+        }
+        let data = std::fs::read(&path).ok()?;
+
+        let (width, height) = image_size(&data).unwrap_or((400, 300));
+        let (mut cx, mut cy) = (width * EMU_PER_PIXEL, height * EMU_PER_PIXEL);
+        if cx > MAX_IMAGE_WIDTH {
+            cy = cy * MAX_IMAGE_WIDTH / cx;
+            cx = MAX_IMAGE_WIDTH;
+        }
+        let name = format!("image{}.{extension}", self.package.media.len() + 1);
+        let id = self.package.relationship(IMAGE, &format!("media/{name}"));
+        self.package.media.push((name.clone(), data));
+        self.package.drawings += 1;
+        let drawing = self.package.drawings;
+        Some(format!(
+            "<w:r><w:drawing><wp:inline distT=\"0\" distB=\"0\" distL=\"0\" distR=\"0\">\
+             <wp:extent cx=\"{cx}\" cy=\"{cy}\"/><wp:docPr id=\"{drawing}\" name=\"Picture {drawing}\" descr=\"{}\"/>\
+             <a:graphic><a:graphicData uri=\"http://schemas.openxmlformats.org/drawingml/2006/picture\">\
+             <pic:pic><pic:nvPicPr><pic:cNvPr id=\"{drawing}\" name=\"{name}\"/><pic:cNvPicPr/></pic:nvPicPr>\
+             <pic:blipFill><a:blip r:embed=\"{id}\"/><a:stretch><a:fillRect/></a:stretch></pic:blipFill>\
+             <pic:spPr><a:xfrm><a:off x=\"0\" y=\"0\"/><a:ext cx=\"{cx}\" cy=\"{cy}\"/></a:xfrm>\
+             <a:prstGeom prst=\"rect\"><a:avLst/></a:prstGeom></pic:spPr></pic:pic>\
+             </a:graphicData></a:graphic></wp:inline></w:drawing></w:r>",
+            xml_escape(alt)
+        ))
+    }
+
+    /// Text between inline nodes: line breaks become spaces and bare URLs
+    /// become links
+    fn text_run(&mut self, range: std::ops::Range<usize>, format: Format, out: &mut String) {
+        let range = range.start.max(self.bounds.start)..range.end.min(self.bounds.end);
+        if range.start >= range.end {
+            return;
+        }
+        let mut source = without(self.text, range.clone(), &self.skip);
+        if range.start == self.bounds.start {
+            source = source.trim_start().to_string();
+        }
+        if range.end == self.bounds.end {
+            source = source.trim_end().to_string();
+        }
+        let text = source
+            .split('\n')
+            .map(|line| line.trim_matches([' ', '\t']))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let text = match (
+            source.starts_with([' ', '\t']),
+            source.ends_with([' ', '\t']),
+        ) {
+            (true, true) => format!(" {text} "),
+            (true, false) => format!(" {text}"),
+            (false, true) => format!("{text} "),
+            (false, false) => text,
+        };
+
+        let mut rest = text.as_str();
+        while !format.link
+            && let Some(start) = find_url(rest)
+        {
+            out.push_str(&run(&rest[..start], format));
+            let candidate = &rest[start..];
+            let end = candidate
+                .find(|c: char| c.is_whitespace() || c == '<')
+                .unwrap_or(candidate.len());
+            let url = trim_url(&candidate[..end]);
+            let target = if url.starts_with("www.") {
+                format!("http://{url}")
+            } else {
+                url.to_string()
+            };
+            self.url_link(&target, url, format, out);
+            rest = &candidate[url.len()..];
+        }
+        out.push_str(&run(rest, format));
+    }
+}
+
+/// A run of text; tabs become tab characters
+fn run(text: &str, format: Format) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    let mut properties = String::new();
+    if format.code {
+        properties.push_str("<w:rStyle w:val=\"VerbatimChar\"/>");
+    } else if format.link {
+        properties.push_str("<w:rStyle w:val=\"Hyperlink\"/>");
+    }
+    if format.bold {
+        properties.push_str("<w:b/>");
+    }
+    if format.italic {
+        properties.push_str("<w:i/>");
+    }
+    if format.strike {
+        properties.push_str("<w:strike/>");
+    }
+
+    let mut out = String::from("<w:r>");
+    if !properties.is_empty() {
+        let _ = write!(out, "<w:rPr>{properties}</w:rPr>");
+    }
+    for (i, part) in text.split('\t').enumerate() {
+        if i > 0 {
+            out.push_str("<w:tab/>");
+        }
+        if !part.is_empty() {
+            let _ = write!(
+                out,
+                "<w:t xml:space=\"preserve\">{}</w:t>",
+                xml_escape(part)
+            );
+        }
+    }
+    out.push_str("</w:r>");
+    out
+}
+
+/// Escape text for XML, dropping characters XML cannot contain
+fn xml_escape(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .filter(|&c| c >= ' ' || matches!(c, '\t' | '\n' | '\r'))
+        .collect();
+    escape(&text)
+}
+
+/// Word bookmark names are at most 40 characters and start with a letter
+fn bookmark_name(slug: &str) -> String {
+    let name: String = slug.chars().take(40).collect();
+    if name.starts_with(|c: char| c.is_alphabetic()) {
+        name
+    } else {
+        format!("h{}", name.chars().take(39).collect::<String>())
+    }
+}
+
+/// Pixel size of a PNG, GIF or JPEG image
+fn image_size(data: &[u8]) -> Option<(u64, u64)> {
+    let be16 = |i: usize| {
+        Some(u64::from(u16::from_be_bytes([
+            *data.get(i)?,
+            *data.get(i + 1)?,
+        ])))
+    };
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        let width = u32::from_be_bytes(data.get(16..20)?.try_into().ok()?);
+        let height = u32::from_be_bytes(data.get(20..24)?.try_into().ok()?);
+        return Some((width.into(), height.into()));
+    }
+    if data.starts_with(b"GIF8") {
+        let width = u16::from_le_bytes([*data.get(6)?, *data.get(7)?]);
+        let height = u16::from_le_bytes([*data.get(8)?, *data.get(9)?]);
+        return Some((width.into(), height.into()));
+    }
+    if data.starts_with(&[0xff, 0xd8]) {
+        // Walk the segments to a start-of-frame marker
+        let mut i = 2;
+        while i + 9 < data.len() {
+            if data[i] != 0xff {
+                return None;
+            }
+            let marker = data[i + 1];
+            if matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc) {
+                return Some((be16(i + 7)?, be16(i + 5)?));
+            }
+            i += 2 + usize::try_from(be16(i + 2)?).ok()?;
+        }
+    }
+    None
+}
+
+// --- Import ---
+
+const W: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+
+/// A `.docx` file converted to Markdown
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocxImport {
+    pub markdown: String,
+    /// Images of the document by file name, linked from the Markdown
+    /// inside the image folder
+    pub images: Vec<(String, Vec<u8>)>,
+}
+
+/// Convert a `.docx` file to Markdown. Embedded images are linked as
+/// `image_dir/name` and returned for the caller to write there.
+pub fn import_docx(data: &[u8], image_dir: &str) -> Result<DocxImport> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data))?;
+    let document = read_part(&mut archive, "word/document.xml")?
+        .ok_or_else(|| Error::ParseError("word/document.xml is missing".to_string()))?;
+    let styles = read_part(&mut archive, "word/styles.xml")?;
+    let numbering = read_part(&mut archive, "word/numbering.xml")?;
+    let relationships = read_part(&mut archive, "word/_rels/document.xml.rels")?;
+    let footnotes = read_part(&mut archive, "word/footnotes.xml")?;
+
+    let document = parse_xml(&document)?;
+    let styles = styles.as_deref().map(parse_xml).transpose()?;
+    let numbering = numbering.as_deref().map(parse_xml).transpose()?;
+    let relationships = relationships.as_deref().map(parse_xml).transpose()?;
+    let footnotes = footnotes.as_deref().map(parse_xml).transpose()?;
+
+    let mut reader = Reader {
+        archive: &mut archive,
+        image_dir,
+        styles: styles.as_ref().map(style_kinds).unwrap_or_default(),
+        lists: numbering.as_ref().map(list_levels).unwrap_or_default(),
+        relationships: relationships
+            .iter()
+            .flat_map(|xml| xml.root_element().children())
+            .filter_map(|r| {
+                let external = r.attribute("TargetMode") == Some("External");
+                Some((
+                    r.attribute("Id")?.to_string(),
+                    (r.attribute("Target")?.to_string(), external),
+                ))
+            })
+            .collect(),
+        footnotes: Vec::new(),
+        images: Vec::new(),
+        bookmarks: Vec::new(),
+        headings: Vec::new(),
+    };
+
+    let mut items = Vec::new();
+    if let Some(body) = element(document.root_element(), "body") {
+        reader.items(body, &mut items);
+    }
+    let mut blocks = group(items);
+
+    // Footnotes are numbered in order of reference, including references
+    // from other footnotes
+    let mut notes = Vec::new();
+    let mut i = 0;
+    while let Some(id) = reader.footnotes.get(i).cloned() {
+        i += 1;
+        let note = footnotes
+            .iter()
+            .flat_map(|xml| xml.root_element().children())
+            .find(|n| {
+                n.tag_name().name() == "footnote" && n.attribute((W, "id")) == Some(id.as_str())
+            });
+        let mut items = Vec::new();
+        if let Some(note) = note {
+            reader.items(note, &mut items);
+        }
+        notes.push((i.to_string(), group(items)));
+    }
+
+    // Links to bookmarks in headings go to the heading anchors
+    let mut slugger = Slugger::new();
+    let mut anchors = HashMap::new();
+    for (bookmarks, text) in &reader.headings {
+        let slug = slugger.slug(text);
+        for bookmark in bookmarks {
+            anchors.insert(format!("#{bookmark}"), format!("#{slug}"));
+        }
+    }
+    relink(&mut blocks, &anchors);
+    for (_, blocks) in &mut notes {
+        relink(blocks, &anchors);
+    }
+
+    Ok(DocxImport {
+        markdown: markdown(&blocks, &notes),
+        images: reader.images,
+    })
+}
+
+type Archive<'d> = zip::ZipArchive<std::io::Cursor<&'d [u8]>>;
+
+fn read_part(archive: &mut Archive, name: &str) -> Result<Option<String>> {
+    let mut file = match archive.by_name(name) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut xml = String::new();
+    file.read_to_string(&mut xml)?;
+    Ok(Some(xml))
+}
+
+fn parse_xml(xml: &str) -> Result<roxmltree::Document<'_>> {
+    roxmltree::Document::parse(xml.trim_start_matches('\u{feff}'))
+        .map_err(|e| Error::ParseError(e.to_string()))
+}
+
+/// The first child element with a name
+fn element<'a, 'i>(node: XmlNode<'a, 'i>, name: &str) -> Option<XmlNode<'a, 'i>> {
+    node.children().find(|n| n.tag_name().name() == name)
+}
+
+/// The `w:val` attribute of a child element
+fn value<'a>(node: XmlNode<'a, '_>, name: &str) -> Option<&'a str> {
+    element(node, name)?.attribute((W, "val"))
+}
+
+type XmlNode<'a, 'i> = roxmltree::Node<'a, 'i>;
+
+/// How a paragraph or character style is imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StyleKind {
+    Normal,
+    Heading(u8),
+    Quote,
+    Code,
+}
+
+fn style_kinds(styles: &roxmltree::Document) -> HashMap<String, StyleKind> {
+    styles
+        .root_element()
+        .children()
+        .filter(|n| n.tag_name().name() == "style")
+        .filter_map(|style| {
+            let id = style.attribute((W, "styleId"))?;
+            let name = value(style, "name").unwrap_or(id);
+            let outline = element(style, "pPr")
+                .and_then(|p| value(p, "outlineLvl"))
+                .and_then(|level| level.parse::<u8>().ok());
+            let font = element(style, "rPr")
+                .and_then(|r| element(r, "rFonts"))
+                .and_then(|f| f.attribute((W, "ascii")));
+            let kind = match (style_kind(name), outline) {
+                (StyleKind::Normal, Some(level)) if level < 6 => StyleKind::Heading(level + 1),
+                (StyleKind::Normal, _) if font.is_some_and(is_monospace) => StyleKind::Code,
+                (kind, _) => kind,
+            };
+            Some((id.to_string(), kind))
+        })
+        .collect()
+}
+
+/// Classify a style by its name, as Word names its built-in styles
+fn style_kind(name: &str) -> StyleKind {
+    let name = name.to_lowercase().replace(' ', "");
+    if let Some(level) = name
+        .strip_prefix("heading")
+        .and_then(|l| l.parse::<u8>().ok())
+    {
+        StyleKind::Heading(level.clamp(1, 6))
+    } else if name == "title" {
+        StyleKind::Heading(1)
+    } else if name.contains("quote") || name == "blocktext" {
+        StyleKind::Quote
+    } else if ["code", "source", "verbatim", "preformatted", "plaintext"]
+        .iter()
+        .any(|n| name.contains(n))
+    {
+        StyleKind::Code
+    } else {
+        StyleKind::Normal
+    }
+}
+
+fn is_monospace(font: &str) -> bool {
+    let font = font.to_lowercase();
+    [
+        "consolas",
+        "courier",
+        "menlo",
+        "monaco",
+        "lucida console",
+        "mono",
+        "code",
+    ]
+    .iter()
+    .any(|f| font.contains(f))
+}
+
+/// Whether each list level is numbered, and its first number, by
+/// numbering instance and level
+fn list_levels(numbering: &roxmltree::Document) -> HashMap<(String, usize), (bool, u64)> {
+    let root = numbering.root_element();
+    let mut abstracts: HashMap<&str, Vec<(usize, bool, u64)>> = HashMap::new();
+    for definition in root
+        .children()
+        .filter(|n| n.tag_name().name() == "abstractNum")
+    {
+        let Some(id) = definition.attribute((W, "abstractNumId")) else {
+            continue;
+        };
+        let levels = definition
+            .children()
+            .filter(|n| n.tag_name().name() == "lvl")
+            .filter_map(|level| {
+                let index = level.attribute((W, "ilvl"))?.parse().ok()?;
+                let ordered =
+                    value(level, "numFmt").is_some_and(|f| !matches!(f, "bullet" | "none"));
+                let start = value(level, "start")
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(1);
+                Some((index, ordered, start))
+            })
+            .collect();
+        abstracts.insert(id, levels);
+    }
+
+    let mut lists = HashMap::new();
+    for num in root.children().filter(|n| n.tag_name().name() == "num") {
+        let (Some(id), Some(levels)) = (
+            num.attribute((W, "numId")),
+            value(num, "abstractNumId").and_then(|a| abstracts.get(a)),
+        ) else {
+            continue;
+        };
+        for &(index, ordered, start) in levels {
+            let start = num
+                .children()
+                .filter(|n| n.tag_name().name() == "lvlOverride")
+                .find(|o| o.attribute((W, "ilvl")) == Some(index.to_string().as_str()))
+                .and_then(|o| value(o, "startOverride"))
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(start);
+            lists.insert((id.to_string(), index), (ordered, start));
+        }
+    }
+    lists
+}
+
+/// A paragraph or table read from the document body, before consecutive
+/// list items, code lines and quotes are grouped
+enum Item {
+    Block(Block),
+    ListEntry(ListEntry),
+    CodeLine(String),
+    Quoted(Block),
+}
+
+struct ListEntry {
+    level: usize,
+    num_id: String,
+    ordered: bool,
+    start: u64,
+    item: ListItem,
+}
+
+struct Reader<'r, 'd> {
+    archive: &'r mut Archive<'d>,
+    image_dir: &'r str,
+    styles: HashMap<String, StyleKind>,
+    lists: HashMap<(String, usize), (bool, u64)>,
+    /// Relationship targets by id, and whether they are external
+    relationships: HashMap<String, (String, bool)>,
+    /// Footnote ids in order of first reference
+    footnotes: Vec<String>,
+    images: Vec<(String, Vec<u8>)>,
+    /// Bookmarks in the paragraph being read
+    bookmarks: Vec<String>,
+    /// Bookmarks and text of each heading
+    headings: Vec<(Vec<String>, String)>,
+}
+
+impl Reader<'_, '_> {
+    fn style(&self, id: &str) -> StyleKind {
+        self.styles
+            .get(id)
+            .copied()
+            .unwrap_or_else(|| style_kind(id))
+    }
+
+    fn items(&mut self, parent: XmlNode, out: &mut Vec<Item>) {
+        for child in parent.children().filter(|n| n.is_element()) {
+            match child.tag_name().name() {
+                "p" => out.extend(self.paragraph(child)),
+                "tbl" => out.push(Item::Block(self.table(child))),
+                "sdt" => {
+                    if let Some(content) = element(child, "sdtContent") {
+                        self.items(content, out);
+                    }
+                }
+                "customXml" | "ins" | "smartTag" => self.items(child, out),
+                _ => {}
+            }
+        }
+    }
+
+    fn paragraph(&mut self, paragraph: XmlNode) -> Option<Item> {
+        let properties = element(paragraph, "pPr");
+        let kind = match properties
+            .and_then(|p| value(p, "outlineLvl"))
+            .and_then(|l| l.parse::<u8>().ok())
+        {
+            Some(level) if level < 6 => StyleKind::Heading(level + 1),
+            _ => properties
+                .and_then(|p| value(p, "pStyle"))
+                .map_or(StyleKind::Normal, |style| self.style(style)),
+        };
+        self.bookmarks.clear();
+        let inlines = self.runs(paragraph);
+        if kind == StyleKind::Code {
+            return Some(Item::CodeLine(plain(&inlines)));
+        }
+        let mut inlines = clean(inlines);
+
+        if let StyleKind::Heading(level) = kind {
+            self.headings
+                .push((std::mem::take(&mut self.bookmarks), plain(&inlines)));
+            return Some(Item::Block(Block::Heading(level, inlines)));
+        }
+        let numbering = properties.and_then(|p| element(p, "numPr")).and_then(|n| {
+            let num_id = value(n, "numId").filter(|&id| id != "0")?;
+            let level = value(n, "ilvl").and_then(|l| l.parse().ok()).unwrap_or(0);
+            Some((num_id.to_string(), level))
+        });
+        if let Some((num_id, level)) = numbering {
+            let (ordered, start) = self
+                .lists
+                .get(&(num_id.clone(), level))
+                .copied()
+                .unwrap_or((false, 1));
+            let task = task_marker(&mut inlines);
+            let blocks = if inlines.is_empty() {
+                Vec::new()
+            } else {
+                vec![Block::Paragraph(inlines)]
+            };
+            return Some(Item::ListEntry(ListEntry {
+                level,
+                num_id,
+                ordered,
+                start,
+                item: ListItem { task, blocks },
+            }));
+        }
+        if inlines.is_empty() {
+            // A bottom border alone is a horizontal rule
+            let border = properties
+                .and_then(|p| element(p, "pBdr"))
+                .and_then(|b| element(b, "bottom"));
+            return border.map(|_| Item::Block(Block::Rule));
+        }
+        Some(match kind {
+            StyleKind::Quote => Item::Quoted(Block::Paragraph(inlines)),
+            _ => Item::Block(Block::Paragraph(inlines)),
+        })
+    }
+
+    /// Inline content of a paragraph, hyperlink or other run container
+    fn runs(&mut self, parent: XmlNode) -> Vec<Inline> {
+        let mut out = Vec::new();
+        // Open fields: the instruction, and the result once it starts
+        let mut fields: Vec<(String, Option<Vec<Inline>>)> = Vec::new();
+        for child in parent.children().filter(|n| n.is_element()) {
+            let inlines = match child.tag_name().name() {
+                "r" => {
+                    if let Some(field) = element(child, "fldChar") {
+                        match field.attribute((W, "fldCharType")) {
+                            Some("begin") => fields.push((String::new(), None)),
+                            Some("separate") => {
+                                if let Some((_, result)) = fields.last_mut() {
+                                    *result = Some(Vec::new());
+                                }
+                            }
+                            Some("end") => {
+                                if let Some((instruction, result)) = fields.pop() {
+                                    let result =
+                                        field_result(&instruction, result.unwrap_or_default());
+                                    emit(&mut fields, &mut out, result);
+                                }
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if let Some(instruction) = element(child, "instrText") {
+                        if let Some((text, _)) = fields.last_mut() {
+                            text.push_str(instruction.text().unwrap_or(""));
+                        }
+                        continue;
+                    }
+                    self.run(child)
+                }
+                "hyperlink" => {
+                    let children = self.runs(child);
+                    let url = child
+                        .attribute((RELATIONSHIPS, "id"))
+                        .and_then(|id| self.relationships.get(id))
+                        .map(|(target, _)| target.clone())
+                        .or_else(|| child.attribute((W, "anchor")).map(|a| format!("#{a}")));
+                    match url {
+                        Some(url) => vec![Inline::Link { url, children }],
+                        None => children,
+                    }
+                }
+                "fldSimple" => {
+                    let children = self.runs(child);
+                    field_result(child.attribute((W, "instr")).unwrap_or(""), children)
+                }
+                "ins" | "smartTag" | "customXml" | "moveTo" => self.runs(child),
+                "sdt" => element(child, "sdtContent")
+                    .map(|c| self.runs(c))
+                    .unwrap_or_default(),
+                "bookmarkStart" => {
+                    if let Some(name) = child.attribute((W, "name")) {
+                        self.bookmarks.push(name.to_string());
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            emit(&mut fields, &mut out, inlines);
+        }
+        out
+    }
+
+    fn run(&mut self, run: XmlNode) -> Vec<Inline> {
+        let properties = element(run, "rPr");
+        let on = |name: &str| {
+            properties.and_then(|p| element(p, name)).is_some_and(|e| {
+                !matches!(
+                    e.attribute((W, "val")),
+                    Some("0" | "false" | "off" | "none")
+                )
+            })
+        };
+        let (bold, italic, strike) = (on("b"), on("i"), on("strike") || on("dstrike"));
+        let code = properties.is_some_and(|p| {
+            value(p, "rStyle").is_some_and(|style| self.style(style) == StyleKind::Code)
+                || element(p, "rFonts")
+                    .and_then(|f| f.attribute((W, "ascii")))
+                    .is_some_and(is_monospace)
+        });
+        let format = |text: String| {
+            if code {
+                return Inline::Code(text);
+            }
+            let mut inline = Inline::Text(text);
+            if strike {
+                inline = Inline::Strike(vec![inline]);
+            }
+            if italic {
+                inline = Inline::Emphasis(vec![inline]);
+            }
+            if bold {
+                inline = Inline::Strong(vec![inline]);
+            }
+            inline
+        };
+
+        let mut out = Vec::new();
+        let mut text = String::new();
+        for child in run.children().filter(|n| n.is_element()) {
+            match child.tag_name().name() {
+                "t" => text.push_str(child.text().unwrap_or("")),
+                "tab" => text.push('\t'),
+                "noBreakHyphen" => text.push('-'),
+                name => {
+                    let inline = match name {
+                        // Page and column breaks are dropped
+                        "br" | "cr"
+                            if child
+                                .attribute((W, "type"))
+                                .is_none_or(|t| t == "textWrapping") =>
+                        {
+                            Some(Inline::Break)
+                        }
+                        "drawing" | "pict" => self.image(child),
+                        "footnoteReference" => child.attribute((W, "id")).map(|id| {
+                            let index = match self.footnotes.iter().position(|f| f == id) {
+                                Some(index) => index,
+                                None => {
+                                    self.footnotes.push(id.to_string());
+                                    self.footnotes.len() - 1
+                                }
+                            };
+                            Inline::Footnote((index + 1).to_string())
+                        }),
+                        _ => None,
+                    };
+                    if let Some(inline) = inline {
+                        if !text.is_empty() {
+                            out.push(format(std::mem::take(&mut text)));
+                        }
+                        out.push(inline);
+                    }
+                }
+            }
+        }
+        if !text.is_empty() {
+            out.push(format(text));
+        }
+        out
+    }
+
+    /// An embedded image, extracted for the image folder
+    fn image(&mut self, drawing: XmlNode) -> Option<Inline> {
+        let blip = drawing
+            .descendants()
+            .find(|n| matches!(n.tag_name().name(), "blip" | "imagedata"))?;
+        let id = blip
+            .attribute((RELATIONSHIPS, "embed"))
+            .or_else(|| blip.attribute((RELATIONSHIPS, "id")))
+            .or_else(|| blip.attribute((RELATIONSHIPS, "link")))?;
+        let alt = drawing
+            .descendants()
+            .find(|n| n.tag_name().name() == "docPr")
+            .and_then(|d| {
+                d.attribute("descr")
+                    .filter(|d| !d.is_empty())
+                    .or(d.attribute("title"))
+            })
+            .unwrap_or("")
+            .to_string();
+        let (target, external) = self.relationships.get(id)?.clone();
+        if external {
+            return Some(Inline::Image { url: target, alt });
+        }
+
+        let part = part_name(&target);
+        let name = part.rsplit('/').next().unwrap_or(&part).to_string();
+        if !self.images.iter().any(|(n, _)| *n == name) {
+            let mut file = self.archive.by_name(&part).ok()?;
+            let mut data = Vec::new();
+            file.read_to_end(&mut data).ok()?;
+            self.images.push((name.clone(), data));
+        }
+        let url = if self.image_dir.is_empty() {
+            name
+        } else {
+            format!("{}/{name}", self.image_dir.trim_end_matches('/'))
+        };
+        Some(Inline::Image { url, alt })
+    }
+
+    fn table(&mut self, table: XmlNode) -> Block {
+        let mut aligns = Vec::new();
+        let mut rows = Vec::new();
+        for (i, row) in table
+            .children()
+            .filter(|n| n.tag_name().name() == "tr")
+            .enumerate()
+        {
+            let mut cells = Vec::new();
+            for cell in row.children().filter(|n| n.tag_name().name() == "tc") {
+                if i == 0 {
+                    let align = element(cell, "p")
+                        .and_then(|p| element(p, "pPr"))
+                        .and_then(|p| value(p, "jc"));
+                    aligns.push(match align {
+                        Some("left" | "start") => Align::Left,
+                        Some("center") => Align::Center,
+                        Some("right" | "end") => Align::Right,
+                        _ => Align::None,
+                    });
+                }
+                let mut items = Vec::new();
+                self.items(cell, &mut items);
+                let mut content = clean(flatten(&group(items)));
+                if i == 0 {
+                    // Header cells are bold in Markdown already
+                    content = content
+                        .into_iter()
+                        .flat_map(|inline| match inline {
+                            Inline::Strong(children) => children,
+                            inline => vec![inline],
+                        })
+                        .collect();
+                }
+                cells.push(content);
+            }
+            rows.push(cells);
+        }
+        Block::Table { aligns, rows }
+    }
+}
+
+/// Put inline content into the innermost field result, or the output
+/// when no field is open; field instructions are dropped
+fn emit(fields: &mut [(String, Option<Vec<Inline>>)], out: &mut Vec<Inline>, inlines: Vec<Inline>) {
+    match fields.last_mut() {
+        Some((_, Some(result))) => result.extend(inlines),
+        Some((_, None)) => {}
+        None => out.extend(inlines),
+    }
+}
+
+/// The result of a field, linked when the field is a `HYPERLINK`
+fn field_result(instruction: &str, result: Vec<Inline>) -> Vec<Inline> {
+    let Some(arguments) = instruction.trim().strip_prefix("HYPERLINK") else {
+        return result;
+    };
+    let Some(target) = arguments.split('"').nth(1) else {
+        return result;
+    };
+    let url = if arguments.trim_start().starts_with("\\l") {
+        format!("#{target}")
+    } else {
+        target.to_string()
+    };
+    vec![Inline::Link {
+        url,
+        children: result,
+    }]
+}
+
+/// Package part of a target relative to `word/document.xml`
+fn part_name(target: &str) -> String {
+    let path = match target.strip_prefix('/') {
+        Some(absolute) => absolute.to_string(),
+        None => format!("word/{target}"),
+    };
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            ".." => {
+                parts.pop();
+            }
+            "." | "" => {}
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// Take a task box (☐ or ☒) off the start of a list item
+fn task_marker(inlines: &mut Vec<Inline>) -> Option<bool> {
+    let Some(Inline::Text(text)) = inlines.first_mut() else {
+        return None;
+    };
+    let marker = text.chars().next()?;
+    let checked = match marker {
+        '☐' => false,
+        '☒' | '☑' => true,
+        _ => return None,
+    };
+    *text = text[marker.len_utf8()..].trim_start().to_string();
+    if text.is_empty() {
+        inlines.remove(0);
+    }
+    Some(checked)
+}
+
+/// Group consecutive list entries into nested lists, and code lines and
+/// quoted paragraphs into blocks
+fn group(items: Vec<Item>) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut items = items.into_iter().peekable();
+    while let Some(item) = items.next() {
+        match item {
+            Item::Block(block) => blocks.push(block),
+            Item::CodeLine(line) => {
+                let mut lines = vec![line];
+                while let Some(Item::CodeLine(line)) =
+                    items.next_if(|i| matches!(i, Item::CodeLine(_)))
+                {
+                    lines.push(line);
+                }
+                let code = lines.join("\n");
+                blocks.push(Block::Code {
+                    language: String::new(),
+                    code: code.trim_matches('\n').to_string(),
+                });
+            }
+            Item::Quoted(block) => {
+                let mut quoted = vec![block];
+                while let Some(Item::Quoted(block)) =
+                    items.next_if(|i| matches!(i, Item::Quoted(_)))
+                {
+                    quoted.push(block);
+                }
+                blocks.push(Block::Quote(quoted));
+            }
+            Item::ListEntry(entry) => {
+                let mut entries = vec![entry];
+                while let Some(Item::ListEntry(entry)) =
+                    items.next_if(|i| matches!(i, Item::ListEntry(_)))
+                {
+                    entries.push(entry);
+                }
+                blocks.extend(nest_lists(entries));
+            }
+        }
+    }
+    blocks
+}
+
+/// Lists from entries in document order, deeper levels nested in the item
+/// before them
+fn nest_lists(entries: Vec<ListEntry>) -> Vec<Block> {
+    let Some(base) = entries.first().map(|e| e.level) else {
+        return Vec::new();
+    };
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut list_id = String::new();
+    let mut nested = Vec::new();
+    let flush = |blocks: &mut Vec<Block>, nested: &mut Vec<ListEntry>| {
+        if let Some(Block::List { items, .. }) = blocks.last_mut()
+            && let Some(item) = items.last_mut()
+            && !nested.is_empty()
+        {
+            item.blocks.extend(nest_lists(std::mem::take(nested)));
+        }
+    };
+    for entry in entries {
+        if entry.level > base {
+            nested.push(entry);
+            continue;
+        }
+        flush(&mut blocks, &mut nested);
+        match blocks.last_mut() {
+            Some(Block::List { items, .. }) if entry.num_id == list_id => items.push(entry.item),
+            _ => {
+                list_id = entry.num_id;
+                blocks.push(Block::List {
+                    ordered: entry.ordered,
+                    start: entry.start,
+                    items: vec![entry.item],
+                });
+            }
+        }
+    }
+    flush(&mut blocks, &mut nested);
+    blocks
+}
+
+/// Point links at heading anchors instead of Word bookmarks
+fn relink(blocks: &mut [Block], anchors: &HashMap<String, String>) {
+    fn inlines(content: &mut [Inline], anchors: &HashMap<String, String>) {
+        for inline in content {
+            match inline {
+                Inline::Link { url, children } => {
+                    if let Some(anchor) = anchors.get(url.as_str()) {
+                        *url = anchor.clone();
+                    }
+                    inlines(children, anchors);
+                }
+                Inline::Emphasis(c) | Inline::Strong(c) | Inline::Strike(c) => inlines(c, anchors),
+                _ => {}
+            }
+        }
+    }
+    for block in blocks {
+        match block {
+            Block::Heading(_, content) | Block::Paragraph(content) => inlines(content, anchors),
+            Block::Quote(blocks) => relink(blocks, anchors),
+            Block::List { items, .. } => {
+                for item in items {
+                    relink(&mut item.blocks, anchors);
+                }
+            }
+            Block::Table { rows, .. } => {
+                for cell in rows.iter_mut().flatten() {
+                    inlines(cell, anchors);
+                }
+            }
+            Block::Code { .. } | Block::Rule => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn parts(docx: &[u8]) -> HashMap<String, String> {
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(docx)).unwrap();
+        let mut parts = HashMap::new();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).unwrap();
+            let mut content = Vec::new();
+            file.read_to_end(&mut content).unwrap();
+            parts.insert(
+                file.name().to_string(),
+                String::from_utf8_lossy(&content).into_owned(),
+            );
+        }
+        parts
+    }
+
+    #[test]
+    fn test_docx_structure() {
+        let text = "# Report\n\nSome *em*, **strong**, ~~old~~ and `code` with a \
+                    [link](https://example.com) and note[^1].\n\n\
+                    - one\n  - nested\n- [x] done\n\n3. three\n4. four\n\n\
+                    | Name | Size |\n| :--- | ---: |\n| a | 1 |\n\n\
+                    > quoted\n\n```rust\nfn main() {}\n```\n\n[^1]: The **note**.\n";
+        let parts = parts(&render_docx(text, None).unwrap());
+        for name in [
+            "[Content_Types].xml",
+            "_rels/.rels",
+            "word/_rels/document.xml.rels",
+            "word/styles.xml",
+            "word/numbering.xml",
+        ] {
+            assert!(parts.contains_key(name), "{name}");
+        }
+
+        let document = &parts["word/document.xml"];
+        assert!(document.contains(
+            "<w:pStyle w:val=\"Heading1\"/></w:pPr><w:bookmarkStart w:id=\"0\" w:name=\"report\"/>"
+        ));
+        assert!(
+            document
+                .contains("<w:r><w:rPr><w:i/></w:rPr><w:t xml:space=\"preserve\">em</w:t></w:r>")
+        );
+        assert!(document.contains("<w:rPr><w:b/></w:rPr><w:t xml:space=\"preserve\">strong</w:t>"));
+        assert!(
+            document.contains("<w:rPr><w:strike/></w:rPr><w:t xml:space=\"preserve\">old</w:t>")
+        );
+        assert!(document.contains(
+            "<w:rStyle w:val=\"VerbatimChar\"/></w:rPr><w:t xml:space=\"preserve\">code</w:t>"
+        ));
+        assert!(
+            document
+                .contains("<w:hyperlink r:id=\"rId4\"><w:r><w:rPr><w:rStyle w:val=\"Hyperlink\"/>")
+        );
+        assert!(document.contains("<w:footnoteReference w:id=\"1\"/>"));
+        assert!(
+            document.contains("<w:numPr><w:ilvl w:val=\"1\"/><w:numId w:val=\"2\"/></w:numPr>")
+        );
+        assert!(document.contains("<w:t xml:space=\"preserve\">☒ </w:t>"));
+        assert!(document.contains("<w:jc w:val=\"right\"/>"));
+        assert!(document.contains("<w:pStyle w:val=\"Quote\"/>"));
+        assert!(document.contains(
+            "<w:pStyle w:val=\"SourceCode\"/></w:pPr><w:r><w:t xml:space=\"preserve\">fn main() {}</w:t></w:r></w:p>"
+        ));
+        assert!(!document.contains("The "));
+
+        assert!(parts["word/_rels/document.xml.rels"].contains(
+            "Id=\"rId4\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink\" \
+             Target=\"https://example.com\" TargetMode=\"External\""
+        ));
+        assert!(parts["word/numbering.xml"].contains(
+            "<w:num w:numId=\"3\"><w:abstractNumId w:val=\"1\"/><w:lvlOverride w:ilvl=\"0\"><w:startOverride w:val=\"3\"/>"
+        ));
+        assert!(parts["word/footnotes.xml"].contains(
+            "<w:footnoteRef/></w:r><w:r><w:t xml:space=\"preserve\"> </w:t></w:r>\
+             <w:r><w:t xml:space=\"preserve\">The </w:t></w:r><w:r><w:rPr><w:b/></w:rPr>"
+        ));
+    }
+
+    #[test]
+    fn test_docx_embeds_local_images() {
+        let dir = std::env::temp_dir().join(format!("rmde-docx-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend_from_slice(&100u32.to_be_bytes());
+        png.extend_from_slice(&50u32.to_be_bytes());
+        std::fs::write(dir.join("chart.png"), &png).unwrap();
+
+        let text = "![A chart](chart.png) ![remote](https://example.com/x.png)\n";
+        let parts = parts(&render_docx(text, Some(&dir)).unwrap());
+        assert_eq!(
+            parts["word/media/image1.png"],
+            String::from_utf8_lossy(&png)
+        );
+        let document = &parts["word/document.xml"];
+        assert!(document.contains("<wp:extent cx=\"952500\" cy=\"476250\"/>"));
+        assert!(document.contains("descr=\"A chart\""));
+        assert!(document.contains("<a:blip r:embed=\"rId4\"/>"));
+        assert!(document.contains("<w:t xml:space=\"preserve\">remote</w:t>"));
+        assert!(parts["word/_rels/document.xml.rels"].contains("Target=\"media/image1.png\""));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_docx_round_trip() {
+        let dir = std::env::temp_dir().join(format!("rmde-docx-round-trip-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("images")).unwrap();
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend_from_slice(&[0, 0, 0, 10, 0, 0, 0, 10]);
+        std::fs::write(dir.join("images/image1.png"), &png).unwrap();
+
+        let text = "# Report\n\n\
+                    Some *em*, **strong**, ~~old~~ and `code` with a [link](https://example.com), \
+                    [a jump](#report) and a note[^1].\n\n\
+                    - one\n  - nested\n- [x] done\n\n\
+                    3. three\n4. four\n\n\
+                    | Name | Size |\n| :--- | ---: |\n| a    | 1    |\n\n\
+                    > quoted\n\n\
+                    ```\nfn main() {\n    run();\n}\n```\n\n\
+                    ---\n\n\
+                    ![A chart](images/image1.png)\n\n\
+                    [^1]: The **note**.\n";
+        let docx = render_docx(text, Some(&dir)).unwrap();
+        let imported = import_docx(&docx, "images").unwrap();
+        assert_eq!(imported.markdown, text);
+        assert_eq!(imported.images, vec![("image1.png".to_string(), png)]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_import_word_styles_and_fields() {
+        let document = format!(
+            "<w:document {NAMESPACES}><w:body>\
+             <w:p><w:pPr><w:pStyle w:val=\"berschrift1\"/></w:pPr><w:bookmarkStart w:id=\"0\" w:name=\"_Toc1\"/>\
+             <w:r><w:t>Intro</w:t></w:r></w:p>\
+             <w:p><w:r><w:t xml:space=\"preserve\">See </w:t></w:r>\
+             <w:r><w:fldChar w:fldCharType=\"begin\"/></w:r><w:r><w:instrText> HYPERLINK \"https://example.com\" </w:instrText></w:r>\
+             <w:r><w:fldChar w:fldCharType=\"separate\"/></w:r><w:r><w:t>the site</w:t></w:r><w:r><w:fldChar w:fldCharType=\"end\"/></w:r>\
+             <w:r><w:t xml:space=\"preserve\"> or </w:t></w:r>\
+             <w:hyperlink w:anchor=\"_Toc1\"><w:r><w:t>the intro</w:t></w:r></w:hyperlink>\
+             <w:r><w:rPr><w:rFonts w:ascii=\"Courier New\"/></w:rPr><w:t xml:space=\"preserve\"> x = 1</w:t></w:r>\
+             <w:r><w:br w:type=\"page\"/><w:t>.</w:t></w:r></w:p>\
+             <w:p><w:pPr><w:numPr><w:ilvl w:val=\"0\"/><w:numId w:val=\"5\"/></w:numPr></w:pPr><w:r><w:t>First</w:t></w:r></w:p>\
+             <w:p><w:pPr><w:numPr><w:ilvl w:val=\"0\"/><w:numId w:val=\"5\"/></w:numPr></w:pPr>\
+             <w:r><w:rPr><w:b/></w:rPr><w:t>Sec</w:t></w:r><w:r><w:rPr><w:b/><w:i w:val=\"0\"/></w:rPr><w:t>ond</w:t></w:r></w:p>\
+             </w:body></w:document>"
+        );
+        let styles = "<w:styles xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\">\
+                      <w:style w:type=\"paragraph\" w:styleId=\"berschrift1\"><w:name w:val=\"heading 1\"/></w:style></w:styles>";
+        let numbering = "<w:numbering xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\">\
+                         <w:abstractNum w:abstractNumId=\"2\"><w:lvl w:ilvl=\"0\"><w:start w:val=\"1\"/><w:numFmt w:val=\"decimal\"/></w:lvl></w:abstractNum>\
+                         <w:num w:numId=\"5\"><w:abstractNumId w:val=\"2\"/></w:num></w:numbering>";
+
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, data) in [
+            ("word/document.xml", document.as_str()),
+            ("word/styles.xml", styles),
+            ("word/numbering.xml", numbering),
+        ] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data.as_bytes()).unwrap();
+        }
+        let docx = zip.finish().unwrap().into_inner();
+
+        assert_eq!(
+            import_docx(&docx, "").unwrap().markdown,
+            "# Intro\n\nSee [the site](https://example.com) or [the intro](#intro) `x = 1`.\n\n\
+             1. First\n2. **Second**\n"
+        );
+        assert!(matches!(import_docx(b"not a zip", ""), Err(Error::Zip(_))));
+    }
+}
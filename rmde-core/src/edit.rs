@@ -0,0 +1,116 @@
+use std::ops::Range;
+
+/// A replacement of a byte range with new text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    /// Byte range in the document before the edit
+    pub range: Range<usize>,
+    /// Text inserted in place of the range
+    pub text: String,
+}
+
+impl TextEdit {
+    /// Create an edit replacing a byte range
+    pub fn new(range: Range<usize>, text: impl Into<String>) -> Self {
+        Self {
+            range,
+            text: text.into(),
+        }
+    }
+
+    /// Create an edit inserting text at a position
+    pub fn insert(pos: usize, text: impl Into<String>) -> Self {
+        Self::new(pos..pos, text)
+    }
+
+    /// Create an edit deleting a byte range
+    pub fn delete(range: Range<usize>) -> Self {
+        Self::new(range, String::new())
+    }
+
+    /// Change in document length caused by this edit
+    pub fn delta(&self) -> isize {
+        self.text.len() as isize - self.range.len() as isize
+    }
+}
+
+/// Sort edits by position and drop any that overlap an earlier one
+pub(crate) fn normalize_edits(mut edits: Vec<TextEdit>) -> Vec<TextEdit> {
+    edits.sort_by_key(|e| (e.range.start, e.range.end));
+    let mut out: Vec<TextEdit> = Vec::with_capacity(edits.len());
+    for edit in edits {
+        if let Some(last) = out.last()
+            && edit.range.start < last.range.end
+        {
+            continue;
+        }
+        out.push(edit);
+    }
+    out
+}
+
+/// Map a byte position through a set of sorted, non-overlapping edits.
+/// Positions inside a replaced range move to the end of its replacement;
+/// positions at an insertion point move after the inserted text.
+pub(crate) fn map_position(pos: usize, edits: &[TextEdit]) -> usize {
+    let mut shift: isize = 0;
+    for edit in edits {
+        if edit.range.end <= pos {
+            shift += edit.delta();
+        } else if edit.range.start < pos {
+            return (edit.range.start as isize + shift) as usize + edit.text.len();
+        } else {
+            break;
+        }
+    }
+    (pos as isize + shift) as usize
+}
+
+/// Text after applying sorted, non-overlapping edits
+pub(crate) fn apply(text: &str, edits: &[TextEdit]) -> String {
+    let mut out = text.to_string();
+    for edit in edits.iter().rev() {
+        out.replace_range(edit.range.clone(), &edit.text);
+    }
+    out
+}
+
+/// Text after applying edits in any order, for checking the edits a
+/// function returns
+#[cfg(test)]
+pub(crate) fn edited(text: &str, edits: Vec<TextEdit>) -> String {
+    apply(text, &normalize_edits(edits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_position_after_insert() {
+        let edits = vec![TextEdit::insert(2, "abc")];
+        assert_eq!(map_position(1, &edits), 1);
+        assert_eq!(map_position(2, &edits), 5);
+        assert_eq!(map_position(4, &edits), 7);
+    }
+
+    #[test]
+    fn test_map_position_inside_delete() {
+        let edits = vec![TextEdit::delete(2..6)];
+        assert_eq!(map_position(2, &edits), 2);
+        assert_eq!(map_position(4, &edits), 2);
+        assert_eq!(map_position(8, &edits), 4);
+    }
+
+    #[test]
+    fn test_normalize_drops_overlaps() {
+        let edits = normalize_edits(vec![
+            TextEdit::delete(5..8),
+            TextEdit::insert(0, "x"),
+            TextEdit::delete(6..9),
+        ]);
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits[0].range, 0..0);
+        assert_eq!(edits[1].range, 5..8);
+    }
+}
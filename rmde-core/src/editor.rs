@@ -1,8 +1,31 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
+use crate::anchors::{self, AnchorCompletion, HeadingRename};
 use crate::document::Document;
+use crate::docx;
+use crate::edit::{self, TextEdit};
+use crate::encoding;
+use crate::epub::{self, EpubOptions, EpubSource};
 use crate::error::{Error, Result};
-use crate::{DocumentId, Selection, TabInfo};
+use crate::html::HtmlOptions;
+use crate::import::html_to_markdown;
+use crate::links;
+use crate::prose::{Glossary, ProseRules};
+use crate::save::write_atomic;
+use crate::site::{self, SiteOptions, SiteReport};
+use crate::slides::SlideOptions;
+use crate::slug::slugify;
+use crate::spell::{Misspelling, SpellChecker};
+use crate::stats::{DocumentStats, StatsOptions, TextStats};
+use crate::text::{line_of, line_starts};
+use crate::wiki::{self, Backlink, NoteIndex};
+use crate::writing::{GoalProgress, GoalStorage, WritingHistory, WritingSession, WritingStats};
+use crate::{
+    Date, Diagnostic, Dictionary, DocumentId, Encoding, FrontMatterValue, IndentSettings,
+    InvisibleChar, LineEnding, LineEndingCounts, LinkTarget, NormalizationForm, Selection, TabInfo,
+    TaskIndex, TaskSource, TocEntry, WrapSettings,
+};
 
 /// The main editor state, managing multiple documents as tabs
 pub struct Editor {
@@ -10,6 +33,20 @@ pub struct Editor {
     documents: Vec<Document>,
     /// Index of the active document
     active_idx: usize,
+    /// Notes and wiki-links of the workspace folder, if one is open
+    notes: Option<NoteIndex>,
+    /// Document revisions last pushed into the note index
+    synced_revisions: HashMap<DocumentId, u64>,
+    /// What document statistics count
+    stats_options: StatsOptions,
+    /// Words written since the editor started
+    session: WritingSession,
+    /// Words written per day
+    writing_history: WritingHistory,
+    /// Dictionary and personal word list shared by all documents
+    spell: SpellChecker,
+    /// Style checks run on prose
+    prose_rules: ProseRules,
 }
 
 impl Editor {
@@ -18,6 +55,13 @@ impl Editor {
         Self {
             documents: vec![Document::new()],
             active_idx: 0,
+            notes: None,
+            synced_revisions: HashMap::new(),
+            stats_options: StatsOptions::default(),
+            session: WritingSession::default(),
+            writing_history: WritingHistory::default(),
+            spell: SpellChecker::default(),
+            prose_rules: ProseRules::default(),
         }
     }
 
@@ -49,9 +93,45 @@ impl Editor {
         Ok(id)
     }
 
+    /// Open HTML converted to Markdown in a new untitled tab
+    pub fn import_html(&mut self, html: &str) -> DocumentId {
+        let doc = Document::from_text(&html_to_markdown(html));
+        let id = doc.id();
+        self.documents.push(doc);
+        self.active_idx = self.documents.len() - 1;
+        id
+    }
+
+    /// Open a `.docx` file converted to Markdown in a new untitled tab.
+    /// Its images are written to a `<name>_images` folder next to it.
+    pub fn import_docx(&mut self, path: &Path) -> Result<DocumentId> {
+        let data = std::fs::read(path)?;
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let image_dir = path.with_file_name(format!("{stem}_images"));
+        let imported = docx::import_docx(&data, &image_dir.to_string_lossy())?;
+        if !imported.images.is_empty() {
+            std::fs::create_dir_all(&image_dir)?;
+            for (name, data) in &imported.images {
+                std::fs::write(image_dir.join(name), data)?;
+            }
+        }
+        let doc = Document::from_text(&imported.markdown);
+        let id = doc.id();
+        self.documents.push(doc);
+        self.active_idx = self.documents.len() - 1;
+        Ok(id)
+    }
+
     /// Close a tab by ID, returns true if closed
     pub fn close_tab(&mut self, id: DocumentId) -> bool {
         if let Some(idx) = self.find_doc_index(id) {
+            self.collect_writing_activity();
+            // Unsaved edits no longer count for the note index
+            self.synced_revisions.remove(&id);
+            if let (Some(notes), Some(path)) = (&mut self.notes, self.documents[idx].path()) {
+                let _ = notes.reload(path);
+            }
+
             // Don't close the last document
             if self.documents.len() == 1 {
                 // Replace with new empty document
@@ -134,20 +214,391 @@ impl Editor {
         self.documents.len()
     }
 
+    /// Get the workspace root folder
+    pub fn workspace_root(&self) -> Option<&Path> {
+        self.notes.as_ref().map(|n| n.root())
+    }
+
+    /// Set (or clear) the workspace root folder, indexing its notes
+    pub fn set_workspace_root(&mut self, root: Option<PathBuf>) -> Result<()> {
+        self.notes = root.map(NoteIndex::build).transpose()?;
+        self.synced_revisions.clear();
+        Ok(())
+    }
+
+    /// Pick up workspace files added, changed or removed on disk.
+    /// Returns the re-indexed paths.
+    pub fn refresh_workspace(&mut self) -> Result<Vec<PathBuf>> {
+        let changed = match &mut self.notes {
+            Some(notes) => notes.refresh()?,
+            None => Vec::new(),
+        };
+        self.sync_notes();
+        Ok(changed)
+    }
+
+    /// Notes in the workspace that wiki-link to the active document
+    pub fn backlinks(&mut self) -> Result<Vec<Backlink>> {
+        self.sync_notes();
+        let doc = self.active().ok_or(Error::NoActiveDocument)?;
+        let (Some(notes), Some(path)) = (&self.notes, doc.path()) else {
+            return Ok(Vec::new());
+        };
+        Ok(notes.backlinks(path))
+    }
+
+    /// Note names completing an unclosed `[[` before the primary cursor
+    pub fn wiki_completions(&mut self) -> Result<Vec<String>> {
+        self.sync_notes();
+        let doc = self.active().ok_or(Error::NoActiveDocument)?;
+        let text = doc.content();
+        let Some(prefix) = wiki::completion_prefix(&text, doc.primary_selection().head) else {
+            return Ok(Vec::new());
+        };
+        Ok(self
+            .notes
+            .as_ref()
+            .map(|n| n.complete(prefix))
+            .unwrap_or_default())
+    }
+
+    /// Re-read the active document's file into the note index after a save
+    fn reload_active_note(&mut self) -> Result<()> {
+        let path = self.active().and_then(|d| d.path()).cloned();
+        if let (Some(notes), Some(path)) = (&mut self.notes, path)
+            && path.starts_with(notes.root())
+        {
+            notes.reload(&path)?;
+        }
+        Ok(())
+    }
+
+    /// Push edited open documents inside the workspace into the note index
+    fn sync_notes(&mut self) {
+        let Some(notes) = &mut self.notes else {
+            return;
+        };
+        for doc in &self.documents {
+            let Some(path) = doc.path() else { continue };
+            if !path.starts_with(notes.root()) || !doc.is_dirty() {
+                continue;
+            }
+            if self.synced_revisions.get(&doc.id()) != Some(&doc.revision()) {
+                notes.update_text(path, &doc.content());
+                self.synced_revisions.insert(doc.id(), doc.revision());
+            }
+        }
+    }
+
+    /// Build a task index over the workspace folder and all open documents.
+    /// Open documents take precedence over their files on disk.
+    pub fn task_index(&self) -> Result<TaskIndex> {
+        let mut index = TaskIndex::new();
+        if let Some(root) = self.workspace_root() {
+            index.index_folder(root)?;
+        }
+        for doc in &self.documents {
+            index.update(task_source(doc), &doc.content());
+        }
+        Ok(index)
+    }
+
+    /// Follow the link under the primary cursor of the active document.
+    /// Local files are opened (or switched to) and anchors jumped to;
+    /// external URLs are returned for the caller to open.
+    pub fn follow_link_at_cursor(&mut self) -> Result<Option<LinkTarget>> {
+        let doc = self.active().ok_or(Error::NoActiveDocument)?;
+        let pos = doc.primary_selection().head;
+        let target = match doc.link_target_at(pos, self.workspace_root()) {
+            Some(target) => target,
+            None => match self.wiki_link_target_at(pos) {
+                Some(target) => target,
+                None => return Ok(None),
+            },
+        };
+
+        match &target {
+            LinkTarget::External(_) => {}
+            LinkTarget::Anchor(anchor) => {
+                self.active_mut()
+                    .ok_or(Error::NoActiveDocument)?
+                    .jump_to_anchor(anchor);
+            }
+            LinkTarget::File { path, anchor } => {
+                self.open_file(path)?;
+                if let Some(anchor) = anchor {
+                    self.active_mut()
+                        .ok_or(Error::NoActiveDocument)?
+                        .jump_to_anchor(anchor);
+                }
+            }
+        }
+        Ok(Some(target))
+    }
+
+    /// Resolve a wiki-link at a position in the active document
+    fn wiki_link_target_at(&mut self, pos: usize) -> Option<LinkTarget> {
+        self.sync_notes();
+        let doc = self.active()?;
+        let link = wiki::parse_wiki_links(&doc.content())
+            .into_iter()
+            .find(|l| l.range.start <= pos && pos <= l.range.end)?;
+        let anchor = link.heading.as_deref().map(slugify);
+        if link.target.is_empty() {
+            return anchor.map(LinkTarget::Anchor);
+        }
+        let path = self
+            .notes
+            .as_ref()?
+            .resolve(&link.target, doc.path().map(|p| p.as_path()))?;
+        Some(LinkTarget::File { path, anchor })
+    }
+
+    /// Heading anchors completing a link destination being typed before the
+    /// primary cursor, after `](#` (this document) or `](file.md#`
+    pub fn anchor_completions(&self) -> Result<Vec<AnchorCompletion>> {
+        let doc = self.active().ok_or(Error::NoActiveDocument)?;
+        let text = doc.content();
+        let Some(ctx) = anchors::anchor_context(&text, doc.primary_selection().head) else {
+            return Ok(Vec::new());
+        };
+        if ctx.path.is_empty() {
+            return Ok(anchors::anchor_completions(&text, &ctx.prefix));
+        }
+        let target = links::resolve(
+            &ctx.path,
+            doc.path().map(|p| p.as_path()),
+            self.workspace_root(),
+        );
+        let Some(LinkTarget::File { path, .. }) = target else {
+            return Ok(Vec::new());
+        };
+        let target_text = match self.find_doc_by_path(&path) {
+            Some(open) => open.content(),
+            None => match encoding::read_file(&path) {
+                Ok((text, _)) => text.to_string(),
+                Err(_) => return Ok(Vec::new()),
+            },
+        };
+        Ok(anchors::anchor_completions(&target_text, &ctx.prefix))
+    }
+
+    /// Preview renaming the heading on the primary cursor's line: the new
+    /// anchors and every link in the document and workspace that would be
+    /// updated. Returns `None` if the cursor is not on a heading.
+    pub fn heading_rename_preview(&self, new_text: &str) -> Result<Option<HeadingRename>> {
+        self.plan_heading_rename(new_text, &self.rename_texts()?)
+    }
+
+    /// Rename the heading on the primary cursor's line. With `update_links`,
+    /// links to its old anchor are rewritten too: in open documents as
+    /// undoable edits, in other workspace files on disk. Returns the number
+    /// of links updated.
+    pub fn rename_heading(&mut self, new_text: &str, update_links: bool) -> Result<usize> {
+        self.sync_notes();
+        let texts = self.rename_texts()?;
+        let Some(rename) = self.plan_heading_rename(new_text, &texts)? else {
+            return Ok(0);
+        };
+        let own_path = self.active().and_then(|d| d.path()).cloned();
+
+        let mut by_file: HashMap<Option<PathBuf>, Vec<TextEdit>> = HashMap::new();
+        by_file.insert(own_path.clone(), vec![rename.edit.clone()]);
+        if update_links {
+            for update in &rename.link_updates {
+                by_file
+                    .entry(update.path.clone())
+                    .or_default()
+                    .push(update.edit.clone());
+            }
+        }
+
+        // Rewrite every closed file in memory before writing any, so one
+        // that can't hold the new text leaves the others untouched
+        let mut files = Vec::new();
+        for (path, mut text, encoding) in texts {
+            let Some(encoding) = encoding else { continue };
+            let Some(edits) = by_file.remove(&Some(path.clone())) else {
+                continue;
+            };
+            for edit in edit::normalize_edits(edits).iter().rev() {
+                text.replace_range(edit.range.clone(), &edit.text);
+            }
+            if let Some(character) = encoding.unencodable([text.as_str()]) {
+                return Err(Error::Unencodable {
+                    encoding,
+                    character,
+                });
+            }
+            files.push((path, text, encoding));
+        }
+        for (path, text, encoding) in &files {
+            write_atomic(path, |out| encoding.write([text.as_str()], out))?;
+            if let Some(notes) = &mut self.notes {
+                notes.reload(path)?;
+            }
+        }
+
+        for (path, edits) in by_file {
+            let doc = if path == own_path {
+                self.active_mut()
+            } else {
+                let Some(path) = path else { continue };
+                self.documents.iter_mut().find(|d| d.path() == Some(&path))
+            };
+            if let Some(doc) = doc {
+                doc.apply_edits(edits);
+            }
+        }
+        self.sync_notes();
+        Ok(if update_links {
+            rename.link_updates.len()
+        } else {
+            0
+        })
+    }
+
+    /// Workspace texts a rename of a heading in the active document can
+    /// reach; none if the document has no path
+    fn rename_texts(&self) -> Result<Vec<(PathBuf, String, Option<Encoding>)>> {
+        match self.active().and_then(|d| d.path()) {
+            Some(_) => self.workspace_texts(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn plan_heading_rename(
+        &self,
+        new_text: &str,
+        texts: &[(PathBuf, String, Option<Encoding>)],
+    ) -> Result<Option<HeadingRename>> {
+        let doc = self.active().ok_or(Error::NoActiveDocument)?;
+        let text = doc.content();
+        let line = line_of(&line_starts(&text), doc.primary_selection().head);
+        let Some(mut rename) = anchors::plan_rename(&text, line, new_text) else {
+            return Ok(None);
+        };
+
+        let own_path = doc.path().map(|p| p.as_path());
+        let root = self.workspace_root();
+        let wiki_targets = |link: &wiki::WikiLink, from: Option<&Path>| {
+            own_path.is_some()
+                && self
+                    .notes
+                    .as_ref()
+                    .and_then(|n| n.resolve(&link.target, from))
+                    .as_deref()
+                    == own_path
+        };
+
+        rename.link_updates =
+            anchors::link_updates(&text, own_path, root, &rename, own_path, &|l| {
+                wiki_targets(l, own_path)
+            });
+        if let Some(own_path) = own_path {
+            for (path, other, _) in texts {
+                if path == own_path {
+                    continue;
+                }
+                let updates =
+                    anchors::link_updates(other, Some(path), root, &rename, Some(own_path), &|l| {
+                        wiki_targets(l, Some(path))
+                    });
+                rename.link_updates.extend(updates);
+            }
+        }
+        Ok(Some(rename))
+    }
+
+    /// Report broken links and missing anchors in the active document.
+    /// Linked files that are open are checked against their unsaved content.
+    pub fn link_diagnostics(&self) -> Result<Vec<Diagnostic>> {
+        let doc = self.active().ok_or(Error::NoActiveDocument)?;
+        let read = |path: &Path| match self.find_doc_by_path(path) {
+            Some(open) => Some(open.content()),
+            None => encoding::read_file(path)
+                .ok()
+                .map(|(text, _)| text.to_string()),
+        };
+        Ok(links::validate(
+            &doc.content(),
+            doc.path().map(|p| p.as_path()),
+            self.workspace_root(),
+            &read,
+        ))
+    }
+
+    /// All diagnostics for the active document, ordered by position
+    pub fn diagnostics(&mut self) -> Result<Vec<Diagnostic>> {
+        let mut diagnostics = self.link_diagnostics()?;
+        let doc = self.active().ok_or(Error::NoActiveDocument)?;
+        diagnostics.extend(doc.reference_diagnostics());
+        diagnostics.extend(doc.footnote_diagnostics());
+        diagnostics.extend(doc.unicode_diagnostics());
+        diagnostics.extend(doc.front_matter_diagnostics());
+        diagnostics.extend(doc.prose_diagnostics(&self.prose_rules));
+        diagnostics.extend(self.misspellings()?.iter().map(Diagnostic::from));
+        diagnostics.sort_by_key(|d| d.range.start);
+        Ok(diagnostics)
+    }
+
     // --- Convenience methods that delegate to active document ---
 
     /// Save the active document
     pub fn save(&mut self) -> Result<()> {
         self.active_mut()
             .ok_or(Error::NoActiveDocument)?
-            .save()
+            .save()?;
+        self.reload_active_note()
     }
 
     /// Save the active document to a path
     pub fn save_as(&mut self, path: impl Into<PathBuf>) -> Result<()> {
         self.active_mut()
             .ok_or(Error::NoActiveDocument)?
-            .save_as(path)
+            .save_as(path)?;
+        self.reload_active_note()
+    }
+
+    /// Save the active document in another encoding
+    pub fn save_with_encoding(&mut self, encoding: Encoding) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .save_with_encoding(encoding)?;
+        self.reload_active_note()
+    }
+
+    /// Read the active document's file again in another encoding
+    pub fn reopen_with_encoding(&mut self, encoding: Encoding) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .reopen_with_encoding(encoding)
+    }
+
+    /// Encoding of the active document
+    pub fn encoding(&self) -> Result<Encoding> {
+        Ok(self.active().ok_or(Error::NoActiveDocument)?.encoding())
+    }
+
+    /// Line ending the active document is saved with
+    pub fn line_ending(&self) -> Result<LineEnding> {
+        Ok(self.active().ok_or(Error::NoActiveDocument)?.line_ending())
+    }
+
+    /// How often each line ending occurs in the active document
+    pub fn line_ending_counts(&self) -> Result<LineEndingCounts> {
+        Ok(self
+            .active()
+            .ok_or(Error::NoActiveDocument)?
+            .line_ending_counts())
+    }
+
+    /// Convert every line break in the active document to one style
+    pub fn convert_line_endings(&mut self, ending: LineEnding) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .convert_line_endings(ending);
+        Ok(())
     }
 
     /// Get content of active document
@@ -155,6 +606,14 @@ impl Editor {
         Ok(self.active().ok_or(Error::NoActiveDocument)?.content())
     }
 
+    /// Part of the active document's content; see [`Document::content_chunk`]
+    pub fn content_chunk(&self, start: usize, max_bytes: usize) -> Result<String> {
+        Ok(self
+            .active()
+            .ok_or(Error::NoActiveDocument)?
+            .content_chunk(start, max_bytes))
+    }
+
     /// Insert text in active document
     pub fn insert(&mut self, text: &str) -> Result<()> {
         self.active_mut()
@@ -228,11 +687,500 @@ impl Editor {
         Ok(())
     }
 
+    /// Undo the last edit in active document
+    pub fn undo(&mut self) -> Result<bool> {
+        Ok(self.active_mut().ok_or(Error::NoActiveDocument)?.undo())
+    }
+
+    /// Redo the last undone edit in active document
+    pub fn redo(&mut self) -> Result<bool> {
+        Ok(self.active_mut().ok_or(Error::NoActiveDocument)?.redo())
+    }
+
+    /// Convert links touched by selections to reference style in active document
+    pub fn links_to_reference(&mut self) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .links_to_reference();
+        Ok(())
+    }
+
+    /// Convert reference links touched by selections to inline in active document
+    pub fn links_to_inline(&mut self) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .links_to_inline();
+        Ok(())
+    }
+
+    /// Move link reference definitions to the end of active document
+    pub fn collect_link_definitions(&mut self) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .collect_link_definitions();
+        Ok(())
+    }
+
+    /// Remove unused link reference definitions from active document
+    pub fn remove_unused_link_definitions(&mut self) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .remove_unused_link_definitions();
+        Ok(())
+    }
+
+    /// Table of contents entries of the active document, for the sidebar
+    pub fn toc(&self, max_level: u8) -> Result<Vec<TocEntry>> {
+        Ok(self.active().ok_or(Error::NoActiveDocument)?.toc(max_level))
+    }
+
+    /// Insert a table of contents at the cursor in the active document
+    pub fn insert_toc(&mut self, max_level: u8) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .insert_toc(max_level);
+        Ok(())
+    }
+
+    /// Regenerate tables of contents in the active document
+    pub fn update_toc(&mut self) -> Result<bool> {
+        Ok(self
+            .active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .update_toc())
+    }
+
+    /// Insert a footnote at each cursor in the active document
+    pub fn insert_footnote(&mut self) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .insert_footnote();
+        Ok(())
+    }
+
+    /// Renumber footnotes in the active document
+    pub fn renumber_footnotes(&mut self) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .renumber_footnotes();
+        Ok(())
+    }
+
+    /// Jump between a footnote reference and its definition
+    pub fn jump_to_footnote(&mut self) -> Result<bool> {
+        Ok(self
+            .active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .jump_to_footnote())
+    }
+
+    /// Remove unused footnote definitions from active document
+    pub fn remove_unused_footnotes(&mut self) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .remove_unused_footnotes();
+        Ok(())
+    }
+
+    /// Invisible characters in the active document
+    pub fn invisible_chars(&self) -> Result<Vec<InvisibleChar>> {
+        Ok(self
+            .active()
+            .ok_or(Error::NoActiveDocument)?
+            .invisible_chars())
+    }
+
+    /// Normalize the selection or the active document to a Unicode form
+    pub fn normalize_unicode(&mut self, form: NormalizationForm) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .normalize_unicode(form);
+        Ok(())
+    }
+
+    /// Remove invisible characters from the selection or active document,
+    /// turning space-like ones into spaces
+    pub fn strip_invisible_chars(&mut self) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .strip_invisible_chars();
+        Ok(())
+    }
+
+    /// What document statistics count
+    pub fn stats_options(&self) -> StatsOptions {
+        self.stats_options
+    }
+
+    /// Set what document statistics count
+    pub fn set_stats_options(&mut self, options: StatsOptions) {
+        self.stats_options = options;
+    }
+
+    /// Statistics of the active document and its sections
+    pub fn stats(&mut self) -> Result<DocumentStats> {
+        let options = self.stats_options;
+        Ok(self
+            .active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .stats(options)
+            .clone())
+    }
+
+    /// Statistics of the selected text in the active document
+    pub fn selection_stats(&mut self) -> Result<TextStats> {
+        let options = self.stats_options;
+        Ok(self
+            .active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .selection_stats(options))
+    }
+
+    /// Word goal of the active document
+    pub fn word_goal(&self) -> Result<Option<usize>> {
+        self.active().ok_or(Error::NoActiveDocument)?.word_goal()
+    }
+
+    /// Set or clear the word goal of the active document
+    pub fn set_word_goal(&mut self, goal: Option<usize>, storage: GoalStorage) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .set_word_goal(goal, storage)
+    }
+
+    /// Progress of the active document towards its word goal
+    pub fn goal_progress(&mut self) -> Result<Option<GoalProgress>> {
+        let options = self.stats_options;
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .goal_progress(options)
+    }
+
+    /// Words added and removed and time spent writing since the editor
+    /// started, across all documents
+    pub fn session_stats(&mut self) -> WritingStats {
+        self.collect_writing_activity();
+        self.session.stats()
+    }
+
+    /// Keep the daily writing history in a file, loading what it already
+    /// holds. Days recorded so far are added to it.
+    pub fn set_writing_history_path(&mut self, path: impl Into<PathBuf>) -> Result<()> {
+        self.collect_writing_activity();
+        let mut history = WritingHistory::load(path)?;
+        history.merge(&self.writing_history);
+        self.writing_history = history;
+        Ok(())
+    }
+
+    /// Writing statistics of the days between two dates, inclusive
+    pub fn writing_history(&mut self, from: Date, to: Date) -> Vec<(Date, WritingStats)> {
+        self.collect_writing_activity();
+        self.writing_history.days(from, to)
+    }
+
+    /// Write the daily writing history to its file
+    pub fn save_writing_history(&mut self) -> Result<()> {
+        self.collect_writing_activity();
+        self.writing_history.save()
+    }
+
+    /// Spell check with a Hunspell dictionary, replacing the current one
+    pub fn load_dictionary(&mut self, aff: &Path, dic: &Path) -> Result<()> {
+        self.spell.set_dictionary(Dictionary::load(aff, dic)?);
+        Ok(())
+    }
+
+    /// Keep the personal word list in a file, loading the words it has
+    pub fn set_personal_word_list(&mut self, path: impl Into<PathBuf>) -> Result<()> {
+        self.spell.load_personal_words(path)
+    }
+
+    /// Add a word to the personal word list
+    pub fn add_to_dictionary(&mut self, word: &str) -> Result<()> {
+        self.spell.add_personal_word(word)
+    }
+
+    /// Accept a word in the active document only, recorded in its front
+    /// matter
+    pub fn ignore_word(&mut self, word: &str) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .ignore_word(word)
+    }
+
+    /// Misspelled words in the active document
+    pub fn misspellings(&mut self) -> Result<Vec<Misspelling>> {
+        let doc = self
+            .documents
+            .get_mut(self.active_idx)
+            .ok_or(Error::NoActiveDocument)?;
+        Ok(doc.misspellings(&self.spell))
+    }
+
+    /// Ranked corrections for a word
+    pub fn spelling_suggestions(&mut self, word: &str) -> Vec<String> {
+        self.spell.suggest(word)
+    }
+
+    /// Ranked corrections for the misspelled word under the cursor, empty
+    /// if the word there is spelled correctly
+    pub fn spelling_suggestions_at_cursor(&mut self) -> Result<Vec<String>> {
+        let head = self
+            .active()
+            .ok_or(Error::NoActiveDocument)?
+            .primary_selection()
+            .head;
+        let word = self
+            .misspellings()?
+            .into_iter()
+            .find(|m| m.range.start <= head && head <= m.range.end);
+        Ok(word
+            .map(|m| self.spell.suggest(&m.word))
+            .unwrap_or_default())
+    }
+
+    /// Style checks run on prose
+    pub fn prose_rules(&self) -> &ProseRules {
+        &self.prose_rules
+    }
+
+    /// Set which style checks run
+    pub fn set_prose_rules(&mut self, rules: ProseRules) {
+        self.prose_rules = rules;
+    }
+
+    /// Check terminology against a glossary file
+    pub fn load_glossary(&mut self, path: &Path) -> Result<()> {
+        self.prose_rules.glossary = Glossary::load(path)?;
+        Ok(())
+    }
+
+    /// Move word changes recorded by the documents into the session and
+    /// the daily history, in the order they happened
+    fn collect_writing_activity(&mut self) {
+        let mut activity: Vec<_> = self
+            .documents
+            .iter_mut()
+            .flat_map(|doc| doc.take_activity())
+            .collect();
+        activity.sort_by_key(|a| a.started);
+        for change in &activity {
+            let (date, delta) = self.session.record(change);
+            self.writing_history.add(date, &delta);
+        }
+    }
+
+    /// Set a front matter value in the active document
+    pub fn set_front_matter(&mut self, path: &str, value: &FrontMatterValue) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .set_front_matter(path, value)
+    }
+
+    /// Remove a front matter key from the active document
+    pub fn remove_front_matter(&mut self, path: &str) -> Result<bool> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .remove_front_matter(path)
+    }
+
+    /// Toggle task items touched by selections in active document
+    pub fn toggle_tasks(&mut self) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .toggle_tasks();
+        Ok(())
+    }
+
+    /// Indent lines touched by selections in active document
+    pub fn indent(&mut self) -> Result<()> {
+        self.active_mut().ok_or(Error::NoActiveDocument)?.indent();
+        Ok(())
+    }
+
+    /// Outdent lines touched by selections in active document
+    pub fn outdent(&mut self) -> Result<()> {
+        self.active_mut().ok_or(Error::NoActiveDocument)?.outdent();
+        Ok(())
+    }
+
+    /// Handle the Tab key in active document
+    pub fn insert_tab(&mut self) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .insert_tab();
+        Ok(())
+    }
+
+    /// Get indentation settings of active document
+    pub fn indent_settings(&self) -> Result<IndentSettings> {
+        Ok(self
+            .active()
+            .ok_or(Error::NoActiveDocument)?
+            .indent_settings())
+    }
+
+    /// Set indentation settings of active document
+    pub fn set_indent_settings(&mut self, settings: IndentSettings) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .set_indent_settings(settings);
+        Ok(())
+    }
+
+    /// Get hard wrap settings of active document
+    pub fn wrap_settings(&self) -> Result<WrapSettings> {
+        Ok(self
+            .active()
+            .ok_or(Error::NoActiveDocument)?
+            .wrap_settings())
+    }
+
+    /// Set hard wrap settings of active document
+    pub fn set_wrap_settings(&mut self, settings: WrapSettings) -> Result<()> {
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .set_wrap_settings(settings);
+        Ok(())
+    }
+
+    /// Rewrap the paragraphs under the selections in the active document
+    pub fn reflow(&mut self) -> Result<()> {
+        self.active_mut().ok_or(Error::NoActiveDocument)?.reflow();
+        Ok(())
+    }
+
+    /// Render the active document as HTML
+    pub fn export_html(&self, options: &HtmlOptions) -> Result<String> {
+        Ok(self
+            .active()
+            .ok_or(Error::NoActiveDocument)?
+            .to_html(options))
+    }
+
+    /// Write the active document as HTML to a file
+    pub fn export_html_to(&self, path: &Path, options: &HtmlOptions) -> Result<()> {
+        std::fs::write(path, self.export_html(options)?)?;
+        Ok(())
+    }
+
+    /// Paste HTML from the clipboard as Markdown at each cursor
+    pub fn paste_html(&mut self, html: &str) -> Result<()> {
+        let markdown = html_to_markdown(html);
+        self.active_mut()
+            .ok_or(Error::NoActiveDocument)?
+            .insert(markdown.trim_end());
+        Ok(())
+    }
+
+    /// Write the active document as a `.docx` file
+    pub fn export_docx_to(&self, path: &Path) -> Result<()> {
+        let docx = self.active().ok_or(Error::NoActiveDocument)?.to_docx()?;
+        std::fs::write(path, docx)?;
+        Ok(())
+    }
+
+    /// Write the active document as an EPUB book
+    pub fn export_epub_to(&self, path: &Path, options: &EpubOptions) -> Result<()> {
+        let book = self
+            .active()
+            .ok_or(Error::NoActiveDocument)?
+            .to_epub(options)?;
+        std::fs::write(path, book)?;
+        Ok(())
+    }
+
+    /// Write Markdown files, in order, as one EPUB book. Open documents
+    /// contribute their unsaved content.
+    pub fn export_book_to(
+        &self,
+        files: &[PathBuf],
+        path: &Path,
+        options: &EpubOptions,
+    ) -> Result<()> {
+        let sources = files
+            .iter()
+            .map(|file| {
+                let text = match self.documents.iter().find(|d| d.path() == Some(file)) {
+                    Some(doc) => doc.content(),
+                    None => encoding::read_file(file)?.0.to_string(),
+                };
+                Ok(EpubSource {
+                    text,
+                    path: Some(file.clone()),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        std::fs::write(path, epub::render_epub(&sources, options)?)?;
+        Ok(())
+    }
+
+    /// Write the active document as an HTML slide deck to a file
+    pub fn export_slides_to(&self, path: &Path, options: &SlideOptions) -> Result<()> {
+        let deck = self
+            .active()
+            .ok_or(Error::NoActiveDocument)?
+            .to_slides(options);
+        std::fs::write(path, deck)?;
+        Ok(())
+    }
+
+    /// Publish the workspace folder as a static site in `output`, rendering
+    /// only the notes changed since the last build
+    pub fn build_site(&self, output: &Path, options: &SiteOptions) -> Result<SiteReport> {
+        let root = self.workspace_root().ok_or(Error::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "No workspace folder set",
+        )))?;
+        site::build_site(root, output, options)
+    }
+
     // --- Private helpers ---
 
     fn find_doc_index(&self, id: DocumentId) -> Option<usize> {
         self.documents.iter().position(|d| d.id() == id)
     }
+
+    fn find_doc_by_path(&self, path: &Path) -> Option<&Document> {
+        self.documents
+            .iter()
+            .find(|d| d.path().map(|p| p.as_path()) == Some(path))
+    }
+
+    /// Paths and content of workspace Markdown files and open documents
+    /// saved anywhere, preferring open documents' unsaved content, with the
+    /// encoding of each file read from disk
+    fn workspace_texts(&self) -> Result<Vec<(PathBuf, String, Option<Encoding>)>> {
+        let mut paths = match self.workspace_root() {
+            Some(root) => crate::workspace::markdown_files(root)?,
+            None => Vec::new(),
+        };
+        paths.extend(self.documents.iter().filter_map(|d| d.path().cloned()));
+        paths.sort();
+        paths.dedup();
+
+        let mut texts = Vec::new();
+        for path in paths {
+            let (text, encoding) = match self.find_doc_by_path(&path) {
+                Some(doc) => (doc.content(), None),
+                None => {
+                    let (rope, encoding) = encoding::read_file(&path)?;
+                    (rope.to_string(), Some(encoding))
+                }
+            };
+            texts.push((path, text, encoding));
+        }
+        Ok(texts)
+    }
+}
+
+/// Task index key for an open document
+pub(crate) fn task_source(doc: &Document) -> TaskSource {
+    match doc.path() {
+        Some(path) => TaskSource::File(path.clone()),
+        None => TaskSource::Untitled(doc.id()),
+    }
 }
 
 impl Default for Editor {
@@ -308,4 +1256,228 @@ mod tests {
         editor.prev_tab();
         assert_eq!(editor.active_id(), Some(id1));
     }
+
+    #[test]
+    fn test_follow_link_opens_file_at_anchor() {
+        let dir = std::env::temp_dir().join(format!("rmde-follow-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("design")).unwrap();
+        std::fs::write(dir.join("notes.md"), "See [spec](design/spec.md#goals)\n").unwrap();
+        std::fs::write(dir.join("design/spec.md"), "# Spec\n\n## Goals\n").unwrap();
+
+        let mut editor = Editor::new();
+        editor.open_file(dir.join("notes.md")).unwrap();
+        editor.set_cursor(6).unwrap();
+        let target = editor.follow_link_at_cursor().unwrap();
+
+        assert!(matches!(target, Some(LinkTarget::File { .. })));
+        assert_eq!(editor.active().unwrap().title(), "spec.md");
+        assert_eq!(editor.active().unwrap().primary_selection().head, 8);
+        assert!(editor.link_diagnostics().unwrap().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_backlinks_follow_unsaved_edits() {
+        let root = std::env::temp_dir().join(format!("rmde-backlinks-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("a.md"), "Nothing yet\n").unwrap();
+        std::fs::write(root.join("b.md"), "# B\n").unwrap();
+
+        let mut editor = Editor::new();
+        editor.set_workspace_root(Some(root.clone())).unwrap();
+        editor.open_file(root.join("a.md")).unwrap();
+        editor.set_cursor(0).unwrap();
+        editor.insert("See [[b]] ").unwrap();
+        let a_id = editor.active_id().unwrap();
+
+        editor.open_file(root.join("b.md")).unwrap();
+        let backlinks = editor.backlinks().unwrap();
+        assert_eq!(backlinks.len(), 1);
+        assert_eq!(backlinks[0].source, root.join("a.md"));
+
+        // Closing without saving drops the unsaved link
+        editor.close_tab(a_id);
+        assert!(editor.backlinks().unwrap().is_empty());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_rename_heading_updates_links() {
+        let root = std::env::temp_dir().join(format!("rmde-rename-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let spec = root.join("spec.md");
+        std::fs::write(
+            &spec,
+            "# Spec\n\n## Old goals\n\nSee [goals](#old-goals).\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("plan.md"),
+            "Read [these](spec.md#old-goals) and [[spec#Old goals]].\n",
+        )
+        .unwrap();
+
+        let mut editor = Editor::new();
+        editor.set_workspace_root(Some(root.clone())).unwrap();
+        editor.open_file(&spec).unwrap();
+        editor.set_cursor(9).unwrap();
+
+        let preview = editor.heading_rename_preview("New goals").unwrap().unwrap();
+        assert_eq!(preview.link_updates.len(), 3);
+        assert_eq!(editor.rename_heading("New goals", true).unwrap(), 3);
+
+        assert_eq!(
+            editor.content().unwrap(),
+            "# Spec\n\n## New goals\n\nSee [goals](#new-goals).\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join("plan.md")).unwrap(),
+            "Read [these](spec.md#new-goals) and [[spec#New goals]].\n"
+        );
+
+        // The heading and its same-document links are one undo step
+        assert!(editor.undo().unwrap());
+        assert!(editor.content().unwrap().contains("## Old goals"));
+        assert!(editor.content().unwrap().contains("(#old-goals)"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_rename_heading_keeps_encoding() {
+        let root = std::env::temp_dir().join(format!("rmde-rename-enc-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let spec = root.join("spec.md");
+        let notes = root.join("notes.md");
+        std::fs::write(&spec, "## Goals\n").unwrap();
+        let latin1 = b"Caf\xe9: [[spec#Goals]]\n".to_vec();
+        std::fs::write(&notes, &latin1).unwrap();
+
+        let mut editor = Editor::new();
+        editor.set_workspace_root(Some(root.clone())).unwrap();
+        editor.open_file(&spec).unwrap();
+        editor.set_cursor(3).unwrap();
+
+        // The new text doesn't fit the other file's encoding: nothing changes
+        assert!(editor.rename_heading("Ω goals", true).is_err());
+        assert_eq!(editor.content().unwrap(), "## Goals\n");
+        assert_eq!(std::fs::read(&notes).unwrap(), latin1);
+
+        assert_eq!(editor.rename_heading("Aims", true).unwrap(), 1);
+        assert_eq!(std::fs::read(&notes).unwrap(), b"Caf\xe9: [[spec#Aims]]\n");
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_anchor_completions_in_other_file() {
+        let root = std::env::temp_dir().join(format!("rmde-anchors-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("b.md"), "# Setup\n\n## Install\n").unwrap();
+        std::fs::write(root.join("a.md"), "").unwrap();
+
+        let mut editor = Editor::new();
+        editor.open_file(root.join("a.md")).unwrap();
+        editor.insert("[x](b.md#in").unwrap();
+        let slugs: Vec<_> = editor
+            .anchor_completions()
+            .unwrap()
+            .into_iter()
+            .map(|c| c.slug)
+            .collect();
+        assert_eq!(slugs, vec!["install"]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_task_index_includes_open_documents() {
+        let mut editor = Editor::new();
+        editor.insert("- [ ] one\n- [x] two\n").unwrap();
+        let id = editor.active_id().unwrap();
+
+        let index = editor.task_index().unwrap();
+        let progress = index.progress(&TaskSource::Untitled(id));
+        assert_eq!((progress.done, progress.total), (1, 2));
+    }
+
+    #[test]
+    fn test_session_stats_and_goal() {
+        use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+        fn noon() -> SystemTime {
+            UNIX_EPOCH + Duration::from_secs(1_709_294_400)
+        }
+        let mut editor = Editor::new();
+        editor.active_mut().unwrap().set_clock(noon);
+        editor.insert("Three short words").unwrap();
+        editor.new_tab();
+        editor.active_mut().unwrap().set_clock(noon);
+        editor.insert("One").unwrap();
+        editor.delete_backward().unwrap();
+        editor.delete_backward().unwrap();
+        editor.delete_backward().unwrap();
+        // Undoing isn't writing
+        editor.undo().unwrap();
+        editor.redo().unwrap();
+
+        let stats = editor.session_stats();
+        assert_eq!((stats.words_added, stats.words_removed), (4, 1));
+        let day = Date::local(noon());
+        assert_eq!(editor.writing_history(day, day).len(), 1);
+
+        editor.insert("Some words").unwrap();
+        editor
+            .set_word_goal(Some(4), GoalStorage::FrontMatter)
+            .unwrap();
+        let progress = editor.goal_progress().unwrap().unwrap();
+        assert_eq!((progress.goal, progress.words), (4, 2));
+        assert_eq!(progress.fraction(), 0.5);
+    }
+
+    #[test]
+    fn test_import_and_paste_html() {
+        let mut editor = Editor::new();
+        let id = editor.import_html("<h1>Title</h1><p>Some <b>text</b></p>");
+        assert_eq!(editor.active_id(), Some(id));
+        assert_eq!(editor.content().unwrap(), "# Title\n\nSome **text**\n");
+        assert!(editor.active().unwrap().is_dirty());
+        assert!(editor.active().unwrap().path().is_none());
+
+        editor.new_tab();
+        editor.insert("See: ").unwrap();
+        editor
+            .paste_html("<a href=\"https://example.com\">the <i>site</i></a>")
+            .unwrap();
+        assert_eq!(
+            editor.content().unwrap(),
+            "See: [the *site*](https://example.com)"
+        );
+    }
+
+    #[test]
+    fn test_spelling_suggestions_at_cursor() {
+        let dir = std::env::temp_dir().join(format!("rmde-editor-spell-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (aff, dic) = (dir.join("en.aff"), dir.join("en.dic"));
+        std::fs::write(&aff, "SET UTF-8\n").unwrap();
+        std::fs::write(&dic, "2\nthe\ndog\n").unwrap();
+
+        let mut editor = Editor::new();
+        editor.load_dictionary(&aff, &dic).unwrap();
+        editor.insert("The dgo").unwrap();
+        assert_eq!(
+            editor.spelling_suggestions_at_cursor().unwrap(),
+            vec!["dog"]
+        );
+        editor.active_mut().unwrap().set_cursor(1);
+        assert!(editor.spelling_suggestions_at_cursor().unwrap().is_empty());
+
+        editor.ignore_word("dgo").unwrap();
+        assert!(editor.misspellings().unwrap().is_empty());
+        assert!(editor.content().unwrap().starts_with("---\nspell_ignore:"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,270 @@
+//! Emphasis matching for HTML rendering: pairs `*` and `_` delimiter runs
+//! into `<em>` and `<strong>` with the CommonMark delimiter algorithm. The
+//! Markdown grammar's emphasis nodes go wrong when emphasis nests, as in
+//! `*foo**bar**baz*`.
+
+use std::collections::HashMap;
+
+/// Rendered inline content, split at the delimiter runs that may become
+/// emphasis
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Piece {
+    Html(String),
+    /// A run of `*` or `_` with the characters around it in the source;
+    /// `None` is the start or end of the inline content
+    Delimiters {
+        ch: char,
+        count: usize,
+        before: Option<char>,
+        after: Option<char>,
+    },
+}
+
+/// A delimiter run while matching
+struct Run {
+    ch: char,
+    /// Length in the source
+    length: usize,
+    /// Characters not yet used for emphasis
+    left: usize,
+    can_open: bool,
+    can_close: bool,
+    /// Still on the delimiter stack
+    active: bool,
+    /// Tags this run opens and closes, innermost first
+    opens: Vec<&'static str>,
+    closes: Vec<&'static str>,
+}
+
+impl Run {
+    fn new(ch: char, count: usize, before: Option<char>, after: Option<char>) -> Self {
+        let before = before.unwrap_or('\n');
+        let after = after.unwrap_or('\n');
+        let left_flanking = !after.is_whitespace()
+            && (!is_punctuation(after) || before.is_whitespace() || is_punctuation(before));
+        let right_flanking = !before.is_whitespace()
+            && (!is_punctuation(before) || after.is_whitespace() || is_punctuation(after));
+        let (can_open, can_close) = if ch == '_' {
+            (
+                left_flanking && (!right_flanking || is_punctuation(before)),
+                right_flanking && (!left_flanking || is_punctuation(after)),
+            )
+        } else {
+            (left_flanking, right_flanking)
+        };
+        Self {
+            ch,
+            length: count,
+            left: count,
+            can_open,
+            can_close,
+            active: true,
+            opens: Vec::new(),
+            closes: Vec::new(),
+        }
+    }
+
+    /// Whether this opener and `closer` can't pair because their lengths
+    /// would make the delimiters ambiguous ("rule of 3")
+    fn mismatched(&self, closer: &Run) -> bool {
+        (self.can_close || closer.can_open)
+            && (self.length + closer.length).is_multiple_of(3)
+            && !(self.length.is_multiple_of(3) && closer.length.is_multiple_of(3))
+    }
+}
+
+enum Item {
+    Html(String),
+    Run(usize),
+}
+
+/// Match the delimiter runs and render the pieces. Unmatched delimiters
+/// are kept as text.
+pub(crate) fn render(pieces: Vec<Piece>) -> String {
+    let mut items = Vec::new();
+    let mut runs: Vec<Run> = Vec::new();
+    // A run split over several pieces (delimiters of nested nodes) is
+    // classified once it is complete
+    let mut pending: Option<(char, usize, Option<char>, Option<char>)> = None;
+    for piece in pieces {
+        match piece {
+            Piece::Delimiters {
+                ch,
+                count,
+                before,
+                after,
+            } => match &mut pending {
+                Some((c, n, _, end)) if *c == ch => {
+                    *n += count;
+                    *end = after;
+                }
+                _ => {
+                    if let Some((c, n, start, end)) = pending.take() {
+                        items.push(Item::Run(runs.len()));
+                        runs.push(Run::new(c, n, start, end));
+                    }
+                    pending = Some((ch, count, before, after));
+                }
+            },
+            Piece::Html(html) => {
+                if let Some((c, n, start, end)) = pending.take() {
+                    items.push(Item::Run(runs.len()));
+                    runs.push(Run::new(c, n, start, end));
+                }
+                items.push(Item::Html(html));
+            }
+        }
+    }
+    if let Some((c, n, start, end)) = pending {
+        items.push(Item::Run(runs.len()));
+        runs.push(Run::new(c, n, start, end));
+    }
+
+    match_runs(&mut runs);
+
+    let mut out = String::new();
+    for item in items {
+        match item {
+            Item::Html(html) => out.push_str(&html),
+            Item::Run(i) => {
+                let run = &runs[i];
+                for tag in &run.closes {
+                    out.push_str(&format!("</{tag}>"));
+                }
+                out.extend(std::iter::repeat_n(run.ch, run.left));
+                for tag in run.opens.iter().rev() {
+                    out.push_str(&format!("<{tag}>"));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Pair closers with the nearest possible openers, as in the CommonMark
+/// "process emphasis" procedure
+fn match_runs(runs: &mut [Run]) {
+    // Lowest opener worth trying, by delimiter, closer length mod 3 and
+    // whether the closer can open
+    let mut bottom: HashMap<(char, usize, bool), usize> = HashMap::new();
+    let mut closer = 0;
+    while closer < runs.len() {
+        let c = &runs[closer];
+        if !c.active || !c.can_close || c.left == 0 {
+            closer += 1;
+            continue;
+        }
+        let key = (c.ch, c.length % 3, c.can_open);
+        let floor = bottom.get(&key).copied().unwrap_or(0);
+        let opener = (floor..closer).rev().find(|&o| {
+            let o = &runs[o];
+            o.active && o.can_open && o.left > 0 && o.ch == c.ch && !o.mismatched(c)
+        });
+
+        let Some(opener) = opener else {
+            bottom.insert(key, closer);
+            if !runs[closer].can_open {
+                runs[closer].active = false;
+            }
+            closer += 1;
+            continue;
+        };
+
+        let used = if runs[opener].left >= 2 && runs[closer].left >= 2 {
+            2
+        } else {
+            1
+        };
+        let tag = if used == 2 { "strong" } else { "em" };
+        runs[opener].left -= used;
+        runs[opener].opens.push(tag);
+        runs[closer].left -= used;
+        runs[closer].closes.push(tag);
+        for run in &mut runs[opener + 1..closer] {
+            run.active = false;
+        }
+        if runs[opener].left == 0 {
+            runs[opener].active = false;
+        }
+        if runs[closer].left == 0 {
+            runs[closer].active = false;
+            closer += 1;
+        }
+    }
+}
+
+/// Unicode punctuation or symbol, as CommonMark counts for flanking
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation() || !(c.is_alphanumeric() || c.is_whitespace() || c.is_control())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Render text where every `*` and `_` run is a delimiter run
+    fn emphasize(text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut pieces = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let ch = chars[i];
+            let end = (i..chars.len())
+                .find(|&j| chars[j] != ch)
+                .unwrap_or(chars.len());
+            if matches!(ch, '*' | '_') {
+                pieces.push(Piece::Delimiters {
+                    ch,
+                    count: end - i,
+                    before: i.checked_sub(1).map(|j| chars[j]),
+                    after: chars.get(end).copied(),
+                });
+            } else {
+                pieces.push(Piece::Html(chars[i..end].iter().collect()));
+            }
+            i = end;
+        }
+        render(pieces)
+    }
+
+    #[test]
+    fn test_nested_emphasis() {
+        assert_eq!(
+            emphasize("*foo**bar**baz*"),
+            "<em>foo<strong>bar</strong>baz</em>"
+        );
+        assert_eq!(
+            emphasize("**foo*bar*baz**"),
+            "<strong>foo<em>bar</em>baz</strong>"
+        );
+        assert_eq!(emphasize("*foo**bar*"), "<em>foo**bar</em>");
+        assert_eq!(emphasize("***foo***"), "<em><strong>foo</strong></em>");
+        assert_eq!(emphasize("**foo*"), "*<em>foo</em>");
+    }
+
+    #[test]
+    fn test_underscore_within_words() {
+        assert_eq!(emphasize("snake_case_name"), "snake_case_name");
+        assert_eq!(emphasize("*in*word"), "<em>in</em>word");
+        assert_eq!(emphasize("_foo_bar"), "_foo_bar");
+        assert_eq!(emphasize("a * b *"), "a * b *");
+    }
+
+    #[test]
+    fn test_split_runs_merge() {
+        let delimiter = |before, after| Piece::Delimiters {
+            ch: '*',
+            count: 1,
+            before,
+            after,
+        };
+        let pieces = vec![
+            delimiter(None, Some('*')),
+            delimiter(Some('*'), Some('a')),
+            Piece::Html("a".into()),
+            delimiter(Some('a'), Some('*')),
+            delimiter(Some('*'), None),
+        ];
+        assert_eq!(render(pieces), "<strong>a</strong>");
+    }
+}
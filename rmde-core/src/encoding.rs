@@ -0,0 +1,317 @@
+//! Text encodings of files on disk: detection, strict decoding and
+//! encoding back to the same bytes
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use ropey::Rope;
+
+use crate::error::{Error, Result};
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16BE_BOM: &[u8] = b"\xFE\xFF";
+
+/// Windows-1252 characters for bytes 0x80-0x9F; 0 marks unassigned bytes
+const WINDOWS_1252_HIGH: [u16; 32] = [
+    0x20AC, 0, 0x201A, 0x0192, 0x201E, 0x2026, 0x2020, 0x2021, 0x02C6, 0x2030, 0x0160, 0x2039,
+    0x0152, 0, 0x017D, 0, 0, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014, 0x02DC,
+    0x2122, 0x0161, 0x203A, 0x0153, 0, 0x017E, 0x0178,
+];
+
+/// How a document's text is stored on disk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// UTF-8 starting with a byte order mark
+    Utf8Bom,
+    /// UTF-16 little-endian with a byte order mark
+    Utf16Le,
+    /// UTF-16 big-endian with a byte order mark
+    Utf16Be,
+    Windows1252,
+    /// ISO-8859-1
+    Latin1,
+}
+
+impl Encoding {
+    /// Every supported encoding, for menus
+    pub const ALL: [Encoding; 6] = [
+        Self::Utf8,
+        Self::Utf8Bom,
+        Self::Utf16Le,
+        Self::Utf16Be,
+        Self::Windows1252,
+        Self::Latin1,
+    ];
+
+    /// Display name, also accepted by [`Encoding::from_name`]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf8Bom => "UTF-8 with BOM",
+            Self::Utf16Le => "UTF-16 LE",
+            Self::Utf16Be => "UTF-16 BE",
+            Self::Windows1252 => "Windows-1252",
+            Self::Latin1 => "ISO-8859-1",
+        }
+    }
+
+    /// Look up an encoding by display name or common label, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        let label: String = name
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        Some(match label.as_str() {
+            "utf8" => Self::Utf8,
+            "utf8withbom" | "utf8bom" => Self::Utf8Bom,
+            "utf16le" | "utf16" => Self::Utf16Le,
+            "utf16be" => Self::Utf16Be,
+            "windows1252" | "cp1252" => Self::Windows1252,
+            "iso88591" | "latin1" => Self::Latin1,
+            _ => return None,
+        })
+    }
+
+    /// Guess the encoding of file contents: a byte order mark decides,
+    /// then valid UTF-8, then Windows-1252 unless it leaves bytes
+    /// unassigned, and Latin-1, which decodes anything
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(UTF8_BOM) {
+            Self::Utf8Bom
+        } else if bytes.starts_with(UTF16LE_BOM) {
+            Self::Utf16Le
+        } else if bytes.starts_with(UTF16BE_BOM) {
+            Self::Utf16Be
+        } else if std::str::from_utf8(bytes).is_ok() {
+            Self::Utf8
+        } else if bytes.iter().all(|&b| windows_1252(b).is_some()) {
+            Self::Windows1252
+        } else {
+            Self::Latin1
+        }
+    }
+
+    fn bom(self) -> &'static [u8] {
+        match self {
+            Self::Utf8Bom => UTF8_BOM,
+            Self::Utf16Le => UTF16LE_BOM,
+            Self::Utf16Be => UTF16BE_BOM,
+            _ => b"",
+        }
+    }
+
+    /// Decode file contents, skipping this encoding's byte order mark.
+    /// Bytes that aren't valid in the encoding are an [`Error::Decode`].
+    pub fn decode(self, bytes: &[u8]) -> Result<String> {
+        let bom = if bytes.starts_with(self.bom()) {
+            self.bom().len()
+        } else {
+            0
+        };
+        let body = &bytes[bom..];
+        let error = |offset: usize| Error::Decode {
+            encoding: self,
+            offset: bom + offset,
+        };
+        match self {
+            Self::Utf8 | Self::Utf8Bom => std::str::from_utf8(body)
+                .map(str::to_string)
+                .map_err(|e| error(e.valid_up_to())),
+            Self::Utf16Le | Self::Utf16Be => {
+                if body.len() % 2 == 1 {
+                    return Err(error(body.len() - 1));
+                }
+                let units = body.chunks_exact(2).map(|pair| match self {
+                    Self::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                    _ => u16::from_be_bytes([pair[0], pair[1]]),
+                });
+                let mut out = String::with_capacity(body.len() / 2);
+                let mut offset = 0;
+                for c in char::decode_utf16(units) {
+                    let c = c.map_err(|_| error(offset))?;
+                    offset += c.len_utf16() * 2;
+                    out.push(c);
+                }
+                Ok(out)
+            }
+            Self::Windows1252 => body
+                .iter()
+                .enumerate()
+                .map(|(i, &b)| windows_1252(b).ok_or_else(|| error(i)))
+                .collect(),
+            Self::Latin1 => Ok(body.iter().map(|&b| char::from(b)).collect()),
+        }
+    }
+
+    /// First character of the text this encoding can't represent
+    pub fn unencodable<'a>(self, chunks: impl IntoIterator<Item = &'a str>) -> Option<char> {
+        let single_byte: fn(char) -> Option<u8> = match self {
+            Self::Windows1252 => windows_1252_byte,
+            Self::Latin1 => |c: char| u8::try_from(c).ok(),
+            _ => return None,
+        };
+        chunks
+            .into_iter()
+            .flat_map(str::chars)
+            .find(|&c| single_byte(c).is_none())
+    }
+
+    /// Write text in this encoding, starting with its byte order mark.
+    /// Characters the encoding can't represent become `?`; check with
+    /// [`Encoding::unencodable`] first.
+    pub fn write<'a>(
+        self,
+        chunks: impl IntoIterator<Item = &'a str>,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        out.write_all(self.bom())?;
+        let mut buf = Vec::new();
+        for chunk in chunks {
+            buf.clear();
+            match self {
+                Self::Utf8 | Self::Utf8Bom => buf.extend_from_slice(chunk.as_bytes()),
+                Self::Utf16Le => buf.extend(chunk.encode_utf16().flat_map(u16::to_le_bytes)),
+                Self::Utf16Be => buf.extend(chunk.encode_utf16().flat_map(u16::to_be_bytes)),
+                Self::Windows1252 => {
+                    buf.extend(chunk.chars().map(|c| windows_1252_byte(c).unwrap_or(b'?')))
+                }
+                Self::Latin1 => buf.extend(chunk.chars().map(|c| u8::try_from(c).unwrap_or(b'?'))),
+            }
+            out.write_all(&buf)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+fn windows_1252(byte: u8) -> Option<char> {
+    match byte {
+        0x80..=0x9F => match WINDOWS_1252_HIGH[usize::from(byte - 0x80)] {
+            0 => None,
+            code => char::from_u32(u32::from(code)),
+        },
+        _ => Some(char::from(byte)),
+    }
+}
+
+fn windows_1252_byte(c: char) -> Option<u8> {
+    match u32::from(c) {
+        0x80..=0x9F => None,
+        code @ 0..=0xFF => Some(code as u8),
+        code => WINDOWS_1252_HIGH
+            .iter()
+            .position(|&high| u32::from(high) == code)
+            .map(|i| 0x80 + i as u8),
+    }
+}
+
+/// Read a file into a rope, detecting its encoding. UTF-8 streams straight
+/// into the rope; other encodings are decoded from the whole file.
+pub(crate) fn read_file(path: &Path) -> Result<(Rope, Encoding)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let head = reader.fill_buf()?;
+    let utf16 = head.starts_with(UTF16LE_BOM) || head.starts_with(UTF16BE_BOM);
+    if !utf16 {
+        let encoding = if head.starts_with(UTF8_BOM) {
+            reader.consume(UTF8_BOM.len());
+            Encoding::Utf8Bom
+        } else {
+            Encoding::Utf8
+        };
+        match Rope::from_reader(reader) {
+            Ok(rope) => return Ok((rope, encoding)),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let bytes = std::fs::read(path)?;
+    let encoding = Encoding::detect(&bytes);
+    Ok((Rope::from_str(&encoding.decode(&bytes)?), encoding))
+}
+
+/// Read a file in a given encoding
+pub(crate) fn read_file_as(path: &Path, encoding: Encoding) -> Result<Rope> {
+    let bytes = std::fs::read(path)?;
+    Ok(Rope::from_str(&encoding.decode(&bytes)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(encoding: Encoding, text: &str) -> Vec<u8> {
+        let mut out = Vec::new();
+        encoding.write([text], &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Encoding::detect(b"plain"), Encoding::Utf8);
+        assert_eq!(Encoding::detect("caf\u{e9}".as_bytes()), Encoding::Utf8);
+        assert_eq!(Encoding::detect(b"\xEF\xBB\xBFhi"), Encoding::Utf8Bom);
+        assert_eq!(Encoding::detect(b"\xFF\xFEh\0"), Encoding::Utf16Le);
+        assert_eq!(Encoding::detect(b"\xFE\xFF\0h"), Encoding::Utf16Be);
+        assert_eq!(
+            Encoding::detect(b"caf\xE9 \x93q\x94"),
+            Encoding::Windows1252
+        );
+        assert_eq!(Encoding::detect(b"caf\xE9 \x81"), Encoding::Latin1);
+        assert_eq!(Encoding::from_name("utf-16le"), Some(Encoding::Utf16Le));
+        assert_eq!(Encoding::from_name("Latin1"), Some(Encoding::Latin1));
+        for encoding in Encoding::ALL {
+            assert_eq!(Encoding::from_name(encoding.name()), Some(encoding));
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let text = "Caf\u{e9} \u{201c}quoted\u{201d} \u{20ac}5\n";
+        for encoding in [
+            Encoding::Utf8,
+            Encoding::Utf8Bom,
+            Encoding::Utf16Le,
+            Encoding::Utf16Be,
+            Encoding::Windows1252,
+        ] {
+            let bytes = encode(encoding, text);
+            assert_eq!(Encoding::detect(&bytes), encoding);
+            assert_eq!(encoding.decode(&bytes).unwrap(), text);
+        }
+        assert_eq!(encode(Encoding::Windows1252, "\u{20ac}"), b"\x80");
+        assert_eq!(
+            encode(Encoding::Utf16Be, "\u{1F600}"),
+            b"\xFE\xFF\xD8\x3D\xDE\x00"
+        );
+        assert_eq!(
+            Encoding::Latin1.unencodable(["ok \u{e9}", " \u{20ac}"]),
+            Some('\u{20ac}')
+        );
+        assert_eq!(Encoding::Windows1252.unencodable(["\u{20ac}\u{e9}"]), None);
+        assert_eq!(Encoding::Utf8.unencodable(["\u{1F600}"]), None);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let error = |encoding: Encoding, bytes: &[u8]| match encoding.decode(bytes) {
+            Err(Error::Decode { offset, .. }) => offset,
+            other => panic!("expected a decode error, got {other:?}"),
+        };
+        assert_eq!(error(Encoding::Utf8Bom, b"\xEF\xBB\xBFab\xFF"), 5);
+        assert_eq!(error(Encoding::Utf16Le, b"\xFF\xFEa\0b"), 4);
+        assert_eq!(error(Encoding::Utf16Le, b"\xFF\xFEa\0\0\xDC"), 4);
+        assert_eq!(error(Encoding::Windows1252, b"ok\x8D"), 2);
+        assert_eq!(Encoding::Latin1.decode(b"\x8D").unwrap(), "\u{8d}");
+    }
+}
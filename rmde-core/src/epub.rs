@@ -0,0 +1,720 @@
+//! EPUB 3 export: a document or an ordered list of files packaged as an
+//! e-book with one chapter per top-level heading
+
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use zip::CompressionMethod;
+use zip::write::SimpleFileOptions;
+
+use crate::Date;
+use crate::error::Result;
+use crate::footnotes;
+use crate::front_matter::{FrontMatterValue, parse_front_matter};
+use crate::html::{self, HtmlOptions, decode_entities, decode_entity, escape};
+use crate::links::{normalize_path, percent_decode};
+use crate::outline::outline;
+use crate::references::definitions;
+use crate::site::fnv_hash;
+use crate::slug::heading_slugs;
+use crate::syntax::MarkdownTree;
+
+/// Default reading-system friendly stylesheet
+const EPUB_STYLESHEET: &str = "\
+body { font-family: serif; line-height: 1.5; margin: 0 5%; }
+h1, h2, h3, h4, h5, h6 { font-family: sans-serif; line-height: 1.25; page-break-after: avoid; }
+h1 { margin-top: 2em; }
+pre, code { font-family: monospace; font-size: 0.9em; }
+pre { white-space: pre-wrap; padding: 0.5em; background: #f6f8fa; }
+blockquote { margin: 1em 1.5em; padding-left: 0.75em; border-left: 0.25em solid #d1d9e0; color: #59636e; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #d1d9e0; padding: 0.25em 0.5em; }
+img { max-width: 100%; }
+ul.contains-task-list, li.task-list-item { list-style: none; }
+.footnotes { font-size: 0.85em; border-top: 1px solid #d1d9e0; margin-top: 2em; }
+nav ol { list-style: none; padding-left: 1em; }
+";
+
+/// Elements written as `<br />` in XHTML
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// A Markdown file to include in a book
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpubSource {
+    pub text: String,
+    /// Location of the file; relative images and links to other sources
+    /// resolve against it
+    pub path: Option<PathBuf>,
+}
+
+/// Options for EPUB export
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EpubOptions {
+    /// Book title; defaults to the `title` front matter of the first file,
+    /// then its first heading
+    pub title: Option<String>,
+    /// Author; defaults to the `author` front matter of the first file
+    pub author: Option<String>,
+    /// CSS replacing the built-in stylesheet
+    pub stylesheet: Option<String>,
+}
+
+/// A chapter cut from one of the sources
+struct Chapter {
+    source: usize,
+    title: String,
+    /// Chapter text with the link and footnote definitions it uses
+    markdown: String,
+    /// Headings below the chapter heading: depth (1 or 2), text and anchor
+    sections: Vec<(usize, String, String)>,
+    /// Anchors of every heading in the chapter
+    anchors: Vec<String>,
+}
+
+/// An entry of the table of contents
+struct TocEntry {
+    title: String,
+    href: String,
+    children: Vec<TocEntry>,
+}
+
+/// Package sources, in reading order, as an EPUB 3 book. Local images are
+/// embedded and links between sources point to the matching chapter.
+pub fn render_epub(sources: &[EpubSource], options: &EpubOptions) -> Result<Vec<u8>> {
+    let first = sources.first().map_or("", |source| source.text.as_str());
+    let front_matter = parse_front_matter(first).ok().flatten();
+    let string = |key: &str| {
+        front_matter
+            .as_ref()
+            .and_then(|fm| fm.get(key))
+            .and_then(|entry| entry.value.as_str())
+            .map(str::to_string)
+    };
+    let title = options
+        .title
+        .clone()
+        .or_else(|| string("title"))
+        .or_else(|| {
+            let tree = MarkdownTree::parse(first);
+            outline(&tree, first).first().map(|h| h.text.clone())
+        })
+        .unwrap_or_else(|| "Untitled".to_string());
+    let authors: Vec<String> = match &options.author {
+        Some(author) => vec![author.clone()],
+        None => match front_matter.as_ref().and_then(|fm| fm.get("author")) {
+            Some(entry) => match &entry.value {
+                FrontMatterValue::List(items) => items
+                    .iter()
+                    .filter_map(|item| item.as_str().map(str::to_string))
+                    .collect(),
+                value => value.as_str().map(str::to_string).into_iter().collect(),
+            },
+            None => Vec::new(),
+        },
+    };
+    let language = string("lang")
+        .or_else(|| string("language"))
+        .unwrap_or_else(|| "en".to_string());
+
+    let chapters: Vec<Chapter> = sources
+        .iter()
+        .enumerate()
+        .flat_map(|(index, source)| chapters(index, source, &title))
+        .collect();
+    let paths: Vec<Option<PathBuf>> = sources
+        .iter()
+        .map(|source| source.path.as_deref().map(normalize_path))
+        .collect();
+
+    let mut images: Vec<(PathBuf, String, Vec<u8>)> = Vec::new();
+    let mut pages = Vec::new();
+    for (index, chapter) in chapters.iter().enumerate() {
+        let body = html::render_html(
+            &chapter.markdown,
+            &HtmlOptions {
+                standalone: false,
+                ..HtmlOptions::default()
+            },
+        );
+        let base = paths[chapter.source].as_deref().and_then(Path::parent);
+        let body = rewrite_attribute(&body, "src", |src| embed_image(src, base, &mut images));
+        let body = rewrite_attribute(&body, "href", |href| {
+            chapter_link(href, index, &chapters, &paths, base)
+        });
+        let body = format!(
+            "<section epub:type=\"chapter\">\n{}</section>\n",
+            xhtml(&body)
+        );
+        pages.push(page(&chapter.title, &language, &body));
+    }
+
+    let mut entries = Vec::new();
+    for (index, chapter) in chapters.iter().enumerate() {
+        let file = chapter_file(index);
+        entries.push((0, chapter.title.clone(), file.clone()));
+        for (depth, text, anchor) in &chapter.sections {
+            entries.push((*depth, text.clone(), format!("{file}#{anchor}")));
+        }
+    }
+    let toc = toc_tree(&entries);
+
+    let mut nav_list = String::new();
+    write_nav(&toc, &mut nav_list);
+    let nav = page(
+        &title,
+        &language,
+        &format!("<nav epub:type=\"toc\" id=\"toc\">\n<h1>Contents</h1>\n{nav_list}</nav>\n"),
+    );
+
+    let mut hash_input = title.clone();
+    for source in sources {
+        hash_input.push_str(&source.text);
+    }
+    let identifier = format!("urn:rmde:{:016x}", fnv_hash(hash_input.as_bytes()));
+
+    let mut points = String::new();
+    write_nav_points(&toc, &mut 0, &mut points);
+    let ncx = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <ncx xmlns=\"http://www.daisy.org/z3986/2005/ncx/\" version=\"2005-1\">\n\
+         <head>\n<meta name=\"dtb:uid\" content=\"{identifier}\" />\n</head>\n\
+         <docTitle><text>{}</text></docTitle>\n<navMap>\n{points}</navMap>\n</ncx>\n",
+        escape(&title)
+    );
+
+    let mut metadata = String::new();
+    let _ = writeln!(
+        metadata,
+        "<dc:identifier id=\"book-id\">{identifier}</dc:identifier>"
+    );
+    let _ = writeln!(metadata, "<dc:title>{}</dc:title>", escape(&title));
+    for author in &authors {
+        let _ = writeln!(metadata, "<dc:creator>{}</dc:creator>", escape(author));
+    }
+    let _ = writeln!(metadata, "<dc:language>{}</dc:language>", escape(&language));
+    let _ = writeln!(
+        metadata,
+        "<meta property=\"dcterms:modified\">{}T00:00:00Z</meta>",
+        Date::today()
+    );
+    let mut manifest = String::from(
+        "<item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\" />\n\
+         <item id=\"ncx\" href=\"toc.ncx\" media-type=\"application/x-dtbncx+xml\" />\n\
+         <item id=\"style\" href=\"style.css\" media-type=\"text/css\" />\n",
+    );
+    let mut spine = String::from("<itemref idref=\"nav\" />\n");
+    for index in 0..chapters.len() {
+        let _ = writeln!(
+            manifest,
+            "<item id=\"chapter-{n}\" href=\"{}\" media-type=\"application/xhtml+xml\" />",
+            chapter_file(index),
+            n = index + 1
+        );
+        let _ = writeln!(spine, "<itemref idref=\"chapter-{}\" />", index + 1);
+    }
+    for (index, (_, name, _)) in images.iter().enumerate() {
+        let extension = name.rsplit('.').next().unwrap_or_default();
+        let _ = writeln!(
+            manifest,
+            "<item id=\"image-{}\" href=\"{name}\" media-type=\"{}\" />",
+            index + 1,
+            media_type(extension).unwrap_or("application/octet-stream")
+        );
+    }
+    let package = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" \
+         unique-identifier=\"book-id\" xml:lang=\"{lang}\">\n\
+         <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n{metadata}</metadata>\n\
+         <manifest>\n{manifest}</manifest>\n<spine toc=\"ncx\">\n{spine}</spine>\n</package>\n",
+        lang = escape(&language)
+    );
+
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    // Readers identify the format by an uncompressed `mimetype` entry
+    // at the very start of the archive
+    zip.start_file(
+        "mimetype",
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    zip.write_all(b"application/epub+zip")?;
+    let options_deflated =
+        SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let stylesheet = options.stylesheet.as_deref().unwrap_or(EPUB_STYLESHEET);
+    let parts = [
+        ("META-INF/container.xml", CONTAINER.as_bytes()),
+        ("OEBPS/content.opf", package.as_bytes()),
+        ("OEBPS/nav.xhtml", nav.as_bytes()),
+        ("OEBPS/toc.ncx", ncx.as_bytes()),
+        ("OEBPS/style.css", stylesheet.as_bytes()),
+    ];
+    for (name, data) in parts {
+        zip.start_file(name, options_deflated)?;
+        zip.write_all(data)?;
+    }
+    for (index, content) in pages.iter().enumerate() {
+        zip.start_file(format!("OEBPS/{}", chapter_file(index)), options_deflated)?;
+        zip.write_all(content.as_bytes())?;
+    }
+    for (_, name, data) in &images {
+        zip.start_file(format!("OEBPS/{name}"), options_deflated)?;
+        zip.write_all(data)?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+const CONTAINER: &str = "\
+<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">
+<rootfiles>
+<rootfile full-path=\"OEBPS/content.opf\" media-type=\"application/oebps-package+xml\" />
+</rootfiles>
+</container>
+";
+
+/// Split a source at its top-level headings. Content before the first
+/// heading becomes a chapter of its own, titled after the file.
+fn chapters(index: usize, source: &EpubSource, book_title: &str) -> Vec<Chapter> {
+    let text = source.text.as_str();
+    let tree = MarkdownTree::parse(text);
+    let body_start = parse_front_matter(text)
+        .ok()
+        .flatten()
+        .map_or(0, |fm| fm.range.end);
+    let headings: Vec<_> = outline(&tree, text)
+        .into_iter()
+        .filter(|h| h.range.start >= body_start)
+        .collect();
+    let top = headings.iter().map(|h| h.level).min();
+
+    let mut starts: Vec<(usize, Option<String>)> = headings
+        .iter()
+        .filter(|h| Some(h.level) == top)
+        .map(|h| (h.range.start, Some(h.text.clone())))
+        .collect();
+    let first = starts.first().map_or(text.len(), |(start, _)| *start);
+    if !text[body_start..first].trim().is_empty() || starts.is_empty() {
+        starts.insert(0, (body_start, None));
+    }
+
+    let references = definitions(&tree, text);
+    let (notes, note_refs) = footnotes::footnotes(text);
+    let untitled = || {
+        source
+            .path
+            .as_deref()
+            .and_then(Path::file_stem)
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| book_title.to_string())
+    };
+    let inside = |range: &Range<usize>, chunk: &Range<usize>| {
+        range.start >= chunk.start && range.end <= chunk.end
+    };
+
+    let mut chapters = Vec::new();
+    for (i, (start, title)) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).map_or(text.len(), |(next, _)| *next);
+        let chunk = *start..end;
+        let mut markdown = text[chunk.clone()].trim_end().to_string();
+        markdown.push_str("\n\n");
+        for definition in &references {
+            if !inside(&definition.range, &chunk) {
+                markdown.push_str(text[definition.range.clone()].trim_end());
+                markdown.push('\n');
+            }
+        }
+        for note in &notes {
+            let used = note_refs
+                .iter()
+                .any(|r| r.label == note.label && inside(&r.range, &chunk));
+            if used && !inside(&note.range, &chunk) {
+                markdown.push('\n');
+                markdown.push_str(text[note.range.clone()].trim_end());
+                markdown.push('\n');
+            }
+        }
+
+        let chapter_tree = MarkdownTree::parse(&markdown);
+        let chapter_headings = outline(&chapter_tree, &markdown);
+        let anchors = heading_slugs(&chapter_headings);
+        let sections = match top {
+            Some(top) => chapter_headings
+                .iter()
+                .zip(&anchors)
+                .filter(|(h, _)| h.level > top && h.level <= top + 2)
+                .map(|(h, anchor)| ((h.level - top) as usize, h.text.clone(), anchor.clone()))
+                .collect(),
+            None => Vec::new(),
+        };
+        chapters.push(Chapter {
+            source: index,
+            title: title.clone().unwrap_or_else(untitled),
+            markdown,
+            sections,
+            anchors,
+        });
+    }
+    chapters
+}
+
+fn chapter_file(index: usize) -> String {
+    format!("chapter-{}.xhtml", index + 1)
+}
+
+/// Point a link at the chapter holding its target: `#anchor` links to
+/// headings in other chapters of the same source, and links to other
+/// sources of the book
+fn chapter_link(
+    href: &str,
+    current: usize,
+    chapters: &[Chapter],
+    paths: &[Option<PathBuf>],
+    base: Option<&Path>,
+) -> Option<String> {
+    let source = chapters[current].source;
+    let find = |source: usize, anchor: &str| {
+        chapters
+            .iter()
+            .position(|c| c.source == source && c.anchors.iter().any(|a| a == anchor))
+    };
+    if let Some(anchor) = href.strip_prefix('#') {
+        if chapters[current].anchors.iter().any(|a| a == anchor) {
+            return None;
+        }
+        let target = find(source, anchor)?;
+        return Some(format!("{}#{anchor}", chapter_file(target)));
+    }
+    if href.contains("://") || href.starts_with("mailto:") {
+        return None;
+    }
+    let (path, anchor) = match href.split_once('#') {
+        Some((path, anchor)) => (path, Some(anchor)),
+        None => (href, None),
+    };
+    let path = Path::new(path);
+    let path = match base {
+        Some(base) if path.is_relative() => base.join(percent_decode(&path.to_string_lossy())),
+        _ => PathBuf::from(percent_decode(&path.to_string_lossy())),
+    };
+    let path = normalize_path(&path);
+    let target = paths
+        .iter()
+        .position(|p| p.as_deref() == Some(path.as_path()))?;
+    match anchor.and_then(|anchor| Some((find(target, anchor)?, anchor))) {
+        Some((chapter, anchor)) => Some(format!("{}#{anchor}", chapter_file(chapter))),
+        None => {
+            let chapter = chapters.iter().position(|c| c.source == target)?;
+            Some(chapter_file(chapter))
+        }
+    }
+}
+
+/// Copy a local image into the book, returning its path inside the package
+fn embed_image(
+    src: &str,
+    base: Option<&Path>,
+    images: &mut Vec<(PathBuf, String, Vec<u8>)>,
+) -> Option<String> {
+    if src.contains("://") || src.starts_with("data:") {
+        return None;
+    }
+    let path = PathBuf::from(percent_decode(src));
+    let path = match base {
+        Some(base) if path.is_relative() => base.join(path),
+        _ => path,
+    };
+    let path = normalize_path(&path);
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    media_type(&extension)?;
+    if let Some((_, name, _)) = images.iter().find(|(p, ..)| *p == path) {
+        return Some(name.clone());
+    }
+    let data = std::fs::read(&path).ok()?;
+    let name = format!("images/image{}.{extension}", images.len() + 1);
+    images.push((path, name.clone(), data));
+    Some(name)
+}
+
+fn media_type(extension: &str) -> Option<&'static str> {
+    Some(match extension {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        _ => return None,
+    })
+}
+
+/// Replace the values of an attribute in rendered HTML
+fn rewrite_attribute(
+    html: &str,
+    name: &str,
+    mut rewrite: impl FnMut(&str) -> Option<String>,
+) -> String {
+    let pattern = format!(" {name}=\"");
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(&pattern) {
+        let value_start = start + pattern.len();
+        let Some(len) = rest[value_start..].find('"') else {
+            break;
+        };
+        out.push_str(&rest[..value_start]);
+        let value = &rest[value_start..value_start + len];
+        match rewrite(&decode_entities(value)) {
+            Some(new) => out.push_str(&escape(&new)),
+            None => out.push_str(value),
+        }
+        rest = &rest[value_start + len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Make rendered HTML well-formed XML: void elements are self-closed and
+/// named entities other than the XML ones become character references
+fn xhtml(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(i) = rest.find(['<', '&']) {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if rest.starts_with('&') {
+            let entity = rest[1..]
+                .find(';')
+                .filter(|&end| end <= 32)
+                .map(|end| &rest[..end + 2]);
+            match entity {
+                Some(entity)
+                    if !entity.starts_with("&#")
+                        && !matches!(entity, "&amp;" | "&lt;" | "&gt;" | "&quot;" | "&apos;")
+                        && entity[1..entity.len() - 1]
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric()) =>
+                {
+                    let decoded = decode_entity(entity);
+                    if decoded == entity {
+                        out.push_str("&amp;");
+                        rest = &rest[1..];
+                        continue;
+                    }
+                    for c in decoded.chars() {
+                        let _ = write!(out, "&#x{:x};", c as u32);
+                    }
+                    rest = &rest[entity.len()..];
+                }
+                Some(_) => {
+                    out.push('&');
+                    rest = &rest[1..];
+                }
+                None => {
+                    out.push_str("&amp;");
+                    rest = &rest[1..];
+                }
+            }
+            continue;
+        }
+        let Some(end) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[..=end];
+        let name: String = tag[1..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect();
+        if VOID.contains(&name.to_ascii_lowercase().as_str()) && !tag.ends_with("/>") {
+            out.push_str(&tag[..tag.len() - 1]);
+            out.push_str(" />");
+        } else {
+            out.push_str(tag);
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn page(title: &str, language: &str, body: &str) -> String {
+    let language = escape(language);
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" \
+         xml:lang=\"{language}\" lang=\"{language}\">\n<head>\n<meta charset=\"UTF-8\" />\n\
+         <title>{}</title>\n<link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\" />\n\
+         </head>\n<body>\n{body}</body>\n</html>\n",
+        escape(title)
+    )
+}
+
+/// Nest flat `(depth, title, href)` entries under their parents
+fn toc_tree(entries: &[(usize, String, String)]) -> Vec<TocEntry> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < entries.len() {
+        let (depth, title, href) = &entries[i];
+        let end = entries[i + 1..]
+            .iter()
+            .position(|(d, ..)| d <= depth)
+            .map_or(entries.len(), |p| i + 1 + p);
+        out.push(TocEntry {
+            title: title.clone(),
+            href: href.clone(),
+            children: toc_tree(&entries[i + 1..end]),
+        });
+        i = end;
+    }
+    out
+}
+
+fn write_nav(entries: &[TocEntry], out: &mut String) {
+    out.push_str("<ol>\n");
+    for entry in entries {
+        let _ = write!(
+            out,
+            "<li><a href=\"{}\">{}</a>",
+            escape(&entry.href),
+            escape(&entry.title)
+        );
+        if !entry.children.is_empty() {
+            out.push('\n');
+            write_nav(&entry.children, out);
+        }
+        out.push_str("</li>\n");
+    }
+    out.push_str("</ol>\n");
+}
+
+fn write_nav_points(entries: &[TocEntry], order: &mut usize, out: &mut String) {
+    for entry in entries {
+        *order += 1;
+        let _ = write!(
+            out,
+            "<navPoint id=\"navpoint-{order}\" playOrder=\"{order}\">\n\
+             <navLabel><text>{}</text></navLabel>\n<content src=\"{}\" />\n",
+            escape(&entry.title),
+            escape(&entry.href)
+        );
+        write_nav_points(&entry.children, order, out);
+        out.push_str("</navPoint>\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn unpack(epub: &[u8]) -> Vec<(String, String)> {
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(epub)).unwrap();
+        (0..archive.len())
+            .map(|i| {
+                let mut file = archive.by_index(i).unwrap();
+                let mut data = Vec::new();
+                file.read_to_end(&mut data).unwrap();
+                (
+                    file.name().to_string(),
+                    String::from_utf8_lossy(&data).into_owned(),
+                )
+            })
+            .collect()
+    }
+
+    fn part<'a>(files: &'a [(String, String)], name: &str) -> &'a str {
+        &files.iter().find(|(n, _)| n == name).unwrap().1
+    }
+
+    #[test]
+    fn test_epub_package_and_chapters() {
+        let text = "---\ntitle: The Book\nauthor:\n  - Ann\n  - Bo\nlang: fr\n---\n\
+                    Preface text.\n\n# One\n\nSee [two](#sub-two) and [^n].\n\n\
+                    ## Part A\n\n- [x] done\n\n# Two\n\n### Sub two\n\nA&nbsp;b<br>\n\n[^n]: Note.\n";
+        let epub = render_epub(
+            &[EpubSource {
+                text: text.to_string(),
+                path: None,
+            }],
+            &EpubOptions::default(),
+        )
+        .unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(&epub[..])).unwrap();
+        let mimetype = archive.by_index(0).unwrap();
+        assert_eq!(mimetype.name(), "mimetype");
+        assert_eq!(mimetype.compression(), CompressionMethod::Stored);
+        drop(mimetype);
+
+        let files = unpack(&epub);
+        let opf = part(&files, "OEBPS/content.opf");
+        assert!(opf.contains("<dc:title>The Book</dc:title>"));
+        assert!(opf.contains("<dc:creator>Ann</dc:creator>\n<dc:creator>Bo</dc:creator>"));
+        assert!(opf.contains("<dc:language>fr</dc:language>"));
+        assert!(opf.contains("<itemref idref=\"nav\" />\n<itemref idref=\"chapter-1\" />"));
+        assert!(opf.contains("href=\"chapter-3.xhtml\""));
+
+        let nav = part(&files, "OEBPS/nav.xhtml");
+        assert!(nav.contains(
+            "<li><a href=\"chapter-2.xhtml\">One</a>\n<ol>\n\
+             <li><a href=\"chapter-2.xhtml#part-a\">Part A</a></li>\n</ol>\n</li>"
+        ));
+        assert!(nav.contains("<a href=\"chapter-3.xhtml#sub-two\">Sub two</a>"));
+
+        let one = part(&files, "OEBPS/chapter-2.xhtml");
+        assert!(one.contains("<a href=\"chapter-3.xhtml#sub-two\">two</a>"));
+        assert!(one.contains("Note."));
+        assert!(part(&files, "OEBPS/chapter-1.xhtml").contains("<title>The Book</title>"));
+        let xml = roxmltree::ParsingOptions {
+            allow_dtd: true,
+            ..roxmltree::ParsingOptions::default()
+        };
+        for (name, content) in &files {
+            if name.ends_with(".xhtml") || name.ends_with(".opf") || name.ends_with(".ncx") {
+                roxmltree::Document::parse_with_options(content, xml)
+                    .unwrap_or_else(|e| panic!("{name} is not well-formed: {e}"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_epub_book_from_files() {
+        let dir = std::env::temp_dir().join(format!("rmde-epub-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("img")).unwrap();
+        std::fs::write(dir.join("img/cover.png"), b"\x89PNG fake").unwrap();
+        let sources = [
+            EpubSource {
+                text: "# Start\n\n![Cover](img/cover.png)\n\nOn to [the end](end.md#more).\n"
+                    .to_string(),
+                path: Some(dir.join("start.md")),
+            },
+            EpubSource {
+                text: "# End\n\n![Again](./img/cover.png)\n\n# More\n".to_string(),
+                path: Some(dir.join("end.md")),
+            },
+        ];
+        let options = EpubOptions {
+            author: Some("Cy".to_string()),
+            ..EpubOptions::default()
+        };
+        let files = unpack(&render_epub(&sources, &options).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let opf = part(&files, "OEBPS/content.opf");
+        assert!(opf.contains("<dc:title>Start</dc:title>"));
+        assert!(opf.contains("<dc:creator>Cy</dc:creator>"));
+        assert!(opf.contains(
+            "<item id=\"image-1\" href=\"images/image1.png\" media-type=\"image/png\" />"
+        ));
+        assert!(!opf.contains("image-2"));
+        assert_eq!(part(&files, "OEBPS/images/image1.png"), "\u{fffd}PNG fake");
+
+        let start = part(&files, "OEBPS/chapter-1.xhtml");
+        assert!(start.contains("<img src=\"images/image1.png\" alt=\"Cover\" />"));
+        assert!(start.contains("<a href=\"chapter-3.xhtml#more\">the end</a>"));
+        assert!(part(&files, "OEBPS/chapter-2.xhtml").contains("src=\"images/image1.png\""));
+    }
+}
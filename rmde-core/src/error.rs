@@ -1,10 +1,23 @@
+use std::path::PathBuf;
+
 use thiserror::Error;
 
+use crate::encoding::Encoding;
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    #[error("File is read-only: {}", .0.display())]
+    ReadOnly(PathBuf),
+
+    #[error("File is not valid {encoding}: undecodable byte at offset {offset}")]
+    Decode { encoding: Encoding, offset: usize },
+
+    #[error("{encoding} cannot represent {character:?}")]
+    Unencodable { encoding: Encoding, character: char },
+
     #[error("Document not found: {0}")]
     DocumentNotFound(u64),
 
@@ -16,6 +29,9 @@ pub enum Error {
 
     #[error("Parse error: {0}")]
     ParseError(String),
+
+    #[error("Zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -1,9 +1,97 @@
 //! FFI bridge for Swift interop via swift-bridge
 
-use crate::{DocumentId, Editor};
+// swift-bridge's generated glue casts opaque pointers to their own type.
+// The bridge macro drops every attribute but `cfg` from the module, so the
+// lint can't be allowed any closer to the generated code than this file.
+#![allow(clippy::unnecessary_cast)]
+
+use std::path::{Path, PathBuf};
+
+use crate::{
+    Date, Diagnostic, DocumentId, Editor, Encoding, EpubOptions, FrontMatter, FrontMatterValue,
+    GoalStorage, HtmlOptions, IndentSettings, IndentStyle, LineEnding, LinkTarget,
+    NormalizationForm, ProseRules, SiteOptions, SlideOptions, SlideSplit, StatsOptions,
+    TaskProgress, TextStats, WrapSettings, WritingStats,
+};
 
 #[swift_bridge::bridge]
 mod ffi {
+    #[swift_bridge(swift_repr = "struct")]
+    struct FfiDiagnostic {
+        start: usize,
+        end: usize,
+        severity: u8,
+        code: String,
+        message: String,
+    }
+
+    #[swift_bridge(swift_repr = "struct")]
+    struct FfiBacklink {
+        path: String,
+        line: usize,
+        context: String,
+    }
+
+    #[swift_bridge(swift_repr = "struct")]
+    struct FfiAnchorCompletion {
+        slug: String,
+        heading: String,
+        level: u8,
+    }
+
+    #[swift_bridge(swift_repr = "struct")]
+    struct FfiTocEntry {
+        text: String,
+        slug: String,
+        level: u8,
+        depth: usize,
+        line: usize,
+    }
+
+    #[swift_bridge(swift_repr = "struct")]
+    struct FfiInvisibleChar {
+        start: usize,
+        end: usize,
+        code_point: u32,
+        name: String,
+    }
+
+    #[swift_bridge(swift_repr = "struct")]
+    struct FfiLinkUpdate {
+        path: String,
+        line: usize,
+        context: String,
+    }
+
+    #[swift_bridge(swift_repr = "struct")]
+    struct FfiTextStats {
+        words: usize,
+        characters: usize,
+        characters_no_spaces: usize,
+        sentences: usize,
+        paragraphs: usize,
+        reading_time_seconds: u64,
+    }
+
+    #[swift_bridge(swift_repr = "struct")]
+    struct FfiSectionStats {
+        heading: String,
+        level: u8,
+        line: usize,
+        words: usize,
+        characters: usize,
+        reading_time_seconds: u64,
+    }
+
+    #[swift_bridge(swift_repr = "struct")]
+    struct FfiWritingStats {
+        date: String,
+        words_added: usize,
+        words_removed: usize,
+        active_seconds: u64,
+        words_per_minute: f64,
+    }
+
     extern "Rust" {
         type RMDEEditor;
 
@@ -23,9 +111,17 @@ mod ffi {
         // Document content
         fn get_content(&self) -> String;
         fn get_content_length(&self) -> usize;
+        // Chunked reads - re-read only when the active tab or its revision
+        // changes; revisions count per document
+        fn get_content_chunk(&self, start: usize, max_bytes: usize) -> String;
+        fn get_content_revision(&self) -> u64;
         fn insert_text(&mut self, text: &str);
         fn delete_backward(&mut self);
         fn delete_forward(&mut self);
+        fn undo(&mut self) -> bool;
+        fn redo(&mut self) -> bool;
+        fn can_undo(&self) -> bool;
+        fn can_redo(&self) -> bool;
 
         // Cursor/selection
         fn set_cursor(&mut self, pos: usize);
@@ -34,11 +130,151 @@ mod ffi {
         fn select_all(&mut self);
         fn get_cursor_position(&self) -> usize;
 
+        // Indentation
+        fn indent_lines(&mut self);
+        fn outdent_lines(&mut self);
+        fn insert_tab(&mut self);
+        fn set_indent_style(&mut self, use_tabs: bool, tab_width: usize);
+        fn get_indent_uses_tabs(&self) -> bool;
+        fn get_tab_width(&self) -> usize;
+        fn reflow(&mut self);
+        fn set_wrap_settings(&mut self, width: usize, wrap_as_you_type: bool);
+        fn get_wrap_width(&self) -> usize;
+        fn get_wrap_as_you_type(&self) -> bool;
+
+        // Tasks
+        fn toggle_tasks(&mut self);
+        fn set_workspace_root(&mut self, path: &str) -> String;
+        fn get_task_done_count(&self) -> usize;
+        fn get_task_total_count(&self) -> usize;
+
+        // Workspace notes and wiki-links
+        fn refresh_workspace(&mut self) -> String;
+        fn get_backlinks(&mut self) -> Vec<FfiBacklink>;
+        fn get_wiki_completions(&mut self) -> Vec<String>;
+
+        // Links - follow returns empty string on success, error message on failure
+        fn follow_link_at_cursor(&mut self) -> String;
+        fn get_external_link_at_cursor(&self) -> String;
+        fn get_anchor_completions(&self) -> Vec<FfiAnchorCompletion>;
+
+        // Heading rename - preview lists the links that would be updated
+        fn preview_heading_rename(&mut self, new_text: &str) -> Vec<FfiLinkUpdate>;
+        fn rename_heading(&mut self, new_text: &str, update_links: bool) -> String;
+
+        // Reference-style links
+        fn links_to_reference(&mut self);
+        fn links_to_inline(&mut self);
+        fn collect_link_definitions(&mut self);
+        fn remove_unused_link_definitions(&mut self);
+
+        // Footnotes
+        fn insert_footnote(&mut self);
+        fn renumber_footnotes(&mut self);
+        fn jump_to_footnote(&mut self) -> bool;
+        fn remove_unused_footnotes(&mut self);
+
+        // Table of contents - also regenerated between its markers on save
+        fn get_toc(&self, max_level: u8) -> Vec<FfiTocEntry>;
+        fn insert_toc(&mut self, max_level: u8);
+        fn update_toc(&mut self) -> bool;
+
+        // Statistics
+        fn set_stats_options(
+            &mut self,
+            exclude_code: bool,
+            exclude_front_matter: bool,
+            words_per_minute: u32,
+        );
+        fn get_document_stats(&mut self) -> FfiTextStats;
+        fn get_section_stats(&mut self) -> Vec<FfiSectionStats>;
+        fn get_selection_stats(&mut self) -> FfiTextStats;
+
+        // Writing goals and history - setters return empty string on success,
+        // error message on failure. A goal of 0 means none.
+        fn get_word_goal(&self) -> usize;
+        fn set_word_goal(&mut self, goal: usize, in_front_matter: bool) -> String;
+        fn get_goal_fraction(&mut self) -> f64;
+        fn get_session_stats(&mut self) -> FfiWritingStats;
+        fn set_writing_history_path(&mut self, path: &str) -> String;
+        fn save_writing_history(&mut self) -> String;
+        fn get_writing_history(&mut self, from: &str, to: &str) -> Vec<FfiWritingStats>;
+
+        // Spell checking - setters return empty string on success, error message on failure
+        fn load_dictionary(&mut self, aff_path: &str, dic_path: &str) -> String;
+        fn set_personal_word_list(&mut self, path: &str) -> String;
+        fn add_to_dictionary(&mut self, word: &str) -> String;
+        fn ignore_word(&mut self, word: &str) -> String;
+        fn get_spelling_suggestions(&mut self, word: &str) -> Vec<String>;
+        fn get_spelling_suggestions_at_cursor(&mut self) -> Vec<String>;
+
+        // Prose style checks - a limit of 0 turns that check off
+        fn set_prose_rules(
+            &mut self,
+            repeated_words: bool,
+            passive_voice: bool,
+            max_sentence_words: usize,
+            max_paragraph_words: usize,
+        );
+        fn set_weasel_words(&mut self, words: Vec<String>);
+        fn load_glossary(&mut self, path: &str) -> String;
+
+        // HTML export - the file variant returns empty string on success, error message on failure
+        fn export_html(&self, standalone: bool) -> String;
+        fn export_html_to_file(&self, path: &str, standalone: bool) -> String;
+        fn build_site(&self, output_path: &str, title: &str) -> String;
+
+        // Slide export - a split level of 0 splits on `---` only
+        fn export_slides_to_file(&self, path: &str, split_level: u8) -> String;
+
+        // DOCX export - returns empty string on success, error message on failure
+        fn export_docx_to_file(&self, path: &str) -> String;
+
+        // EPUB export - the book variant joins files in the given order
+        fn export_epub_to_file(&self, path: &str) -> String;
+        fn export_book_to_file(&self, files: Vec<String>, path: &str) -> String;
+
+        // Import as Markdown - the file variant opens a new tab and returns
+        // empty string on success, error message on failure
+        fn import_docx_file(&mut self, path: &str) -> String;
+        fn import_html(&mut self, html: &str) -> u64;
+        fn paste_html(&mut self, html: &str);
+
+        // Front matter - setters return empty string on success, error message on failure
+        fn get_front_matter_keys(&self) -> Vec<String>;
+        fn get_front_matter_value(&self, key: &str) -> String;
+        fn set_front_matter_string(&mut self, key: &str, value: &str) -> String;
+        fn set_front_matter_list(&mut self, key: &str, items: Vec<String>) -> String;
+        fn remove_front_matter_key(&mut self, key: &str) -> String;
+
+        // Diagnostics for the active document
+        fn get_diagnostics(&mut self) -> Vec<FfiDiagnostic>;
+
         // File operations - returns empty string on success, error message on failure
         fn open_file(&mut self, path: &str) -> String;
         fn save_file(&mut self) -> String;
         fn save_file_as(&mut self, path: &str) -> String;
 
+        // Encodings by name ("UTF-8", "UTF-16 LE", "Windows-1252"...) -
+        // returns empty string on success, error message on failure
+        fn get_encoding(&self) -> String;
+        fn get_encoding_names(&self) -> Vec<String>;
+        fn reopen_with_encoding(&mut self, name: &str) -> String;
+        fn save_with_encoding(&mut self, name: &str) -> String;
+
+        // Line endings by name ("LF", "CRLF", "CR") - convert returns empty
+        // string on success, error message on failure
+        fn get_line_ending(&self) -> String;
+        fn has_mixed_line_endings(&self) -> bool;
+        fn convert_line_endings(&mut self, name: &str) -> String;
+
+        // Unicode hygiene - normalization forms by name ("NFC", "NFD"),
+        // applied to the selection or the whole document if nothing is
+        // selected; returns empty string on success, error message on failure
+        fn get_invisible_chars(&self) -> Vec<FfiInvisibleChar>;
+        fn normalize_unicode(&mut self, form: &str) -> String;
+        fn strip_invisible_chars(&mut self);
+
         // Document info
         fn is_dirty(&self) -> bool;
         fn get_title(&self) -> String;
@@ -96,6 +332,16 @@ impl RMDEEditor {
             .unwrap_or(0)
     }
 
+    fn get_content_chunk(&self, start: usize, max_bytes: usize) -> String {
+        self.inner
+            .content_chunk(start, max_bytes)
+            .unwrap_or_default()
+    }
+
+    fn get_content_revision(&self) -> u64 {
+        self.inner.active().map_or(0, |d| d.revision())
+    }
+
     fn insert_text(&mut self, text: &str) {
         let _ = self.inner.insert(text);
     }
@@ -108,6 +354,22 @@ impl RMDEEditor {
         let _ = self.inner.delete_forward();
     }
 
+    fn undo(&mut self) -> bool {
+        self.inner.undo().unwrap_or(false)
+    }
+
+    fn redo(&mut self) -> bool {
+        self.inner.redo().unwrap_or(false)
+    }
+
+    fn can_undo(&self) -> bool {
+        self.inner.active().map(|d| d.can_undo()).unwrap_or(false)
+    }
+
+    fn can_redo(&self) -> bool {
+        self.inner.active().map(|d| d.can_redo()).unwrap_or(false)
+    }
+
     fn set_cursor(&mut self, pos: usize) {
         let _ = self.inner.set_cursor(pos);
     }
@@ -131,6 +393,541 @@ impl RMDEEditor {
             .unwrap_or(0)
     }
 
+    fn indent_lines(&mut self) {
+        let _ = self.inner.indent();
+    }
+
+    fn outdent_lines(&mut self) {
+        let _ = self.inner.outdent();
+    }
+
+    fn insert_tab(&mut self) {
+        let _ = self.inner.insert_tab();
+    }
+
+    fn set_indent_style(&mut self, use_tabs: bool, tab_width: usize) {
+        let settings = if use_tabs {
+            IndentSettings::tabs(tab_width)
+        } else {
+            IndentSettings::spaces(tab_width)
+        };
+        let _ = self.inner.set_indent_settings(settings);
+    }
+
+    fn get_indent_uses_tabs(&self) -> bool {
+        self.inner
+            .indent_settings()
+            .map(|s| s.style() == IndentStyle::Tabs)
+            .unwrap_or(false)
+    }
+
+    fn get_tab_width(&self) -> usize {
+        self.inner
+            .indent_settings()
+            .map(|s| s.tab_width())
+            .unwrap_or_else(|_| IndentSettings::default().tab_width())
+    }
+
+    fn reflow(&mut self) {
+        let _ = self.inner.reflow();
+    }
+
+    fn set_wrap_settings(&mut self, width: usize, wrap_as_you_type: bool) {
+        let _ = self.inner.set_wrap_settings(WrapSettings {
+            width: width.max(1),
+            wrap_as_you_type,
+        });
+    }
+
+    fn get_wrap_width(&self) -> usize {
+        self.inner
+            .wrap_settings()
+            .map(|s| s.width)
+            .unwrap_or_else(|_| WrapSettings::default().width)
+    }
+
+    fn get_wrap_as_you_type(&self) -> bool {
+        self.inner
+            .wrap_settings()
+            .map(|s| s.wrap_as_you_type)
+            .unwrap_or(false)
+    }
+
+    fn toggle_tasks(&mut self) {
+        let _ = self.inner.toggle_tasks();
+    }
+
+    fn set_workspace_root(&mut self, path: &str) -> String {
+        let root = (!path.is_empty()).then(|| PathBuf::from(path));
+        match self.inner.set_workspace_root(root) {
+            Ok(_) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn refresh_workspace(&mut self) -> String {
+        match self.inner.refresh_workspace() {
+            Ok(_) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn get_backlinks(&mut self) -> Vec<ffi::FfiBacklink> {
+        self.inner
+            .backlinks()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|b| ffi::FfiBacklink {
+                path: b.source.to_string_lossy().into_owned(),
+                line: b.line,
+                context: b.context,
+            })
+            .collect()
+    }
+
+    fn get_wiki_completions(&mut self) -> Vec<String> {
+        self.inner.wiki_completions().unwrap_or_default()
+    }
+
+    fn get_task_done_count(&self) -> usize {
+        self.active_task_progress().done
+    }
+
+    fn get_task_total_count(&self) -> usize {
+        self.active_task_progress().total
+    }
+
+    fn active_task_progress(&self) -> TaskProgress {
+        self.inner
+            .active()
+            .map(|d| TaskProgress::of(&d.tasks()))
+            .unwrap_or_default()
+    }
+
+    fn set_stats_options(
+        &mut self,
+        exclude_code: bool,
+        exclude_front_matter: bool,
+        words_per_minute: u32,
+    ) {
+        self.inner.set_stats_options(StatsOptions {
+            exclude_code,
+            exclude_front_matter,
+            words_per_minute,
+        });
+    }
+
+    fn get_document_stats(&mut self) -> ffi::FfiTextStats {
+        let stats = self.inner.stats().unwrap_or_default();
+        self.text_stats(stats.total)
+    }
+
+    fn get_section_stats(&mut self) -> Vec<ffi::FfiSectionStats> {
+        let wpm = self.inner.stats_options().words_per_minute;
+        self.inner
+            .stats()
+            .unwrap_or_default()
+            .sections
+            .into_iter()
+            .map(|s| ffi::FfiSectionStats {
+                heading: s.heading.text,
+                level: s.heading.level,
+                line: s.heading.line,
+                words: s.stats.words,
+                characters: s.stats.characters,
+                reading_time_seconds: s.stats.reading_time_seconds(wpm),
+            })
+            .collect()
+    }
+
+    fn get_selection_stats(&mut self) -> ffi::FfiTextStats {
+        let stats = self.inner.selection_stats().unwrap_or_default();
+        self.text_stats(stats)
+    }
+
+    fn text_stats(&self, stats: TextStats) -> ffi::FfiTextStats {
+        let wpm = self.inner.stats_options().words_per_minute;
+        ffi::FfiTextStats {
+            words: stats.words,
+            characters: stats.characters,
+            characters_no_spaces: stats.characters_no_spaces,
+            sentences: stats.sentences,
+            paragraphs: stats.paragraphs,
+            reading_time_seconds: stats.reading_time_seconds(wpm),
+        }
+    }
+
+    fn get_word_goal(&self) -> usize {
+        self.inner.word_goal().ok().flatten().unwrap_or(0)
+    }
+
+    fn set_word_goal(&mut self, goal: usize, in_front_matter: bool) -> String {
+        let storage = if in_front_matter {
+            GoalStorage::FrontMatter
+        } else {
+            GoalStorage::Sidecar
+        };
+        let goal = Some(goal).filter(|g| *g > 0);
+        match self.inner.set_word_goal(goal, storage) {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn get_goal_fraction(&mut self) -> f64 {
+        match self.inner.goal_progress() {
+            Ok(Some(progress)) => progress.fraction(),
+            _ => 0.0,
+        }
+    }
+
+    fn get_session_stats(&mut self) -> ffi::FfiWritingStats {
+        writing_stats(String::new(), self.inner.session_stats())
+    }
+
+    fn set_writing_history_path(&mut self, path: &str) -> String {
+        match self.inner.set_writing_history_path(path) {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn save_writing_history(&mut self) -> String {
+        match self.inner.save_writing_history() {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn get_writing_history(&mut self, from: &str, to: &str) -> Vec<ffi::FfiWritingStats> {
+        let (Some(from), Some(to)) = (Date::parse(from), Date::parse(to)) else {
+            return Vec::new();
+        };
+        self.inner
+            .writing_history(from, to)
+            .into_iter()
+            .map(|(date, stats)| writing_stats(date.to_string(), stats))
+            .collect()
+    }
+
+    fn load_dictionary(&mut self, aff_path: &str, dic_path: &str) -> String {
+        match self
+            .inner
+            .load_dictionary(Path::new(aff_path), Path::new(dic_path))
+        {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn set_personal_word_list(&mut self, path: &str) -> String {
+        match self.inner.set_personal_word_list(path) {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn add_to_dictionary(&mut self, word: &str) -> String {
+        match self.inner.add_to_dictionary(word) {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn ignore_word(&mut self, word: &str) -> String {
+        match self.inner.ignore_word(word) {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn get_spelling_suggestions(&mut self, word: &str) -> Vec<String> {
+        self.inner.spelling_suggestions(word)
+    }
+
+    fn get_spelling_suggestions_at_cursor(&mut self) -> Vec<String> {
+        self.inner
+            .spelling_suggestions_at_cursor()
+            .unwrap_or_default()
+    }
+
+    fn set_prose_rules(
+        &mut self,
+        repeated_words: bool,
+        passive_voice: bool,
+        max_sentence_words: usize,
+        max_paragraph_words: usize,
+    ) {
+        let limit = |max: usize| (max > 0).then_some(max);
+        self.inner.set_prose_rules(ProseRules {
+            repeated_words,
+            passive_voice,
+            max_sentence_words: limit(max_sentence_words),
+            max_paragraph_words: limit(max_paragraph_words),
+            ..self.inner.prose_rules().clone()
+        });
+    }
+
+    fn set_weasel_words(&mut self, words: Vec<String>) {
+        self.inner.set_prose_rules(ProseRules {
+            weasel_words: words,
+            ..self.inner.prose_rules().clone()
+        });
+    }
+
+    fn load_glossary(&mut self, path: &str) -> String {
+        match self.inner.load_glossary(Path::new(path)) {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn export_html(&self, standalone: bool) -> String {
+        let options = HtmlOptions {
+            standalone,
+            ..HtmlOptions::default()
+        };
+        self.inner.export_html(&options).unwrap_or_default()
+    }
+
+    fn export_html_to_file(&self, path: &str, standalone: bool) -> String {
+        let options = HtmlOptions {
+            standalone,
+            ..HtmlOptions::default()
+        };
+        match self.inner.export_html_to(Path::new(path), &options) {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn build_site(&self, output_path: &str, title: &str) -> String {
+        let mut options = SiteOptions::default();
+        if !title.is_empty() {
+            options.title = title.to_string();
+        }
+        match self.inner.build_site(Path::new(output_path), &options) {
+            Ok(_) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn export_slides_to_file(&self, path: &str, split_level: u8) -> String {
+        let options = SlideOptions {
+            split: match split_level {
+                0 => SlideSplit::Rule,
+                level => SlideSplit::Heading(level.min(6)),
+            },
+            ..SlideOptions::default()
+        };
+        match self.inner.export_slides_to(Path::new(path), &options) {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn export_docx_to_file(&self, path: &str) -> String {
+        match self.inner.export_docx_to(Path::new(path)) {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn export_epub_to_file(&self, path: &str) -> String {
+        match self
+            .inner
+            .export_epub_to(Path::new(path), &EpubOptions::default())
+        {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn export_book_to_file(&self, files: Vec<String>, path: &str) -> String {
+        let files: Vec<PathBuf> = files.into_iter().map(PathBuf::from).collect();
+        match self
+            .inner
+            .export_book_to(&files, Path::new(path), &EpubOptions::default())
+        {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn import_docx_file(&mut self, path: &str) -> String {
+        match self.inner.import_docx(Path::new(path)) {
+            Ok(_) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn import_html(&mut self, html: &str) -> u64 {
+        self.inner.import_html(html).as_u64()
+    }
+
+    fn paste_html(&mut self, html: &str) {
+        let _ = self.inner.paste_html(html);
+    }
+
+    fn follow_link_at_cursor(&mut self) -> String {
+        match self.inner.follow_link_at_cursor() {
+            Ok(_) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn get_external_link_at_cursor(&self) -> String {
+        let Some(doc) = self.inner.active() else {
+            return String::new();
+        };
+        match doc.link_target_at(doc.primary_selection().head, self.inner.workspace_root()) {
+            Some(LinkTarget::External(url)) => url,
+            _ => String::new(),
+        }
+    }
+
+    fn links_to_reference(&mut self) {
+        let _ = self.inner.links_to_reference();
+    }
+
+    fn links_to_inline(&mut self) {
+        let _ = self.inner.links_to_inline();
+    }
+
+    fn collect_link_definitions(&mut self) {
+        let _ = self.inner.collect_link_definitions();
+    }
+
+    fn remove_unused_link_definitions(&mut self) {
+        let _ = self.inner.remove_unused_link_definitions();
+    }
+
+    fn insert_footnote(&mut self) {
+        let _ = self.inner.insert_footnote();
+    }
+
+    fn renumber_footnotes(&mut self) {
+        let _ = self.inner.renumber_footnotes();
+    }
+
+    fn jump_to_footnote(&mut self) -> bool {
+        self.inner.jump_to_footnote().unwrap_or(false)
+    }
+
+    fn remove_unused_footnotes(&mut self) {
+        let _ = self.inner.remove_unused_footnotes();
+    }
+
+    fn get_toc(&self, max_level: u8) -> Vec<ffi::FfiTocEntry> {
+        self.inner
+            .toc(max_level)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|e| ffi::FfiTocEntry {
+                text: e.text,
+                slug: e.slug,
+                level: e.level,
+                depth: e.depth,
+                line: e.line,
+            })
+            .collect()
+    }
+
+    fn insert_toc(&mut self, max_level: u8) {
+        let _ = self.inner.insert_toc(max_level);
+    }
+
+    fn update_toc(&mut self) -> bool {
+        self.inner.update_toc().unwrap_or(false)
+    }
+
+    fn get_anchor_completions(&self) -> Vec<ffi::FfiAnchorCompletion> {
+        self.inner
+            .anchor_completions()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|c| ffi::FfiAnchorCompletion {
+                slug: c.slug,
+                heading: c.heading,
+                level: c.level,
+            })
+            .collect()
+    }
+
+    fn preview_heading_rename(&mut self, new_text: &str) -> Vec<ffi::FfiLinkUpdate> {
+        let Ok(Some(rename)) = self.inner.heading_rename_preview(new_text) else {
+            return Vec::new();
+        };
+        rename
+            .link_updates
+            .into_iter()
+            .map(|u| ffi::FfiLinkUpdate {
+                path: u
+                    .path
+                    .map(|p| p.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                line: u.line,
+                context: u.context,
+            })
+            .collect()
+    }
+
+    fn rename_heading(&mut self, new_text: &str, update_links: bool) -> String {
+        match self.inner.rename_heading(new_text, update_links) {
+            Ok(_) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn get_front_matter_keys(&self) -> Vec<String> {
+        self.active_front_matter()
+            .map(|fm| fm.entries.into_iter().map(|e| e.key).collect())
+            .unwrap_or_default()
+    }
+
+    fn get_front_matter_value(&self, key: &str) -> String {
+        self.active_front_matter()
+            .and_then(|fm| fm.get(key).map(|e| e.value.to_string()))
+            .unwrap_or_default()
+    }
+
+    fn set_front_matter_string(&mut self, key: &str, value: &str) -> String {
+        let value = FrontMatterValue::String(value.to_string());
+        match self.inner.set_front_matter(key, &value) {
+            Ok(_) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn set_front_matter_list(&mut self, key: &str, items: Vec<String>) -> String {
+        let value =
+            FrontMatterValue::List(items.into_iter().map(FrontMatterValue::String).collect());
+        match self.inner.set_front_matter(key, &value) {
+            Ok(_) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn remove_front_matter_key(&mut self, key: &str) -> String {
+        match self.inner.remove_front_matter(key) {
+            Ok(_) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn active_front_matter(&self) -> Option<FrontMatter> {
+        self.inner.active()?.front_matter().ok().flatten()
+    }
+
+    fn get_diagnostics(&mut self) -> Vec<ffi::FfiDiagnostic> {
+        self.inner
+            .diagnostics()
+            .unwrap_or_default()
+            .into_iter()
+            .map(ffi::FfiDiagnostic::from)
+            .collect()
+    }
+
     fn open_file(&mut self, path: &str) -> String {
         match self.inner.open_file(path) {
             Ok(_) => String::new(),
@@ -152,6 +949,88 @@ impl RMDEEditor {
         }
     }
 
+    fn get_encoding(&self) -> String {
+        self.inner
+            .encoding()
+            .map(|e| e.name().to_string())
+            .unwrap_or_default()
+    }
+
+    fn get_encoding_names(&self) -> Vec<String> {
+        Encoding::ALL.iter().map(|e| e.name().to_string()).collect()
+    }
+
+    fn reopen_with_encoding(&mut self, name: &str) -> String {
+        let Some(encoding) = Encoding::from_name(name) else {
+            return format!("Unknown encoding: {name}");
+        };
+        match self.inner.reopen_with_encoding(encoding) {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn save_with_encoding(&mut self, name: &str) -> String {
+        let Some(encoding) = Encoding::from_name(name) else {
+            return format!("Unknown encoding: {name}");
+        };
+        match self.inner.save_with_encoding(encoding) {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn get_line_ending(&self) -> String {
+        self.inner
+            .line_ending()
+            .map(|e| e.name().to_string())
+            .unwrap_or_default()
+    }
+
+    fn has_mixed_line_endings(&self) -> bool {
+        self.inner
+            .line_ending_counts()
+            .is_ok_and(|counts| counts.is_mixed())
+    }
+
+    fn convert_line_endings(&mut self, name: &str) -> String {
+        let Some(ending) = LineEnding::from_name(name) else {
+            return format!("Unknown line ending: {name}");
+        };
+        match self.inner.convert_line_endings(ending) {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn get_invisible_chars(&self) -> Vec<ffi::FfiInvisibleChar> {
+        self.inner
+            .invisible_chars()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|c| ffi::FfiInvisibleChar {
+                start: c.range.start,
+                end: c.range.end,
+                code_point: u32::from(c.ch),
+                name: c.name.to_string(),
+            })
+            .collect()
+    }
+
+    fn normalize_unicode(&mut self, form: &str) -> String {
+        let Some(form) = NormalizationForm::from_name(form) else {
+            return format!("Unknown normalization form: {form}");
+        };
+        match self.inner.normalize_unicode(form) {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn strip_invisible_chars(&mut self) {
+        let _ = self.inner.strip_invisible_chars();
+    }
+
     fn is_dirty(&self) -> bool {
         self.inner.active().map(|d| d.is_dirty()).unwrap_or(false)
     }
@@ -160,3 +1039,26 @@ impl RMDEEditor {
         self.inner.active().map(|d| d.title()).unwrap_or_default()
     }
 }
+
+impl From<Diagnostic> for ffi::FfiDiagnostic {
+    fn from(d: Diagnostic) -> Self {
+        Self {
+            start: d.range.start,
+            end: d.range.end,
+            severity: d.severity as u8,
+            code: d.code.to_string(),
+            message: d.message,
+        }
+    }
+}
+
+/// Writing statistics for Swift; `date` is empty for the session
+fn writing_stats(date: String, stats: WritingStats) -> ffi::FfiWritingStats {
+    ffi::FfiWritingStats {
+        date,
+        words_added: stats.words_added,
+        words_removed: stats.words_removed,
+        active_seconds: stats.active_time.as_secs(),
+        words_per_minute: stats.words_per_minute(),
+    }
+}
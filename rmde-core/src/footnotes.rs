@@ -0,0 +1,424 @@
+//! Footnotes (`[^label]`): insertion, renumbering, navigation and checks
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::edit::TextEdit;
+use crate::references::{normalize_label, removal_edits, strip_brackets};
+use crate::selection::Selection;
+use crate::syntax::{MarkdownTree, child_of_kind};
+use crate::text::{line_of, line_starts, line_text};
+
+/// A footnote definition (`[^label]: text`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FootnoteDefinition {
+    /// Label without `[^` and `]`
+    pub label: String,
+    /// Byte range of the `[^label]`
+    pub label_range: Range<usize>,
+    /// Byte offset where the footnote text starts
+    pub content_start: usize,
+    /// Byte range of the whole definition, including continuation lines
+    /// and the final line break
+    pub range: Range<usize>,
+}
+
+/// A footnote reference (`[^label]`) in the text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FootnoteReference {
+    /// Label without `[^` and `]`
+    pub label: String,
+    pub range: Range<usize>,
+}
+
+/// Collect footnote definitions and references in document order.
+///
+/// The Markdown grammar has no footnote nodes: definitions parse either as
+/// link reference definitions or, when their text has spaces, as a
+/// shortcut link at the start of a paragraph line followed by `:`.
+pub fn footnotes(text: &str) -> (Vec<FootnoteDefinition>, Vec<FootnoteReference>) {
+    let tree = MarkdownTree::parse(text);
+    let starts = line_starts(text);
+    let mut labels: Vec<(Range<usize>, bool)> = Vec::new();
+
+    for node in tree.nodes_of_kind("link_reference_definition") {
+        if let Some(label) = child_of_kind(node, "link_label")
+            && text[label.byte_range()].starts_with("[^")
+        {
+            labels.push((label.byte_range(), true));
+        }
+    }
+    tree.visit(&mut |node| {
+        if node.kind() != "shortcut_link" || !text[node.byte_range()].starts_with("[^") {
+            return;
+        }
+        let range = node.byte_range();
+        let line_start = starts[line_of(&starts, range.start)];
+        let at_line_start =
+            text[line_start..range.start].trim().is_empty() && range.start - line_start <= 3;
+        let definition = at_line_start && text[range.end..].starts_with(':');
+        labels.push((range, definition));
+    });
+    labels.sort_by_key(|(range, _)| range.start);
+
+    let definition_lines: HashSet<usize> = labels
+        .iter()
+        .filter(|(_, def)| *def)
+        .map(|(range, _)| line_of(&starts, range.start))
+        .collect();
+
+    let mut definitions = Vec::new();
+    let mut references = Vec::new();
+    for (range, definition) in labels {
+        let label = label_of(&text[range.clone()]);
+        if !definition {
+            references.push(FootnoteReference { label, range });
+            continue;
+        }
+        let after = &text[range.end + 1..];
+        let content_start =
+            range.end + 1 + (after.len() - after.trim_start_matches([' ', '\t']).len());
+        let first_line = line_of(&starts, range.start);
+        let last_line = definition_end_line(text, &starts, first_line, &definition_lines);
+        let end = starts.get(last_line + 1).copied().unwrap_or(text.len());
+        definitions.push(FootnoteDefinition {
+            label,
+            label_range: range,
+            content_start,
+            range: starts[first_line]..end,
+        });
+    }
+    (definitions, references)
+}
+
+fn label_of(source: &str) -> String {
+    strip_brackets(source).trim_start_matches('^').to_string()
+}
+
+/// Last line of a definition: continuation lines run until another
+/// definition, or a blank line followed by unindented text
+fn definition_end_line(
+    text: &str,
+    starts: &[usize],
+    first: usize,
+    definition_lines: &HashSet<usize>,
+) -> usize {
+    let mut last = first;
+    let mut line = first + 1;
+    while line < starts.len() && starts[line] < text.len() {
+        if definition_lines.contains(&line) {
+            break;
+        }
+        let content = line_text(text, starts, line);
+        if content.trim().is_empty() {
+            line += 1;
+            continue;
+        }
+        let indented = content.starts_with("    ") || content.starts_with('\t');
+        let after_blank = line_text(text, starts, line - 1).trim().is_empty();
+        if after_blank && !indented {
+            break;
+        }
+        last = line;
+        line += 1;
+    }
+    last
+}
+
+/// Edits inserting a new numbered footnote reference at each selection's
+/// end, with empty definitions appended after the existing ones (or at the
+/// end of the document). Also returns the cursor positions after the edits,
+/// just past each new reference.
+pub(crate) fn insert_edits(text: &str, selections: &[Selection]) -> (Vec<TextEdit>, Vec<usize>) {
+    let (definitions, references) = footnotes(text);
+    let first = definitions
+        .iter()
+        .map(|d| d.label.as_str())
+        .chain(references.iter().map(|r| r.label.as_str()))
+        .filter_map(|l| l.parse::<usize>().ok())
+        .max()
+        .unwrap_or(0)
+        + 1;
+
+    let mut sorted = selections.to_vec();
+    sorted.sort_by_key(|s| s.end());
+    let mut edits = Vec::new();
+    let mut cursors = Vec::new();
+    let mut inserted = 0;
+    let mut new_definitions = String::new();
+    for (number, sel) in (first..).zip(sorted) {
+        let reference = format!("[^{number}]");
+        inserted += reference.len();
+        cursors.push(sel.end() + inserted);
+        edits.push(TextEdit::insert(sel.end(), reference));
+        new_definitions.push_str(&format!("[^{number}]: \n"));
+    }
+
+    let (at, separator) = match definitions.last() {
+        Some(last) if text[..last.range.end].ends_with('\n') => (last.range.end, ""),
+        Some(last) => (last.range.end, "\n"),
+        None if text.is_empty() || text.ends_with("\n\n") => (text.len(), ""),
+        None if text.ends_with('\n') => (text.len(), "\n"),
+        None => (text.len(), "\n\n"),
+    };
+    let definitions = format!("{separator}{new_definitions}");
+    // Cursors past the insertion point (text after the footnotes) shift too
+    for (cursor, edit) in cursors.iter_mut().zip(&edits) {
+        if edit.range.start > at {
+            *cursor += definitions.len();
+        }
+    }
+    edits.push(TextEdit::insert(at, definitions));
+    (edits, cursors)
+}
+
+/// Edits renumbering numeric footnotes 1, 2, ... in order of first
+/// reference; unreferenced ones follow in definition order. A block of
+/// numeric definitions is also sorted into the new order.
+pub(crate) fn renumber_edits(text: &str) -> Vec<TextEdit> {
+    let (definitions, references) = footnotes(text);
+    let is_numeric = |label: &str| !label.is_empty() && label.bytes().all(|b| b.is_ascii_digit());
+
+    let mut numbers: HashMap<String, usize> = HashMap::new();
+    let labels = references
+        .iter()
+        .map(|r| &r.label)
+        .chain(definitions.iter().map(|d| &d.label));
+    for label in labels {
+        if is_numeric(label) && !numbers.contains_key(label) {
+            let number = numbers.len() + 1;
+            numbers.insert(label.clone(), number);
+        }
+    }
+    let relabel = |label: &str| numbers.get(label).map(|n| n.to_string());
+
+    // Adjacent numeric definitions are rewritten as one sorted block
+    let block = numeric_block(text, &definitions, &is_numeric);
+    let mut edits = Vec::new();
+    if let Some(block) = &block {
+        let mut sorted: Vec<&FootnoteDefinition> = block.iter().map(|&i| &definitions[i]).collect();
+        sorted.sort_by_key(|d| numbers.get(&d.label));
+        let rewritten: String = sorted
+            .iter()
+            .map(|d| {
+                let body = text[d.label_range.end..d.range.end].trim_end();
+                format!("[^{}]{body}\n", numbers[&d.label])
+            })
+            .collect();
+        let first = &definitions[block[0]];
+        let last = &definitions[*block.last().expect("block is non-empty")];
+        let span = first.range.start..last.range.end;
+        let original = &text[span.clone()];
+        let trailing = &original[original.trim_end().len()..];
+        let rewritten = format!("{}{}", rewritten.trim_end(), trailing.trim_end_matches(' '));
+        if rewritten != original {
+            edits.push(TextEdit::new(span, rewritten));
+        }
+    }
+
+    let in_block = |i: usize| block.as_ref().is_some_and(|b| b.contains(&i));
+    let label_ranges = references
+        .iter()
+        .map(|r| (&r.label, r.range.clone()))
+        .chain(
+            definitions
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !in_block(*i))
+                .map(|(_, d)| (&d.label, d.label_range.clone())),
+        );
+    for (label, range) in label_ranges {
+        if let Some(new) = relabel(label)
+            && &new != label
+        {
+            edits.push(TextEdit::new(range.start + 2..range.end - 1, new));
+        }
+    }
+    edits
+}
+
+/// Indices of the definitions forming the last run of adjacent numeric
+/// definitions, when there are at least two
+fn numeric_block(
+    text: &str,
+    definitions: &[FootnoteDefinition],
+    is_numeric: &dyn Fn(&str) -> bool,
+) -> Option<Vec<usize>> {
+    let mut runs: Vec<Vec<usize>> = Vec::new();
+    for (i, def) in definitions.iter().enumerate() {
+        if !is_numeric(&def.label) {
+            runs.push(Vec::new());
+            continue;
+        }
+        match runs.last_mut() {
+            Some(run)
+                if run.last().is_some_and(|&p| {
+                    text[definitions[p].range.end..def.range.start]
+                        .trim()
+                        .is_empty()
+                }) =>
+            {
+                run.push(i)
+            }
+            _ => runs.push(vec![i]),
+        }
+    }
+    runs.into_iter().rfind(|run| run.len() > 1)
+}
+
+/// Where to jump from a position: from a reference to its definition's
+/// text, from a definition to its first reference
+pub(crate) fn jump_target(text: &str, pos: usize) -> Option<usize> {
+    let (definitions, references) = footnotes(text);
+    let same = |a: &str, b: &str| normalize_label(a) == normalize_label(b);
+
+    if let Some(reference) = references
+        .iter()
+        .find(|r| r.range.start <= pos && pos <= r.range.end)
+    {
+        return definitions
+            .iter()
+            .find(|d| same(&d.label, &reference.label))
+            .map(|d| d.content_start);
+    }
+    let definition = definitions
+        .iter()
+        .find(|d| d.range.start <= pos && pos < d.range.end.max(d.range.start + 1))?;
+    references
+        .iter()
+        .find(|r| same(&r.label, &definition.label))
+        .map(|r| r.range.start)
+}
+
+/// Edits removing definitions that no reference uses
+pub(crate) fn remove_unused_edits(text: &str) -> Vec<TextEdit> {
+    let (definitions, references) = footnotes(text);
+    let used: HashSet<String> = references
+        .iter()
+        .map(|r| normalize_label(&r.label))
+        .collect();
+    let unused = definitions
+        .into_iter()
+        .filter(|d| !used.contains(&normalize_label(&d.label)))
+        .map(|d| d.range);
+    removal_edits(text, unused)
+}
+
+/// Report references without a definition, definitions without a
+/// reference, and duplicate definitions
+pub(crate) fn diagnostics(text: &str) -> Vec<Diagnostic> {
+    let (definitions, references) = footnotes(text);
+    let used: HashSet<String> = references
+        .iter()
+        .map(|r| normalize_label(&r.label))
+        .collect();
+    let mut defined = HashSet::new();
+    let mut diagnostics = Vec::new();
+
+    for def in &definitions {
+        let label = normalize_label(&def.label);
+        if !defined.insert(label.clone()) {
+            diagnostics.push(Diagnostic::new(
+                def.label_range.clone(),
+                Severity::Warning,
+                "duplicate-footnote",
+                format!("Duplicate footnote definition [^{}]", def.label),
+            ));
+        } else if !used.contains(&label) {
+            diagnostics.push(Diagnostic::new(
+                def.label_range.clone(),
+                Severity::Warning,
+                "orphan-footnote",
+                format!("Footnote [^{}] is never referenced", def.label),
+            ));
+        }
+    }
+    for r in &references {
+        if !defined.contains(&normalize_label(&r.label)) {
+            diagnostics.push(Diagnostic::new(
+                r.range.clone(),
+                Severity::Warning,
+                "undefined-footnote",
+                format!("No definition for footnote [^{}]", r.label),
+            ));
+        }
+    }
+
+    diagnostics.sort_by_key(|d| d.range.start);
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit::edited;
+
+    const NOTE: &str = "Text[^1] and[^note] more [^2].\n\n`[^x]`\n\n[^1]: First\n    continued.\n[^note]: Named one\n\n[^9]: Orphan\n";
+
+    #[test]
+    fn test_parse_footnotes() {
+        let (defs, refs) = footnotes(NOTE);
+        let labels: Vec<_> = defs.iter().map(|d| d.label.as_str()).collect();
+        assert_eq!(labels, vec!["1", "note", "9"]);
+        assert_eq!(
+            &NOTE[defs[0].range.clone()],
+            "[^1]: First\n    continued.\n"
+        );
+        assert_eq!(
+            &NOTE[defs[1].content_start..defs[1].range.end],
+            "Named one\n"
+        );
+        let labels: Vec<_> = refs.iter().map(|r| r.label.as_str()).collect();
+        assert_eq!(labels, vec!["1", "note", "2"]);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let diags = diagnostics(NOTE);
+        let codes: Vec<_> = diags.iter().map(|d| d.code).collect();
+        assert_eq!(codes, vec!["undefined-footnote", "orphan-footnote"]);
+        assert_eq!(&NOTE[diags[1].range.clone()], "[^9]");
+    }
+
+    #[test]
+    fn test_insert() {
+        let text = "One. Two.\n\n[^1]: Existing\n\nAfter.\n";
+        let sel = [Selection::cursor(4), Selection::cursor(9)];
+        let (edits, cursors) = insert_edits(text, &sel);
+        assert_eq!(cursors, vec![8, 17]);
+        let out = edited(text, edits);
+        assert_eq!(
+            out,
+            "One.[^2] Two.[^3]\n\n[^1]: Existing\n[^2]: \n[^3]: \n\nAfter.\n"
+        );
+
+        let out = edited("Plain", insert_edits("Plain", &[Selection::cursor(5)]).0);
+        assert_eq!(out, "Plain[^1]\n\n[^1]: \n");
+    }
+
+    #[test]
+    fn test_renumber() {
+        let text = "A[^3] b[^1] c[^3] d[^x].\n\n[^1]: one\n[^3]: three\n[^x]: named\n";
+        let out = edited(text, renumber_edits(text));
+        assert_eq!(
+            out,
+            "A[^1] b[^2] c[^1] d[^x].\n\n[^1]: three\n[^2]: one\n[^x]: named\n"
+        );
+        assert!(renumber_edits(&out).is_empty());
+    }
+
+    #[test]
+    fn test_jump_target() {
+        let text = "See[^a].\n\n[^a]: The note.\n";
+        assert_eq!(jump_target(text, 4), Some(16));
+        assert_eq!(jump_target(text, 18), Some(3));
+        assert_eq!(jump_target(text, 0), None);
+    }
+
+    #[test]
+    fn test_remove_unused() {
+        let out = edited(NOTE, remove_unused_edits(NOTE));
+        assert!(out.ends_with("[^note]: Named one\n"));
+    }
+}
@@ -0,0 +1,1988 @@
+//! YAML (`---`) and TOML (`+++`) front matter: parsing into a key/value
+//! tree with byte ranges, and edits that rewrite only the changed key
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::edit::TextEdit;
+use crate::error::{Error, Result};
+use crate::syntax::MarkdownTree;
+
+/// Syntax of a front matter block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrontMatterFormat {
+    /// `---` fenced YAML
+    Yaml,
+    /// `+++` fenced TOML
+    Toml,
+}
+
+/// A parsed front matter value
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrontMatterValue {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    /// Strings, and scalars without a more specific type such as dates
+    String(String),
+    List(Vec<FrontMatterValue>),
+    Map(Vec<FrontMatterEntry>),
+}
+
+/// A key and its value, with byte ranges in the document
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrontMatterEntry {
+    pub key: String,
+    pub key_range: Range<usize>,
+    pub value: FrontMatterValue,
+    /// Byte range of the value as written, excluding trailing comments
+    pub value_range: Range<usize>,
+    /// Byte range of the whole entry. Entries on their own lines span
+    /// whole lines, including the line break.
+    pub range: Range<usize>,
+}
+
+/// A front matter block at the start of a document
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrontMatter {
+    pub format: FrontMatterFormat,
+    /// Byte range of the block including its fences
+    pub range: Range<usize>,
+    /// Byte range between the fences
+    pub body_range: Range<usize>,
+    pub entries: Vec<FrontMatterEntry>,
+}
+
+impl FrontMatterValue {
+    /// Build a map value from keys and values
+    pub fn map(pairs: impl IntoIterator<Item = (String, FrontMatterValue)>) -> Self {
+        Self::Map(
+            pairs
+                .into_iter()
+                .map(|(key, value)| FrontMatterEntry {
+                    key,
+                    key_range: 0..0,
+                    value,
+                    value_range: 0..0,
+                    range: 0..0,
+                })
+                .collect(),
+        )
+    }
+
+    /// The string, if this is a string value
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The items, if this is a list
+    pub fn as_list(&self) -> Option<&[FrontMatterValue]> {
+        match self {
+            Self::List(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for FrontMatterValue {
+    fn from(s: &str) -> Self {
+        Self::String(s.to_string())
+    }
+}
+
+impl fmt::Display for FrontMatterValue {
+    /// Plain display form: strings unquoted, lists comma-separated
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => Ok(()),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Integer(i) => write!(f, "{i}"),
+            Self::Float(x) => write!(f, "{x}"),
+            Self::String(s) => f.write_str(s),
+            Self::List(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                Ok(())
+            }
+            Self::Map(entries) => {
+                f.write_str("{")?;
+                for (i, entry) in entries.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", entry.key, entry.value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+impl FrontMatter {
+    /// Look up an entry by a dot-separated key path (`author.name`)
+    pub fn get(&self, path: &str) -> Option<&FrontMatterEntry> {
+        find_entry(&self.entries, path)
+    }
+}
+
+fn find_entry<'a>(entries: &'a [FrontMatterEntry], path: &str) -> Option<&'a FrontMatterEntry> {
+    entries.iter().rev().find_map(|entry| {
+        if entry.key == path {
+            return Some(entry);
+        }
+        let rest = path.strip_prefix(entry.key.as_str())?.strip_prefix('.')?;
+        match &entry.value {
+            FrontMatterValue::Map(children) => find_entry(children, rest),
+            FrontMatterValue::List(items) => match items.last() {
+                // TOML arrays of tables resolve into their last table
+                Some(FrontMatterValue::Map(children)) => find_entry(children, rest),
+                _ => None,
+            },
+            _ => None,
+        }
+    })
+}
+
+/// A syntax error inside a front matter block
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SyntaxError {
+    range: Range<usize>,
+    message: String,
+}
+
+type Parsed<T> = std::result::Result<T, SyntaxError>;
+
+fn syntax_error<T>(range: Range<usize>, message: impl Into<String>) -> Parsed<T> {
+    Err(SyntaxError {
+        range,
+        message: message.into(),
+    })
+}
+
+impl From<SyntaxError> for Error {
+    fn from(e: SyntaxError) -> Self {
+        Error::ParseError(format!("front matter: {}", e.message))
+    }
+}
+
+/// Parse the front matter block at the start of a document, if any
+pub fn parse_front_matter(text: &str) -> Result<Option<FrontMatter>> {
+    Ok(parse(text)?)
+}
+
+fn parse(text: &str) -> Parsed<Option<FrontMatter>> {
+    let Some((format, range)) = locate(text) else {
+        return Ok(None);
+    };
+    let block = &text[range.clone()];
+    let body_start = range.start + block.find('\n').map_or(block.len(), |i| i + 1);
+    let trimmed = block.trim_end_matches(['\n', '\r']);
+    let body_end = range.start + trimmed.rfind('\n').map_or(trimmed.len(), |i| i + 1);
+    let body_range = body_start..body_end.max(body_start);
+
+    let lines = split_lines(text, body_range.clone());
+    let entries = match format {
+        FrontMatterFormat::Yaml => YamlParser::new(text, lines).parse()?,
+        FrontMatterFormat::Toml => parse_toml(text, &lines, body_range.end)?,
+    };
+    Ok(Some(FrontMatter {
+        format,
+        range,
+        body_range,
+        entries,
+    }))
+}
+
+/// Find the metadata block the Markdown parser recognized at the start
+fn locate(text: &str) -> Option<(FrontMatterFormat, Range<usize>)> {
+    let tree = MarkdownTree::parse(text);
+    let root = tree.root();
+    let mut cursor = root.walk();
+    let node = root
+        .children(&mut cursor)
+        .find(|n| n.start_byte() == 0 && n.kind().ends_with("_metadata"))?;
+    let format = match node.kind() {
+        "plus_metadata" => FrontMatterFormat::Toml,
+        _ => FrontMatterFormat::Yaml,
+    };
+    Some((format, node.byte_range()))
+}
+
+/// Report malformed front matter
+pub(crate) fn diagnostics(text: &str) -> Vec<Diagnostic> {
+    match parse(text) {
+        Ok(_) => Vec::new(),
+        Err(e) => {
+            let range = e.range.clone();
+            vec![Diagnostic::new(
+                range,
+                Severity::Error,
+                "front-matter",
+                Error::from(e).to_string(),
+            )]
+        }
+    }
+}
+
+// --- Lines ---
+
+/// One line of the front matter body
+#[derive(Debug, Clone, Copy)]
+struct Line<'a> {
+    /// Byte offset of the line start
+    start: usize,
+    /// Byte offset after the line break
+    end: usize,
+    /// Line text without the line break
+    text: &'a str,
+    /// Bytes of indentation before the content
+    indent: usize,
+}
+
+impl<'a> Line<'a> {
+    fn content(&self) -> &'a str {
+        &self.text[self.indent..]
+    }
+
+    fn content_start(&self) -> usize {
+        self.start + self.indent
+    }
+
+    /// Content without a trailing comment or whitespace
+    fn code(&self) -> &'a str {
+        strip_comment(self.content()).trim_end()
+    }
+
+    fn is_blank(&self) -> bool {
+        self.code().is_empty()
+    }
+}
+
+fn split_lines(text: &str, range: Range<usize>) -> Vec<Line<'_>> {
+    let mut lines = Vec::new();
+    let mut start = range.start;
+    while start < range.end {
+        let end = text[start..range.end]
+            .find('\n')
+            .map_or(range.end, |i| start + i + 1);
+        let line = text[start..end].trim_end_matches(['\n', '\r']);
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        lines.push(Line {
+            start,
+            end,
+            text: line,
+            indent,
+        });
+        start = end;
+    }
+    lines
+}
+
+/// Cut a `#` comment that starts outside quotes, at the line start or
+/// after whitespace
+fn strip_comment(s: &str) -> &str {
+    let mut quote: Option<char> = None;
+    let mut prev: Option<char> = None;
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match quote {
+            Some('"') if c == '\\' => {
+                chars.next();
+            }
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None => {
+                let at_token_start = prev
+                    .is_none_or(|p| p.is_whitespace() || matches!(p, '[' | '{' | ',' | ':' | '='));
+                if c == '#' && prev.is_none_or(char::is_whitespace) {
+                    return &s[..i];
+                }
+                if matches!(c, '"' | '\'') && at_token_start {
+                    quote = Some(c);
+                }
+            }
+        }
+        prev = Some(c);
+    }
+    s
+}
+
+// --- YAML ---
+
+struct YamlParser<'a> {
+    text: &'a str,
+    lines: Vec<Line<'a>>,
+    pos: usize,
+    /// End of the last consumed line, including its line break
+    last_end: usize,
+    /// End of the last consumed value text
+    last_value_end: usize,
+}
+
+impl<'a> YamlParser<'a> {
+    fn new(text: &'a str, lines: Vec<Line<'a>>) -> Self {
+        Self {
+            text,
+            lines,
+            pos: 0,
+            last_end: 0,
+            last_value_end: 0,
+        }
+    }
+
+    fn parse(mut self) -> Parsed<Vec<FrontMatterEntry>> {
+        for line in &self.lines {
+            if !line.is_blank() && line.content().starts_with('\t') {
+                return syntax_error(line.start..line.end, "tabs are not allowed for indentation");
+            }
+        }
+        let entries = self.parse_map(0)?;
+        self.skip_blank();
+        if let Some(line) = self.lines.get(self.pos) {
+            return syntax_error(
+                line.content_start()..line.start + line.text.len(),
+                "unexpected indentation",
+            );
+        }
+        Ok(entries)
+    }
+
+    fn skip_blank(&mut self) {
+        while self.lines.get(self.pos).is_some_and(|l| l.is_blank()) {
+            self.pos += 1;
+        }
+    }
+
+    /// Mark the current line consumed, ending a value at `value_end`
+    fn consume(&mut self, value_end: usize) {
+        self.last_end = self.lines[self.pos].end;
+        self.last_value_end = value_end;
+        self.pos += 1;
+    }
+
+    fn parse_map(&mut self, indent: usize) -> Parsed<Vec<FrontMatterEntry>> {
+        let mut entries: Vec<FrontMatterEntry> = Vec::new();
+        loop {
+            self.skip_blank();
+            let Some(line) = self.lines.get(self.pos).copied() else {
+                break;
+            };
+            if line.indent < indent {
+                break;
+            }
+            let code_range = line.content_start()..line.content_start() + line.code().len();
+            if line.indent > indent {
+                return syntax_error(code_range, "unexpected indentation");
+            }
+            let code = line.code();
+            if is_list_item(code) {
+                return syntax_error(code_range, "expected `key: value`, found a list item");
+            }
+
+            let (key, key_range, colon) = parse_yaml_key(self.text, code, line.content_start())?;
+            let after = &code[colon + 1..];
+            let value_start =
+                line.content_start() + colon + 1 + (after.len() - after.trim_start().len());
+            let value_src = after.trim();
+
+            let (value, value_range) = if value_src.is_empty() {
+                self.consume(line.content_start() + colon + 1);
+                self.parse_nested(indent, line.content_start() + colon + 1)?
+            } else if value_src.starts_with(['|', '>']) {
+                self.consume(code_range.end);
+                self.parse_block_scalar(indent, value_src)?
+            } else {
+                let end = value_start + value_src.len();
+                let mut parser = ValueParser::new(self.text, value_start, end, Syntax::Yaml);
+                let value = parser.value_to_end()?;
+                self.consume(end);
+                let plain = !value_src.starts_with(['"', '\'', '[', '{']);
+                self.continue_plain(indent, value, value_start..end, plain)
+            };
+
+            if entries.iter().any(|e| e.key == key) {
+                return syntax_error(key_range, format!("duplicate key `{key}`"));
+            }
+            entries.push(FrontMatterEntry {
+                key,
+                key_range,
+                value,
+                value_range,
+                range: line.start..self.last_end,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Fold more-indented continuation lines into a plain scalar
+    fn continue_plain(
+        &mut self,
+        indent: usize,
+        value: FrontMatterValue,
+        range: Range<usize>,
+        plain: bool,
+    ) -> (FrontMatterValue, Range<usize>) {
+        if !plain {
+            return (value, range);
+        }
+        let mut folded = self.text[range.clone()].to_string();
+        let mut end = range.end;
+        while let Some(line) = self.lines.get(self.pos).copied() {
+            if line.is_blank() || line.indent <= indent {
+                break;
+            }
+            folded.push(' ');
+            folded.push_str(line.code());
+            end = line.content_start() + line.code().len();
+            self.consume(end);
+        }
+        if end == range.end {
+            (value, range)
+        } else {
+            (yaml_scalar(&folded), range.start..end)
+        }
+    }
+
+    /// Value on the lines after `key:` (a nested map or list), or null
+    fn parse_nested(
+        &mut self,
+        indent: usize,
+        after_colon: usize,
+    ) -> Parsed<(FrontMatterValue, Range<usize>)> {
+        let mark = self.pos;
+        self.skip_blank();
+        match self.lines.get(self.pos).copied() {
+            Some(next) if is_list_item(next.code()) && next.indent >= indent => {
+                self.parse_list(next.indent)
+            }
+            Some(next) if next.indent > indent => {
+                let start = next.content_start();
+                let entries = self.parse_map(next.indent)?;
+                Ok((FrontMatterValue::Map(entries), start..self.last_value_end))
+            }
+            _ => {
+                self.pos = mark;
+                Ok((FrontMatterValue::Null, after_colon..after_colon))
+            }
+        }
+    }
+
+    fn parse_list(&mut self, indent: usize) -> Parsed<(FrontMatterValue, Range<usize>)> {
+        let start = self.lines[self.pos].content_start();
+        let mut items = Vec::new();
+        loop {
+            self.skip_blank();
+            let Some(line) = self.lines.get(self.pos).copied() else {
+                break;
+            };
+            if line.indent != indent || !is_list_item(line.code()) {
+                break;
+            }
+            let code = line.code();
+            let after_dash = &code[1..];
+            let item_offset = 1 + after_dash.len() - after_dash.trim_start().len();
+            let item_src = &code[item_offset..];
+            let item_start = line.content_start() + item_offset;
+
+            if item_src.is_empty() {
+                self.consume(item_start);
+                let (value, _) = self.parse_nested(indent, item_start)?;
+                items.push(value);
+            } else if is_list_item(item_src) || find_mapping_colon(item_src).is_some() {
+                // Treat the item content as a line of its own, indented to
+                // where it starts (`- key: value` / `- - nested`)
+                let shifted = indent + item_offset;
+                self.lines[self.pos].indent = shifted;
+                if is_list_item(item_src) {
+                    items.push(self.parse_list(shifted)?.0);
+                } else {
+                    items.push(FrontMatterValue::Map(self.parse_map(shifted)?));
+                }
+            } else {
+                let end = item_start + item_src.len();
+                let mut parser = ValueParser::new(self.text, item_start, end, Syntax::Yaml);
+                let value = parser.value_to_end()?;
+                self.consume(end);
+                let plain = !item_src.starts_with(['"', '\'', '[', '{']);
+                items.push(
+                    self.continue_plain(indent + 1, value, item_start..end, plain)
+                        .0,
+                );
+            }
+        }
+        Ok((FrontMatterValue::List(items), start..self.last_value_end))
+    }
+
+    /// `|` (literal) or `>` (folded) block scalar on the following lines
+    fn parse_block_scalar(
+        &mut self,
+        indent: usize,
+        header: &str,
+    ) -> Parsed<(FrontMatterValue, Range<usize>)> {
+        let folded = header.starts_with('>');
+        let strip = header.contains('-');
+        let keep = header.contains('+');
+        let mut lines: Vec<&str> = Vec::new();
+        let mut content_indent = None;
+        let mut range: Option<Range<usize>> = None;
+
+        while let Some(line) = self.lines.get(self.pos).copied() {
+            let blank = line.text.trim().is_empty();
+            if !blank && line.indent <= indent {
+                break;
+            }
+            if blank {
+                lines.push("");
+                self.pos += 1;
+                continue;
+            }
+            let ci = *content_indent.get_or_insert(line.indent);
+            if line.indent < ci {
+                break;
+            }
+            lines.push(&line.text[ci..]);
+            let end = line.start + line.text.len();
+            range = Some(range.map_or(line.start + ci..end, |r| r.start..end));
+            self.consume(end);
+        }
+        let mut trailing = 0;
+        while lines.last() == Some(&"") {
+            lines.pop();
+            trailing += 1;
+        }
+
+        let mut value = if folded {
+            fold_lines(&lines)
+        } else {
+            lines.join("\n")
+        };
+        if !strip && !lines.is_empty() {
+            value.push('\n');
+        }
+        if keep {
+            value.push_str(&"\n".repeat(trailing));
+        }
+        let range = range.unwrap_or(self.last_value_end..self.last_value_end);
+        Ok((FrontMatterValue::String(value), range))
+    }
+}
+
+/// Join folded block scalar lines: single breaks become spaces, and each
+/// empty line a line break
+fn fold_lines(lines: &[&str]) -> String {
+    let mut out = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        if line.is_empty() {
+            out.push('\n');
+        } else if i > 0 && !lines[i - 1].is_empty() {
+            out.push(' ');
+        }
+        out.push_str(line);
+    }
+    out
+}
+
+fn is_list_item(code: &str) -> bool {
+    code == "-" || code.starts_with("- ")
+}
+
+/// Position of the `:` separating a key from its value, outside quotes and
+/// brackets
+fn find_mapping_colon(s: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    let mut depth = 0usize;
+    let bytes = s.as_bytes();
+    for (i, c) in s.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None => match c {
+                '"' | '\'' if i == 0 => quote = Some(c),
+                '[' | '{' if i == 0 || depth > 0 => depth += 1,
+                ']' | '}' => depth = depth.saturating_sub(1),
+                ':' if depth == 0 && bytes.get(i + 1).is_none_or(|b| b.is_ascii_whitespace()) => {
+                    return Some(i);
+                }
+                _ => {}
+            },
+        }
+    }
+    None
+}
+
+/// Parse `key:` at the start of a line's code. Returns the key, its range
+/// and the offset of the colon in `code`.
+fn parse_yaml_key(text: &str, code: &str, start: usize) -> Parsed<(String, Range<usize>, usize)> {
+    let code_range = start..start + code.len();
+    if code.starts_with(['"', '\'']) {
+        let mut parser = ValueParser::new(text, start, start + code.len(), Syntax::Yaml);
+        let key = parser.value()?;
+        let key_end = parser.pos;
+        let rest = &code[key_end - start..];
+        let trimmed = rest.trim_start();
+        if !trimmed.starts_with(':') {
+            return syntax_error(code_range, "expected `:` after key");
+        }
+        let colon = key_end - start + rest.len() - trimmed.len();
+        return Ok((key.to_string(), start..key_end, colon));
+    }
+    let Some(colon) = find_mapping_colon(code) else {
+        return syntax_error(code_range, "expected `key: value`");
+    };
+    let key = code[..colon].trim_end();
+    if key.is_empty() {
+        return syntax_error(code_range, "missing key before `:`");
+    }
+    Ok((key.to_string(), start..start + key.len(), colon))
+}
+
+/// Type a plain YAML scalar
+fn yaml_scalar(s: &str) -> FrontMatterValue {
+    let s = s.trim();
+    match s {
+        "" | "~" | "null" | "Null" | "NULL" => return FrontMatterValue::Null,
+        "true" | "True" | "TRUE" => return FrontMatterValue::Bool(true),
+        "false" | "False" | "FALSE" => return FrontMatterValue::Bool(false),
+        _ => {}
+    }
+    if let Some(number) = parse_number(s) {
+        return number;
+    }
+    FrontMatterValue::String(s.to_string())
+}
+
+/// Parse a decimal integer or float written with digits
+fn parse_number(s: &str) -> Option<FrontMatterValue> {
+    let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+    if !digits.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    if !digits.chars().any(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    if let Ok(i) = s.parse::<i64>() {
+        return Some(FrontMatterValue::Integer(i));
+    }
+    if digits
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '-' | '+'))
+    {
+        return s.parse::<f64>().ok().map(FrontMatterValue::Float);
+    }
+    None
+}
+
+// --- TOML ---
+
+fn parse_toml(text: &str, lines: &[Line<'_>], body_end: usize) -> Parsed<Vec<FrontMatterEntry>> {
+    let mut root: Vec<FrontMatterEntry> = Vec::new();
+    let mut table: Vec<String> = Vec::new();
+    let mut idx = 0;
+
+    while idx < lines.len() {
+        let line = lines[idx];
+        let code = line.code();
+        idx += 1;
+        if code.is_empty() {
+            continue;
+        }
+        let code = code.trim_start();
+        let code_start = line.start + line.text.len() - line.text.trim_start().len();
+        let code_range = code_start..code_start + code.len();
+
+        if let Some(header) = code.strip_prefix('[') {
+            let array = header.starts_with('[');
+            let header = if array { &header[1..] } else { header };
+            let close = if array { "]]" } else { "]" };
+            let Some(inner) = header.strip_suffix(close) else {
+                return syntax_error(code_range, format!("expected `{close}` after table name"));
+            };
+            let inner_start = code_start + if array { 2 } else { 1 };
+            let path = parse_toml_key_path(inner, inner_start)?;
+            let keys: Vec<String> = path.iter().map(|(k, _)| k.clone()).collect();
+            let (last, parents) = path.split_last().expect("key path is never empty");
+            let header_end = line.end;
+            let entries = descend(&mut root, parents, line.start..header_end)?;
+            if array {
+                let entry = match entries.iter_mut().find(|e| e.key == last.0) {
+                    Some(entry) => entry,
+                    None => {
+                        entries.push(FrontMatterEntry {
+                            key: last.0.clone(),
+                            key_range: last.1.clone(),
+                            value: FrontMatterValue::List(Vec::new()),
+                            value_range: header_end..header_end,
+                            range: line.start..header_end,
+                        });
+                        entries.last_mut().expect("just pushed")
+                    }
+                };
+                let FrontMatterValue::List(items) = &mut entry.value else {
+                    return syntax_error(
+                        last.1.clone(),
+                        format!("`{}` is not an array of tables", last.0),
+                    );
+                };
+                items.push(FrontMatterValue::Map(Vec::new()));
+            } else {
+                if entries.iter().any(|e| e.key == last.0) {
+                    return syntax_error(
+                        last.1.clone(),
+                        format!("duplicate table `{}`", keys.join(".")),
+                    );
+                }
+                entries.push(FrontMatterEntry {
+                    key: last.0.clone(),
+                    key_range: last.1.clone(),
+                    value: FrontMatterValue::Map(Vec::new()),
+                    value_range: header_end..header_end,
+                    range: line.start..header_end,
+                });
+            }
+            table = keys;
+            continue;
+        }
+
+        let Some(eq) = find_toml_equals(code) else {
+            return syntax_error(code_range, "expected `key = value`");
+        };
+        let key_src = code[..eq].trim_end();
+        if key_src.is_empty() {
+            return syntax_error(code_range, "missing key before `=`");
+        }
+        let path = parse_toml_key_path(key_src, code_start)?;
+        let after = &code[eq + 1..];
+        let value_start = code_start + eq + 1 + (after.len() - after.trim_start().len());
+
+        let mut parser = ValueParser::new(text, value_start, body_end, Syntax::Toml);
+        let value = parser.value()?;
+        let value_end = parser.pos;
+        // Only a comment may follow the value on its last line
+        let line_end = text[value_end..body_end]
+            .find('\n')
+            .map_or(body_end, |i| value_end + i + 1);
+        let rest = strip_comment(&text[value_end..line_end]).trim();
+        if !rest.is_empty() {
+            return syntax_error(
+                value_end..value_end + rest.len(),
+                "unexpected text after value",
+            );
+        }
+        while idx < lines.len() && lines[idx].start < line_end {
+            idx += 1;
+        }
+
+        let entry_range = line.start..line_end;
+        let table_keys: Vec<(String, Range<usize>)> = table
+            .iter()
+            .map(|k| (k.clone(), entry_range.clone()))
+            .collect();
+        let mut full_path = table_keys;
+        full_path.extend(path.iter().cloned());
+        let (last, parents) = full_path.split_last().expect("key path is never empty");
+        let entries = descend(&mut root, parents, entry_range.clone())?;
+        if entries.iter().any(|e| e.key == last.0) {
+            return syntax_error(last.1.clone(), format!("duplicate key `{}`", last.0));
+        }
+        entries.push(FrontMatterEntry {
+            key: last.0.clone(),
+            key_range: last.1.clone(),
+            value,
+            value_range: value_start..value_end,
+            range: entry_range,
+        });
+    }
+    Ok(root)
+}
+
+/// Walk (creating as needed) the maps along a key path, extending each to
+/// cover `range`. Arrays of tables descend into their last table.
+fn descend<'a>(
+    mut entries: &'a mut Vec<FrontMatterEntry>,
+    path: &[(String, Range<usize>)],
+    range: Range<usize>,
+) -> Parsed<&'a mut Vec<FrontMatterEntry>> {
+    for (key, key_range) in path {
+        let idx = match entries.iter().position(|e| &e.key == key) {
+            Some(idx) => idx,
+            None => {
+                entries.push(FrontMatterEntry {
+                    key: key.clone(),
+                    key_range: key_range.clone(),
+                    value: FrontMatterValue::Map(Vec::new()),
+                    value_range: range.clone(),
+                    range: range.clone(),
+                });
+                entries.len() - 1
+            }
+        };
+        let entry = &mut entries[idx];
+        entry.range.end = entry.range.end.max(range.end);
+        entry.value_range.end = entry.value_range.end.max(range.end);
+        entries = match &mut entry.value {
+            FrontMatterValue::Map(children) => children,
+            FrontMatterValue::List(items) => match items.last_mut() {
+                Some(FrontMatterValue::Map(children)) => children,
+                _ => return syntax_error(key_range.clone(), format!("`{key}` is not a table")),
+            },
+            _ => return syntax_error(key_range.clone(), format!("`{key}` is not a table")),
+        };
+    }
+    Ok(entries)
+}
+
+/// Position of the `=` after a key, outside quoted key segments
+fn find_toml_equals(code: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    for (i, c) in code.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '=' => return Some(i),
+                _ => {}
+            },
+        }
+    }
+    None
+}
+
+/// Split a dotted TOML key (`site."page title".x`) into its segments
+fn parse_toml_key_path(src: &str, start: usize) -> Parsed<Vec<(String, Range<usize>)>> {
+    let mut path = Vec::new();
+    let mut pos = 0;
+    let bytes = src.as_bytes();
+    loop {
+        while pos < src.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        let seg_start = pos;
+        let key = match bytes.get(pos) {
+            Some(b'"') | Some(b'\'') => {
+                let quote = bytes[pos] as char;
+                let Some(len) = src[pos + 1..].find(quote) else {
+                    return syntax_error(start + pos..start + src.len(), "unterminated quoted key");
+                };
+                pos += len + 2;
+                src[seg_start + 1..pos - 1].to_string()
+            }
+            _ => {
+                while pos < src.len()
+                    && (bytes[pos].is_ascii_alphanumeric() || matches!(bytes[pos], b'_' | b'-'))
+                {
+                    pos += 1;
+                }
+                if pos == seg_start {
+                    return syntax_error(
+                        start..start + src.len(),
+                        format!("invalid key `{}`", src.trim()),
+                    );
+                }
+                src[seg_start..pos].to_string()
+            }
+        };
+        path.push((key, start + seg_start..start + pos));
+        while pos < src.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        match bytes.get(pos) {
+            None => return Ok(path),
+            Some(b'.') => pos += 1,
+            Some(_) => {
+                return syntax_error(
+                    start..start + src.len(),
+                    format!("invalid key `{}`", src.trim()),
+                );
+            }
+        }
+    }
+}
+
+// --- Inline values ---
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Syntax {
+    Yaml,
+    Toml,
+}
+
+/// Parser for one inline value: scalars, quoted strings, `[...]` and `{...}`
+struct ValueParser<'a> {
+    text: &'a str,
+    pos: usize,
+    end: usize,
+    syntax: Syntax,
+}
+
+impl<'a> ValueParser<'a> {
+    fn new(text: &'a str, pos: usize, end: usize, syntax: Syntax) -> Self {
+        Self {
+            text,
+            pos,
+            end,
+            syntax,
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..self.end]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// Parse a value that must fill the whole range
+    fn value_to_end(&mut self) -> Parsed<FrontMatterValue> {
+        let value = self.value()?;
+        self.skip_space(false);
+        if self.pos < self.end {
+            return syntax_error(self.pos..self.end, "unexpected text after value");
+        }
+        Ok(value)
+    }
+
+    /// Skip spaces, and inside brackets line breaks and comments too
+    fn skip_space(&mut self, multiline: bool) {
+        loop {
+            let rest = self.rest();
+            let trimmed = if multiline {
+                rest.trim_start()
+            } else {
+                rest.trim_start_matches([' ', '\t'])
+            };
+            self.pos += rest.len() - trimmed.len();
+            if multiline && trimmed.starts_with('#') {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+                continue;
+            }
+            break;
+        }
+    }
+
+    fn value(&mut self) -> Parsed<FrontMatterValue> {
+        self.skip_space(false);
+        match self.peek() {
+            Some('"') => self.double_quoted().map(FrontMatterValue::String),
+            Some('\'') => self.single_quoted().map(FrontMatterValue::String),
+            Some('[') => self.sequence(),
+            Some('{') => self.mapping(),
+            _ => self.plain(false),
+        }
+    }
+
+    fn flow_value(&mut self) -> Parsed<FrontMatterValue> {
+        self.skip_space(true);
+        match self.peek() {
+            Some('"') | Some('\'') | Some('[') | Some('{') => self.value(),
+            _ => self.plain(true),
+        }
+    }
+
+    fn plain(&mut self, in_flow: bool) -> Parsed<FrontMatterValue> {
+        let start = self.pos;
+        let rest = self.rest();
+        let len = match self.syntax {
+            Syntax::Yaml if in_flow => {
+                rest.char_indices()
+                    .find(|&(i, c)| {
+                        matches!(c, ',' | ']' | '}' | '\n')
+                            || (c == ':'
+                                && rest[i + 1..].chars().next().is_none_or(|n| {
+                                    n.is_whitespace() || matches!(n, ',' | ']' | '}')
+                                }))
+                    })
+                    .map_or(rest.len(), |(i, _)| i)
+            }
+            Syntax::Yaml => rest.len(),
+            Syntax::Toml => rest
+                .find(|c: char| c.is_whitespace() || matches!(c, ',' | ']' | '}' | '#'))
+                .map_or(rest.len(), |i| i),
+        };
+        let token = rest[..len].trim_end();
+        self.pos += len;
+        match self.syntax {
+            Syntax::Yaml => {
+                if token.starts_with(['"', '\'']) {
+                    return syntax_error(start..start + len, "unterminated string");
+                }
+                Ok(yaml_scalar(token))
+            }
+            Syntax::Toml => toml_scalar(token).ok_or_else(|| SyntaxError {
+                range: start..start + len.max(1),
+                message: if token.is_empty() {
+                    "missing value".to_string()
+                } else {
+                    format!("invalid value `{token}` (strings must be quoted)")
+                },
+            }),
+        }
+    }
+
+    fn double_quoted(&mut self) -> Parsed<String> {
+        let start = self.pos;
+        let multiline = self.syntax == Syntax::Toml && self.rest().starts_with("\"\"\"");
+        let delimiter = if multiline { "\"\"\"" } else { "\"" };
+        self.pos += delimiter.len();
+        if multiline && self.rest().starts_with('\n') {
+            self.pos += 1;
+        }
+        let mut out = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            if self.rest()[i..].starts_with(delimiter) {
+                self.pos += i + delimiter.len();
+                return Ok(out);
+            }
+            match c {
+                '\\' => {
+                    let Some((_, escaped)) = chars.next() else {
+                        break;
+                    };
+                    match escaped {
+                        'n' => out.push('\n'),
+                        't' => out.push('\t'),
+                        'r' => out.push('\r'),
+                        '0' => out.push('\0'),
+                        '"' | '\\' | '/' => out.push(escaped),
+                        'u' | 'U' => {
+                            let len = if escaped == 'u' { 4 } else { 8 };
+                            let hex: String = chars.by_ref().take(len).map(|(_, c)| c).collect();
+                            match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                                Some(c) => out.push(c),
+                                None => {
+                                    return syntax_error(
+                                        start..self.pos + i,
+                                        "invalid unicode escape",
+                                    );
+                                }
+                            }
+                        }
+                        '\n' if multiline => {
+                            // Line-ending backslash trims the break and indentation
+                            while let Some((_, c)) = chars.clone().next() {
+                                if !c.is_whitespace() {
+                                    break;
+                                }
+                                chars.next();
+                            }
+                        }
+                        other => {
+                            return syntax_error(
+                                start..self.pos + i + 2,
+                                format!("unknown escape `\\{other}`"),
+                            );
+                        }
+                    }
+                }
+                '\n' if !multiline => break,
+                c => out.push(c),
+            }
+        }
+        syntax_error(
+            start..self.end.min(start + self.rest().len() + 1),
+            "unterminated string",
+        )
+    }
+
+    fn single_quoted(&mut self) -> Parsed<String> {
+        let start = self.pos;
+        let multiline = self.syntax == Syntax::Toml && self.rest().starts_with("'''");
+        let delimiter = if multiline { "'''" } else { "'" };
+        self.pos += delimiter.len();
+        if multiline && self.rest().starts_with('\n') {
+            self.pos += 1;
+        }
+        let rest = self.rest();
+        let mut out = String::new();
+        let mut i = 0;
+        while i < rest.len() {
+            let tail = &rest[i..];
+            // YAML escapes a quote by doubling it
+            if self.syntax == Syntax::Yaml && tail.starts_with("''") {
+                out.push('\'');
+                i += 2;
+                continue;
+            }
+            if tail.starts_with(delimiter) {
+                self.pos += i + delimiter.len();
+                return Ok(out);
+            }
+            let c = tail.chars().next().expect("non-empty tail");
+            if c == '\n' && !multiline {
+                break;
+            }
+            out.push(c);
+            i += c.len_utf8();
+        }
+        syntax_error(start..start + delimiter.len() + i, "unterminated string")
+    }
+
+    fn sequence(&mut self) -> Parsed<FrontMatterValue> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_space(true);
+            match self.peek() {
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(FrontMatterValue::List(items));
+                }
+                None => return syntax_error(start..self.pos, "expected `]`"),
+                _ => {}
+            }
+            items.push(self.flow_value()?);
+            self.skip_space(true);
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {}
+                _ => return syntax_error(start..self.pos, "expected `,` or `]`"),
+            }
+        }
+    }
+
+    fn mapping(&mut self) -> Parsed<FrontMatterValue> {
+        let start = self.pos;
+        self.pos += 1;
+        let separator = match self.syntax {
+            Syntax::Yaml => ':',
+            Syntax::Toml => '=',
+        };
+        let mut entries: Vec<FrontMatterEntry> = Vec::new();
+        loop {
+            self.skip_space(true);
+            match self.peek() {
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(FrontMatterValue::Map(entries));
+                }
+                None => return syntax_error(start..self.pos, "expected `}`"),
+                _ => {}
+            }
+            let entry_start = self.pos;
+            let key = match self.syntax {
+                Syntax::Yaml => self.flow_value()?.to_string(),
+                Syntax::Toml => {
+                    let rest = self.rest();
+                    let len = rest.find('=').unwrap_or(rest.len());
+                    let path = parse_toml_key_path(rest[..len].trim_end(), self.pos)?;
+                    self.pos += rest[..len].trim_end().len();
+                    path.into_iter()
+                        .map(|(k, _)| k)
+                        .collect::<Vec<_>>()
+                        .join(".")
+                }
+            };
+            let key_range = entry_start..self.pos;
+            self.skip_space(true);
+            if self.peek() != Some(separator) {
+                return syntax_error(key_range, format!("expected `{separator}` after key"));
+            }
+            self.pos += 1;
+            self.skip_space(true);
+            let value_start = self.pos;
+            let value = self.flow_value()?;
+            if entries.iter().any(|e| e.key == key) {
+                return syntax_error(key_range, format!("duplicate key `{key}`"));
+            }
+            entries.push(FrontMatterEntry {
+                key,
+                key_range,
+                value,
+                value_range: value_start..self.pos,
+                range: entry_start..self.pos,
+            });
+            self.skip_space(true);
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {}
+                _ => return syntax_error(start..self.pos, "expected `,` or `}`"),
+            }
+        }
+    }
+}
+
+/// Type a bare TOML value. Dates and times are kept as strings.
+fn toml_scalar(token: &str) -> Option<FrontMatterValue> {
+    match token {
+        "true" => return Some(FrontMatterValue::Bool(true)),
+        "false" => return Some(FrontMatterValue::Bool(false)),
+        "inf" | "+inf" => return Some(FrontMatterValue::Float(f64::INFINITY)),
+        "-inf" => return Some(FrontMatterValue::Float(f64::NEG_INFINITY)),
+        "nan" | "+nan" | "-nan" => return Some(FrontMatterValue::Float(f64::NAN)),
+        _ => {}
+    }
+    let unsigned = token.strip_prefix(['-', '+']).unwrap_or(token);
+    for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+        if let Some(digits) = token.strip_prefix(prefix) {
+            return i64::from_str_radix(&digits.replace('_', ""), radix)
+                .ok()
+                .map(FrontMatterValue::Integer);
+        }
+    }
+    if unsigned.starts_with(|c: char| c.is_ascii_digit()) {
+        let cleaned = token.replace('_', "");
+        if let Some(number) = parse_number(&cleaned) {
+            return Some(number);
+        }
+        if token.contains(['-', ':']) {
+            return Some(FrontMatterValue::String(token.to_string()));
+        }
+    }
+    None
+}
+
+// --- Editing ---
+
+/// Edits setting the value at a dot-separated key path. Existing entries
+/// keep their position, surrounding formatting and comments; missing ones
+/// are added to their parent map. A YAML block is created if the document
+/// has no front matter.
+pub(crate) fn set_edits(text: &str, path: &str, value: &FrontMatterValue) -> Result<Vec<TextEdit>> {
+    let keys: Vec<&str> = path.split('.').collect();
+    if keys.iter().any(|k| k.is_empty()) {
+        return Err(Error::ParseError(format!("invalid key path `{path}`")));
+    }
+    let Some(fm) = parse(text)? else {
+        let entry = render_yaml_entry(keys[0], &nest(&keys[1..], value), 0, true);
+        return Ok(vec![TextEdit::insert(0, format!("---\n{entry}---\n"))]);
+    };
+
+    if let Some(entry) = fm.get(path) {
+        return replace_edits(text, &fm, entry, path, value);
+    }
+
+    // Deepest existing ancestor
+    let mut depth = keys.len() - 1;
+    while depth > 0 && fm.get(&keys[..depth].join(".")).is_none() {
+        depth -= 1;
+    }
+    let new_value = nest(&keys[depth + 1..], value);
+    let key = keys[depth];
+
+    let parent = (depth > 0).then(|| fm.get(&keys[..depth].join(".")).expect("checked above"));
+    let siblings = match parent.map(|p| &p.value) {
+        None => &fm.entries,
+        Some(FrontMatterValue::Map(children)) if is_block(text, parent.expect("some")) => children,
+        Some(FrontMatterValue::Map(children)) => {
+            // Inline map: rewrite it with the key added
+            let mut map = children.clone();
+            map.push(plain_entry(key, new_value));
+            let parent_path = keys[..depth].join(".");
+            return set_edits(text, &parent_path, &FrontMatterValue::Map(map));
+        }
+        Some(FrontMatterValue::Null) => {
+            let parent_path = keys[..depth].join(".");
+            let map = FrontMatterValue::Map(vec![plain_entry(key, new_value)]);
+            return set_edits(text, &parent_path, &map);
+        }
+        Some(_) => {
+            return Err(Error::ParseError(format!(
+                "front matter: `{}` is not a map",
+                keys[..depth].join(".")
+            )));
+        }
+    };
+
+    match fm.format {
+        FrontMatterFormat::Yaml => {
+            let (at, indent) = match parent {
+                None => (
+                    insertion_point(&fm.entries, fm.body_range.start, |_| true),
+                    0,
+                ),
+                Some(parent) => {
+                    let indent = siblings
+                        .first()
+                        .map(|e| column(text, e.key_range.start))
+                        .unwrap_or_else(|| column(text, parent.key_range.start) + 2);
+                    (parent.range.end, indent)
+                }
+            };
+            let at = line_end_at(text, at);
+            let line = render_yaml_entry(key, &new_value, indent, true);
+            Ok(vec![TextEdit::insert(at, line)])
+        }
+        FrontMatterFormat::Toml => {
+            // Keys go after the last plain key of the root or the closest
+            // enclosing [table], as a dotted key relative to it
+            let mut table_depth = depth;
+            while table_depth > 0
+                && fm
+                    .get(&keys[..table_depth].join("."))
+                    .and_then(|e| toml_table_header(text, e))
+                    .is_none()
+            {
+                table_depth -= 1;
+            }
+            let relative = keys[table_depth..depth].join(".");
+            let key_path = if relative.is_empty() {
+                toml_key(key)
+            } else {
+                format!("{}.{}", toml_key_path(&relative), toml_key(key))
+            };
+            let at = match table_depth {
+                0 => insertion_point(&fm.entries, fm.body_range.start, |e| {
+                    !is_toml_table(text, e)
+                }),
+                _ => {
+                    // Before the table's subtables, which its range includes
+                    let table = fm.get(&keys[..table_depth].join(".")).expect("table");
+                    let header_end = toml_table_header(text, table).expect("table header");
+                    let children = match &table.value {
+                        FrontMatterValue::Map(children) => children.as_slice(),
+                        _ => &[],
+                    };
+                    insertion_point(children, header_end, |e| !is_toml_table(text, e))
+                }
+            };
+            let at = line_end_at(text, at);
+            let line = format!("{key_path} = {}\n", render_toml(&new_value)?);
+            Ok(vec![TextEdit::insert(at, line)])
+        }
+    }
+}
+
+/// Edits removing the entry at a key path; empty if it does not exist
+pub(crate) fn remove_edits(text: &str, path: &str) -> Result<Vec<TextEdit>> {
+    let Some(fm) = parse(text)? else {
+        return Ok(Vec::new());
+    };
+    let Some(entry) = fm.get(path) else {
+        return Ok(Vec::new());
+    };
+    if is_own_line(text, entry) {
+        return Ok(vec![TextEdit::delete(entry.range.clone())]);
+    }
+    // Entry inside an inline map: rewrite the map without it
+    let (parent_path, key) = path.rsplit_once('.').expect("nested entry");
+    let Some(FrontMatterValue::Map(children)) = fm.get(parent_path).map(|p| &p.value) else {
+        return Ok(Vec::new());
+    };
+    let map = children.iter().filter(|e| e.key != key).cloned().collect();
+    set_edits(text, parent_path, &FrontMatterValue::Map(map))
+}
+
+/// Replace an existing entry's value
+fn replace_edits(
+    text: &str,
+    fm: &FrontMatter,
+    entry: &FrontMatterEntry,
+    path: &str,
+    value: &FrontMatterValue,
+) -> Result<Vec<TextEdit>> {
+    match fm.format {
+        FrontMatterFormat::Toml => {
+            if is_toml_table(text, entry) {
+                return Err(Error::ParseError(format!(
+                    "front matter: `{path}` is a table; set its keys individually"
+                )));
+            }
+            let raw = &text[entry.value_range.clone()];
+            let rendered = match value {
+                // Keep a literal string literal when it can hold the value
+                FrontMatterValue::String(s)
+                    if raw.starts_with('\'')
+                        && !raw.starts_with("'''")
+                        && !s.contains(|c: char| c == '\'' || c.is_control()) =>
+                {
+                    format!("'{s}'")
+                }
+                _ => render_toml(value)?,
+            };
+            Ok(vec![TextEdit::new(entry.value_range.clone(), rendered)])
+        }
+        FrontMatterFormat::Yaml => {
+            if let Some((colon, style)) = block_scalar(text, entry) {
+                return Ok(vec![match value {
+                    FrontMatterValue::String(s) => {
+                        let indent = column(text, entry.value_range.start);
+                        TextEdit::new(entry.value_range.clone(), block_lines(s, indent, style))
+                    }
+                    _ => TextEdit::new(
+                        colon..entry.value_range.end,
+                        format!(": {}", render_yaml_inline(value, false)),
+                    ),
+                }]);
+            }
+            if is_block(text, entry) {
+                // Rewrite the whole entry in block style at its indentation
+                let indent = column(text, entry.key_range.start);
+                let key = &text[entry.key_range.clone()];
+                let rendered = render_yaml_entry_raw(key, value, indent, true);
+                return Ok(vec![TextEdit::new(entry.range.clone(), rendered)]);
+            }
+            let raw = &text[entry.value_range.clone()];
+            let inline = match value {
+                // Keep the quotes of a quoted string
+                FrontMatterValue::String(s) if raw.starts_with('"') => double_quote(s),
+                FrontMatterValue::String(s)
+                    if raw.starts_with('\'') && !s.contains(|c: char| c.is_control()) =>
+                {
+                    format!("'{}'", s.replace('\'', "''"))
+                }
+                _ => render_yaml_inline(value, false),
+            };
+            if entry.value_range.is_empty() {
+                return Ok(vec![TextEdit::new(
+                    entry.value_range.clone(),
+                    format!(" {inline}"),
+                )]);
+            }
+            Ok(vec![TextEdit::new(entry.value_range.clone(), inline)])
+        }
+    }
+}
+
+/// Whether an entry's value is written on the lines after its key. TOML
+/// maps implied by dotted keys or subtable headers start before their key.
+fn is_block(text: &str, entry: &FrontMatterEntry) -> bool {
+    is_own_line(text, entry)
+        && matches!(
+            entry.value,
+            FrontMatterValue::List(_) | FrontMatterValue::Map(_)
+        )
+        && (entry.value_range.start < entry.key_range.end
+            || text[entry.key_range.end..entry.value_range.start].contains('\n'))
+}
+
+/// Offset of the colon and the style (`|` or `>`) of a block scalar entry
+fn block_scalar(text: &str, entry: &FrontMatterEntry) -> Option<(usize, char)> {
+    let between = &text[entry.key_range.end..entry.value_range.start];
+    let colon = between.find(':')?;
+    let style = between[colon + 1..].trim_start().chars().next()?;
+    matches!(style, '|' | '>').then_some((entry.key_range.end + colon, style))
+}
+
+/// Lines of a block scalar holding `s`, after the first line's indentation.
+/// Folded scalars need an empty line for each line break.
+fn block_lines(s: &str, indent: usize, style: char) -> String {
+    let pad = " ".repeat(indent);
+    let mut out = String::new();
+    for (i, line) in s.trim_end_matches('\n').split('\n').enumerate() {
+        if i > 0 {
+            out.push_str(if style == '>' { "\n\n" } else { "\n" });
+            if !line.is_empty() {
+                out.push_str(&pad);
+            }
+        }
+        out.push_str(line);
+    }
+    out
+}
+
+/// Whether an entry starts its own line (rather than sitting in an inline map)
+fn is_own_line(text: &str, entry: &FrontMatterEntry) -> bool {
+    let line_start = text[..entry.range.start].rfind('\n').map_or(0, |i| i + 1);
+    entry.range.start == line_start
+}
+
+fn is_toml_table(text: &str, entry: &FrontMatterEntry) -> bool {
+    text[entry.range.clone()].trim_start().starts_with('[')
+}
+
+/// End of the header line of a TOML table written as `[table]`. Tables
+/// only implied by a subtable's header have none.
+fn toml_table_header(text: &str, entry: &FrontMatterEntry) -> Option<usize> {
+    let implied = match &entry.value {
+        FrontMatterValue::Map(children) => {
+            children.iter().any(|c| c.range.start == entry.range.start)
+        }
+        _ => return None,
+    };
+    (is_toml_table(text, entry) && !implied).then(|| line_end_at(text, entry.range.start + 1))
+}
+
+/// Offset after the last entry matching `filter`, or `start`
+fn insertion_point(
+    entries: &[FrontMatterEntry],
+    start: usize,
+    filter: impl Fn(&FrontMatterEntry) -> bool,
+) -> usize {
+    entries
+        .iter()
+        .filter(|e| filter(e))
+        .map(|e| e.range.end)
+        .max()
+        .unwrap_or(start)
+}
+
+/// Move an offset to the start of the next line unless it is at one
+fn line_end_at(text: &str, at: usize) -> usize {
+    if at == 0 || text[..at].ends_with('\n') {
+        at
+    } else {
+        text[at..].find('\n').map_or(text.len(), |i| at + i + 1)
+    }
+}
+
+fn column(text: &str, pos: usize) -> usize {
+    pos - text[..pos].rfind('\n').map_or(0, |i| i + 1)
+}
+
+fn plain_entry(key: &str, value: FrontMatterValue) -> FrontMatterEntry {
+    match FrontMatterValue::map([(key.to_string(), value)]) {
+        FrontMatterValue::Map(mut entries) => entries.remove(0),
+        _ => unreachable!("map() builds a map"),
+    }
+}
+
+/// Wrap a value in maps for the remaining keys of a path
+fn nest(keys: &[&str], value: &FrontMatterValue) -> FrontMatterValue {
+    keys.iter().rev().fold(value.clone(), |inner, key| {
+        FrontMatterValue::map([(key.to_string(), inner)])
+    })
+}
+
+// --- Rendering ---
+
+fn render_yaml_entry(key: &str, value: &FrontMatterValue, indent: usize, block: bool) -> String {
+    render_yaml_entry_raw(&yaml_key(key), value, indent, block)
+}
+
+/// Render `key: value` lines; with `block`, non-empty lists and maps go on
+/// the following lines
+fn render_yaml_entry_raw(
+    key: &str,
+    value: &FrontMatterValue,
+    indent: usize,
+    block: bool,
+) -> String {
+    let pad = " ".repeat(indent);
+    match value {
+        FrontMatterValue::List(items) if block && !items.is_empty() => {
+            let mut out = format!("{pad}{key}:\n");
+            for item in items {
+                out.push_str(&format!("{pad}  - {}\n", render_yaml_inline(item, false)));
+            }
+            out
+        }
+        FrontMatterValue::Map(entries) if block && !entries.is_empty() => {
+            let mut out = format!("{pad}{key}:\n");
+            for entry in entries {
+                out.push_str(&render_yaml_entry(
+                    &entry.key,
+                    &entry.value,
+                    indent + 2,
+                    true,
+                ));
+            }
+            out
+        }
+        FrontMatterValue::Null => format!("{pad}{key}:\n"),
+        _ => format!("{pad}{key}: {}\n", render_yaml_inline(value, false)),
+    }
+}
+
+fn render_yaml_inline(value: &FrontMatterValue, in_flow: bool) -> String {
+    match value {
+        FrontMatterValue::Null => "null".to_string(),
+        FrontMatterValue::Bool(b) => b.to_string(),
+        FrontMatterValue::Integer(i) => i.to_string(),
+        FrontMatterValue::Float(x) => render_float(*x, ".inf", "-.inf", ".nan"),
+        FrontMatterValue::String(s) => yaml_string(s, in_flow),
+        FrontMatterValue::List(items) => {
+            let items: Vec<_> = items.iter().map(|i| render_yaml_inline(i, true)).collect();
+            format!("[{}]", items.join(", "))
+        }
+        FrontMatterValue::Map(entries) => {
+            let entries: Vec<_> = entries
+                .iter()
+                .map(|e| {
+                    format!(
+                        "{}: {}",
+                        yaml_key(&e.key),
+                        render_yaml_inline(&e.value, true)
+                    )
+                })
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+    }
+}
+
+fn render_float(x: f64, inf: &str, neg_inf: &str, nan: &str) -> String {
+    if x.is_nan() {
+        nan.to_string()
+    } else if x.is_infinite() {
+        if x > 0.0 { inf } else { neg_inf }.to_string()
+    } else {
+        let s = x.to_string();
+        if s.contains(['.', 'e']) {
+            s
+        } else {
+            format!("{s}.0")
+        }
+    }
+}
+
+/// A YAML string, plain when that reads back as the same string
+fn yaml_string(s: &str, in_flow: bool) -> String {
+    let needs_quotes = s.is_empty()
+        || s != s.trim()
+        || s.starts_with([
+            '-', '?', ':', ',', '[', ']', '{', '}', '#', '&', '*', '!', '|', '>', '\'', '"', '%',
+            '@', '`',
+        ])
+        || s.contains(": ")
+        || s.contains(" #")
+        || s.ends_with(':')
+        || s.contains(['\n', '\t'])
+        || (in_flow && s.contains([',', '[', ']', '{', '}']))
+        || yaml_scalar(s) != FrontMatterValue::String(s.to_string());
+    if needs_quotes {
+        double_quote(s)
+    } else {
+        s.to_string()
+    }
+}
+
+fn yaml_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | ' ' | '.'))
+        && key == key.trim()
+    {
+        key.to_string()
+    } else {
+        double_quote(key)
+    }
+}
+
+fn render_toml(value: &FrontMatterValue) -> Result<String> {
+    Ok(match value {
+        FrontMatterValue::Null => {
+            return Err(Error::ParseError(
+                "front matter: TOML has no null value".to_string(),
+            ));
+        }
+        FrontMatterValue::Bool(b) => b.to_string(),
+        FrontMatterValue::Integer(i) => i.to_string(),
+        FrontMatterValue::Float(x) => render_float(*x, "inf", "-inf", "nan"),
+        FrontMatterValue::String(s) => double_quote(s),
+        FrontMatterValue::List(items) => {
+            let items = items.iter().map(render_toml).collect::<Result<Vec<_>>>()?;
+            format!("[{}]", items.join(", "))
+        }
+        FrontMatterValue::Map(entries) => {
+            let entries = entries
+                .iter()
+                .map(|e| Ok(format!("{} = {}", toml_key(&e.key), render_toml(&e.value)?)))
+                .collect::<Result<Vec<_>>>()?;
+            if entries.is_empty() {
+                "{}".to_string()
+            } else {
+                format!("{{ {} }}", entries.join(", "))
+            }
+        }
+    })
+}
+
+fn toml_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+    {
+        key.to_string()
+    } else {
+        double_quote(key)
+    }
+}
+
+fn toml_key_path(path: &str) -> String {
+    path.split('.').map(toml_key).collect::<Vec<_>>().join(".")
+}
+
+fn double_quote(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit::edited;
+
+    const YAML: &str = "---\ntitle: \"Weekly notes\" # shown in the index\ndate: 2024-05-01\ndraft: false\ntags:\n  - work\n  - 'q2'\nauthor:\n  name: Sam\n  posts: 12\nsummary: >\n  Two lines\n  folded.\n---\n\n# Body\n";
+
+    #[test]
+    fn test_parse_yaml() {
+        let fm = parse_front_matter(YAML).unwrap().unwrap();
+        assert_eq!(fm.format, FrontMatterFormat::Yaml);
+        let keys: Vec<_> = fm.entries.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            ["title", "date", "draft", "tags", "author", "summary"]
+        );
+
+        let title = fm.get("title").unwrap();
+        assert_eq!(title.value.as_str(), Some("Weekly notes"));
+        assert_eq!(&YAML[title.value_range.clone()], "\"Weekly notes\"");
+        assert_eq!(&YAML[title.key_range.clone()], "title");
+        assert_eq!(
+            fm.get("draft").unwrap().value,
+            FrontMatterValue::Bool(false)
+        );
+        assert_eq!(fm.get("tags").unwrap().value.to_string(), "work, q2");
+        assert_eq!(
+            fm.get("author.posts").unwrap().value,
+            FrontMatterValue::Integer(12)
+        );
+        assert_eq!(
+            fm.get("summary").unwrap().value.as_str(),
+            Some("Two lines folded.\n")
+        );
+        assert!(fm.get("missing").is_none());
+    }
+
+    #[test]
+    fn test_parse_toml() {
+        let text = "+++\ntitle = \"Release\" # comment\nweight = 1_000\ntags = [\n  \"a\", # first\n  \"b\",\n]\ndate = 2024-05-01\n\n[params]\nshow = true\n+++\nBody\n";
+        let fm = parse_front_matter(text).unwrap().unwrap();
+        assert_eq!(fm.format, FrontMatterFormat::Toml);
+        assert_eq!(fm.get("title").unwrap().value.as_str(), Some("Release"));
+        assert_eq!(
+            fm.get("weight").unwrap().value,
+            FrontMatterValue::Integer(1000)
+        );
+        assert_eq!(fm.get("tags").unwrap().value.to_string(), "a, b");
+        assert_eq!(fm.get("date").unwrap().value.as_str(), Some("2024-05-01"));
+        assert_eq!(
+            fm.get("params.show").unwrap().value,
+            FrontMatterValue::Bool(true)
+        );
+    }
+
+    #[test]
+    fn test_no_front_matter() {
+        assert_eq!(parse_front_matter("# Title\n\n---\n").unwrap(), None);
+    }
+
+    #[test]
+    fn test_malformed_front_matter() {
+        let text = "---\ntitle: ok\njust words\n---\n";
+        assert!(matches!(
+            parse_front_matter(text),
+            Err(Error::ParseError(_))
+        ));
+        let diags = diagnostics(text);
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].code, "front-matter");
+        assert_eq!(&text[diags[0].range.clone()], "just words");
+
+        let text = "+++\ntitle = unquoted\n+++\n";
+        assert_eq!(diagnostics(text)[0].severity, Severity::Error);
+        assert!(
+            diagnostics("---\na: 1\na: 2\n---\n")[0]
+                .message
+                .contains("duplicate key")
+        );
+    }
+
+    #[test]
+    fn test_set_keeps_formatting() {
+        let value = FrontMatterValue::String("Monthly notes".into());
+        let out = edited(YAML, set_edits(YAML, "title", &value).unwrap());
+        assert!(
+            out.starts_with(
+                "---\ntitle: \"Monthly notes\" # shown in the index\ndate: 2024-05-01\n"
+            )
+        );
+
+        let out = edited(
+            YAML,
+            set_edits(YAML, "author.name", &"Alex: Jr".into()).unwrap(),
+        );
+        assert!(out.contains("author:\n  name: \"Alex: Jr\"\n  posts: 12\n"));
+
+        let tags = FrontMatterValue::List(vec!["home".into()]);
+        let out = edited(YAML, set_edits(YAML, "tags", &tags).unwrap());
+        assert!(out.contains("draft: false\ntags:\n  - home\nauthor:"));
+    }
+
+    #[test]
+    fn test_set_adds_missing_keys() {
+        let out = edited(
+            YAML,
+            set_edits(YAML, "author.email", &"s@x.org".into()).unwrap(),
+        );
+        assert!(out.contains("  posts: 12\n  email: s@x.org\nsummary:"));
+
+        let out = edited(YAML, set_edits(YAML, "layout", &"post".into()).unwrap());
+        assert!(out.contains("  folded.\nlayout: post\n---\n"));
+
+        let text = "# Body\n";
+        let out = edited(
+            text,
+            set_edits(text, "meta.draft", &FrontMatterValue::Bool(true)).unwrap(),
+        );
+        assert_eq!(out, "---\nmeta:\n  draft: true\n---\n# Body\n");
+        assert!(parse_front_matter(&out).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_set_toml() {
+        let text = "+++\ntitle = 'Old' # keep\n\n[params]\nshow = true\n+++\n";
+        let out = edited(text, set_edits(text, "title", &"New".into()).unwrap());
+        assert!(out.starts_with("+++\ntitle = 'New' # keep\n"));
+        let out = edited(text, set_edits(text, "title", &"It's".into()).unwrap());
+        assert!(out.starts_with("+++\ntitle = \"It's\" # keep\n"));
+
+        let out = edited(
+            text,
+            set_edits(text, "weight", &FrontMatterValue::Integer(3)).unwrap(),
+        );
+        assert!(out.starts_with("+++\ntitle = 'Old' # keep\nweight = 3\n\n[params]"));
+
+        let out = edited(
+            text,
+            set_edits(text, "params.color", &"red".into()).unwrap(),
+        );
+        assert!(out.ends_with("show = true\ncolor = \"red\"\n+++\n"));
+        assert!(set_edits(text, "params", &"x".into()).is_err());
+
+        // New keys of a table go before its subtables
+        let text = "+++\ntitle = \"x\"\n[meta]\na = 1\n[meta.b]\nc = 2\n+++\n";
+        for (path, line) in [("meta.z", "z = 3\n"), ("meta.z.y", "z = { y = 3 }\n")] {
+            let value = FrontMatterValue::Integer(3);
+            let out = edited(text, set_edits(text, path, &value).unwrap());
+            assert_eq!(out, text.replace("a = 1\n", &format!("a = 1\n{line}")));
+            let fm = parse_front_matter(&out).unwrap().unwrap();
+            assert_eq!(fm.get(path).map(|e| &e.value), Some(&value));
+        }
+        let text = "+++\n[meta]\n[meta.b]\nc = 2\n+++\n";
+        let out = edited(
+            text,
+            set_edits(text, "meta.z", &FrontMatterValue::Bool(true)).unwrap(),
+        );
+        assert_eq!(out, "+++\n[meta]\nz = true\n[meta.b]\nc = 2\n+++\n");
+        let text = "+++\n[meta.b]\nc = 2\n+++\n";
+        let out = edited(
+            text,
+            set_edits(text, "meta.z", &FrontMatterValue::Bool(true)).unwrap(),
+        );
+        let fm = parse_front_matter(&out).unwrap().unwrap();
+        assert_eq!(
+            fm.get("meta.z").map(|e| &e.value),
+            Some(&FrontMatterValue::Bool(true))
+        );
+        let text = "+++\na.b = 1\n+++\n";
+        let out = edited(
+            text,
+            set_edits(text, "a.c", &FrontMatterValue::Integer(2)).unwrap(),
+        );
+        assert_eq!(out, "+++\na.b = 1\na.c = 2\n+++\n");
+    }
+
+    #[test]
+    fn test_yaml_flow_collections() {
+        let text = "---\nsize: {w: 1, h: [2, 3]}\nlist: [a, 'b, c', {k: v}]\n---\n";
+        let fm = parse_front_matter(text).unwrap().unwrap();
+        assert_eq!(
+            &text[fm.get("size.h").unwrap().value_range.clone()],
+            "[2, 3]"
+        );
+        let list = fm.get("list").unwrap().value.as_list().unwrap();
+        assert_eq!(list[1].as_str(), Some("b, c"));
+        assert_eq!(list[2].to_string(), "{k: v}");
+
+        let h = FrontMatterValue::List(vec![FrontMatterValue::Integer(4)]);
+        let out = edited(text, set_edits(text, "size.h", &h).unwrap());
+        assert!(out.starts_with("---\nsize: {w: 1, h: [4]}\nlist:"));
+    }
+
+    #[test]
+    fn test_yaml_block_scalars() {
+        let text = "---\nliteral: |\n  one\n  two\nstrip: |-\n  no newline\nfolded: >\n  a\n  b\n\n  c\nkeep: |+\n  kept\n\nend: 1\n---\n";
+        let fm = parse_front_matter(text).unwrap().unwrap();
+        let value = |key| fm.get(key).unwrap().value.as_str().unwrap().to_string();
+        assert_eq!(value("literal"), "one\ntwo\n");
+        assert_eq!(value("strip"), "no newline");
+        assert_eq!(value("folded"), "a b\nc\n");
+        assert_eq!(value("keep"), "kept\n\n");
+
+        // New text keeps the block style
+        let out = edited(text, set_edits(text, "literal", &"x\ny\n".into()).unwrap());
+        assert!(out.starts_with("---\nliteral: |\n  x\n  y\nstrip:"));
+        let out = edited(text, set_edits(text, "folded", &"p\nq\n".into()).unwrap());
+        let fm = parse_front_matter(&out).unwrap().unwrap();
+        assert_eq!(fm.get("folded").unwrap().value.as_str(), Some("p\nq\n"));
+        let out = edited(
+            text,
+            set_edits(text, "strip", &FrontMatterValue::Integer(2)).unwrap(),
+        );
+        assert!(out.contains("\nstrip: 2\nfolded:"));
+    }
+
+    #[test]
+    fn test_toml_multiline_values() {
+        let text = "+++\nauthors = [\n  { name = \"A\", id = 1 },\n  { name = \"B\" },\n]\npoint = { x = 1, y = { z = 2 } }\n+++\n";
+        let fm = parse_front_matter(text).unwrap().unwrap();
+        let authors = fm.get("authors").unwrap().value.as_list().unwrap();
+        assert_eq!(authors.len(), 2);
+        assert_eq!(
+            fm.get("point.y.z").unwrap().value,
+            FrontMatterValue::Integer(2)
+        );
+
+        let out = edited(
+            text,
+            set_edits(text, "point.x", &FrontMatterValue::Integer(5)).unwrap(),
+        );
+        assert!(out.contains("\n]\npoint = { x = 5, y = { z = 2 } }\n"));
+    }
+
+    #[test]
+    fn test_error_ranges() {
+        let error = |text: &str| {
+            let diagnostics = diagnostics(text);
+            assert_eq!(diagnostics.len(), 1, "{text:?}");
+            let d = &diagnostics[0];
+            (text[d.range.clone()].to_string(), d.message.clone())
+        };
+        let (source, message) = error("---\ntitle: \"open\nnext: 1\n---\n");
+        assert_eq!(source, "\"open");
+        assert!(message.contains("unterminated string"));
+        assert_eq!(error("---\ntags: [a, b\nnext: 1\n---\n").0, "[a, b");
+        assert_eq!(error("+++\n[table\nx = 1\n+++\n").0, "[table");
+        assert_eq!(error("+++\nx = { a = 1\n+++\n").0, "{ a = 1\n");
+    }
+
+    #[test]
+    fn test_remove() {
+        let out = edited(YAML, remove_edits(YAML, "draft").unwrap());
+        assert!(out.contains("date: 2024-05-01\ntags:"));
+        let out = edited(YAML, remove_edits(YAML, "author").unwrap());
+        assert!(out.contains("  - 'q2'\nsummary:"));
+        assert!(remove_edits(YAML, "missing").unwrap().is_empty());
+
+        let text = "---\nsize: {w: 1, h: 2}\n---\n";
+        let out = edited(text, remove_edits(text, "size.w").unwrap());
+        assert_eq!(out, "---\nsize: {h: 2}\n---\n");
+    }
+}
@@ -0,0 +1,177 @@
+//! Undo/redo history of document states
+
+use ropey::Rope;
+
+use crate::line_ending::LineEnding;
+use crate::selection::Selection;
+
+/// Maximum number of undo steps kept per document
+const MAX_UNDO_STEPS: usize = 1000;
+
+/// Document state captured before an edit. Rope clones share structure,
+/// so snapshots are cheap.
+#[derive(Clone)]
+pub(crate) struct Snapshot {
+    pub content: Rope,
+    pub selections: Vec<Selection>,
+    /// Line ending in use, which converting line endings changes
+    pub line_ending: LineEnding,
+}
+
+/// Undo and redo stacks of snapshots
+#[derive(Default)]
+pub(crate) struct History {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    /// Depth of the undo stack at the saved state, if it can still be
+    /// reached by undo and redo
+    saved: Option<usize>,
+    /// Selections after the last typed character while more typing joins
+    /// its undo step, and whether that character was whitespace
+    typing: Option<(Vec<Selection>, bool)>,
+}
+
+impl History {
+    /// Record the state before a new edit; clears the redo stack
+    pub fn record(&mut self, before: Snapshot) {
+        if self.saved.is_some_and(|depth| depth > self.undo.len()) {
+            self.saved = None;
+        }
+        self.undo.push(before);
+        if self.undo.len() > MAX_UNDO_STEPS {
+            self.undo.remove(0);
+            self.saved = self.saved.and_then(|depth| depth.checked_sub(1));
+        }
+        self.redo.clear();
+        self.typing = None;
+    }
+
+    /// Whether typing `text` with the given selections continues the
+    /// current typing step: single characters typed where the last one
+    /// left the cursors, until a line break or the start of a new word
+    pub fn continues_typing(&self, selections: &[Selection], text: &str) -> bool {
+        let mut chars = text.chars();
+        let (Some(c), None) = (chars.next(), chars.next()) else {
+            return false;
+        };
+        self.typing.as_ref().is_some_and(|(after, space)| {
+            after == selections && !matches!(c, '\n' | '\r') && (c.is_whitespace() || !space)
+        })
+    }
+
+    /// Note the selections after typing `text`, so the next character can
+    /// join its undo step
+    pub fn typed(&mut self, selections: &[Selection], text: &str) {
+        let mut chars = text.chars();
+        self.typing = match (chars.next(), chars.next()) {
+            (Some(c), None) => Some((selections.to_vec(), c.is_whitespace())),
+            _ => None,
+        };
+    }
+
+    /// Step back: returns the state to restore, saving `current` for redo
+    pub fn undo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let previous = self.undo.pop()?;
+        self.redo.push(current);
+        self.typing = None;
+        Some(previous)
+    }
+
+    /// Step forward: returns the state to restore, saving `current` for undo
+    pub fn redo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let next = self.redo.pop()?;
+        self.undo.push(current);
+        self.typing = None;
+        Some(next)
+    }
+
+    /// Remember the current state as saved. Typing after it starts a new
+    /// undo step, so undo can return to it.
+    pub fn mark_saved(&mut self) {
+        self.saved = Some(self.undo.len());
+        self.typing = None;
+    }
+
+    /// Whether the current state is the saved one
+    pub fn is_saved(&self) -> bool {
+        self.saved == Some(self.undo.len())
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snap(text: &str) -> Snapshot {
+        Snapshot {
+            content: Rope::from_str(text),
+            selections: vec![Selection::default()],
+            line_ending: LineEnding::Lf,
+        }
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut history = History::default();
+        history.record(snap("a"));
+        let restored = history.undo(snap("ab")).unwrap();
+        assert_eq!(restored.content.to_string(), "a");
+        assert!(history.can_redo());
+        let restored = history.redo(snap("a")).unwrap();
+        assert_eq!(restored.content.to_string(), "ab");
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn test_record_clears_redo() {
+        let mut history = History::default();
+        history.record(snap("a"));
+        history.undo(snap("ab"));
+        history.record(snap("a"));
+        assert!(!history.can_redo());
+        assert!(history.can_undo());
+    }
+
+    #[test]
+    fn test_saved_state() {
+        let mut history = History::default();
+        history.mark_saved();
+        history.record(snap("a"));
+        assert!(!history.is_saved());
+        history.undo(snap("ab"));
+        assert!(history.is_saved());
+
+        // The saved state is lost once an edit replaces the redo stack
+        history.redo(snap("a"));
+        history.mark_saved();
+        history.undo(snap("ab"));
+        history.record(snap("a"));
+        assert!(!history.is_saved());
+        history.undo(snap("ac"));
+        assert!(!history.is_saved());
+    }
+
+    #[test]
+    fn test_typing_steps() {
+        let mut history = History::default();
+        let at = |pos| vec![Selection::cursor(pos)];
+        history.typed(&at(1), "a");
+        assert!(history.continues_typing(&at(1), "b"));
+        assert!(!history.continues_typing(&at(2), "b"));
+        assert!(!history.continues_typing(&at(1), "\n"));
+        assert!(!history.continues_typing(&at(1), "bc"));
+        history.typed(&at(2), " ");
+        assert!(history.continues_typing(&at(2), " "));
+        assert!(!history.continues_typing(&at(2), "c"));
+        history.record(snap("a "));
+        assert!(!history.continues_typing(&at(2), " "));
+    }
+}
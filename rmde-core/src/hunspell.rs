@@ -0,0 +1,724 @@
+//! Hunspell-format dictionaries (`.aff` + `.dic`): word lookup with prefix
+//! and suffix rules, and ranked suggestions for unknown words.
+//!
+//! Covers the parts of the format used by common language dictionaries:
+//! flag types, `PFX`/`SFX` rules with conditions and cross products, `TRY`,
+//! `REP` and the `FORBIDDENWORD`, `NOSUGGEST`, `KEEPCASE`, `NEEDAFFIX` and
+//! `ONLYINCOMPOUND` flags. Compounding is not supported.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::text::capitalize;
+
+/// Letters tried for suggestions when the affix file has no `TRY` line
+const DEFAULT_TRY: &str = "esianrtolcdugmphbyfvkwzESIANRTOLCDUGMPHBYFVKWZ'";
+
+/// How affix flags are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FlagMode {
+    /// One character per flag (the default, and `FLAG UTF-8`)
+    Char,
+    /// Two characters per flag (`FLAG long`)
+    Long,
+    /// Comma-separated numbers (`FLAG num`)
+    Num,
+}
+
+impl FlagMode {
+    fn parse(self, flags: &str) -> Vec<u32> {
+        match self {
+            FlagMode::Char => flags.chars().map(u32::from).collect(),
+            FlagMode::Long => {
+                let chars: Vec<char> = flags.chars().collect();
+                chars
+                    .chunks(2)
+                    .map(|pair| pair.iter().fold(0, |acc, c| (acc << 16) | u32::from(*c)))
+                    .collect()
+            }
+            FlagMode::Num => flags
+                .split(',')
+                .filter_map(|n| n.trim().parse().ok())
+                .collect(),
+        }
+    }
+
+    fn parse_one(self, flag: &str) -> Option<u32> {
+        self.parse(flag).first().copied()
+    }
+}
+
+/// One element of an affix condition
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ConditionPart {
+    Any,
+    Char(char),
+    Set { chars: Vec<char>, negated: bool },
+}
+
+impl ConditionPart {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            ConditionPart::Any => true,
+            ConditionPart::Char(expected) => c == *expected,
+            ConditionPart::Set { chars, negated } => chars.contains(&c) != *negated,
+        }
+    }
+}
+
+/// Affix condition: a simplified regular expression of single characters,
+/// `.` and `[...]`/`[^...]` classes, matched against a stem's start (prefixes)
+/// or end (suffixes)
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Condition(Vec<ConditionPart>);
+
+impl Condition {
+    fn parse(source: &str) -> Self {
+        if source == "." {
+            return Self(Vec::new());
+        }
+        let mut parts = Vec::new();
+        let mut chars = source.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '.' => parts.push(ConditionPart::Any),
+                '[' => {
+                    let mut set = Vec::new();
+                    let mut negated = false;
+                    for c in chars.by_ref() {
+                        match c {
+                            ']' => break,
+                            '^' if set.is_empty() && !negated => negated = true,
+                            c => set.push(c),
+                        }
+                    }
+                    parts.push(ConditionPart::Set {
+                        chars: set,
+                        negated,
+                    });
+                }
+                c => parts.push(ConditionPart::Char(c)),
+            }
+        }
+        Self(parts)
+    }
+
+    fn matches_start(&self, word: &str) -> bool {
+        let mut chars = word.chars();
+        self.0
+            .iter()
+            .all(|part| chars.next().is_some_and(|c| part.matches(c)))
+    }
+
+    fn matches_end(&self, word: &str) -> bool {
+        let mut chars = word.chars().rev();
+        self.0
+            .iter()
+            .rev()
+            .all(|part| chars.next().is_some_and(|c| part.matches(c)))
+    }
+}
+
+/// A `PFX` or `SFX` rule
+#[derive(Debug, Clone)]
+struct Affix {
+    flag: u32,
+    /// Whether it combines with affixes of the other kind
+    cross_product: bool,
+    /// Characters removed from the stem before adding
+    strip: String,
+    /// Characters added to the stem
+    add: String,
+    condition: Condition,
+}
+
+/// Flags that change how a dictionary word is used
+#[derive(Debug, Clone, Copy, Default)]
+struct SpecialFlags {
+    forbidden: Option<u32>,
+    no_suggest: Option<u32>,
+    keep_case: Option<u32>,
+    need_affix: Option<u32>,
+    only_in_compound: Option<u32>,
+}
+
+/// A loaded Hunspell dictionary
+#[derive(Debug, Clone, Default)]
+pub struct Dictionary {
+    /// Stems with the flag sets of each homonym
+    words: HashMap<String, Vec<Vec<u32>>>,
+    prefixes: Vec<Affix>,
+    suffixes: Vec<Affix>,
+    /// Prefix rule indices by the first character they add
+    prefix_index: HashMap<Option<char>, Vec<usize>>,
+    /// Suffix rule indices by the last character they add
+    suffix_index: HashMap<Option<char>, Vec<usize>>,
+    /// Lower-case stems by first letter and length, for similar words
+    stem_index: HashMap<(char, usize), Vec<String>>,
+    try_chars: Vec<char>,
+    replacements: Vec<(String, String)>,
+    flags: SpecialFlags,
+}
+
+/// Capitalization of a word
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Casing {
+    Lower,
+    Capitalized,
+    Upper,
+    Mixed,
+}
+
+fn casing(word: &str) -> Casing {
+    let mut letters = word.chars().filter(|c| c.is_alphabetic());
+    let Some(first) = letters.next() else {
+        return Casing::Lower;
+    };
+    let rest: Vec<char> = letters.collect();
+    let rest_upper = rest.iter().all(|c| c.is_uppercase());
+    let rest_lower = rest.iter().all(|c| c.is_lowercase());
+    match (first.is_uppercase(), rest_lower, rest_upper) {
+        (false, true, _) => Casing::Lower,
+        (true, _, true) if !rest.is_empty() => Casing::Upper,
+        (true, true, _) => Casing::Capitalized,
+        _ => Casing::Mixed,
+    }
+}
+
+/// Decode dictionary bytes: UTF-8 when the affix file says so, otherwise
+/// treated as Latin-1 (ISO 8859-1)
+fn decode(bytes: &[u8], utf8: bool) -> Result<String> {
+    if utf8 {
+        let text = String::from_utf8(bytes.to_vec())
+            .map_err(|e| Error::ParseError(format!("dictionary is not valid UTF-8: {e}")))?;
+        Ok(text.trim_start_matches('\u{feff}').to_string())
+    } else {
+        Ok(bytes.iter().map(|&b| char::from(b)).collect())
+    }
+}
+
+fn declares_utf8(aff: &[u8]) -> bool {
+    String::from_utf8_lossy(aff).lines().any(|line| {
+        let mut fields = line.split_whitespace();
+        fields.next() == Some("SET")
+            && fields
+                .next()
+                .is_some_and(|set| set.eq_ignore_ascii_case("UTF-8"))
+    })
+}
+
+impl Dictionary {
+    /// Load a dictionary from its `.aff` and `.dic` files
+    pub fn load(aff: &Path, dic: &Path) -> Result<Self> {
+        let aff_bytes = std::fs::read(aff)?;
+        let dic_bytes = std::fs::read(dic)?;
+        let utf8 = declares_utf8(&aff_bytes);
+        Self::parse(&decode(&aff_bytes, utf8)?, &decode(&dic_bytes, utf8)?)
+    }
+
+    /// Build a dictionary from the text of its affix and word files
+    pub fn parse(aff: &str, dic: &str) -> Result<Self> {
+        let mut dict = Dictionary::default();
+        let mut mode = FlagMode::Char;
+        let mut try_chars = None;
+
+        // FLAG has to be known before any flag is read
+        for line in aff.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.first() == Some(&"FLAG") {
+                mode = match fields.get(1).copied() {
+                    Some("long") => FlagMode::Long,
+                    Some("num") => FlagMode::Num,
+                    _ => FlagMode::Char,
+                };
+            }
+        }
+
+        let mut lines = aff.lines().enumerate();
+        while let Some((number, line)) = lines.next() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let Some(&keyword) = fields.first() else {
+                continue;
+            };
+            let flag = || fields.get(1).and_then(|f| mode.parse_one(f));
+            match keyword {
+                "TRY" => try_chars = fields.get(1).map(|t| t.chars().collect()),
+                "FORBIDDENWORD" => dict.flags.forbidden = flag(),
+                "NOSUGGEST" => dict.flags.no_suggest = flag(),
+                "KEEPCASE" => dict.flags.keep_case = flag(),
+                "NEEDAFFIX" | "PSEUDOROOT" => dict.flags.need_affix = flag(),
+                "ONLYINCOMPOUND" => dict.flags.only_in_compound = flag(),
+                "REP" => {
+                    // The first REP line only holds the count
+                    if let [_, from, to, ..] = fields.as_slice() {
+                        dict.replacements
+                            .push((from.replace('_', " "), to.replace('_', " ")));
+                    }
+                }
+                "PFX" | "SFX" => {
+                    let header = |i: usize| {
+                        fields.get(i).copied().ok_or_else(|| {
+                            Error::ParseError(format!(
+                                "affix file line {}: incomplete {keyword} header",
+                                number + 1
+                            ))
+                        })
+                    };
+                    let flag = mode.parse_one(header(1)?).unwrap_or_default();
+                    let cross_product = header(2)? == "Y";
+                    let count: usize = header(3)?.parse().unwrap_or(0);
+                    for _ in 0..count {
+                        let Some((rule_number, rule)) = lines.next() else {
+                            break;
+                        };
+                        let rule: Vec<&str> = rule.split_whitespace().collect();
+                        if rule.len() < 4 || rule[0] != keyword {
+                            return Err(Error::ParseError(format!(
+                                "affix file line {}: expected a {keyword} rule",
+                                rule_number + 1
+                            )));
+                        }
+                        let empty = |s: &str| {
+                            if s == "0" {
+                                String::new()
+                            } else {
+                                s.to_string()
+                            }
+                        };
+                        // Continuation classes after `/` are not supported
+                        let add = rule[3].split('/').next().unwrap_or("");
+                        let affix = Affix {
+                            flag,
+                            cross_product,
+                            strip: empty(rule[2]),
+                            add: empty(add),
+                            condition: Condition::parse(rule.get(4).copied().unwrap_or(".")),
+                        };
+                        if keyword == "PFX" {
+                            dict.prefixes.push(affix);
+                        } else {
+                            dict.suffixes.push(affix);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        dict.try_chars = try_chars.unwrap_or_else(|| DEFAULT_TRY.chars().collect());
+
+        for (i, affix) in dict.prefixes.iter().enumerate() {
+            dict.prefix_index
+                .entry(affix.add.chars().next())
+                .or_default()
+                .push(i);
+        }
+        for (i, affix) in dict.suffixes.iter().enumerate() {
+            dict.suffix_index
+                .entry(affix.add.chars().next_back())
+                .or_default()
+                .push(i);
+        }
+
+        let mut entries = dic.lines();
+        // The first line is an approximate word count
+        if let Some(first) = dic.lines().next()
+            && first.trim().parse::<usize>().is_ok()
+        {
+            entries.next();
+        }
+        for line in entries {
+            let entry = line.split(['\t', ' ']).next().unwrap_or("").trim();
+            if entry.is_empty() {
+                continue;
+            }
+            let (word, flags) = split_entry(entry);
+            dict.words.entry(word).or_default().push(mode.parse(&flags));
+        }
+        for stem in dict.words.keys() {
+            let lower = stem.to_lowercase();
+            if let Some(first) = lower.chars().next() {
+                let key = (first, lower.chars().count());
+                dict.stem_index.entry(key).or_default().push(stem.clone());
+            }
+        }
+        Ok(dict)
+    }
+
+    /// Number of stems
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Whether the dictionary has no words
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Whether a word is spelled correctly. Capitalized and upper-case
+    /// words also match lower-case entries, unless marked `KEEPCASE`.
+    pub fn check(&self, word: &str) -> bool {
+        if word.is_empty() {
+            return true;
+        }
+        if self.check_form(word, false) {
+            return true;
+        }
+        let lower = word.to_lowercase();
+        match casing(word) {
+            Casing::Lower | Casing::Mixed => false,
+            Casing::Capitalized => self.check_form(&lower, true),
+            Casing::Upper => {
+                self.check_form(&lower, true) || self.check_form(&capitalize(&lower), true)
+            }
+        }
+    }
+
+    fn check_form(&self, word: &str, case_changed: bool) -> bool {
+        if let Some(homonyms) = self.words.get(word) {
+            if homonyms
+                .iter()
+                .any(|flags| self.has(flags, self.flags.forbidden))
+            {
+                return false;
+            }
+            let usable = homonyms.iter().any(|flags| {
+                !self.has(flags, self.flags.need_affix) && self.standalone(flags, case_changed)
+            });
+            if usable {
+                return true;
+            }
+        }
+        self.check_affixed(word, case_changed)
+    }
+
+    fn has(&self, flags: &[u32], flag: Option<u32>) -> bool {
+        flag.is_some_and(|f| flags.contains(&f))
+    }
+
+    /// Whether a word with these flags may appear outside compounds, in
+    /// the given casing
+    fn standalone(&self, flags: &[u32], case_changed: bool) -> bool {
+        let keep_case = case_changed && self.has(flags, self.flags.keep_case);
+        !keep_case && !self.has(flags, self.flags.only_in_compound)
+    }
+
+    /// Whether a stem exists with all the given flags
+    fn stem_has(&self, stem: &str, required: &[u32], case_changed: bool) -> bool {
+        self.words.get(stem).is_some_and(|homonyms| {
+            homonyms.iter().any(|flags| {
+                required.iter().all(|f| flags.contains(f))
+                    && !self.has(flags, self.flags.forbidden)
+                    && self.standalone(flags, case_changed)
+            })
+        })
+    }
+
+    fn rules<'a>(
+        &'a self,
+        index: &'a HashMap<Option<char>, Vec<usize>>,
+        key: Option<char>,
+    ) -> impl Iterator<Item = usize> + 'a {
+        let keyed = key.and_then(|k| index.get(&Some(k)));
+        keyed
+            .into_iter()
+            .chain(index.get(&None))
+            .flat_map(|rules| rules.iter().copied())
+    }
+
+    fn check_affixed(&self, word: &str, case_changed: bool) -> bool {
+        for i in self.rules(&self.suffix_index, word.chars().next_back()) {
+            let sfx = &self.suffixes[i];
+            let Some(base) = strip_suffix(word, sfx) else {
+                continue;
+            };
+            if self.stem_has(&base, &[sfx.flag], case_changed) {
+                return true;
+            }
+            if !sfx.cross_product {
+                continue;
+            }
+            for j in self.rules(&self.prefix_index, base.chars().next()) {
+                let pfx = &self.prefixes[j];
+                if !pfx.cross_product {
+                    continue;
+                }
+                if let Some(root) = strip_prefix(&base, pfx)
+                    && self.stem_has(&root, &[sfx.flag, pfx.flag], case_changed)
+                {
+                    return true;
+                }
+            }
+        }
+        for i in self.rules(&self.prefix_index, word.chars().next()) {
+            let pfx = &self.prefixes[i];
+            if let Some(root) = strip_prefix(word, pfx)
+                && self.stem_has(&root, &[pfx.flag], case_changed)
+            {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Up to `max` suggestions for a misspelled word, best first
+    pub fn suggest(&self, word: &str, max: usize) -> Vec<String> {
+        let word_casing = casing(word);
+        let mut candidates: Vec<(String, bool)> = Vec::new();
+
+        // Likely replacements and case fixes rank first
+        for (from, to) in &self.replacements {
+            for (i, _) in word.match_indices(from.as_str()) {
+                let mut candidate = word.to_string();
+                candidate.replace_range(i..i + from.len(), to);
+                candidates.push((candidate, true));
+            }
+        }
+        candidates.push((capitalize(word), true));
+        candidates.push((word.to_lowercase(), true));
+        candidates.extend(self.edits(word).into_iter().map(|c| (c, false)));
+        let chars: Vec<char> = word.chars().collect();
+        for i in 1..chars.len() {
+            let left: String = chars[..i].iter().collect();
+            let right: String = chars[i..].iter().collect();
+            candidates.push((format!("{left} {right}"), false));
+        }
+
+        let mut seen = HashSet::new();
+        let mut valid: Vec<(String, bool)> = candidates
+            .into_iter()
+            .filter(|(c, _)| c != word && seen.insert(c.clone()) && self.suggestable(c))
+            .collect();
+
+        // Nothing close by: look for similar dictionary words
+        if valid.is_empty() {
+            valid = self.similar_stems(word);
+        }
+
+        let lower = word.to_lowercase();
+        let first = lower.chars().next();
+        let mut ranked: Vec<(usize, (String, bool))> = valid.into_iter().enumerate().collect();
+        ranked.sort_by_cached_key(|(i, (candidate, preferred))| {
+            let candidate = candidate.to_lowercase();
+            (
+                !preferred,
+                edit_distance(&lower, &candidate),
+                candidate.chars().next() != first,
+                *i,
+            )
+        });
+
+        let mut out = Vec::new();
+        for (_, (candidate, _)) in ranked {
+            let candidate = match word_casing {
+                Casing::Capitalized => capitalize(&candidate),
+                Casing::Upper => candidate.to_uppercase(),
+                _ => candidate,
+            };
+            if !out.contains(&candidate) {
+                out.push(candidate);
+            }
+            if out.len() == max {
+                break;
+            }
+        }
+        out
+    }
+
+    /// Whether a candidate can be suggested: correct, and not made of a
+    /// `NOSUGGEST` word
+    fn suggestable(&self, candidate: &str) -> bool {
+        candidate.split(' ').all(|part| {
+            !part.is_empty()
+                && self.check(part)
+                && !self.words.get(part).is_some_and(|homonyms| {
+                    homonyms
+                        .iter()
+                        .any(|flags| self.has(flags, self.flags.no_suggest))
+                })
+        })
+    }
+
+    /// Words one edit away: a deletion, swap of neighbours, replacement or
+    /// insertion of a `TRY` character
+    fn edits(&self, word: &str) -> Vec<String> {
+        let chars: Vec<char> = word.chars().collect();
+        let mut out = Vec::new();
+        let build = |chars: &[char]| chars.iter().collect::<String>();
+        for i in 0..chars.len() {
+            let mut deleted = chars.clone();
+            deleted.remove(i);
+            out.push(build(&deleted));
+        }
+        for i in 1..chars.len() {
+            let mut swapped = chars.clone();
+            swapped.swap(i - 1, i);
+            out.push(build(&swapped));
+        }
+        for &c in &self.try_chars {
+            for i in 0..chars.len() {
+                if chars[i] != c {
+                    let mut replaced = chars.clone();
+                    replaced[i] = c;
+                    out.push(build(&replaced));
+                }
+            }
+            for i in 0..=chars.len() {
+                let mut inserted = chars.clone();
+                inserted.insert(i, c);
+                out.push(build(&inserted));
+            }
+        }
+        out
+    }
+
+    /// Dictionary stems within a small edit distance of a word. Only stems
+    /// with the same first letter and a close length are compared.
+    fn similar_stems(&self, word: &str) -> Vec<(String, bool)> {
+        let lower = word.to_lowercase();
+        let Some(first) = lower.chars().next() else {
+            return Vec::new();
+        };
+        let len = lower.chars().count();
+        let limit = (len / 3).max(1);
+        let mut out: Vec<(usize, &String)> = (len.saturating_sub(limit)..=len + limit)
+            .filter_map(|stem_len| self.stem_index.get(&(first, stem_len)))
+            .flatten()
+            .map(|stem| (edit_distance(&lower, &stem.to_lowercase()), stem))
+            .filter(|(distance, stem)| *distance <= limit && self.suggestable(stem))
+            .collect();
+        out.sort();
+        out.into_iter()
+            .map(|(_, stem)| (stem.clone(), false))
+            .collect()
+    }
+}
+
+/// Dictionary entry `word/FLAGS`, where `\/` is a literal slash
+fn split_entry(entry: &str) -> (String, String) {
+    let mut word = String::new();
+    let mut chars = entry.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some((_, next)) = chars.next() {
+                    word.push(next);
+                }
+            }
+            '/' if !word.is_empty() => return (word, entry[i + 1..].to_string()),
+            c => word.push(c),
+        }
+    }
+    (word, String::new())
+}
+
+/// Stem a word would have before a suffix rule was applied
+fn strip_suffix(word: &str, sfx: &Affix) -> Option<String> {
+    let stem = word.strip_suffix(sfx.add.as_str())?;
+    if stem.is_empty() {
+        return None;
+    }
+    let base = format!("{stem}{}", sfx.strip);
+    sfx.condition.matches_end(&base).then_some(base)
+}
+
+/// Stem a word would have before a prefix rule was applied
+fn strip_prefix(word: &str, pfx: &Affix) -> Option<String> {
+    let stem = word.strip_prefix(pfx.add.as_str())?;
+    if stem.is_empty() {
+        return None;
+    }
+    let base = format!("{}{stem}", pfx.strip);
+    pfx.condition.matches_start(&base).then_some(base)
+}
+
+/// Edit distance counting insertions, deletions, substitutions and swaps
+/// of neighbouring characters
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AFF: &str = "SET UTF-8\nTRY esianrtolcdugmphbyfvkwz\nKEEPCASE K\nFORBIDDENWORD F\n\
+        REP 1\nREP f ph\n\
+        PFX U Y 1\nPFX U 0 un .\n\
+        SFX S Y 2\nSFX S 0 s [^y]\nSFX S y ies [^aeiou]y\n\
+        SFX D Y 2\nSFX D 0 ed [^e]\nSFX D 0 d e\n";
+    const DIC: &str = "7\nhello\nworld/S\nstory/S\nlock/USD\ntie/D\nParis/K\nirregardless/F\n";
+
+    fn dictionary() -> Dictionary {
+        Dictionary::parse(AFF, DIC).unwrap()
+    }
+
+    #[test]
+    fn test_check_affixes() {
+        let dict = dictionary();
+        for word in [
+            "hello", "worlds", "stories", "locked", "unlocks", "unlocked", "tied",
+        ] {
+            assert!(dict.check(word), "{word}");
+        }
+        for word in ["storys", "tieed", "unhello", "irregardless", "wor"] {
+            assert!(!dict.check(word), "{word}");
+        }
+    }
+
+    #[test]
+    fn test_check_case() {
+        let dict = dictionary();
+        assert!(dict.check("Hello"));
+        assert!(dict.check("HELLO"));
+        assert!(!dict.check("hELLo"));
+        assert!(dict.check("Paris"));
+        assert!(!dict.check("PARIS"));
+        assert!(!dict.check("paris"));
+    }
+
+    #[test]
+    fn test_flag_modes() {
+        let aff = "FLAG long\nSFX Aa Y 1\nSFX Aa 0 s .\n";
+        let dict = Dictionary::parse(aff, "cat/Aa\n").unwrap();
+        assert!(dict.check("cats"));
+
+        let aff = "FLAG num\nSFX 101 Y 1\nSFX 101 0 s .\n";
+        let dict = Dictionary::parse(aff, "1\ndog/7,101\n").unwrap();
+        assert!(dict.check("dogs"));
+    }
+
+    #[test]
+    fn test_suggest() {
+        let dict = dictionary();
+        assert_eq!(dict.suggest("helo", 3)[0], "hello");
+        assert_eq!(dict.suggest("Wrold", 3)[0], "World");
+        assert_eq!(dict.suggest("helloworld", 3), vec!["hello world"]);
+        // Two edits away, found through the stem index
+        assert_eq!(dict.suggest("hellooo", 3), vec!["hello"]);
+        assert!(dict.suggest("xyzzy", 3).is_empty());
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("ab", "ba"), 1);
+    }
+}
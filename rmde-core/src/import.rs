@@ -0,0 +1,1085 @@
+//! Conversion of HTML and Word content to Markdown
+
+use tree_sitter::{Node, Parser};
+
+use crate::html::{decode_entities, decode_entity};
+
+/// Block content read by an importer
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Block {
+    Heading(u8, Vec<Inline>),
+    Paragraph(Vec<Inline>),
+    Quote(Vec<Block>),
+    List {
+        ordered: bool,
+        start: u64,
+        items: Vec<ListItem>,
+    },
+    Code {
+        language: String,
+        code: String,
+    },
+    /// The first row is the header
+    Table {
+        aligns: Vec<Align>,
+        rows: Vec<Vec<Vec<Inline>>>,
+    },
+    Rule,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ListItem {
+    /// Checked state of a task list item
+    pub(crate) task: Option<bool>,
+    pub(crate) blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Align {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Inline {
+    Text(String),
+    Emphasis(Vec<Inline>),
+    Strong(Vec<Inline>),
+    Strike(Vec<Inline>),
+    Code(String),
+    Link {
+        url: String,
+        children: Vec<Inline>,
+    },
+    Image {
+        url: String,
+        alt: String,
+    },
+    Break,
+    /// Reference to a footnote by label
+    Footnote(String),
+}
+
+/// Convert an HTML document or fragment, such as a web page selection on
+/// the clipboard, to Markdown
+pub fn html_to_markdown(html: &str) -> String {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_html::LANGUAGE.into())
+        .expect("HTML grammar is compatible with tree-sitter");
+    let Some(tree) = parser.parse(html, None) else {
+        return String::new();
+    };
+    let reader = HtmlReader { html };
+    markdown(&reader.blocks(tree.root_node()), &[])
+}
+
+// --- HTML ---
+
+/// Elements that hold blocks of their own; their content is read as
+/// separate paragraphs
+const CONTAINERS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "body",
+    "center",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "header",
+    "html",
+    "li",
+    "main",
+    "nav",
+    "p",
+    "section",
+    "summary",
+];
+
+/// Elements dropped with their content
+const IGNORED: &[&str] = &[
+    "button", "head", "iframe", "link", "meta", "noscript", "object", "select", "svg", "template",
+    "textarea", "title",
+];
+
+/// Elements without content or an end tag
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+struct HtmlReader<'a> {
+    html: &'a str,
+}
+
+impl HtmlReader<'_> {
+    fn tag(&self, node: Node) -> String {
+        node.child(0)
+            .and_then(|tag| {
+                let mut cursor = tag.walk();
+                tag.children(&mut cursor).find(|c| c.kind() == "tag_name")
+            })
+            .map(|name| self.html[name.byte_range()].to_ascii_lowercase())
+            .unwrap_or_default()
+    }
+
+    fn attribute(&self, node: Node, name: &str) -> Option<String> {
+        let tag = node.child(0)?;
+        let mut cursor = tag.walk();
+        let attributes: Vec<Node> = tag
+            .children(&mut cursor)
+            .filter(|c| c.kind() == "attribute")
+            .collect();
+        attributes.into_iter().find_map(|attribute| {
+            let attribute_name = attribute.child(0)?;
+            if !self.html[attribute_name.byte_range()].eq_ignore_ascii_case(name) {
+                return None;
+            }
+            let value = attribute.child(2).map_or("", |value| {
+                let value = &self.html[value.byte_range()];
+                value.trim_matches(['"', '\''])
+            });
+            Some(decode_entities(value))
+        })
+    }
+
+    /// Child nodes of an element between its tags, each with whether
+    /// whitespace precedes it. The end tag is included when whitespace
+    /// precedes it.
+    fn content<'t>(&self, node: Node<'t>) -> Vec<(Node<'t>, bool)> {
+        let mut pos = node.start_byte();
+        let mut content = Vec::new();
+        self.collect_content(node, &mut pos, &mut content);
+        content
+    }
+
+    fn collect_content<'t>(
+        &self,
+        node: Node<'t>,
+        pos: &mut usize,
+        content: &mut Vec<(Node<'t>, bool)>,
+    ) {
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.children(&mut cursor).collect();
+        for child in children {
+            let gap = self.html[*pos..child.start_byte()].contains(char::is_whitespace);
+            match child.kind() {
+                "start_tag" | "self_closing_tag" | "erroneous_end_tag" => {
+                    *pos = child.end_byte();
+                    continue;
+                }
+                "end_tag" if !gap => continue,
+                _ => {}
+            }
+            content.push((child, gap));
+            if child.kind() == "element" && VOID.contains(&self.tag(child).as_str()) {
+                // The grammar puts the text after a void element inside it
+                *pos = child
+                    .child(0)
+                    .map_or(child.start_byte(), |tag| tag.start_byte());
+                self.collect_content(child, pos, content);
+            } else {
+                *pos = child.end_byte();
+            }
+        }
+    }
+
+    /// Whether an element holds block elements, like the `<b>` wrapper
+    /// Google Docs puts around copied paragraphs
+    fn has_blocks(&self, node: Node) -> bool {
+        self.content(node).into_iter().any(|(child, _)| {
+            child.kind() == "element" && {
+                let tag = self.tag(child);
+                is_block(&tag) || self.has_blocks(child)
+            }
+        })
+    }
+
+    fn blocks(&self, node: Node) -> Vec<Block> {
+        let mut blocks = Vec::new();
+        let mut inlines = Vec::new();
+        for (child, gap) in self.content(node) {
+            if gap {
+                inlines.push(Inline::Text(" ".to_string()));
+            }
+            match self.block(child) {
+                Some(child_blocks) => {
+                    paragraph(&mut inlines, &mut blocks);
+                    blocks.extend(child_blocks);
+                }
+                None => self.inline(child, &mut inlines),
+            }
+        }
+        paragraph(&mut inlines, &mut blocks);
+        blocks
+    }
+
+    /// The blocks of a block-level element, or `None` for inline content
+    fn block(&self, node: Node) -> Option<Vec<Block>> {
+        if node.kind() != "element" {
+            return None;
+        }
+        let tag = self.tag(node);
+        let block = match tag.as_str() {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = tag[1..].parse().unwrap_or(1);
+                Block::Heading(level, clean(self.inlines(node)))
+            }
+            "blockquote" => Block::Quote(self.blocks(node)),
+            "ul" | "ol" | "menu" => self.list(node, tag == "ol"),
+            "pre" => {
+                let language = std::iter::once(node)
+                    .chain(self.content(node).into_iter().map(|(child, _)| child))
+                    .filter_map(|n| self.attribute(n, "class"))
+                    .flat_map(|class| {
+                        class
+                            .split_whitespace()
+                            .filter_map(|c| c.strip_prefix("language-").or(c.strip_prefix("lang-")))
+                            .map(str::to_string)
+                            .collect::<Vec<_>>()
+                    })
+                    .next()
+                    .unwrap_or_default();
+                let code = self.text_content(node, true);
+                let code = code.strip_prefix('\n').unwrap_or(&code);
+                Block::Code {
+                    language,
+                    code: code.trim_end_matches('\n').to_string(),
+                }
+            }
+            "table" => self.table(node),
+            "hr" => Block::Rule,
+            tag if CONTAINERS.contains(&tag) => return Some(self.blocks(node)),
+            tag if IGNORED.contains(&tag) => return Some(Vec::new()),
+            _ if self.has_blocks(node) => return Some(self.blocks(node)),
+            _ => return None,
+        };
+        Some(vec![block])
+    }
+
+    fn list(&self, node: Node, ordered: bool) -> Block {
+        let start = self
+            .attribute(node, "start")
+            .and_then(|s| s.trim().parse().ok())
+            .unwrap_or(1);
+        let mut items: Vec<ListItem> = Vec::new();
+        for (child, _) in self.content(node) {
+            if child.kind() != "element" {
+                continue;
+            }
+            match self.tag(child).as_str() {
+                "li" => items.push(ListItem {
+                    task: self.checkbox(child),
+                    blocks: self.blocks(child),
+                }),
+                // A list directly inside a list belongs to the item before
+                _ => {
+                    let blocks = self.block(child).unwrap_or_default();
+                    match items.last_mut() {
+                        Some(item) => item.blocks.extend(blocks),
+                        None => items.push(ListItem { task: None, blocks }),
+                    }
+                }
+            }
+        }
+        Block::List {
+            ordered,
+            start,
+            items,
+        }
+    }
+
+    /// Checked state of a checkbox at the start of a list item
+    fn checkbox(&self, item: Node) -> Option<bool> {
+        let (first, _) = self.content(item).into_iter().next()?;
+        if first.kind() != "element" {
+            return None;
+        }
+        match self.tag(first).as_str() {
+            "input" => self
+                .attribute(first, "type")
+                .filter(|t| t.eq_ignore_ascii_case("checkbox"))
+                .map(|_| self.attribute(first, "checked").is_some()),
+            "p" | "label" | "span" => self.checkbox(first),
+            _ => None,
+        }
+    }
+
+    fn table(&self, node: Node) -> Block {
+        let mut rows = Vec::new();
+        self.rows(node, &mut rows);
+        let aligns = rows
+            .first()
+            .map(|cells: &Vec<Node>| {
+                cells
+                    .iter()
+                    .map(|&cell| {
+                        let style = self.attribute(cell, "style").unwrap_or_default();
+                        let align = self.attribute(cell, "align").or_else(|| {
+                            style
+                                .split(';')
+                                .filter_map(|d| d.split_once(':'))
+                                .find(|(name, _)| name.trim() == "text-align")
+                                .map(|(_, value)| value.trim().to_string())
+                        });
+                        match align.as_deref() {
+                            Some("left") => Align::Left,
+                            Some("center") => Align::Center,
+                            Some("right") => Align::Right,
+                            _ => Align::None,
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let rows = rows
+            .into_iter()
+            .map(|cells| {
+                cells
+                    .into_iter()
+                    .map(|cell| clean(flatten(&self.blocks(cell))))
+                    .collect()
+            })
+            .collect();
+        Block::Table { aligns, rows }
+    }
+
+    /// Cells of the rows of a table, through `thead`, `tbody` and `tfoot`
+    fn rows<'t>(&self, node: Node<'t>, rows: &mut Vec<Vec<Node<'t>>>) {
+        for (child, _) in self.content(node) {
+            if child.kind() != "element" {
+                continue;
+            }
+            match self.tag(child).as_str() {
+                "tr" => rows.push(
+                    self.content(child)
+                        .into_iter()
+                        .map(|(cell, _)| cell)
+                        .filter(|&cell| {
+                            cell.kind() == "element"
+                                && matches!(self.tag(cell).as_str(), "td" | "th")
+                        })
+                        .collect(),
+                ),
+                "thead" | "tbody" | "tfoot" => self.rows(child, rows),
+                _ => {}
+            }
+        }
+    }
+
+    fn inlines(&self, node: Node) -> Vec<Inline> {
+        let mut inlines = Vec::new();
+        for (child, gap) in self.content(node) {
+            if gap {
+                inlines.push(Inline::Text(" ".to_string()));
+            }
+            self.inline(child, &mut inlines);
+        }
+        inlines
+    }
+
+    fn inline(&self, node: Node, out: &mut Vec<Inline>) {
+        match node.kind() {
+            "text" => out.push(Inline::Text(self.html[node.byte_range()].to_string())),
+            "entity" => out.push(Inline::Text(decode_entity(&self.html[node.byte_range()]))),
+            "element" => {}
+            // Comments, scripts and styles
+            _ => return,
+        }
+        if node.kind() != "element" {
+            return;
+        }
+        let tag = self.tag(node);
+        let inline = match tag.as_str() {
+            "em" | "i" | "cite" | "dfn" | "var" => Inline::Emphasis(self.inlines(node)),
+            "strong" | "b" => {
+                // Google Docs wraps copied content in a `<b>` that isn't bold
+                let style = self.attribute(node, "style").unwrap_or_default();
+                if style.replace(' ', "").contains("font-weight:normal") {
+                    out.extend(self.inlines(node));
+                    return;
+                }
+                Inline::Strong(self.inlines(node))
+            }
+            "s" | "strike" | "del" => Inline::Strike(self.inlines(node)),
+            "code" | "kbd" | "samp" | "tt" => Inline::Code(self.text_content(node, false)),
+            "a" => match self.attribute(node, "href") {
+                Some(url) if !url.trim_start().starts_with("javascript:") => Inline::Link {
+                    url: url.trim().to_string(),
+                    children: self.inlines(node),
+                },
+                _ => {
+                    out.extend(self.inlines(node));
+                    return;
+                }
+            },
+            "img" => Inline::Image {
+                url: self.attribute(node, "src").unwrap_or_default(),
+                alt: self.attribute(node, "alt").unwrap_or_default(),
+            },
+            "br" => Inline::Break,
+            "input" => return,
+            tag if IGNORED.contains(&tag) => return,
+            _ => {
+                // Inline styles, as copied from word processors
+                let style = self
+                    .attribute(node, "style")
+                    .unwrap_or_default()
+                    .to_ascii_lowercase()
+                    .replace(' ', "");
+                let mut inline = self.inlines(node);
+                if style.contains("line-through") {
+                    inline = vec![Inline::Strike(inline)];
+                }
+                if style.contains("font-style:italic") {
+                    inline = vec![Inline::Emphasis(inline)];
+                }
+                if [
+                    "font-weight:bold",
+                    "font-weight:600",
+                    "font-weight:700",
+                    "font-weight:800",
+                ]
+                .iter()
+                .any(|bold| style.contains(bold))
+                {
+                    inline = vec![Inline::Strong(inline)];
+                }
+                out.extend(inline);
+                return;
+            }
+        };
+        out.push(inline);
+    }
+
+    /// Text of an element; whitespace is kept as written in `<pre>` and
+    /// collapsed elsewhere
+    fn text_content(&self, node: Node, preserve: bool) -> String {
+        let mut text = String::new();
+        let mut pos = node
+            .child(0)
+            .map_or(node.start_byte(), |tag| tag.end_byte());
+        for (child, gap) in self.content(node) {
+            if preserve {
+                text.push_str(&self.html[pos..child.start_byte()]);
+            } else if gap {
+                text.push(' ');
+            }
+            pos = child.end_byte();
+            match child.kind() {
+                "text" => text.push_str(&self.html[child.byte_range()]),
+                "entity" => text.push_str(&decode_entity(&self.html[child.byte_range()])),
+                "element" if VOID.contains(&self.tag(child).as_str()) => {
+                    pos = child.child(0).map_or(pos, |tag| tag.end_byte());
+                    if self.tag(child) == "br" {
+                        text.push('\n');
+                    }
+                }
+                "element" => text.push_str(&self.text_content(child, preserve)),
+                _ => {}
+            }
+        }
+        if preserve {
+            let end = node
+                .child(node.child_count().saturating_sub(1))
+                .filter(|c| c.kind() == "end_tag")
+                .map_or(node.end_byte(), |c| c.start_byte());
+            if pos < end {
+                text.push_str(&self.html[pos..end]);
+            }
+            text
+        } else {
+            text.split_whitespace().collect::<Vec<_>>().join(" ")
+        }
+    }
+}
+
+fn is_block(tag: &str) -> bool {
+    CONTAINERS.contains(&tag)
+        || matches!(
+            tag,
+            "h1" | "h2"
+                | "h3"
+                | "h4"
+                | "h5"
+                | "h6"
+                | "blockquote"
+                | "ul"
+                | "ol"
+                | "menu"
+                | "pre"
+                | "table"
+                | "hr"
+        )
+}
+
+/// End an implicit paragraph of loose inline content
+fn paragraph(inlines: &mut Vec<Inline>, blocks: &mut Vec<Block>) {
+    let content = clean(std::mem::take(inlines));
+    if !content.is_empty() {
+        blocks.push(Block::Paragraph(content));
+    }
+}
+
+// --- Shared by the importers ---
+
+/// Normalize inline content: merge adjacent text and formatting, move line
+/// breaks out of formatting, collapse whitespace and drop what is empty
+pub(crate) fn clean(inlines: Vec<Inline>) -> Vec<Inline> {
+    let mut merged = merge(inlines);
+    let mut space = true;
+    collapse(&mut merged, &mut space);
+    drop_empty(merged)
+}
+
+fn merge(inlines: Vec<Inline>) -> Vec<Inline> {
+    let mut out: Vec<Inline> = Vec::new();
+    for inline in inlines {
+        let inline = match inline {
+            Inline::Emphasis(c) => Inline::Emphasis(merge(c)),
+            Inline::Strong(c) => Inline::Strong(merge(c)),
+            Inline::Strike(c) => Inline::Strike(merge(c)),
+            Inline::Link { url, children } => Inline::Link {
+                url,
+                children: merge(children),
+            },
+            inline => inline,
+        };
+        // Breaks at the edges of formatting go outside it
+        let (before, inline, after) = hoist_breaks(inline);
+        out.extend(before);
+        match (out.last_mut(), inline) {
+            (Some(Inline::Text(a)), Inline::Text(b)) => a.push_str(&b),
+            (Some(Inline::Code(a)), Inline::Code(b)) => a.push_str(&b),
+            (Some(Inline::Emphasis(a)), Inline::Emphasis(b))
+            | (Some(Inline::Strong(a)), Inline::Strong(b))
+            | (Some(Inline::Strike(a)), Inline::Strike(b)) => {
+                a.extend(b);
+                *a = merge(std::mem::take(a));
+            }
+            (
+                Some(Inline::Link { url, children }),
+                Inline::Link {
+                    url: next_url,
+                    children: next,
+                },
+            ) if *url == next_url => {
+                children.extend(next);
+                *children = merge(std::mem::take(children));
+            }
+            (_, inline) => out.push(inline),
+        }
+        out.extend(after);
+    }
+    out
+}
+
+fn hoist_breaks(mut inline: Inline) -> (Vec<Inline>, Inline, Vec<Inline>) {
+    let (mut before, mut after) = (Vec::new(), Vec::new());
+    if let Inline::Emphasis(c) | Inline::Strong(c) | Inline::Strike(c) = &mut inline {
+        while c.first() == Some(&Inline::Break) {
+            before.push(c.remove(0));
+        }
+        while c.len() > 1 && c.last() == Some(&Inline::Break) {
+            after.extend(c.pop());
+        }
+    }
+    (before, inline, after)
+}
+
+/// Collapse whitespace runs to single spaces across inline boundaries
+fn collapse(inlines: &mut [Inline], space: &mut bool) {
+    for inline in inlines {
+        match inline {
+            Inline::Text(text) => {
+                let mut collapsed = String::new();
+                for c in text.chars() {
+                    if c.is_whitespace() {
+                        if !*space {
+                            collapsed.push(' ');
+                        }
+                        *space = true;
+                    } else {
+                        collapsed.push(c);
+                        *space = false;
+                    }
+                }
+                *text = collapsed;
+            }
+            Inline::Emphasis(c) | Inline::Strong(c) | Inline::Strike(c) => collapse(c, space),
+            Inline::Link { children, .. } => collapse(children, space),
+            Inline::Break => *space = true,
+            Inline::Code(_) | Inline::Image { .. } | Inline::Footnote(_) => *space = false,
+        }
+    }
+}
+
+fn drop_empty(inlines: Vec<Inline>) -> Vec<Inline> {
+    inlines
+        .into_iter()
+        .filter_map(|inline| match inline {
+            Inline::Text(text) if text.is_empty() => None,
+            Inline::Emphasis(c) => Some(Inline::Emphasis(drop_empty(c))),
+            Inline::Strong(c) => Some(Inline::Strong(drop_empty(c))),
+            Inline::Strike(c) => Some(Inline::Strike(drop_empty(c))),
+            Inline::Link { url, children } => Some(Inline::Link {
+                url,
+                children: drop_empty(children),
+            }),
+            inline => Some(inline),
+        })
+        .filter(|inline| match inline {
+            Inline::Emphasis(c) | Inline::Strong(c) | Inline::Strike(c) => !c.is_empty(),
+            _ => true,
+        })
+        .collect()
+}
+
+/// Blocks as inline content, for table cells: paragraphs are separated by
+/// line breaks
+pub(crate) fn flatten(blocks: &[Block]) -> Vec<Inline> {
+    let mut out = Vec::new();
+    for block in blocks {
+        if !out.is_empty() {
+            out.push(Inline::Break);
+        }
+        match block {
+            Block::Heading(_, inlines) | Block::Paragraph(inlines) => {
+                out.extend(inlines.iter().cloned())
+            }
+            Block::Quote(blocks) => out.extend(flatten(blocks)),
+            Block::List { items, .. } => {
+                for item in items {
+                    if !out.is_empty() && out.last() != Some(&Inline::Break) {
+                        out.push(Inline::Break);
+                    }
+                    out.extend(flatten(&item.blocks));
+                }
+            }
+            Block::Code { code, .. } => out.push(Inline::Code(code.replace('\n', " "))),
+            Block::Table { rows, .. } => {
+                for cells in rows {
+                    for cell in cells {
+                        out.extend(cell.iter().cloned());
+                        out.push(Inline::Text(" ".to_string()));
+                    }
+                }
+            }
+            Block::Rule => {}
+        }
+    }
+    out
+}
+
+/// Text of inline content without formatting; line breaks become newlines
+pub(crate) fn plain(inlines: &[Inline]) -> String {
+    let mut text = String::new();
+    for inline in inlines {
+        match inline {
+            Inline::Text(t) | Inline::Code(t) => text.push_str(t),
+            Inline::Emphasis(c) | Inline::Strong(c) | Inline::Strike(c) => text.push_str(&plain(c)),
+            Inline::Link { children, .. } => text.push_str(&plain(children)),
+            Inline::Image { alt, .. } => text.push_str(alt),
+            Inline::Break => text.push('\n'),
+            Inline::Footnote(_) => {}
+        }
+    }
+    text
+}
+
+// --- Markdown ---
+
+/// Write blocks and footnote definitions as Markdown
+pub(crate) fn markdown(blocks: &[Block], footnotes: &[(String, Vec<Block>)]) -> String {
+    let mut out = write_blocks(blocks, "\n\n");
+    for (label, blocks) in footnotes {
+        let marker = format!("[^{label}]: ");
+        let body = write_blocks(blocks, "\n\n");
+        if !out.is_empty() {
+            out.push_str("\n\n");
+        }
+        out.push_str(&prefix_lines(&body, &marker, "    "));
+    }
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+fn write_blocks(blocks: &[Block], separator: &str) -> String {
+    let mut out = String::new();
+    let mut previous_list = None;
+    for block in blocks {
+        let text = write_block(block);
+        if text.is_empty() {
+            continue;
+        }
+        let list = match block {
+            Block::List { ordered, .. } => Some(*ordered),
+            _ => None,
+        };
+        if !out.is_empty() {
+            // Two lists of a kind in a row would run together into one
+            out.push_str(if list.is_some() && list == previous_list {
+                "\n\n<!-- -->\n\n"
+            } else {
+                separator
+            });
+        }
+        out.push_str(&text);
+        previous_list = list;
+    }
+    out
+}
+
+fn write_block(block: &Block) -> String {
+    match block {
+        Block::Heading(level, inlines) => {
+            let text = write_inlines(trim_breaks(inlines), " ");
+            let text = text.trim();
+            if text.is_empty() {
+                return String::new();
+            }
+            format!("{} {text}", "#".repeat(usize::from((*level).clamp(1, 6))))
+        }
+        Block::Paragraph(inlines) => write_inlines(trim_breaks(inlines), "\\\n")
+            .trim()
+            .lines()
+            .map(|line| escape_line_start(line.trim_start()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Block::Quote(blocks) => {
+            let body = write_blocks(blocks, "\n\n");
+            if body.is_empty() {
+                return body;
+            }
+            prefix_lines(&body, "> ", "> ")
+        }
+        Block::List {
+            ordered,
+            start,
+            items,
+        } => {
+            // A list is loose when an item has several paragraphs
+            let loose = items.iter().any(|item| {
+                item.blocks
+                    .iter()
+                    .filter(|b| !matches!(b, Block::List { .. }))
+                    .count()
+                    > 1
+            });
+            let separator = if loose { "\n\n" } else { "\n" };
+            let mut out = String::new();
+            for (i, item) in items.iter().enumerate() {
+                let marker = if *ordered {
+                    format!("{}. ", start + i as u64)
+                } else {
+                    "- ".to_string()
+                };
+                let mut body = write_blocks(&item.blocks, separator);
+                match item.task {
+                    Some(true) => body.insert_str(0, "[x] "),
+                    Some(false) => body.insert_str(0, "[ ] "),
+                    None => {}
+                }
+                if i > 0 {
+                    out.push_str(separator);
+                }
+                let indent = " ".repeat(marker.len());
+                out.push_str(prefix_lines(&body, &marker, &indent).trim_end());
+            }
+            out
+        }
+        Block::Code { language, code } => {
+            let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+            format!("{fence}{language}\n{code}\n{fence}")
+        }
+        Block::Table { aligns, rows } => write_table(aligns, rows),
+        Block::Rule => "---".to_string(),
+    }
+}
+
+fn write_table(aligns: &[Align], rows: &[Vec<Vec<Inline>>]) -> String {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            (0..columns)
+                .map(|i| {
+                    row.get(i).map_or(String::new(), |cell| {
+                        write_inlines(trim_breaks(cell), "<br>")
+                            .trim()
+                            .replace('|', "\\|")
+                    })
+                })
+                .collect()
+        })
+        .collect();
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            cells
+                .iter()
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or(0)
+                .max(3)
+        })
+        .collect();
+
+    let line = |row: &[String]| {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{cell}{}", " ".repeat(width - cell.chars().count())))
+            .collect();
+        format!("| {} |", cells.join(" | "))
+    };
+    let delimiter: Vec<String> = widths
+        .iter()
+        .enumerate()
+        .map(
+            |(i, &width)| match aligns.get(i).copied().unwrap_or(Align::None) {
+                Align::None => "-".repeat(width),
+                Align::Left => format!(":{}", "-".repeat(width - 1)),
+                Align::Center => format!(":{}:", "-".repeat(width - 2)),
+                Align::Right => format!("{}:", "-".repeat(width - 1)),
+            },
+        )
+        .collect();
+    let mut out = vec![line(&cells[0]), format!("| {} |", delimiter.join(" | "))];
+    out.extend(cells[1..].iter().map(|row| line(row)));
+    out.join("\n")
+}
+
+/// Prefix the first line and indent the others; blank lines stay blank
+fn prefix_lines(text: &str, first: &str, rest: &str) -> String {
+    if text.is_empty() {
+        return first.trim_end().to_string();
+    }
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let prefix = if i == 0 { first } else { rest };
+            if line.is_empty() {
+                prefix.trim_end().to_string()
+            } else {
+                format!("{prefix}{line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn trim_breaks(inlines: &[Inline]) -> &[Inline] {
+    let start = inlines
+        .iter()
+        .position(|i| *i != Inline::Break)
+        .unwrap_or(inlines.len());
+    let end = inlines
+        .iter()
+        .rposition(|i| *i != Inline::Break)
+        .map_or(start, |end| end + 1);
+    &inlines[start..end]
+}
+
+fn write_inlines(inlines: &[Inline], line_break: &str) -> String {
+    let mut out = String::new();
+    for inline in inlines {
+        match inline {
+            Inline::Text(text) => out.push_str(&escape_text(text)),
+            Inline::Emphasis(c) => wrap(&write_inlines(c, line_break), "*", &mut out),
+            Inline::Strong(c) => wrap(&write_inlines(c, line_break), "**", &mut out),
+            Inline::Strike(c) => wrap(&write_inlines(c, line_break), "~~", &mut out),
+            Inline::Code(code) => {
+                // Spaces at the edges, common in word processor runs, go
+                // outside the code span
+                let trimmed = code.trim_matches(' ');
+                if trimmed.is_empty() {
+                    out.push_str(code);
+                    continue;
+                }
+                let fence = "`".repeat(longest_run(trimmed, '`') + 1);
+                let pad = if trimmed.starts_with('`') || trimmed.ends_with('`') {
+                    " "
+                } else {
+                    ""
+                };
+                let leading = &code[..code.len() - code.trim_start_matches(' ').len()];
+                let trailing = &code[code.trim_end_matches(' ').len()..];
+                out.push_str(&format!(
+                    "{leading}{fence}{pad}{trimmed}{pad}{fence}{trailing}"
+                ));
+            }
+            Inline::Link { url, children } => {
+                let text = write_inlines(children, line_break);
+                if text.trim().is_empty() {
+                    continue;
+                }
+                if plain(children) == *url && url.contains("://") && !url.contains([' ', '<', '>'])
+                {
+                    out.push_str(&format!("<{url}>"));
+                } else {
+                    out.push_str(&format!("[{text}]({})", destination(url)));
+                }
+            }
+            Inline::Image { url, alt } => {
+                out.push_str(&format!("![{}]({})", escape_text(alt), destination(url)))
+            }
+            Inline::Break => {
+                out.truncate(out.trim_end_matches(' ').len());
+                out.push_str(line_break);
+            }
+            Inline::Footnote(label) => out.push_str(&format!("[^{label}]")),
+        }
+    }
+    out
+}
+
+/// Wrap formatted text in delimiters, keeping its outer spaces outside
+fn wrap(text: &str, delimiter: &str, out: &mut String) {
+    let trimmed = text.trim_matches(' ');
+    if trimmed.is_empty() {
+        out.push_str(text);
+        return;
+    }
+    let leading = text.len() - text.trim_start_matches(' ').len();
+    let trailing = text.len() - text.trim_end_matches(' ').len();
+    out.push_str(&text[..leading]);
+    out.push_str(delimiter);
+    out.push_str(trimmed);
+    out.push_str(delimiter);
+    out.push_str(&text[text.len() - trailing..]);
+}
+
+fn destination(url: &str) -> String {
+    let balanced = url.matches('(').count() == url.matches(')').count();
+    if url.is_empty() || url.contains(char::is_whitespace) || !balanced {
+        format!("<{}>", url.replace('<', "%3C").replace('>', "%3E"))
+    } else {
+        url.to_string()
+    }
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    text.split(|x| x != c).map(str::len).max().unwrap_or(0)
+}
+
+/// Backslash-escape characters that would otherwise be read as Markdown
+fn escape_text(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let previous = i.checked_sub(1).map(|p| chars[p]);
+        let next = chars.get(i + 1).copied();
+        let escape = match c {
+            '\\' | '`' | '*' | '[' | ']' => true,
+            // `snake_case` words are safe
+            '_' => {
+                !(previous.is_some_and(char::is_alphanumeric)
+                    && next.is_some_and(char::is_alphanumeric))
+            }
+            '<' => next.is_some_and(|n| n.is_ascii_alphabetic() || matches!(n, '/' | '!' | '?')),
+            '&' => next.is_some_and(|n| n.is_ascii_alphanumeric() || n == '#'),
+            '~' => previous == Some('~') || next == Some('~'),
+            _ => false,
+        };
+        if escape {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Escape a paragraph line that would otherwise start a heading, list,
+/// quote or other block
+fn escape_line_start(line: &str) -> String {
+    let hashes = line.len() - line.trim_start_matches('#').len();
+    let after_hashes = &line[hashes..];
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let after_digits = &line[digits..];
+    let ends_marker = |rest: &str| rest.is_empty() || rest.starts_with(' ');
+    if (1..=6).contains(&hashes) && ends_marker(after_hashes)
+        || line.starts_with(['>', '|'])
+        || line.starts_with("~~~")
+        || (line.starts_with(['-', '+']) && ends_marker(&line[1..]))
+        || (line.len() >= 2 && line.chars().all(|c| c == '=' || c == '-'))
+    {
+        format!("\\{line}")
+    } else if (1..=9).contains(&digits)
+        && after_digits.starts_with(['.', ')'])
+        && ends_marker(&after_digits[1..])
+    {
+        format!("{}\\{}", &line[..digits], after_digits)
+    } else {
+        line.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_blocks() {
+        let html = "<h1>Title <small>v2</small></h1>\n<p>Some <em>emphasis</em>, <b>bold</b> and \
+                    <code>a &lt; b</code> with a <a href=\"https://example.com/a b\">link</a>.</p>\
+                    <ul><li>one<ul><li>nested</li></ul></li><li><input type=\"checkbox\" checked> done</li></ul>\
+                    <ol start=\"3\"><li><p>three</p><p>more</p></li><li>four</ol>\
+                    <blockquote><p>quoted<br>line</p></blockquote>\
+                    <pre><code class=\"language-rust\">fn main() {\n    x &amp;&amp; y;\n}\n</code></pre>\
+                    <table><thead><tr><th>Name</th><th style=\"text-align: right\">Size</th></tr></thead>\
+                    <tbody><tr><td>a|b</td><td><p>1</p><p>2</p></td></tr></tbody></table><hr>\
+                    <p><img src=\"pic.png\" alt=\"A pic\"> <s>old</s></p>";
+        assert_eq!(
+            html_to_markdown(html),
+            "# Title v2\n\n\
+             Some *emphasis*, **bold** and `a < b` with a [link](<https://example.com/a b>).\n\n\
+             - one\n  - nested\n- [x] done\n\n\
+             3. three\n\n   more\n\n4. four\n\n\
+             > quoted\\\n> line\n\n\
+             ```rust\nfn main() {\n    x && y;\n}\n```\n\n\
+             | Name | Size   |\n| ---- | -----: |\n| a\\|b | 1<br>2 |\n\n\
+             ---\n\n\
+             ![A pic](pic.png) ~~old~~\n"
+        );
+    }
+
+    #[test]
+    fn test_html_fragment_whitespace_and_escaping() {
+        let html = "<meta charset=\"utf-8\"><b style=\"font-weight:normal;\" id=\"docs-internal-guid\">\
+                    <p><span style=\"font-weight:700\">Bold</span><span> and </span>\
+                    <span style=\"font-style:italic\">italic </span>text</p>\
+                    <p>1. not a list, *not* emphasis, snake_case &amp; [brackets]</p></b>\
+                    <!-- comment --><div>  spaced\n   out  </div>";
+        assert_eq!(
+            html_to_markdown(html),
+            "**Bold** and *italic* text\n\n\
+             1\\. not a list, \\*not\\* emphasis, snake_case & \\[brackets\\]\n\n\
+             spaced out\n"
+        );
+    }
+
+    #[test]
+    fn test_clean_merges_runs() {
+        let inlines = clean(vec![
+            Inline::Strong(vec![Inline::Text("Hel".to_string())]),
+            Inline::Strong(vec![Inline::Text("lo  ".to_string()), Inline::Break]),
+            Inline::Text("  world".to_string()),
+        ]);
+        assert_eq!(write_inlines(&inlines, "\\\n"), "**Hello**\\\nworld");
+    }
+}
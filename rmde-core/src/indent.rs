@@ -0,0 +1,411 @@
+//! Indentation settings and list-aware indent/outdent
+
+use std::collections::BTreeMap;
+
+use tree_sitter::Node;
+
+use crate::edit::TextEdit;
+use crate::selection::Selection;
+use crate::syntax::{MarkdownTree, ancestor_of_kind};
+use crate::text::{columns, line_of, line_starts, line_text, touched_lines};
+
+/// Character used for one level of indentation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    /// Soft tabs: indent with spaces
+    Spaces,
+    /// Hard tabs: indent with `\t`
+    Tabs,
+}
+
+/// Indentation settings for a document. The tab width is at least 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndentSettings {
+    style: IndentStyle,
+    /// Columns per indent level (and tab stop width)
+    tab_width: usize,
+}
+
+impl IndentSettings {
+    /// Soft tabs of the given width
+    pub fn spaces(tab_width: usize) -> Self {
+        Self {
+            style: IndentStyle::Spaces,
+            tab_width: tab_width.max(1),
+        }
+    }
+
+    /// Hard tabs displayed at the given width
+    pub fn tabs(tab_width: usize) -> Self {
+        Self {
+            style: IndentStyle::Tabs,
+            tab_width: tab_width.max(1),
+        }
+    }
+
+    /// Character used for one indent level
+    pub fn style(&self) -> IndentStyle {
+        self.style
+    }
+
+    /// Columns per indent level, and the tab stop width
+    pub fn tab_width(&self) -> usize {
+        self.tab_width
+    }
+
+    /// Text for one indent level
+    pub fn unit(&self) -> String {
+        match self.style {
+            IndentStyle::Spaces => " ".repeat(self.tab_width),
+            IndentStyle::Tabs => "\t".to_string(),
+        }
+    }
+
+    /// Detect the indent style used by existing text.
+    /// Returns `None` if no line is indented.
+    pub fn detect(text: &str) -> Option<Self> {
+        Self::detect_lines(text.lines())
+    }
+
+    /// Detect the indent style from lines, which may keep their line breaks
+    pub(crate) fn detect_lines<S: AsRef<str>>(lines: impl IntoIterator<Item = S>) -> Option<Self> {
+        let mut tab_lines = 0usize;
+        let mut space_lines = 0usize;
+        // Histogram of indent increases between consecutive non-blank lines
+        let mut steps = [0usize; 9];
+        let mut prev_indent = 0usize;
+
+        for line in lines {
+            let line = line.as_ref();
+            if line.trim().is_empty() {
+                continue;
+            }
+            if line.starts_with('\t') {
+                tab_lines += 1;
+                continue;
+            }
+            let indent = line.len() - line.trim_start_matches(' ').len();
+            if indent > 0 {
+                space_lines += 1;
+            }
+            if indent > prev_indent && indent - prev_indent < steps.len() {
+                steps[indent - prev_indent] += 1;
+            }
+            prev_indent = indent;
+        }
+
+        if tab_lines == 0 && space_lines == 0 {
+            return None;
+        }
+        if tab_lines > space_lines {
+            return Some(Self::tabs(Self::default().tab_width));
+        }
+
+        // Prefer the larger step on ties so 4-space files aren't read as 2
+        let width = (2..steps.len())
+            .max_by_key(|&w| (steps[w], w))
+            .filter(|&w| steps[w] > 0)
+            .unwrap_or(Self::default().tab_width);
+        Some(Self::spaces(width))
+    }
+}
+
+impl Default for IndentSettings {
+    fn default() -> Self {
+        Self::spaces(4)
+    }
+}
+
+/// Direction of an indent operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    In,
+    Out,
+}
+
+/// Edits indenting every line touched by the selections
+pub(crate) fn indent_edits(
+    text: &str,
+    selections: &[Selection],
+    settings: IndentSettings,
+) -> Vec<TextEdit> {
+    let lines = touched_lines(&line_starts(text), selections);
+    line_edits(text, &lines, settings, Direction::In)
+}
+
+/// Edits outdenting every line touched by the selections
+pub(crate) fn outdent_edits(
+    text: &str,
+    selections: &[Selection],
+    settings: IndentSettings,
+) -> Vec<TextEdit> {
+    let lines = touched_lines(&line_starts(text), selections);
+    line_edits(text, &lines, settings, Direction::Out)
+}
+
+/// Edits for the Tab key: selections spanning lines and cursors in front of a
+/// list item's content indent; other cursors insert indentation at the cursor
+pub(crate) fn tab_edits(
+    text: &str,
+    selections: &[Selection],
+    settings: IndentSettings,
+) -> Vec<TextEdit> {
+    let starts = line_starts(text);
+    let items = list_items(text, &starts, settings.tab_width);
+
+    let mut indent_sels = Vec::new();
+    let mut edits = Vec::new();
+    for sel in selections {
+        let start_line = line_of(&starts, sel.start());
+        let end_line = line_of(&starts, sel.end());
+        let before_item_content = sel.is_cursor()
+            && items
+                .iter()
+                .any(|item| item.first_line == start_line && sel.head <= item.content_start);
+        if start_line != end_line || before_item_content {
+            indent_sels.push(*sel);
+            continue;
+        }
+        let text_insert = match settings.style {
+            IndentStyle::Tabs => "\t".to_string(),
+            IndentStyle::Spaces => {
+                let col = columns(&text[starts[start_line]..sel.start()], settings.tab_width);
+                " ".repeat(settings.tab_width - col % settings.tab_width)
+            }
+        };
+        edits.push(TextEdit::new(sel.start()..sel.end(), text_insert));
+    }
+
+    let lines = touched_lines(&starts, &indent_sels);
+    edits.extend(line_edits(text, &lines, settings, Direction::In));
+    edits
+}
+
+/// A list item located by line
+struct ListItem {
+    first_line: usize,
+    last_line: usize,
+    /// Column where the item's marker starts
+    indent: usize,
+    /// Width of the marker plus its padding (`- `, `10. `)
+    marker_width: usize,
+    /// Byte offset of the item's content
+    content_start: usize,
+    /// Index of the enclosing list item, if nested
+    parent: Option<usize>,
+    /// Index of the previous sibling item in the same list
+    prev_sibling: Option<usize>,
+}
+
+fn list_items(text: &str, starts: &[usize], tab_width: usize) -> Vec<ListItem> {
+    let tree = MarkdownTree::parse(text);
+    let nodes = tree.nodes_of_kind("list_item");
+    let find = |node: Node| nodes.iter().position(|n| n.id() == node.id());
+
+    nodes
+        .iter()
+        .map(|node| {
+            let first_line = line_of(starts, node.start_byte());
+            let last_line = last_content_line(text, starts, node.end_byte()).max(first_line);
+            let line_start = starts[first_line];
+            let indent = columns(&text[line_start..node.start_byte()], tab_width);
+            let marker = node
+                .child(0)
+                .filter(|c| c.kind().starts_with("list_marker"))
+                .map(|c| c.byte_range())
+                .unwrap_or(node.start_byte()..node.start_byte());
+            let parent = node
+                .parent()
+                .and_then(|list| list.parent())
+                .and_then(|p| ancestor_of_kind(p, "list_item"))
+                .and_then(find);
+            let prev_sibling = node
+                .prev_named_sibling()
+                .filter(|n| n.kind() == "list_item")
+                .and_then(find);
+            ListItem {
+                first_line,
+                last_line,
+                indent,
+                marker_width: marker.len(),
+                content_start: marker.end,
+                parent,
+                prev_sibling,
+            }
+        })
+        .collect()
+}
+
+/// Build per-line edits. List items on touched lines carry their nested
+/// content along, shifted by the item's own nesting step.
+fn line_edits(
+    text: &str,
+    lines: &[usize],
+    settings: IndentSettings,
+    direction: Direction,
+) -> Vec<TextEdit> {
+    let starts = line_starts(text);
+    let items = list_items(text, &starts, settings.tab_width);
+    let unit_cols = settings.tab_width;
+
+    // line -> columns to shift (first assignment wins, outer items first)
+    let mut shifts: BTreeMap<usize, usize> = BTreeMap::new();
+    for &line in lines {
+        if shifts.contains_key(&line) {
+            continue;
+        }
+        // Outermost item starting on this line owns the subtree
+        let item = items.iter().find(|item| item.first_line == line);
+        match item {
+            Some(item) => {
+                let cols = match direction {
+                    Direction::In => match (settings.style, item.prev_sibling) {
+                        (IndentStyle::Spaces, Some(prev)) => items[prev].marker_width,
+                        _ => unit_cols,
+                    },
+                    Direction::Out => match item.parent {
+                        Some(parent) => item.indent.saturating_sub(items[parent].indent),
+                        None => unit_cols,
+                    },
+                };
+                for l in item.first_line..=item.last_line {
+                    shifts.entry(l).or_insert(cols);
+                }
+            }
+            None => {
+                shifts.insert(line, unit_cols);
+            }
+        }
+    }
+
+    let multi_line = lines.len() > 1;
+    let mut edits = Vec::new();
+    for (&line, &cols) in &shifts {
+        if cols == 0 {
+            continue;
+        }
+        let start = starts[line];
+        let content = line_text(text, &starts, line);
+        let blank = content.trim().is_empty();
+        match direction {
+            Direction::In => {
+                // Don't leave trailing whitespace on blank lines in a block
+                if blank && (multi_line || !lines.contains(&line)) {
+                    continue;
+                }
+                let insert = match settings.style {
+                    IndentStyle::Spaces => " ".repeat(cols),
+                    IndentStyle::Tabs => "\t".repeat(cols.div_ceil(unit_cols)),
+                };
+                edits.push(TextEdit::insert(start, insert));
+            }
+            Direction::Out => {
+                let remove = leading_bytes_for_columns(content, cols, settings.tab_width);
+                if remove > 0 {
+                    edits.push(TextEdit::delete(start..start + remove));
+                }
+            }
+        }
+    }
+    edits
+}
+
+/// Last non-blank line ending at or before `end`
+fn last_content_line(text: &str, starts: &[usize], end: usize) -> usize {
+    let mut line = line_of(starts, end.saturating_sub(1));
+    while line > 0 && line_text(text, starts, line).trim().is_empty() {
+        line -= 1;
+    }
+    line
+}
+
+/// Number of leading whitespace bytes covering up to `cols` columns
+fn leading_bytes_for_columns(line: &str, cols: usize, tab_width: usize) -> usize {
+    let mut col = 0;
+    let mut bytes = 0;
+    for c in line.chars() {
+        if col >= cols {
+            break;
+        }
+        match c {
+            ' ' => col += 1,
+            '\t' => col += tab_width - col % tab_width,
+            _ => break,
+        }
+        bytes += 1;
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit::edited;
+
+    #[test]
+    fn test_detect_indent() {
+        assert_eq!(IndentSettings::detect("plain\ntext\n"), None);
+        assert_eq!(
+            IndentSettings::detect("- a\n  - b\n    - c\n"),
+            Some(IndentSettings::spaces(2))
+        );
+        assert_eq!(
+            IndentSettings::detect("- a\n\t- b\n\t\t- c\n"),
+            Some(IndentSettings::tabs(4))
+        );
+    }
+
+    #[test]
+    fn test_indent_plain_lines() {
+        let text = "one\ntwo\nthree\n";
+        let sel = [Selection::new(0, 6)];
+        let edits = indent_edits(text, &sel, IndentSettings::spaces(2));
+        assert_eq!(edited(text, edits), "  one\n  two\nthree\n");
+    }
+
+    #[test]
+    fn test_indent_list_item_nests_under_sibling() {
+        let text = "1. one\n2. two\n   more\n\n   ```\n   code\n   ```\n";
+        let sel = [Selection::cursor(8)];
+        let edits = indent_edits(text, &sel, IndentSettings::spaces(2));
+        assert_eq!(
+            edited(text, edits),
+            "1. one\n   2. two\n      more\n\n      ```\n      code\n      ```\n"
+        );
+    }
+
+    #[test]
+    fn test_outdent_nested_item() {
+        let text = "- a\n  - b\n    more\n";
+        let sel = [Selection::cursor(7)];
+        let edits = outdent_edits(text, &sel, IndentSettings::spaces(4));
+        assert_eq!(edited(text, edits), "- a\n- b\n  more\n");
+    }
+
+    #[test]
+    fn test_outdent_uses_tab_width() {
+        let text = "- a\n\t- b\n\t\t- c\n";
+        let sel = [Selection::cursor(text.len() - 2)];
+        let edits = outdent_edits(text, &sel, IndentSettings::tabs(2));
+        assert_eq!(edited(text, edits), "- a\n\t- b\n\t- c\n");
+        assert_eq!(IndentSettings::spaces(0).tab_width(), 1);
+    }
+
+    #[test]
+    fn test_tab_inserts_to_tab_stop() {
+        let text = "ab\n";
+        let sel = [Selection::cursor(1)];
+        let edits = tab_edits(text, &sel, IndentSettings::spaces(4));
+        assert_eq!(edited(text, edits), "a   b\n");
+        let edits = tab_edits(text, &sel, IndentSettings::tabs(4));
+        assert_eq!(edited(text, edits), "a\tb\n");
+    }
+
+    #[test]
+    fn test_tab_in_empty_list_item_indents() {
+        let text = "- a\n- \n";
+        let sel = [Selection::cursor(6)];
+        let edits = tab_edits(text, &sel, IndentSettings::spaces(4));
+        assert_eq!(edited(text, edits), "- a\n  - \n");
+    }
+}
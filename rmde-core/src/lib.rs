@@ -1,16 +1,84 @@
+mod anchors;
+mod date;
+mod diagnostic;
 mod document;
+mod docx;
+mod edit;
 mod editor;
+mod emphasis;
+mod encoding;
+mod entities;
+mod epub;
 mod error;
 mod ffi;
+mod footnotes;
+mod front_matter;
+mod history;
+mod html;
+mod hunspell;
+mod import;
+mod indent;
+mod line_ending;
+mod links;
+mod outline;
+mod prose;
+mod references;
+mod reflow;
+mod save;
 mod selection;
+mod site;
+mod slides;
+mod slug;
+mod spell;
+mod stats;
+mod syntax;
+mod tasks;
+mod text;
+mod toc;
+mod unicode;
+mod wiki;
+mod workspace;
+mod writing;
 
+pub use anchors::{AnchorCompletion, HeadingRename, LinkUpdate};
+pub use date::Date;
+pub use diagnostic::{Diagnostic, Severity};
 pub use document::Document;
+pub use docx::{DocxImport, import_docx, render_docx};
+pub use edit::TextEdit;
 pub use editor::Editor;
+pub use encoding::Encoding;
+pub use epub::{EpubOptions, EpubSource, render_epub};
 pub use error::Error;
+pub use footnotes::{FootnoteDefinition, FootnoteReference};
+pub use front_matter::{
+    FrontMatter, FrontMatterEntry, FrontMatterFormat, FrontMatterValue, parse_front_matter,
+};
+pub use html::{HtmlOptions, render_html};
+pub use hunspell::Dictionary;
+pub use import::html_to_markdown;
+pub use indent::{IndentSettings, IndentStyle};
+pub use line_ending::{LineEnding, LineEndingCounts};
+pub use links::{Link, LinkTarget};
+pub use outline::Heading;
+pub use prose::{Glossary, GlossaryTerm, ProseRules};
+pub use references::{LinkDefinition, LinkReference};
+pub use reflow::WrapSettings;
 pub use selection::Selection;
+pub use site::{SiteOptions, SiteReport, build_site};
+pub use slides::{Slide, SlideOptions, SlideSplit, render_slides, slides};
+pub use slug::{Slugger, heading_slugs, slugify};
+pub use spell::{Misspelling, SpellChecker};
+pub use stats::{DocumentStats, SectionStats, StatsOptions, TextStats, document_stats};
+pub use syntax::MarkdownTree;
+pub use tasks::{Task, TaskIndex, TaskProgress, TaskQuery, TaskSource, TaskState};
+pub use toc::{TocEntry, toc_entries};
+pub use unicode::{InvisibleChar, NormalizationForm, invisible_chars};
+pub use wiki::{Backlink, NoteIndex, WikiLink};
+pub use writing::{GoalProgress, GoalStorage, WritingHistory, WritingSession, WritingStats};
 
 /// Document identifier for tab tracking
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct DocumentId(u64);
 
 impl DocumentId {
@@ -0,0 +1,204 @@
+//! Line ending styles: detection, normalization of inserted text and
+//! whole-document conversion
+
+use std::borrow::Cow;
+
+use crate::edit::TextEdit;
+
+/// A line break sequence
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LineEnding {
+    /// `\n`, used by Unix and macOS
+    #[default]
+    Lf,
+    /// `\r\n`, used by Windows
+    Crlf,
+    /// `\r`, used by classic Mac OS
+    Cr,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+            Self::Cr => "\r",
+        }
+    }
+
+    /// Display name: `LF`, `CRLF` or `CR`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Lf => "LF",
+            Self::Crlf => "CRLF",
+            Self::Cr => "CR",
+        }
+    }
+
+    /// Look up a line ending by display name, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Lf, Self::Crlf, Self::Cr]
+            .into_iter()
+            .find(|ending| ending.name().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+/// How often each line ending occurs in a text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineEndingCounts {
+    pub lf: usize,
+    pub crlf: usize,
+    pub cr: usize,
+}
+
+impl LineEndingCounts {
+    /// Count line breaks in text split into chunks, which may cut a `\r\n`
+    pub fn count<'a>(chunks: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut counts = Self::default();
+        let mut after_cr = false;
+        for chunk in chunks {
+            for byte in chunk.bytes() {
+                match byte {
+                    b'\n' if after_cr => {
+                        counts.cr -= 1;
+                        counts.crlf += 1;
+                    }
+                    b'\n' => counts.lf += 1,
+                    b'\r' => counts.cr += 1,
+                    _ => {}
+                }
+                after_cr = byte == b'\r';
+            }
+        }
+        counts
+    }
+
+    /// The most common line ending, preferring LF, then CRLF on ties.
+    /// `None` for text without line breaks.
+    pub fn dominant(&self) -> Option<LineEnding> {
+        [
+            (self.lf, LineEnding::Lf),
+            (self.crlf, LineEnding::Crlf),
+            (self.cr, LineEnding::Cr),
+        ]
+        .into_iter()
+        .filter(|&(count, _)| count > 0)
+        .reduce(|best, next| if next.0 > best.0 { next } else { best })
+        .map(|(_, ending)| ending)
+    }
+
+    /// Whether more than one kind of line ending occurs
+    pub fn is_mixed(&self) -> bool {
+        [self.lf, self.crlf, self.cr]
+            .iter()
+            .filter(|&&count| count > 0)
+            .count()
+            > 1
+    }
+}
+
+/// Byte ranges of the line breaks in text, with `\r\n` as one break
+pub(crate) fn line_breaks(text: &str) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    std::iter::from_fn(move || {
+        while i < bytes.len() {
+            let start = i;
+            i += 1;
+            match bytes[start] {
+                b'\n' => return Some((start, i)),
+                b'\r' => {
+                    if bytes.get(i) == Some(&b'\n') {
+                        i += 1;
+                    }
+                    return Some((start, i));
+                }
+                _ => {}
+            }
+        }
+        None
+    })
+}
+
+/// Number of line breaks at the end of text, in any style
+pub(crate) fn trailing_breaks(text: &str) -> usize {
+    let mut rest = text;
+    let mut count = 0;
+    while let Some(before) = rest
+        .strip_suffix("\r\n")
+        .or_else(|| rest.strip_suffix(['\n', '\r']))
+    {
+        rest = before;
+        count += 1;
+    }
+    count
+}
+
+/// Text with every line break replaced by `ending`, borrowed if unchanged
+pub(crate) fn normalize(text: &str, ending: LineEnding) -> Cow<'_, str> {
+    let target = ending.as_str();
+    if line_breaks(text).all(|(start, end)| &text[start..end] == target) {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, end) in line_breaks(text) {
+        out.push_str(&text[last..start]);
+        out.push_str(target);
+        last = end;
+    }
+    out.push_str(&text[last..]);
+    Cow::Owned(out)
+}
+
+/// Edits converting every line break to `ending`
+pub(crate) fn convert_edits(text: &str, ending: LineEnding) -> Vec<TextEdit> {
+    line_breaks(text)
+        .filter(|&(start, end)| &text[start..end] != ending.as_str())
+        .map(|(start, end)| TextEdit::new(start..end, ending.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit::edited;
+
+    #[test]
+    fn test_counts() {
+        let counts = LineEndingCounts::count(["a\r", "\nb\nc\r\nd\re"]);
+        assert_eq!(
+            counts,
+            LineEndingCounts {
+                lf: 1,
+                crlf: 2,
+                cr: 1
+            }
+        );
+        assert_eq!(counts.dominant(), Some(LineEnding::Crlf));
+        assert!(counts.is_mixed());
+
+        let counts = LineEndingCounts::count(["a\nb\r\n"]);
+        assert_eq!(counts.dominant(), Some(LineEnding::Lf));
+        assert_eq!(LineEndingCounts::count(["no breaks"]).dominant(), None);
+        assert!(!LineEndingCounts::count(["a\rb\r"]).is_mixed());
+        assert_eq!(LineEnding::from_name("crlf"), Some(LineEnding::Crlf));
+    }
+
+    #[test]
+    fn test_normalize_and_convert() {
+        assert!(matches!(
+            normalize("a\r\nb\r\n", LineEnding::Crlf),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(
+            normalize("a\nb\r\nc\rd", LineEnding::Crlf),
+            "a\r\nb\r\nc\r\nd"
+        );
+        assert_eq!(normalize("a\r\n\r\nb", LineEnding::Lf), "a\n\nb");
+
+        let text = "a\r\nb\nc\r";
+        let out = edited(text, convert_edits(text, LineEnding::Lf));
+        assert_eq!(out, "a\nb\nc\n");
+    }
+}
@@ -0,0 +1,305 @@
+//! Link resolution, validation and following
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::outline::outline;
+use crate::slug::heading_slugs;
+use crate::syntax::{MarkdownTree, child_of_kind};
+
+/// A link destination written in the document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// Byte range of the whole link, image or reference definition
+    pub range: Range<usize>,
+    /// Byte range of the destination
+    pub destination_range: Range<usize>,
+    /// Destination as written, without angle brackets
+    pub destination: String,
+}
+
+/// Where a link points
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTarget {
+    /// A URL with a scheme (`https:`, `mailto:`, ...)
+    External(String),
+    /// A heading anchor in the same document
+    Anchor(String),
+    /// A local file, optionally with a heading anchor
+    File {
+        path: PathBuf,
+        anchor: Option<String>,
+    },
+}
+
+/// Collect inline links, images and link reference definitions
+pub fn links(tree: &MarkdownTree, text: &str) -> Vec<Link> {
+    let mut out = Vec::new();
+    tree.visit(&mut |node| {
+        if !matches!(
+            node.kind(),
+            "inline_link" | "image" | "link_reference_definition"
+        ) {
+            return;
+        }
+        // `[^1]: text` is a footnote, not a link definition
+        if node.kind() == "link_reference_definition"
+            && child_of_kind(node, "link_label")
+                .is_some_and(|label| text[label.byte_range()].starts_with("[^"))
+        {
+            return;
+        }
+        let Some(dest) = child_of_kind(node, "link_destination") else {
+            return;
+        };
+        let raw = &text[dest.byte_range()];
+        let destination = raw
+            .strip_prefix('<')
+            .and_then(|d| d.strip_suffix('>'))
+            .unwrap_or(raw);
+        out.push(Link {
+            range: node.byte_range(),
+            destination_range: dest.byte_range(),
+            destination: destination.to_string(),
+        });
+    });
+    out
+}
+
+/// Resolve a destination relative to the document at `base`. Root-relative
+/// paths (`/notes/a.md`) resolve against `root` when given. Returns `None`
+/// for relative paths when the document has no path yet.
+pub fn resolve(destination: &str, base: Option<&Path>, root: Option<&Path>) -> Option<LinkTarget> {
+    if has_scheme(destination) {
+        return Some(LinkTarget::External(destination.to_string()));
+    }
+
+    let (path_part, anchor) = match destination.split_once('#') {
+        Some((p, a)) => (p, Some(percent_decode(a))),
+        None => (destination, None),
+    };
+    let path_part = path_part.split('?').next().unwrap_or("");
+    if path_part.is_empty() {
+        return anchor.map(LinkTarget::Anchor);
+    }
+
+    let decoded = percent_decode(path_part);
+    let path = match decoded.strip_prefix('/') {
+        Some(rest) => match root {
+            Some(root) => root.join(rest),
+            None => PathBuf::from(&decoded),
+        },
+        None => base?.parent()?.join(&decoded),
+    };
+    Some(LinkTarget::File {
+        path: normalize_path(&path),
+        anchor: anchor.filter(|a| !a.is_empty()),
+    })
+}
+
+/// Anchor slugs of every heading in Markdown text
+pub fn anchors(text: &str) -> Vec<String> {
+    heading_slugs(&outline(&MarkdownTree::parse(text), text))
+}
+
+/// Byte offset of the heading an anchor points at
+pub fn anchor_position(text: &str, anchor: &str) -> Option<usize> {
+    let headings = outline(&MarkdownTree::parse(text), text);
+    let slugs = heading_slugs(&headings);
+    slugs
+        .iter()
+        .position(|s| s.eq_ignore_ascii_case(anchor))
+        .map(|idx| headings[idx].range.start)
+}
+
+/// The link whose range contains a byte offset
+pub fn link_at(links: &[Link], pos: usize) -> Option<&Link> {
+    links
+        .iter()
+        .find(|l| l.range.start <= pos && pos <= l.range.end)
+}
+
+/// Report links to missing files and anchors. `read` supplies the content of
+/// a linked Markdown file (open documents or disk).
+pub(crate) fn validate(
+    text: &str,
+    base: Option<&Path>,
+    root: Option<&Path>,
+    read: &dyn Fn(&Path) -> Option<String>,
+) -> Vec<Diagnostic> {
+    let tree = MarkdownTree::parse(text);
+    let own_anchors = heading_slugs(&outline(&tree, text));
+    let mut file_anchors: HashMap<PathBuf, Option<Vec<String>>> = HashMap::new();
+    let mut diagnostics = Vec::new();
+
+    for link in links(&tree, text) {
+        let Some(target) = resolve(&link.destination, base, root) else {
+            continue;
+        };
+        match target {
+            LinkTarget::External(_) => {}
+            LinkTarget::Anchor(anchor) => {
+                if !contains_anchor(&own_anchors, &anchor) {
+                    diagnostics.push(Diagnostic::new(
+                        link.destination_range,
+                        Severity::Warning,
+                        "missing-anchor",
+                        format!("No heading for anchor #{anchor}"),
+                    ));
+                }
+            }
+            LinkTarget::File { path, anchor } => {
+                if !path.exists() && read(&path).is_none() {
+                    diagnostics.push(Diagnostic::new(
+                        link.destination_range,
+                        Severity::Warning,
+                        "broken-link",
+                        format!("Link target not found: {}", path.display()),
+                    ));
+                    continue;
+                }
+                let Some(anchor) = anchor else { continue };
+                if !crate::workspace::is_markdown_file(&path) {
+                    continue;
+                }
+                let target_anchors = file_anchors
+                    .entry(path.clone())
+                    .or_insert_with(|| read(&path).map(|t| anchors(&t)));
+                if let Some(target_anchors) = target_anchors
+                    && !contains_anchor(target_anchors, &anchor)
+                {
+                    diagnostics.push(Diagnostic::new(
+                        link.destination_range,
+                        Severity::Warning,
+                        "missing-anchor",
+                        format!("No heading for anchor #{anchor} in {}", path.display()),
+                    ));
+                }
+            }
+        }
+    }
+    diagnostics
+}
+
+fn contains_anchor(anchors: &[String], anchor: &str) -> bool {
+    anchors.iter().any(|a| a.eq_ignore_ascii_case(anchor))
+}
+
+/// Check for a URL scheme such as `https:` (but not a Windows drive letter)
+fn has_scheme(dest: &str) -> bool {
+    let Some((scheme, _)) = dest.split_once(':') else {
+        return false;
+    };
+    scheme.len() > 1
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+/// Decode `%XX` escapes, leaving malformed ones as written
+pub(crate) fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(hex) = s.get(i + 1..i + 3)
+            && let Ok(byte) = u8::from_str_radix(hex, 16)
+        {
+            out.push(byte);
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Remove `.` and `..` components without touching the file system
+pub(crate) fn normalize_path(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !out.pop() {
+                    out.push("..");
+                }
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_relative_file_and_anchor() {
+        let base = Path::new("/notes/daily/today.md");
+        assert_eq!(
+            resolve("../design/spec%20v2.md#goals", Some(base), None),
+            Some(LinkTarget::File {
+                path: PathBuf::from("/notes/design/spec v2.md"),
+                anchor: Some("goals".into()),
+            })
+        );
+        assert_eq!(
+            resolve("#intro", None, None),
+            Some(LinkTarget::Anchor("intro".into()))
+        );
+        assert_eq!(
+            resolve("https://example.com/a.md", Some(base), None),
+            Some(LinkTarget::External("https://example.com/a.md".into()))
+        );
+        assert_eq!(resolve("a.md", None, None), None);
+    }
+
+    #[test]
+    fn test_root_relative() {
+        let base = Path::new("/ws/sub/a.md");
+        assert_eq!(
+            resolve("/b.md", Some(base), Some(Path::new("/ws"))),
+            Some(LinkTarget::File {
+                path: PathBuf::from("/ws/b.md"),
+                anchor: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_links_and_link_at() {
+        let text = "See [a](<x y.md>) and ![i](img.png).\n\n[ref]: other.md\n";
+        let found = links(&MarkdownTree::parse(text), text);
+        let dests: Vec<_> = found.iter().map(|l| l.destination.as_str()).collect();
+        assert_eq!(dests, vec!["x y.md", "img.png", "other.md"]);
+        assert_eq!(link_at(&found, 6).unwrap().destination, "x y.md");
+        assert!(link_at(&found, 0).is_none());
+    }
+
+    #[test]
+    fn test_validate_reports_broken_links() {
+        let text = "# Intro\n\n[ok](#intro) [bad](#nope) [file](b.md#missing) [gone](c.md)\n";
+        let read = |path: &Path| (path == Path::new("/ws/b.md")).then(|| "# Present\n".to_string());
+        let diags = validate(text, Some(Path::new("/ws/a.md")), None, &read);
+        let codes: Vec<_> = diags.iter().map(|d| d.code).collect();
+        assert_eq!(
+            codes,
+            vec!["missing-anchor", "missing-anchor", "broken-link"]
+        );
+        assert_eq!(&text[diags[0].range.clone()], "#nope");
+    }
+
+    #[test]
+    fn test_anchor_position() {
+        let text = "# A\n\n## Goals\n";
+        assert_eq!(anchor_position(text, "goals"), Some(5));
+        assert_eq!(anchor_position(text, "none"), None);
+    }
+}
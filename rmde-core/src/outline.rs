@@ -0,0 +1,141 @@
+//! Document outline built from ATX and setext headings
+
+use std::ops::Range;
+
+use crate::syntax::{MarkdownTree, plain_text};
+use crate::text::{line_of, line_starts};
+
+/// A heading in the document outline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    /// Heading level, 1-6
+    pub level: u8,
+    /// Rendered heading text without markers or inline syntax
+    pub text: String,
+    /// Byte range of the whole heading, including markers
+    pub range: Range<usize>,
+    /// Line the heading starts on (0-indexed)
+    pub line: usize,
+}
+
+/// Collect all headings in document order
+pub fn outline(tree: &MarkdownTree, text: &str) -> Vec<Heading> {
+    let starts = line_starts(text);
+    let mut headings = Vec::new();
+    tree.visit(&mut |node| {
+        let (level, content) = match node.kind() {
+            "atx_heading" => {
+                let mut cursor = node.walk();
+                let children: Vec<_> = node.children(&mut cursor).collect();
+                let level = children
+                    .iter()
+                    .find_map(|c| atx_level(c.kind()))
+                    .unwrap_or(1);
+                let content = children.iter().find(|c| c.kind() == "inline").copied();
+                (level, content)
+            }
+            "setext_heading" => {
+                let mut cursor = node.walk();
+                let children: Vec<_> = node.children(&mut cursor).collect();
+                let level = if children.iter().any(|c| c.kind() == "setext_h2_underline") {
+                    2
+                } else {
+                    1
+                };
+                let content = children
+                    .iter()
+                    .find(|c| c.kind() == "paragraph")
+                    .and_then(|p| p.child(0))
+                    .filter(|c| c.kind() == "inline");
+                (level, content)
+            }
+            _ => return,
+        };
+
+        let mut heading_text = content
+            .map(|c| plain_text(tree, c, text))
+            .unwrap_or_default();
+        if node.kind() == "atx_heading" {
+            heading_text = strip_closing_sequence(&heading_text).to_string();
+        }
+        let heading_text = heading_text
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        headings.push(Heading {
+            level,
+            text: heading_text,
+            range: node.byte_range(),
+            line: line_of(&starts, node.start_byte()),
+        });
+    });
+    headings
+}
+
+/// Titles of the headings enclosing a byte offset, outermost first
+pub fn heading_path(headings: &[Heading], pos: usize) -> Vec<String> {
+    let mut stack: Vec<&Heading> = Vec::new();
+    for heading in headings.iter().take_while(|h| h.range.start <= pos) {
+        while stack.last().is_some_and(|h| h.level >= heading.level) {
+            stack.pop();
+        }
+        stack.push(heading);
+    }
+    stack.into_iter().map(|h| h.text.clone()).collect()
+}
+
+fn atx_level(kind: &str) -> Option<u8> {
+    kind.strip_prefix("atx_h")?
+        .strip_suffix("_marker")?
+        .parse()
+        .ok()
+}
+
+/// Remove an optional closing `#` sequence from ATX heading text
+fn strip_closing_sequence(text: &str) -> &str {
+    let trimmed = text.trim_end();
+    let without = trimmed.trim_end_matches('#');
+    if without.len() == trimmed.len() {
+        return trimmed;
+    }
+    if without.is_empty() {
+        ""
+    } else if without.ends_with([' ', '\t']) {
+        without.trim_end()
+    } else {
+        trimmed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headings(text: &str) -> Vec<Heading> {
+        outline(&MarkdownTree::parse(text), text)
+    }
+
+    #[test]
+    fn test_atx_and_setext() {
+        let h = headings("# Top ##\n\nSetext\n------\n\n### C# *notes*\n");
+        let summary: Vec<_> = h.iter().map(|h| (h.level, h.text.as_str())).collect();
+        assert_eq!(summary, vec![(1, "Top"), (2, "Setext"), (3, "C# notes")]);
+        assert_eq!(h[2].line, 5);
+    }
+
+    #[test]
+    fn test_cr_line_breaks() {
+        let h = headings("# A\r\r- [ ] one\r\r## B\r\r- [ ] two\r");
+        let lines: Vec<_> = h.iter().map(|h| h.line).collect();
+        assert_eq!(lines, vec![0, 4]);
+    }
+
+    #[test]
+    fn test_heading_path() {
+        let text = "# A\n## B\n### C\n## D\ntext\n";
+        let h = headings(text);
+        let pos = text.find("text").unwrap();
+        assert_eq!(heading_path(&h, pos), vec!["A", "D"]);
+    }
+}
@@ -0,0 +1,512 @@
+//! Style checks on prose: repeated words, weasel words, passive voice,
+//! long sentences and paragraphs, and glossary terminology
+
+use std::ops::Range;
+use std::path::Path;
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::error::Result;
+use crate::spell::skipped_ranges;
+use crate::syntax::MarkdownTree;
+use crate::text::capitalize;
+
+/// Words and phrases that weaken or pad a sentence
+const WEASEL_WORDS: &[&str] = &[
+    "very",
+    "really",
+    "quite",
+    "fairly",
+    "rather",
+    "somewhat",
+    "extremely",
+    "basically",
+    "actually",
+    "simply",
+    "just",
+    "obviously",
+    "clearly",
+    "of course",
+    "various",
+    "a lot",
+    "in order to",
+];
+
+/// Forms of "to be" that start a passive construction
+const BE_FORMS: &[&str] = &[
+    "am", "is", "are", "was", "were", "be", "been", "being", "isn't", "aren't", "wasn't", "weren't",
+];
+
+/// Past participles that don't end in -ed
+const IRREGULAR_PARTICIPLES: &[&str] = &[
+    "begun",
+    "bitten",
+    "broken",
+    "brought",
+    "built",
+    "bought",
+    "caught",
+    "chosen",
+    "done",
+    "drawn",
+    "driven",
+    "eaten",
+    "fallen",
+    "felt",
+    "found",
+    "forgotten",
+    "frozen",
+    "given",
+    "gone",
+    "grown",
+    "heard",
+    "held",
+    "hidden",
+    "kept",
+    "known",
+    "laid",
+    "led",
+    "left",
+    "lost",
+    "made",
+    "meant",
+    "paid",
+    "put",
+    "read",
+    "run",
+    "said",
+    "seen",
+    "sent",
+    "set",
+    "shown",
+    "shut",
+    "sold",
+    "spoken",
+    "spent",
+    "stolen",
+    "taken",
+    "taught",
+    "thrown",
+    "told",
+    "thought",
+    "understood",
+    "won",
+    "worn",
+    "written",
+];
+
+/// Abbreviations whose period doesn't end a sentence
+const ABBREVIATIONS: &[&str] = &["e.g", "i.e", "vs", "cf", "mr", "mrs", "ms", "dr", "st"];
+
+/// A preferred term and the spellings to replace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlossaryTerm {
+    pub preferred: String,
+    /// Other spellings; the preferred term in different case is always
+    /// reported too
+    pub variants: Vec<String>,
+}
+
+/// A team's preferred terminology
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Glossary {
+    pub terms: Vec<GlossaryTerm>,
+}
+
+impl Glossary {
+    /// Read a glossary file
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    /// Parse a glossary: one term per line as `preferred: variant, variant`,
+    /// or just the preferred term to enforce its case. `#` starts a comment.
+    pub fn parse(source: &str) -> Self {
+        let terms = source
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (preferred, variants) = line.split_once(':').unwrap_or((line, ""));
+                GlossaryTerm {
+                    preferred: preferred.trim().to_string(),
+                    variants: variants
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|v| !v.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                }
+            })
+            .filter(|term| !term.preferred.is_empty())
+            .collect();
+        Self { terms }
+    }
+}
+
+/// Which style checks run and their limits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProseRules {
+    /// Report the same word twice in a row
+    pub repeated_words: bool,
+    /// Words and phrases to report, matched case-insensitively
+    pub weasel_words: Vec<String>,
+    /// Report likely passive constructions such as "was written"
+    pub passive_voice: bool,
+    /// Longest sentence in words, if limited
+    pub max_sentence_words: Option<usize>,
+    /// Longest paragraph in words, if limited
+    pub max_paragraph_words: Option<usize>,
+    pub glossary: Glossary,
+}
+
+impl Default for ProseRules {
+    fn default() -> Self {
+        Self {
+            repeated_words: true,
+            weasel_words: WEASEL_WORDS.iter().map(|w| w.to_string()).collect(),
+            passive_voice: true,
+            max_sentence_words: Some(35),
+            max_paragraph_words: Some(150),
+            glossary: Glossary::default(),
+        }
+    }
+}
+
+/// A word of prose
+#[derive(Debug)]
+struct Token {
+    /// Byte range without surrounding punctuation
+    range: Range<usize>,
+    lower: String,
+    /// Punctuation, code or other syntax separates it from the previous word
+    gap_before: bool,
+    ends_sentence: bool,
+}
+
+/// Check the prose of paragraphs, headings and table cells. Code, HTML,
+/// link destinations and labels, and URLs are not prose.
+pub(crate) fn lint(text: &str, rules: &ProseRules) -> Vec<Diagnostic> {
+    let tree = MarkdownTree::parse(text);
+    let skip = skipped_ranges(&tree, text, None);
+    let mut blocks = Vec::new();
+    tree.visit(&mut |node| {
+        // Inline content shows up in the block tree and again as the root
+        // of its inline tree; only the first has a parent
+        if node.kind() == "inline"
+            && let Some(parent) = node.parent()
+        {
+            blocks.push((node.byte_range(), parent.kind() == "paragraph"));
+        }
+    });
+
+    let mut out = Vec::new();
+    for (block, paragraph) in blocks {
+        let tokens = tokenize(text, block, &skip);
+        check_tokens(text, &tokens, rules, &mut out);
+        if paragraph {
+            check_lengths(&tokens, rules, &mut out);
+        }
+    }
+    out.sort_by_key(|d| d.range.start);
+    out
+}
+
+/// Split a block into words, skipping syntax ranges and URL-like tokens
+fn tokenize(text: &str, block: Range<usize>, skip: &[Range<usize>]) -> Vec<Token> {
+    let skipped = |pos: usize| skip.iter().any(|r| r.contains(&pos));
+    let mut raw: Vec<(Range<usize>, bool)> = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut gap = false;
+    for (i, c) in text[block.clone()].char_indices() {
+        let pos = block.start + i;
+        let is_skipped = skipped(pos);
+        if c.is_whitespace() || is_skipped {
+            if let Some(s) = start.take() {
+                raw.push((s..pos, gap));
+                gap = false;
+            }
+            gap |= is_skipped;
+        } else if start.is_none() {
+            start = Some(pos);
+        }
+    }
+    if let Some(s) = start {
+        raw.push((s..block.end, gap));
+    }
+
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut gap_next = false;
+    for (range, gap) in raw {
+        let source = &text[range.clone()];
+        let is_url = source.contains("://") || source.starts_with("www.") || source.contains('@');
+        let core = source.trim_matches(|c: char| !c.is_alphanumeric());
+        if is_url || core.is_empty() {
+            let ends = if is_url {
+                source.ends_with(['.', '!', '?'])
+            } else {
+                source.contains(['.', '!', '?'])
+            };
+            if ends && let Some(last) = tokens.last_mut() {
+                last.ends_sentence = true;
+            }
+            gap_next = true;
+            continue;
+        }
+        let offset = source.find(core).unwrap_or(0);
+        let core_range = range.start + offset..range.start + offset + core.len();
+        let leading = &source[..offset];
+        let trailing = &source[offset + core.len()..];
+        let lower = core.to_lowercase();
+        let abbreviation = trailing.starts_with('.') && ABBREVIATIONS.contains(&lower.as_str());
+        tokens.push(Token {
+            range: core_range,
+            lower,
+            gap_before: gap || gap_next || !leading.is_empty(),
+            ends_sentence: !abbreviation && trailing.contains(['.', '!', '?']),
+        });
+        gap_next = !trailing.is_empty();
+    }
+    tokens
+}
+
+fn check_tokens(text: &str, tokens: &[Token], rules: &ProseRules, out: &mut Vec<Diagnostic>) {
+    let weasel_words: Vec<Vec<String>> = rules
+        .weasel_words
+        .iter()
+        .map(|w| phrase_words(&w.to_lowercase()))
+        .collect();
+
+    for (i, token) in tokens.iter().enumerate() {
+        let sentence_start = i == 0 || tokens[i - 1].ends_sentence;
+
+        if rules.repeated_words
+            && i > 0
+            && !token.gap_before
+            && !sentence_start
+            && token.lower == tokens[i - 1].lower
+            && token.lower.chars().all(char::is_alphabetic)
+        {
+            out.push(Diagnostic::new(
+                tokens[i - 1].range.start..token.range.end,
+                Severity::Warning,
+                "repeated-word",
+                format!("Repeated word '{}'", &text[token.range.clone()]),
+            ));
+        }
+
+        for phrase in &weasel_words {
+            if let Some(range) = match_phrase(tokens, i, phrase) {
+                out.push(Diagnostic::new(
+                    range.clone(),
+                    Severity::Info,
+                    "weasel-word",
+                    format!("'{}' adds little; consider removing it", &text[range]),
+                ));
+            }
+        }
+
+        if rules.passive_voice
+            && BE_FORMS.contains(&token.lower.as_str())
+            && let Some(end) = passive_participle(tokens, i)
+        {
+            let range = token.range.start..tokens[end].range.end;
+            out.push(Diagnostic::new(
+                range.clone(),
+                Severity::Info,
+                "passive-voice",
+                format!("'{}' may be passive voice", &text[range]),
+            ));
+        }
+
+        for term in &rules.glossary.terms {
+            check_term(text, tokens, i, sentence_start, term, out);
+        }
+    }
+}
+
+/// Index of the participle in "is written" or "was quickly fixed" after
+/// the form of "to be" at `i`
+fn passive_participle(tokens: &[Token], i: usize) -> Option<usize> {
+    let mut next = i + 1;
+    while next < tokens.len() && next <= i + 2 {
+        let token = &tokens[next];
+        if token.gap_before || tokens[next - 1].ends_sentence {
+            return None;
+        }
+        let word = token.lower.as_str();
+        if IRREGULAR_PARTICIPLES.contains(&word) || (word.len() > 4 && word.ends_with("ed")) {
+            return Some(next);
+        }
+        if !word.ends_with("ly") {
+            return None;
+        }
+        next += 1;
+    }
+    None
+}
+
+fn check_term(
+    text: &str,
+    tokens: &[Token],
+    i: usize,
+    sentence_start: bool,
+    term: &GlossaryTerm,
+    out: &mut Vec<Diagnostic>,
+) {
+    for variant in &term.variants {
+        if let Some(range) = match_phrase(tokens, i, &phrase_words(&variant.to_lowercase())) {
+            out.push(Diagnostic::new(
+                range.clone(),
+                Severity::Warning,
+                "terminology",
+                format!("Use '{}' instead of '{}'", term.preferred, &text[range]),
+            ));
+            return;
+        }
+    }
+
+    // The preferred term in the wrong case. A capital first letter is fine
+    // at the start of a sentence.
+    let preferred = phrase_words(&term.preferred.to_lowercase());
+    let Some(range) = match_phrase(tokens, i, &preferred) else {
+        return;
+    };
+    let found = &text[range.clone()];
+    let capitalized = capitalize(&term.preferred);
+    if found != term.preferred && !(sentence_start && found == capitalized) {
+        out.push(Diagnostic::new(
+            range,
+            Severity::Warning,
+            "terminology",
+            format!("Write '{}' as '{}'", found, term.preferred),
+        ));
+    }
+}
+
+fn check_lengths(tokens: &[Token], rules: &ProseRules, out: &mut Vec<Diagnostic>) {
+    if let Some(max) = rules.max_sentence_words {
+        let mut start = 0;
+        for (i, token) in tokens.iter().enumerate() {
+            if token.ends_sentence || i + 1 == tokens.len() {
+                let words = i + 1 - start;
+                if words > max {
+                    out.push(Diagnostic::new(
+                        tokens[start].range.start..token.range.end,
+                        Severity::Info,
+                        "long-sentence",
+                        format!("Sentence has {words} words (more than {max})"),
+                    ));
+                }
+                start = i + 1;
+            }
+        }
+    }
+    if let Some(max) = rules.max_paragraph_words
+        && tokens.len() > max
+        && let (Some(first), Some(last)) = (tokens.first(), tokens.last())
+    {
+        out.push(Diagnostic::new(
+            first.range.start..last.range.end,
+            Severity::Info,
+            "long-paragraph",
+            format!("Paragraph has {} words (more than {max})", tokens.len()),
+        ));
+    }
+}
+
+/// Lower-case words of a phrase, split like prose
+fn phrase_words(phrase: &str) -> Vec<String> {
+    phrase.split_whitespace().map(str::to_string).collect()
+}
+
+/// Range of a phrase starting at token `i`, if the words match and no
+/// punctuation or sentence end separates them
+fn match_phrase(tokens: &[Token], i: usize, words: &[String]) -> Option<Range<usize>> {
+    let matched = tokens.get(i..i + words.len())?;
+    let joined = matched
+        .iter()
+        .zip(words)
+        .enumerate()
+        .all(|(n, (token, word))| {
+            token.lower == *word && (n == 0 || (!token.gap_before && !matched[n - 1].ends_sentence))
+        });
+    joined.then(|| matched[0].range.start..matched[matched.len() - 1].range.end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(text: &str, rules: &ProseRules) -> Vec<(&'static str, String)> {
+        lint(text, rules)
+            .into_iter()
+            .map(|d| (d.code, text[d.range].to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_repeated_and_weasel_words() {
+        let rules = ProseRules::default();
+        let text = "It is is fine. Fine. Run `the` the tool, the, the end.\n\n\
+            This is very simple in order to [help](very/help.md).\n";
+        assert_eq!(
+            codes(text, &rules),
+            vec![
+                ("repeated-word", "is is".to_string()),
+                ("weasel-word", "very".to_string()),
+                ("weasel-word", "in order to".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_passive_voice() {
+        let rules = ProseRules::default();
+        let text = "The file was quickly written. It is needed. We were happy.\n";
+        assert_eq!(
+            codes(text, &rules),
+            vec![
+                ("passive-voice", "was quickly written".to_string()),
+                ("passive-voice", "is needed".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_long_sentences_and_paragraphs() {
+        let rules = ProseRules {
+            max_sentence_words: Some(4),
+            max_paragraph_words: Some(8),
+            ..ProseRules::default()
+        };
+        let text = "One two three four five. Short one, e.g. this.\n\n# One two three four five\n";
+        assert_eq!(
+            codes(text, &rules),
+            vec![
+                ("long-sentence", "One two three four five".to_string()),
+                (
+                    "long-paragraph",
+                    "One two three four five. Short one, e.g. this".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_glossary() {
+        let glossary = Glossary::parse("# Terms\nemail: e-mail, E mail\nJavaScript\n\n");
+        assert_eq!(glossary.terms.len(), 2);
+        let rules = ProseRules {
+            glossary,
+            ..ProseRules::default()
+        };
+        let text = "Send an e-mail about javascript. Email works, `Javascript` too.\n";
+        assert_eq!(
+            codes(text, &rules),
+            vec![
+                ("terminology", "e-mail".to_string()),
+                ("terminology", "javascript".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,464 @@
+//! Reference-style links: conversion, definition cleanup and checks
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::edit::{self, TextEdit};
+use crate::line_ending::{line_breaks, trailing_breaks};
+use crate::selection::Selection;
+use crate::syntax::{MarkdownTree, child_of_kind};
+
+/// A link reference definition (`[label]: destination "title"`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkDefinition {
+    /// Label as written, without brackets
+    pub label: String,
+    /// Destination as written, including any angle brackets
+    pub destination: String,
+    /// Title as written, including its delimiters
+    pub title: Option<String>,
+    /// Byte range of the whole definition, including its line break
+    pub range: Range<usize>,
+    /// Byte range of the `[label]`
+    pub label_range: Range<usize>,
+    /// Whether the definition sits at the top level (not in a list or quote)
+    top_level: bool,
+}
+
+/// A use of a label: `[text][label]`, `[label][]` or `[label]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkReference {
+    /// Label the reference points at, without brackets
+    pub label: String,
+    /// Link text, without brackets
+    pub text: String,
+    /// Byte range of the whole reference
+    pub range: Range<usize>,
+    /// Whether this is an image (`![alt][label]`)
+    pub image: bool,
+    /// Whether this is a shortcut reference (`[label]`), which is plain text
+    /// when the label is undefined
+    pub shortcut: bool,
+}
+
+/// Normalize a label for matching: trim, collapse whitespace, ignore case
+pub fn normalize_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Collect link reference definitions, skipping footnote definitions
+pub fn definitions(tree: &MarkdownTree, text: &str) -> Vec<LinkDefinition> {
+    tree.nodes_of_kind("link_reference_definition")
+        .into_iter()
+        .filter_map(|node| {
+            let label = child_of_kind(node, "link_label")?;
+            let label_text = strip_brackets(&text[label.byte_range()]);
+            if label_text.starts_with('^') {
+                return None;
+            }
+            let destination = child_of_kind(node, "link_destination")?;
+            let title = child_of_kind(node, "link_title").map(|t| text[t.byte_range()].to_string());
+            let top_level = node
+                .parent()
+                .is_some_and(|p| matches!(p.kind(), "section" | "document"));
+            Some(LinkDefinition {
+                label: label_text.to_string(),
+                destination: text[destination.byte_range()].to_string(),
+                title,
+                range: node.byte_range(),
+                label_range: label.byte_range(),
+                top_level,
+            })
+        })
+        .collect()
+}
+
+/// Collect reference links and images, skipping footnote references
+pub fn references(tree: &MarkdownTree, text: &str) -> Vec<LinkReference> {
+    let mut out = Vec::new();
+    tree.visit(&mut |node| {
+        let image = node.kind() == "image";
+        let (text_node, shortcut) = match node.kind() {
+            "full_reference_link" | "collapsed_reference_link" => {
+                (child_of_kind(node, "link_text"), false)
+            }
+            "shortcut_link" => (child_of_kind(node, "link_text"), true),
+            "image" if child_of_kind(node, "link_destination").is_none() => {
+                let collapsed = text[node.byte_range()].ends_with("[]");
+                let shortcut = child_of_kind(node, "link_label").is_none() && !collapsed;
+                (child_of_kind(node, "image_description"), shortcut)
+            }
+            _ => return,
+        };
+        let Some(text_node) = text_node else { return };
+        let link_text = text[text_node.byte_range()].to_string();
+        let label = child_of_kind(node, "link_label")
+            .map(|l| strip_brackets(&text[l.byte_range()]).to_string())
+            .unwrap_or_else(|| link_text.clone());
+        if label.starts_with('^') {
+            return;
+        }
+        out.push(LinkReference {
+            label,
+            text: link_text,
+            range: node.byte_range(),
+            image,
+            shortcut,
+        });
+    });
+    out
+}
+
+/// Edits turning inline links and images touched by the selections into
+/// reference links, appending new definitions at the end of the document.
+/// Existing definitions with the same destination and title are reused.
+pub(crate) fn to_reference_edits(text: &str, selections: &[Selection]) -> Vec<TextEdit> {
+    let tree = MarkdownTree::parse(text);
+    let defs = definitions(&tree, text);
+    let mut used: HashSet<String> = defs.iter().map(|d| normalize_label(&d.label)).collect();
+    let mut next_number = 1usize;
+    let mut created: HashMap<(String, Option<String>), String> = HashMap::new();
+    let mut new_defs = String::new();
+    let mut edits = Vec::new();
+
+    let mut nodes = tree.nodes_of_kind("inline_link");
+    nodes.extend(tree.nodes_of_kind("image"));
+    nodes.sort_by_key(|n| n.start_byte());
+    for node in nodes {
+        if !touches_any(node.byte_range(), selections) {
+            continue;
+        }
+        let Some(dest) = child_of_kind(node, "link_destination") else {
+            continue;
+        };
+        let image = node.kind() == "image";
+        let text_kind = if image {
+            "image_description"
+        } else {
+            "link_text"
+        };
+        let link_text = child_of_kind(node, text_kind)
+            .map(|t| &text[t.byte_range()])
+            .unwrap_or("");
+        let destination = &text[dest.byte_range()];
+        let title = child_of_kind(node, "link_title").map(|t| &text[t.byte_range()]);
+
+        let key = (destination.to_string(), title.map(String::from));
+        let existing = defs
+            .iter()
+            .find(|d| d.destination == destination && d.title.as_deref() == title)
+            .map(|d| d.label.clone())
+            .or_else(|| created.get(&key).cloned());
+        let label = match existing {
+            Some(label) => label,
+            None => {
+                while used.contains(&next_number.to_string()) {
+                    next_number += 1;
+                }
+                let label = next_number.to_string();
+                used.insert(label.clone());
+                new_defs.push_str(&definition_line(&label, destination, title));
+                created.insert(key, label.clone());
+                label
+            }
+        };
+
+        let bang = if image { "!" } else { "" };
+        edits.push(TextEdit::new(
+            node.byte_range(),
+            format!("{bang}[{link_text}][{label}]"),
+        ));
+    }
+
+    if !new_defs.is_empty() {
+        edits.push(TextEdit::insert(
+            text.len(),
+            format!("{}{new_defs}", definitions_separator(text, &defs)),
+        ));
+    }
+    edits
+}
+
+/// Edits turning reference links and images touched by the selections into
+/// inline links. References with no definition are left alone.
+pub(crate) fn to_inline_edits(text: &str, selections: &[Selection]) -> Vec<TextEdit> {
+    let tree = MarkdownTree::parse(text);
+    let all_defs = definitions(&tree, text);
+    let defs = definition_map(&all_defs);
+
+    references(&tree, text)
+        .into_iter()
+        .filter(|r| touches_any(r.range.clone(), selections))
+        .filter_map(|r| {
+            let def = defs.get(&normalize_label(&r.label))?;
+            let bang = if r.image { "!" } else { "" };
+            let title = def
+                .title
+                .as_ref()
+                .map(|t| format!(" {t}"))
+                .unwrap_or_default();
+            Some(TextEdit::new(
+                r.range,
+                format!("{bang}[{}]({}{title})", r.text, def.destination),
+            ))
+        })
+        .collect()
+}
+
+/// Edits moving every top-level definition to one block at the end of the
+/// document, keeping their order. Empty if they are already there.
+pub(crate) fn collect_definitions_edits(text: &str) -> Vec<TextEdit> {
+    let tree = MarkdownTree::parse(text);
+    let defs: Vec<_> = definitions(&tree, text)
+        .into_iter()
+        .filter(|d| d.top_level)
+        .collect();
+    if defs.is_empty() {
+        return Vec::new();
+    }
+
+    // End of the content that stays in place: skip trailing whitespace and
+    // any definitions already at the end
+    let mut body_end = text.len();
+    loop {
+        body_end = text[..body_end].trim_end().len();
+        match defs
+            .iter()
+            .find(|d| d.range.start < body_end && body_end <= d.range.end)
+        {
+            Some(def) => body_end = def.range.start,
+            None => break,
+        }
+    }
+
+    let block: String = defs
+        .iter()
+        .map(|d| format!("{}\n", text[d.range.clone()].trim()))
+        .collect();
+    let separator = if body_end == 0 { "" } else { "\n\n" };
+
+    let mut edits = removal_edits(
+        text,
+        defs.iter()
+            .map(|d| d.range.clone())
+            .filter(|r| r.end <= body_end),
+    );
+    edits.push(TextEdit::new(
+        body_end..text.len(),
+        format!("{separator}{block}"),
+    ));
+
+    if edit::apply(text, &edits) == text {
+        return Vec::new();
+    }
+    edits
+}
+
+/// Edits removing definitions whose label no reference uses
+pub(crate) fn remove_unused_edits(text: &str) -> Vec<TextEdit> {
+    let tree = MarkdownTree::parse(text);
+    let used: HashSet<String> = references(&tree, text)
+        .iter()
+        .map(|r| normalize_label(&r.label))
+        .collect();
+    let unused = definitions(&tree, text)
+        .into_iter()
+        .filter(|d| !used.contains(&normalize_label(&d.label)))
+        .map(|d| d.range);
+    removal_edits(text, unused)
+}
+
+/// Report duplicate definitions and references to undefined labels
+pub(crate) fn diagnostics(text: &str) -> Vec<Diagnostic> {
+    let tree = MarkdownTree::parse(text);
+    let defs = definitions(&tree, text);
+    let mut diagnostics = Vec::new();
+
+    let mut seen = HashSet::new();
+    for def in &defs {
+        if !seen.insert(normalize_label(&def.label)) {
+            diagnostics.push(Diagnostic::new(
+                def.label_range.clone(),
+                Severity::Warning,
+                "duplicate-definition",
+                format!("Duplicate link reference definition [{}]", def.label),
+            ));
+        }
+    }
+
+    for r in references(&tree, text) {
+        if !r.shortcut && !seen.contains(&normalize_label(&r.label)) {
+            diagnostics.push(Diagnostic::new(
+                r.range,
+                Severity::Warning,
+                "undefined-reference",
+                format!("No definition for link reference [{}]", r.label),
+            ));
+        }
+    }
+
+    diagnostics.sort_by_key(|d| d.range.start);
+    diagnostics
+}
+
+/// First definition for each normalized label (later duplicates are ignored,
+/// as in CommonMark)
+fn definition_map(defs: &[LinkDefinition]) -> HashMap<String, &LinkDefinition> {
+    let mut map = HashMap::new();
+    for def in defs {
+        map.entry(normalize_label(&def.label)).or_insert(def);
+    }
+    map
+}
+
+/// Deletions for definition ranges. A run of definitions that forms a block
+/// of its own also takes one surrounding blank line with it.
+pub(crate) fn removal_edits(
+    text: &str,
+    ranges: impl Iterator<Item = Range<usize>>,
+) -> Vec<TextEdit> {
+    let mut groups: Vec<Range<usize>> = Vec::new();
+    for range in ranges {
+        match groups.last_mut() {
+            Some(last) if last.end == range.start => last.end = range.end,
+            _ => groups.push(range),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|mut range| {
+            let before = &text[..range.start];
+            let after = &text[range.end..];
+            let break_after = line_breaks(after)
+                .next()
+                .filter(|&(start, _)| start == 0)
+                .map(|(_, end)| end);
+            let blank_before = trailing_breaks(before) >= 2;
+            if (before.is_empty() || blank_before)
+                && let Some(len) = break_after
+            {
+                range.end += len;
+            } else if (after.is_empty() || break_after.is_some()) && blank_before {
+                range.start -= if before.ends_with("\r\n") { 2 } else { 1 };
+            }
+            TextEdit::delete(range)
+        })
+        .collect()
+}
+
+/// Text needed before appending definitions at the end of the document
+fn definitions_separator(text: &str, defs: &[LinkDefinition]) -> &'static str {
+    let ends_with_definition = defs
+        .last()
+        .is_some_and(|d| text[d.range.end..].trim().is_empty());
+    let breaks = trailing_breaks(text);
+    match (text.is_empty(), breaks > 0, ends_with_definition) {
+        (true, _, _) => "",
+        (_, true, true) => "",
+        (_, false, true) => "\n",
+        (_, true, false) if breaks >= 2 => "",
+        (_, true, false) => "\n",
+        (_, false, false) => "\n\n",
+    }
+}
+
+fn definition_line(label: &str, destination: &str, title: Option<&str>) -> String {
+    match title {
+        Some(title) => format!("[{label}]: {destination} {title}\n"),
+        None => format!("[{label}]: {destination}\n"),
+    }
+}
+
+fn touches_any(range: Range<usize>, selections: &[Selection]) -> bool {
+    selections
+        .iter()
+        .any(|s| range.start <= s.end() && s.start() <= range.end)
+}
+
+pub(crate) fn strip_brackets(label: &str) -> &str {
+    label
+        .strip_prefix('[')
+        .and_then(|l| l.strip_suffix(']'))
+        .unwrap_or(label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit::edited;
+    use crate::line_ending::{LineEnding, normalize};
+
+    #[test]
+    fn test_inline_to_reference() {
+        let text = "See [docs](https://x.io \"Docs\") and ![logo](logo.png).\n";
+        let sel = [Selection::new(0, text.len())];
+        let out = edited(text, to_reference_edits(text, &sel));
+        assert_eq!(
+            out,
+            "See [docs][1] and ![logo][2].\n\n[1]: https://x.io \"Docs\"\n[2]: logo.png\n"
+        );
+    }
+
+    #[test]
+    fn test_reference_reuses_existing_definition() {
+        let text = "[a](x.md)\n\n[1]: y.md\n[xdoc]: x.md\n";
+        let out = edited(text, to_reference_edits(text, &[Selection::cursor(1)]));
+        assert_eq!(out, "[a][xdoc]\n\n[1]: y.md\n[xdoc]: x.md\n");
+    }
+
+    #[test]
+    fn test_reference_to_inline() {
+        let text = "Go [there][Dest] or [dest][].\n\n[dest]: <a b.md> 'T'\n";
+        let sel = [Selection::new(0, 28)];
+        let out = edited(text, to_inline_edits(text, &sel));
+        assert_eq!(
+            out,
+            "Go [there](<a b.md> 'T') or [dest](<a b.md> 'T').\n\n[dest]: <a b.md> 'T'\n"
+        );
+    }
+
+    #[test]
+    fn test_collect_definitions() {
+        let text = "Intro [a][1].\n\n[1]: one.md\n\nMiddle [b][2].\n\n[2]: two.md\n\nEnd.\n";
+        let out = edited(text, collect_definitions_edits(text));
+        assert_eq!(
+            out,
+            "Intro [a][1].\n\nMiddle [b][2].\n\nEnd.\n\n[1]: one.md\n[2]: two.md\n"
+        );
+        assert!(collect_definitions_edits(&out).is_empty());
+
+        for ending in [LineEnding::Crlf, LineEnding::Cr] {
+            let text = normalize(text, ending);
+            let out = edited(&text, collect_definitions_edits(&text));
+            assert_eq!(
+                normalize(&out, ending),
+                normalize(
+                    "Intro [a][1].\n\nMiddle [b][2].\n\nEnd.\n\n[1]: one.md\n[2]: two.md\n",
+                    ending
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_remove_unused() {
+        let text = "Use [a][used].\n\n[used]: a.md\n[unused]: b.md\n";
+        let out = edited(text, remove_unused_edits(text));
+        assert_eq!(out, "Use [a][used].\n\n[used]: a.md\n");
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let text = "[a][missing] [b][ref] [plain]\n\n[ref]: a.md\n[REF]: b.md\n";
+        let diags = diagnostics(text);
+        let codes: Vec<_> = diags.iter().map(|d| d.code).collect();
+        assert_eq!(codes, vec!["undefined-reference", "duplicate-definition"]);
+        assert_eq!(&text[diags[1].range.clone()], "[REF]");
+    }
+}
@@ -0,0 +1,331 @@
+//! Hard wrapping of paragraphs to a column width
+
+use std::ops::Range;
+
+use crate::edit::TextEdit;
+use crate::selection::Selection;
+use crate::syntax::{MarkdownTree, child_of_kind};
+use crate::text::{columns, line_of, line_starts, touched_lines};
+use crate::wiki::parse_wiki_links;
+
+/// Hard wrap settings for a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WrapSettings {
+    /// Column to wrap prose at
+    pub width: usize,
+    /// Rewrap the paragraph under the cursor when typing runs past the width
+    pub wrap_as_you_type: bool,
+}
+
+impl Default for WrapSettings {
+    fn default() -> Self {
+        Self {
+            width: 80,
+            wrap_as_you_type: false,
+        }
+    }
+}
+
+/// A paragraph's words and the whitespace between them
+struct Paragraph {
+    /// Prefix of the first line, such as `> - `
+    first_prefix: String,
+    /// Prefix for the following lines, with list markers turned into spaces
+    prefix: String,
+    words: Vec<Range<usize>>,
+    /// Gaps between consecutive words
+    gaps: Vec<Gap>,
+    /// Line numbers spanned
+    lines: Range<usize>,
+}
+
+struct Gap {
+    range: Range<usize>,
+    /// A hard line break (`\` or two trailing spaces) that must stay
+    hard_break: bool,
+    /// Inside code or a link destination, where a line break would
+    /// change the meaning
+    keep_on_line: bool,
+}
+
+/// Rewrap the paragraphs touched by selections to `width` columns, with
+/// tab stops every `tab_width` columns
+pub(crate) fn reflow_edits(
+    text: &str,
+    selections: &[Selection],
+    width: usize,
+    tab_width: usize,
+) -> Vec<TextEdit> {
+    let starts = line_starts(text);
+    let lines = touched_lines(&starts, selections);
+    paragraphs(text, &starts)
+        .iter()
+        .filter(|p| lines.iter().any(|l| p.lines.contains(l)))
+        .flat_map(|p| fill(text, p, width, tab_width))
+        .collect()
+}
+
+/// Rewrap the paragraphs under cursors whose line runs past `width`
+pub(crate) fn wrap_edits(
+    text: &str,
+    selections: &[Selection],
+    width: usize,
+    tab_width: usize,
+) -> Vec<TextEdit> {
+    let starts = line_starts(text);
+    let long_lines: Vec<usize> = selections
+        .iter()
+        .map(|s| line_of(&starts, s.head))
+        .filter(|&line| {
+            let end = starts.get(line + 1).copied().unwrap_or(text.len());
+            columns(text[starts[line]..end].trim_end(), tab_width) > width
+        })
+        .collect();
+    if long_lines.is_empty() {
+        return Vec::new();
+    }
+    paragraphs(text, &starts)
+        .iter()
+        .filter(|p| long_lines.iter().any(|l| p.lines.contains(l)))
+        .flat_map(|p| fill(text, p, width, tab_width))
+        .collect()
+}
+
+/// Paragraphs of the document split into words
+fn paragraphs(text: &str, starts: &[usize]) -> Vec<Paragraph> {
+    let tree = MarkdownTree::parse(text);
+    let mut no_break = Vec::new();
+    let mut hard_breaks = Vec::new();
+    let mut nodes = Vec::new();
+    tree.visit(&mut |node| match node.kind() {
+        "paragraph" => nodes.push(node.byte_range()),
+        "hard_line_break" => hard_breaks.push(node.byte_range()),
+        "code_span" | "html_tag" | "uri_autolink" | "email_autolink" | "latex_block" => {
+            no_break.push(node.byte_range())
+        }
+        "inline_link" | "image" => {
+            // The destination and title; the link text may wrap
+            let text_end = child_of_kind(node, "link_text")
+                .or_else(|| child_of_kind(node, "image_description"))
+                .map_or(node.start_byte(), |t| t.end_byte());
+            no_break.push(text_end..node.end_byte());
+        }
+        _ => {}
+    });
+    no_break.extend(parse_wiki_links(text).into_iter().map(|link| link.range));
+
+    nodes
+        .into_iter()
+        .map(|range| {
+            let first = line_of(starts, range.start);
+            let last = line_of(starts, range.end.saturating_sub(1).max(range.start));
+            let first_prefix = text[starts[first]..range.start].to_string();
+            let prefix = continuation_prefix(&first_prefix);
+            let mut words = Vec::new();
+            for line in first..=last {
+                let start = if line == first {
+                    range.start
+                } else {
+                    starts[line]
+                };
+                let end = starts.get(line + 1).copied().unwrap_or(text.len());
+                let end = end.min(range.end);
+                let content = content_range(&text[start..end], line != first, &first_prefix);
+                let content = start + content.start..start + content.end;
+                words.extend(split_words(text, content, &no_break));
+            }
+            let gaps = words
+                .windows(2)
+                .map(|pair| {
+                    let range = pair[0].end..pair[1].start;
+                    Gap {
+                        hard_break: hard_breaks
+                            .iter()
+                            .any(|b| b.start < range.end && b.end > range.start),
+                        keep_on_line: no_break
+                            .iter()
+                            .any(|r| r.start < range.start && r.end > range.end),
+                        range,
+                    }
+                })
+                .collect();
+            Paragraph {
+                first_prefix,
+                prefix,
+                words,
+                gaps,
+                lines: first..last + 1,
+            }
+        })
+        .collect()
+}
+
+/// Prefix of continuation lines: blockquote markers are kept and list
+/// markers and task boxes become spaces, giving a hanging indent
+fn continuation_prefix(first_prefix: &str) -> String {
+    first_prefix
+        .chars()
+        .map(|c| match c {
+            '>' | '\t' => c,
+            _ => ' ',
+        })
+        .collect()
+}
+
+/// Range of a line's content, after the blockquote markers and
+/// indentation of continuation lines and before the line break
+fn content_range(line: &str, continuation: bool, first_prefix: &str) -> Range<usize> {
+    let end = line.trim_end_matches(['\n', '\r']).len();
+    let mut rest = &line[..end];
+    if continuation {
+        for _ in 0..first_prefix.matches('>').count() {
+            match rest.trim_start().strip_prefix('>') {
+                Some(after) => rest = after,
+                None => break,
+            }
+        }
+        rest = rest.trim_start();
+    }
+    end - rest.len()..end
+}
+
+/// Whitespace-separated words of a line, keeping code and link
+/// destinations whole
+fn split_words(text: &str, range: Range<usize>, no_break: &[Range<usize>]) -> Vec<Range<usize>> {
+    let inside = |pos: usize| no_break.iter().any(|r| r.start < pos && pos < r.end);
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text[range.clone()].char_indices() {
+        let pos = range.start + i;
+        if c.is_whitespace() && !inside(pos) {
+            if let Some(s) = start.take() {
+                words.push(s..pos);
+            }
+        } else if start.is_none() {
+            start = Some(pos);
+        }
+    }
+    if let Some(s) = start {
+        words.push(s..range.end);
+    }
+    words
+}
+
+/// Greedily fill lines up to `width` and return edits for the gaps that
+/// change
+fn fill(text: &str, paragraph: &Paragraph, width: usize, tab_width: usize) -> Vec<TextEdit> {
+    let Some(first) = paragraph.words.first() else {
+        return Vec::new();
+    };
+    let newline = format!("\n{}", paragraph.prefix);
+    let prefix_width = columns(&paragraph.prefix, tab_width);
+    let mut column = columns(&paragraph.first_prefix, tab_width) + word_width(text, first);
+    let mut edits = Vec::new();
+
+    for (gap, word) in paragraph.gaps.iter().zip(&paragraph.words[1..]) {
+        let width_needed = word_width(text, word);
+        let replacement = if gap.hard_break {
+            let marker = if text[gap.range.clone()].starts_with("  ") {
+                "  "
+            } else {
+                ""
+            };
+            format!("{marker}{newline}")
+        } else if gap.keep_on_line
+            || column + 1 + width_needed <= width
+            || starts_block(&text[word.clone()])
+        {
+            " ".to_string()
+        } else {
+            newline.clone()
+        };
+
+        column = if replacement.contains('\n') {
+            prefix_width + width_needed
+        } else {
+            column + 1 + width_needed
+        };
+        if text[gap.range.clone()] != replacement {
+            edits.push(TextEdit::new(gap.range.clone(), replacement));
+        }
+    }
+    edits
+}
+
+fn word_width(text: &str, word: &Range<usize>) -> usize {
+    text[word.clone()].chars().count()
+}
+
+/// Whether a word at the start of a line would begin a list, heading,
+/// blockquote or other block instead of continuing the paragraph
+fn starts_block(word: &str) -> bool {
+    let digits = word.trim_start_matches(|c: char| c.is_ascii_digit());
+    matches!(word, "-" | "*" | "+" | ">" | "=" | "|")
+        || word.starts_with('#')
+        || word.starts_with("```")
+        || word.starts_with("~~~")
+        || (word.len() >= 3 && word.chars().all(|c| c == '-' || c == '=' || c == '_'))
+        || (digits.len() < word.len() && matches!(digits, "." | ")"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit::edited;
+
+    fn reflow(text: &str, width: usize) -> String {
+        let selections = [Selection::new(0, text.len())];
+        edited(text, reflow_edits(text, &selections, width, 4))
+    }
+
+    #[test]
+    fn test_reflow_paragraph() {
+        let text = "one two three four\nfive six\nseven\n\n# Heading that is long\n";
+        assert_eq!(
+            reflow(text, 14),
+            "one two three\nfour five six\nseven\n\n# Heading that is long\n"
+        );
+        assert_eq!(
+            reflow("Short lines\nare joined\n", 80),
+            "Short lines are joined\n"
+        );
+    }
+
+    #[test]
+    fn test_reflow_keeps_prefixes_and_hard_breaks() {
+        let text =
+            "> - [ ] task one two\n>   three\n\n1. first second\\\n   third  \n   fourth fifth\n";
+        assert_eq!(
+            reflow(text, 18),
+            "> - [ ] task one\n>       two three\n\n1. first second\\\n   third  \n   fourth fifth\n"
+        );
+    }
+
+    #[test]
+    fn test_reflow_keeps_code_and_links_whole() {
+        let text = "see `a b c` and [the link](a.md \"A title\") - 1. x\n";
+        assert_eq!(
+            reflow(text, 8),
+            "see\n`a b c`\nand [the\nlink](a.md \"A title\") - 1.\nx\n"
+        );
+    }
+
+    #[test]
+    fn test_wrap_only_long_cursor_lines() {
+        let text = "one two three\nfour\n";
+        assert!(wrap_edits(text, &[Selection::cursor(3)], 20, 4).is_empty());
+        assert_eq!(
+            wrap_edits(text, &[Selection::cursor(3)], 8, 4),
+            vec![TextEdit::new(7..8, "\n")]
+        );
+
+        // Tabs are measured with the document's tab width; rewrapping
+        // turns the tab into a space
+        let text = "a\tb c\n";
+        assert!(wrap_edits(text, &[Selection::cursor(0)], 10, 4).is_empty());
+        assert_eq!(
+            wrap_edits(text, &[Selection::cursor(0)], 10, 8),
+            vec![TextEdit::new(1..2, " ")]
+        );
+    }
+}
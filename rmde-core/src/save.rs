@@ -0,0 +1,259 @@
+//! Atomic file saving: contents go to a temporary file next to the target,
+//! which is synced and renamed over it. Files in directories that don't
+//! allow new files are overwritten in place.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::error::{Error, Result};
+
+/// Symlink hops followed before giving up, as most kernels do
+const MAX_SYMLINKS: usize = 40;
+
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Replace a file's contents, streamed through `write`, so that readers
+/// see either the old or the new file, never a partial write. Symlinks are
+/// written through to their target, and an existing file keeps its owner,
+/// permissions and extended attributes. Read-only targets fail with
+/// [`Error::ReadOnly`].
+pub(crate) fn write_atomic(
+    path: &Path,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> Result<()> {
+    let target = resolve_symlinks(path)?;
+    let existing = match fs::metadata(&target) {
+        Ok(metadata) => Some(metadata),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    if existing
+        .as_ref()
+        .is_some_and(|m| m.permissions().readonly())
+    {
+        return Err(Error::ReadOnly(target));
+    }
+
+    let dir = match target.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+    let (temp, file) = match create_temp(dir, &name) {
+        Ok(created) => created,
+        // The file itself may still be writable
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied && existing.is_some() => {
+            return write_in_place(&target, write);
+        }
+        Err(e) => return Err(read_only(e, &target)),
+    };
+    let result: io::Result<()> = (|| {
+        let mut out = BufWriter::new(file);
+        write(&mut out)?;
+        let file = out.into_inner().map_err(io::IntoInnerError::into_error)?;
+        if let Some(metadata) = &existing {
+            copy_owner(&file, metadata);
+            file.set_permissions(metadata.permissions())?;
+            copy_xattrs(&target, &temp);
+        }
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&temp, &target)?;
+        sync_dir(dir);
+        Ok(())
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Overwrite a file directly. A failed write can leave it partly written,
+/// so this is only for directories where no temporary file can be made.
+fn write_in_place(
+    target: &Path,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> Result<()> {
+    let file = OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(target)
+        .map_err(|e| read_only(e, target))?;
+    let mut out = BufWriter::new(file);
+    write(&mut out)?;
+    let file = out.into_inner().map_err(io::IntoInnerError::into_error)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Follow symlinks to the file they finally point at, which need not exist
+fn resolve_symlinks(path: &Path) -> io::Result<PathBuf> {
+    let mut path = path.to_path_buf();
+    for _ in 0..MAX_SYMLINKS {
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                let link = fs::read_link(&path)?;
+                path = match path.parent() {
+                    Some(parent) => parent.join(link),
+                    None => link,
+                };
+            }
+            _ => return Ok(path),
+        }
+    }
+    Err(io::Error::other(format!(
+        "Too many levels of symbolic links: {}",
+        path.display()
+    )))
+}
+
+/// Create a new hidden file in the target's directory
+fn create_temp(dir: &Path, name: &str) -> io::Result<(PathBuf, File)> {
+    loop {
+        let n = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let temp = dir.join(format!(".{name}.{}-{n}.tmp", std::process::id()));
+        match OpenOptions::new().write(true).create_new(true).open(&temp) {
+            Ok(file) => return Ok((temp, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Give the new file the old one's owner and group. Only root may change
+/// the owner and others only to one of their groups, so the group is tried
+/// alone before giving up.
+#[cfg(unix)]
+fn copy_owner(file: &File, metadata: &fs::Metadata) {
+    use std::os::unix::fs::{MetadataExt, fchown};
+
+    if fchown(file, Some(metadata.uid()), Some(metadata.gid())).is_err() {
+        let _ = fchown(file, None, Some(metadata.gid()));
+    }
+}
+
+#[cfg(not(unix))]
+fn copy_owner(_file: &File, _metadata: &fs::Metadata) {}
+
+/// Copy extended attributes such as Finder tags. Attributes the file system
+/// or the user can't set are skipped rather than failing the save.
+#[cfg(unix)]
+fn copy_xattrs(from: &Path, to: &Path) {
+    let Ok(names) = xattr::list(from) else {
+        return;
+    };
+    for name in names {
+        if let Ok(Some(value)) = xattr::get(from, &name) {
+            let _ = xattr::set(to, &name, &value);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn copy_xattrs(_from: &Path, _to: &Path) {}
+
+/// Persist the rename itself; not every platform can sync a directory
+fn sync_dir(dir: &Path) {
+    #[cfg(unix)]
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+}
+
+fn read_only(e: io::Error, path: &Path) -> Error {
+    match e.kind() {
+        io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem => {
+            Error::ReadOnly(path.to_path_buf())
+        }
+        _ => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rmde-save-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_replaces_contents_and_leaves_no_temp_files() {
+        let dir = temp_dir("replace");
+        let path = dir.join("note.md");
+        write_atomic(&path, |out| out.write_all(b"first")).unwrap();
+        write_atomic(&path, |out| out.write_all(b"second")).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_keeps_permissions_and_symlinks() {
+        use std::os::unix::fs::{PermissionsExt, symlink};
+
+        let dir = temp_dir("unix");
+        let path = dir.join("note.md");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        let link = dir.join("link.md");
+        symlink("note.md", &link).unwrap();
+        let tagged = xattr::set(&path, "user.rmde.test", b"tag").is_ok();
+
+        write_atomic(&link, |out| out.write_all(b"new")).unwrap();
+        assert!(
+            fs::symlink_metadata(&link)
+                .unwrap()
+                .file_type()
+                .is_symlink()
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+        if tagged {
+            let tag = xattr::get(&path, "user.rmde.test").unwrap();
+            assert_eq!(tag.as_deref(), Some(&b"tag"[..]));
+        }
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o444)).unwrap();
+        assert!(matches!(
+            write_atomic(&link, |out| out.write_all(b"blocked")),
+            Err(Error::ReadOnly(target)) if target == path
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_writes_in_place_in_locked_directory() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let dir = temp_dir("locked");
+        let path = dir.join("note.md");
+        fs::write(&path, "old").unwrap();
+        let owner = fs::metadata(&path).unwrap();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o555)).unwrap();
+
+        let result = write_atomic(&path, |out| out.write_all(b"new"));
+        let created = write_atomic(&dir.join("new.md"), |out| out.write_all(b"x"));
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        result.unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        let metadata = fs::metadata(&path).unwrap();
+        assert_eq!((metadata.uid(), metadata.gid()), (owner.uid(), owner.gid()));
+        if fs::read_dir(&dir).unwrap().count() == 1 {
+            assert!(matches!(created, Err(Error::ReadOnly(_))));
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,612 @@
+//! Static site generation from a workspace folder
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use crate::encoding;
+use crate::error::Result;
+use crate::front_matter::{FrontMatterValue, parse_front_matter};
+use crate::html::{self, HtmlOptions, escape, standalone_page};
+use crate::links::{self, LinkTarget};
+use crate::outline::outline;
+use crate::slug::{heading_slugs, slugify};
+use crate::syntax::MarkdownTree;
+use crate::wiki::{NoteIndex, parse_wiki_links};
+use crate::workspace::{is_markdown_file, markdown_files};
+
+/// File in the output folder recording what was built
+const MANIFEST: &str = ".rmde-site";
+
+/// Layout added to the page stylesheet for the navigation sidebar
+const SITE_STYLESHEET: &str = "\
+body { display: flex; align-items: flex-start; }
+.site-nav { position: sticky; top: 0; flex: 0 0 16em; max-height: 100vh; overflow: auto;
+  padding: 2em 1em; box-sizing: border-box; font: 14px/1.5 -apple-system, sans-serif;
+  border-right: 1px solid #d1d9e0; }
+.site-nav ul { margin: 0; padding-left: 1em; list-style: none; }
+.site-nav .site-title { display: block; margin-bottom: 1em; font-weight: 600; }
+.site-nav .folder > span { color: #59636e; }
+.site-nav .current > a { font-weight: 600; }
+.site-nav .toc { font-size: 90%; }
+.markdown-body { flex: 1; }
+.tags a { margin-right: .5em; }
+";
+
+/// Options for building a site
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiteOptions {
+    /// Site name shown in the navigation and on the index page
+    pub title: String,
+    /// CSS replacing the built-in stylesheet
+    pub stylesheet: Option<String>,
+}
+
+impl Default for SiteOptions {
+    fn default() -> Self {
+        Self {
+            title: "Documentation".to_string(),
+            stylesheet: None,
+        }
+    }
+}
+
+/// What a site build did, with paths relative to the output folder
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SiteReport {
+    /// Pages written, including the index and tag pages
+    pub rendered: Vec<PathBuf>,
+    /// Pages left alone because neither they nor the navigation changed
+    pub unchanged: usize,
+    /// Images and other linked files copied
+    pub copied: Vec<PathBuf>,
+    /// Pages deleted because their note is gone
+    pub removed: Vec<PathBuf>,
+    /// Notes left out because they couldn't be read, relative to the
+    /// source folder
+    pub unreadable: Vec<PathBuf>,
+}
+
+/// A note to publish
+struct Page {
+    path: PathBuf,
+    /// Path relative to the source folder, with `/` separators
+    source: String,
+    /// Output path relative to the output folder
+    url: String,
+    text: String,
+    title: String,
+    tags: Vec<String>,
+    /// Level 2 and 3 headings with their anchors, for the navigation
+    toc: Vec<(u8, String, String)>,
+}
+
+impl Page {
+    fn read(root: &Path, path: &Path) -> Result<Self> {
+        let text = encoding::read_file(path)?.0.to_string();
+        let path = path.to_path_buf();
+        let source = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let url = match source.rfind('.') {
+            Some(dot) => format!("{}.html", &source[..dot]),
+            None => format!("{source}.html"),
+        };
+
+        let tree = MarkdownTree::parse(&text);
+        let headings = outline(&tree, &text);
+        let slugs = heading_slugs(&headings);
+        let front_matter = parse_front_matter(&text).ok().flatten();
+        let field = |key: &str| front_matter.as_ref().and_then(|fm| fm.get(key));
+        let title = field("title")
+            .and_then(|entry| entry.value.as_str())
+            .map(String::from)
+            .or_else(|| {
+                headings
+                    .iter()
+                    .find(|h| h.level == 1)
+                    .map(|h| h.text.clone())
+            })
+            .or_else(|| path.file_stem().map(|s| s.to_string_lossy().into_owned()))
+            .unwrap_or_default();
+        let tags: Vec<&str> = match field("tags").map(|entry| &entry.value) {
+            Some(FrontMatterValue::List(items)) => {
+                items.iter().filter_map(|item| item.as_str()).collect()
+            }
+            Some(FrontMatterValue::String(tags)) => tags.split(',').collect(),
+            _ => Vec::new(),
+        };
+        let tags = tags
+            .into_iter()
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(String::from)
+            .collect();
+        let toc = headings
+            .iter()
+            .zip(slugs)
+            .filter(|(h, _)| matches!(h.level, 2 | 3))
+            .map(|(h, slug)| (h.level, h.text.clone(), slug))
+            .collect();
+
+        Ok(Self {
+            path,
+            source,
+            url,
+            text,
+            title,
+            tags,
+            toc,
+        })
+    }
+
+    fn is_index(&self) -> bool {
+        self.source.eq_ignore_ascii_case("index.md")
+    }
+
+    /// Markdown for the site: links to notes point at their pages and
+    /// wiki-links become regular links. Also returns the local files the
+    /// note links to.
+    fn site_markdown(&self, root: &Path, notes: &NoteIndex) -> (String, Vec<PathBuf>) {
+        let tree = MarkdownTree::parse(&self.text);
+        let mut edits = Vec::new();
+        let mut files = Vec::new();
+        for link in links::links(&tree, &self.text) {
+            let Some(LinkTarget::File { path, .. }) =
+                links::resolve(&link.destination, Some(&self.path), Some(root))
+            else {
+                continue;
+            };
+            if is_markdown_file(&path) {
+                if let Some(destination) = html_link(&link.destination) {
+                    let angled = self.text[link.destination_range.clone()].starts_with('<');
+                    let destination = if angled {
+                        format!("<{destination}>")
+                    } else {
+                        destination
+                    };
+                    edits.push((link.destination_range, destination));
+                }
+            } else if path.starts_with(root) && path.is_file() {
+                files.push(path);
+            }
+        }
+
+        for link in parse_wiki_links(&self.text) {
+            let Some(target) = notes.resolve(&link.target, Some(&self.path)) else {
+                continue;
+            };
+            let target = target.strip_prefix(root).unwrap_or(&target);
+            let Some(target) = html_link(&target.to_string_lossy()) else {
+                continue;
+            };
+            let mut url = relative_url(&self.url, &target.replace('\\', "/"));
+            if let Some(heading) = &link.heading {
+                let _ = write!(url, "#{}", slugify(heading));
+            }
+            let label = link.alias.as_deref().unwrap_or(&link.target);
+            let label = label.replace('[', "\\[").replace(']', "\\]");
+            edits.push((link.range, format!("[{label}](<{url}>)")));
+        }
+
+        let mut markdown = self.text.clone();
+        edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+        for (range, replacement) in edits {
+            markdown.replace_range(range, &replacement);
+        }
+        (markdown, files)
+    }
+}
+
+/// Render every note under `source` to HTML in `output`. Notes that did
+/// not change since the last build are skipped, unless a change to titles,
+/// tags or the set of notes alters the navigation of every page.
+pub fn build_site(source: &Path, output: &Path, options: &SiteOptions) -> Result<SiteReport> {
+    let notes = NoteIndex::build(source)?;
+    let mut pages = Vec::new();
+    let mut unreadable = Vec::new();
+    for path in markdown_files(source)? {
+        if path.starts_with(output) {
+            continue;
+        }
+        match Page::read(source, &path) {
+            Ok(page) => pages.push(page),
+            Err(_) => unreadable.push(path.strip_prefix(source).unwrap_or(&path).to_path_buf()),
+        }
+    }
+    let site = Site {
+        pages: &pages,
+        options,
+        stylesheet: match &options.stylesheet {
+            Some(stylesheet) => stylesheet.clone(),
+            None => format!("{}{SITE_STYLESHEET}", html::STYLESHEET),
+        },
+    };
+
+    let previous = read_manifest(output);
+    let mut manifest = BTreeMap::new();
+    let navigation = site.navigation_hash();
+    let full = previous.get("") != Some(&navigation);
+    manifest.insert(String::new(), navigation);
+    std::fs::create_dir_all(output)?;
+    let mut report = SiteReport {
+        unreadable,
+        ..SiteReport::default()
+    };
+
+    for page in &pages {
+        let (markdown, files) = page.site_markdown(source, &notes);
+        for file in files {
+            let relative = file.strip_prefix(source).unwrap_or(&file).to_path_buf();
+            if copy_if_newer(&file, &output.join(&relative))? {
+                report.copied.push(relative);
+            }
+        }
+
+        let hash = fnv_hash(page.text.as_bytes());
+        manifest.insert(page.source.clone(), hash);
+        let target = output.join(&page.url);
+        if !full && previous.get(&page.source) == Some(&hash) && target.exists() {
+            report.unchanged += 1;
+            continue;
+        }
+        let html = if page.is_index() {
+            site.index_page(Some(&markdown))
+        } else {
+            site.page(page, &markdown)
+        };
+        write_file(&target, &html)?;
+        report.rendered.push(PathBuf::from(&page.url));
+    }
+
+    if !pages.iter().any(Page::is_index) && (full || !output.join("index.html").exists()) {
+        write_file(&output.join("index.html"), &site.index_page(None))?;
+        report.rendered.push(PathBuf::from("index.html"));
+    }
+    if full || !output.join("tags.html").exists() {
+        write_file(&output.join("tags.html"), &site.tags_page())?;
+        report.rendered.push(PathBuf::from("tags.html"));
+    }
+
+    for source in previous
+        .keys()
+        .filter(|s| !s.is_empty() && !manifest.contains_key(*s))
+    {
+        let url = match source.rfind('.') {
+            Some(dot) => format!("{}.html", &source[..dot]),
+            None => continue,
+        };
+        match std::fs::remove_file(output.join(&url)) {
+            Ok(()) => report.removed.push(PathBuf::from(url)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let manifest: String = manifest
+        .iter()
+        .map(|(source, hash)| format!("{hash:016x} {source}\n"))
+        .collect();
+    std::fs::write(output.join(MANIFEST), manifest)?;
+    Ok(report)
+}
+
+struct Site<'a> {
+    pages: &'a [Page],
+    options: &'a SiteOptions,
+    stylesheet: String,
+}
+
+impl Site<'_> {
+    /// Hash of everything shown on every page
+    fn navigation_hash(&self) -> u64 {
+        let mut signature = format!("{}\n{}\n", self.options.title, self.stylesheet);
+        for page in self.pages {
+            let _ = writeln!(
+                signature,
+                "{}\t{}\t{}",
+                page.source,
+                page.title,
+                page.tags.join(",")
+            );
+        }
+        fnv_hash(signature.as_bytes())
+    }
+
+    fn page(&self, page: &Page, markdown: &str) -> String {
+        let root = "../".repeat(page.url.matches('/').count());
+        let mut content = html::render_html(markdown, &HtmlOptions::default());
+        if !page.tags.is_empty() {
+            let links: Vec<String> = page
+                .tags
+                .iter()
+                .map(|tag| {
+                    format!(
+                        "<a href=\"{root}tags.html#{}\">{}</a>",
+                        escape(&slugify(tag)),
+                        escape(tag)
+                    )
+                })
+                .collect();
+            let _ = writeln!(content, "<p class=\"tags\">Tags: {}</p>", links.join(" "));
+        }
+        let body = format!(
+            "{}<article class=\"markdown-body\">\n{content}</article>\n",
+            self.navigation(Some(page))
+        );
+        let title = format!("{} - {}", page.title, self.options.title);
+        standalone_page(&title, &self.stylesheet, &body)
+    }
+
+    /// The home page: the root `index.md` if there is one, then every page
+    fn index_page(&self, markdown: Option<&str>) -> String {
+        let mut content = match markdown {
+            Some(markdown) => html::render_html(markdown, &HtmlOptions::default()),
+            None => format!("<h1>{}</h1>\n", escape(&self.options.title)),
+        };
+        content.push_str("<h2>Pages</h2>\n");
+        self.folder("", "index.html", None, &mut content);
+        let body = format!(
+            "{}<article class=\"markdown-body\">\n{content}</article>\n",
+            self.navigation(None)
+        );
+        standalone_page(&self.options.title, &self.stylesheet, &body)
+    }
+
+    /// Pages grouped by front matter tag
+    fn tags_page(&self) -> String {
+        let mut tags: BTreeMap<&str, Vec<&Page>> = BTreeMap::new();
+        for page in self.pages {
+            for tag in &page.tags {
+                tags.entry(tag).or_default().push(page);
+            }
+        }
+        let mut content = String::from("<h1>Tags</h1>\n");
+        for (tag, pages) in tags {
+            let _ = writeln!(
+                content,
+                "<h2 id=\"{}\">{}</h2>\n<ul>",
+                escape(&slugify(tag)),
+                escape(tag)
+            );
+            for page in pages {
+                let _ = writeln!(
+                    content,
+                    "<li><a href=\"{}\">{}</a></li>",
+                    escape(&page.url),
+                    escape(&page.title)
+                );
+            }
+            content.push_str("</ul>\n");
+        }
+        let body = format!(
+            "{}<article class=\"markdown-body\">\n{content}</article>\n",
+            self.navigation(None)
+        );
+        let title = format!("Tags - {}", self.options.title);
+        standalone_page(&title, &self.stylesheet, &body)
+    }
+
+    /// Sidebar with the folder tree; the current page lists its headings
+    fn navigation(&self, current: Option<&Page>) -> String {
+        let from = current.map_or("index.html", |p| p.url.as_str());
+        let mut out = format!(
+            "<nav class=\"site-nav\">\n<a class=\"site-title\" href=\"{}\">{}</a>\n",
+            escape(&relative_url(from, "index.html")),
+            escape(&self.options.title)
+        );
+        self.folder("", from, current, &mut out);
+        let _ = writeln!(
+            out,
+            "<a href=\"{}\">Tags</a>\n</nav>",
+            escape(&relative_url(from, "tags.html"))
+        );
+        out
+    }
+
+    /// Nested list of the pages in a folder (`dir` ends with `/` unless it
+    /// is the root), files before subfolders
+    fn folder(&self, dir: &str, from: &str, current: Option<&Page>, out: &mut String) {
+        let inside: Vec<&Page> = self
+            .pages
+            .iter()
+            .filter(|p| p.source.starts_with(dir) && !p.is_index())
+            .collect();
+        if inside.is_empty() {
+            return;
+        }
+        out.push_str("<ul>\n");
+        for page in inside
+            .iter()
+            .filter(|p| !p.source[dir.len()..].contains('/'))
+        {
+            let is_current = current.is_some_and(|c| c.source == page.source);
+            let class = if is_current { " class=\"current\"" } else { "" };
+            let _ = write!(
+                out,
+                "<li{class}><a href=\"{}\">{}</a>",
+                escape(&relative_url(from, &page.url)),
+                escape(&page.title)
+            );
+            if is_current && !page.toc.is_empty() {
+                out.push_str("\n<ul class=\"toc\">\n");
+                for (level, text, slug) in &page.toc {
+                    let _ = writeln!(
+                        out,
+                        "<li class=\"toc-h{level}\"><a href=\"#{}\">{}</a></li>",
+                        escape(slug),
+                        escape(text)
+                    );
+                }
+                out.push_str("</ul>\n");
+            }
+            out.push_str("</li>\n");
+        }
+        let mut subfolders: Vec<&str> = inside
+            .iter()
+            .filter_map(|p| p.source[dir.len()..].split_once('/').map(|(name, _)| name))
+            .collect();
+        subfolders.dedup();
+        for name in subfolders {
+            let _ = writeln!(out, "<li class=\"folder\"><span>{}</span>", escape(name));
+            self.folder(&format!("{dir}{name}/"), from, current, out);
+            out.push_str("</li>\n");
+        }
+        out.push_str("</ul>\n");
+    }
+}
+
+/// A link destination to a note rewritten to its page: `a.md#x` becomes
+/// `a.html#x`. `None` if the destination is not a Markdown file.
+fn html_link(destination: &str) -> Option<String> {
+    let split = destination.find(['#', '?']).unwrap_or(destination.len());
+    let (path, rest) = destination.split_at(split);
+    if !is_markdown_file(Path::new(path)) {
+        return None;
+    }
+    let dot = path.rfind('.')?;
+    Some(format!("{}.html{rest}", &path[..dot]))
+}
+
+/// Relative URL from one output page to another
+fn relative_url(from: &str, to: &str) -> String {
+    let from_dirs: Vec<&str> = from.split('/').collect();
+    let from_dirs = &from_dirs[..from_dirs.len() - 1];
+    let to_parts: Vec<&str> = to.split('/').collect();
+    let common = from_dirs
+        .iter()
+        .zip(&to_parts[..to_parts.len() - 1])
+        .take_while(|(a, b)| a == b)
+        .count();
+    format!(
+        "{}{}",
+        "../".repeat(from_dirs.len() - common),
+        to_parts[common..].join("/")
+    )
+}
+
+/// Content hashes of the last build by source path; the navigation hash
+/// is stored under the empty path
+fn read_manifest(output: &Path) -> HashMap<String, u64> {
+    let Ok(manifest) = std::fs::read_to_string(output.join(MANIFEST)) else {
+        return HashMap::new();
+    };
+    manifest
+        .lines()
+        .filter_map(|line| {
+            let (hash, source) = line.split_once(' ')?;
+            Some((source.to_string(), u64::from_str_radix(hash, 16).ok()?))
+        })
+        .collect()
+}
+
+/// FNV-1a, stable across builds so the manifest stays valid
+pub(crate) fn fnv_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn write_file(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+/// Copy a file unless the copy is already up to date
+fn copy_if_newer(from: &Path, to: &Path) -> Result<bool> {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    if let (Some(source), Some(copy)) = (modified(from), modified(to))
+        && copy >= source
+    {
+        return Ok(false);
+    }
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::copy(from, to)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_links_between_pages() {
+        assert_eq!(
+            html_link("guide/a.md#setup").as_deref(),
+            Some("guide/a.html#setup")
+        );
+        assert_eq!(html_link("../b.markdown").as_deref(), Some("../b.html"));
+        assert_eq!(html_link("image.png"), None);
+        assert_eq!(relative_url("a/b/c.html", "a/d.html"), "../d.html");
+        assert_eq!(relative_url("index.html", "a/d.html"), "a/d.html");
+        assert_eq!(relative_url("a/c.html", "a/d.html"), "d.html");
+    }
+
+    #[test]
+    fn test_build_site_incrementally() {
+        let root = std::env::temp_dir().join(format!("rmde-site-{}", std::process::id()));
+        let (source, output) = (root.join("docs"), root.join("site"));
+        std::fs::create_dir_all(source.join("guide/img")).unwrap();
+        std::fs::write(
+            source.join("a.md"),
+            "---\ntags: [intro, ops]\n---\n# Start\n\nSee [setup](guide/setup.md#install) \
+             and [[setup#Install|installing]].\n",
+        )
+        .unwrap();
+        std::fs::write(
+            source.join("guide/setup.md"),
+            "# Setup\n\n## Install\n\n![diagram](img/d.png)\n",
+        )
+        .unwrap();
+        std::fs::write(source.join("guide/img/d.png"), "png").unwrap();
+
+        let options = SiteOptions::default();
+        let report = build_site(&source, &output, &options).unwrap();
+        assert_eq!(
+            report.rendered,
+            ["a.html", "guide/setup.html", "index.html", "tags.html"].map(PathBuf::from)
+        );
+        assert_eq!(report.copied, vec![PathBuf::from("guide/img/d.png")]);
+        assert!(output.join("guide/img/d.png").exists());
+
+        let a = std::fs::read_to_string(output.join("a.html")).unwrap();
+        assert!(a.contains("<title>Start - Documentation</title>"));
+        assert!(a.contains("<a href=\"guide/setup.html#install\">setup</a>"));
+        assert!(a.contains("<a href=\"guide/setup.html#install\">installing</a>"));
+        assert!(a.contains("<li class=\"folder\"><span>guide</span>"));
+        assert!(a.contains("<a href=\"tags.html#ops\">ops</a>"));
+        let setup = std::fs::read_to_string(output.join("guide/setup.html")).unwrap();
+        assert!(setup.contains("<a class=\"site-title\" href=\"../index.html\">"));
+        assert!(setup.contains("<li class=\"toc-h2\"><a href=\"#install\">Install</a></li>"));
+        let tags = std::fs::read_to_string(output.join("tags.html")).unwrap();
+        assert!(
+            tags.contains("<h2 id=\"intro\">intro</h2>\n<ul>\n<li><a href=\"a.html\">Start</a>")
+        );
+
+        // Only the changed note is rendered again
+        let report = build_site(&source, &output, &options).unwrap();
+        assert!(report.rendered.is_empty());
+        assert_eq!(report.unchanged, 2);
+        std::fs::write(source.join("guide/setup.md"), "# Setup\n\nMore.\n").unwrap();
+        let report = build_site(&source, &output, &options).unwrap();
+        assert_eq!(report.rendered, vec![PathBuf::from("guide/setup.html")]);
+
+        // Removing a note changes the navigation of every page
+        std::fs::remove_file(source.join("guide/setup.md")).unwrap();
+        let report = build_site(&source, &output, &options).unwrap();
+        assert_eq!(report.removed, vec![PathBuf::from("guide/setup.html")]);
+        assert!(report.rendered.contains(&PathBuf::from("a.html")));
+        assert!(!output.join("guide/setup.html").exists());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,414 @@
+//! Slide deck export: Markdown split into slides and rendered as a
+//! self-contained HTML page
+
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::front_matter::parse_front_matter;
+use crate::html::{self, HtmlOptions, escape, standalone_page};
+use crate::outline::outline;
+use crate::references::definitions;
+use crate::syntax::MarkdownTree;
+
+/// Slide layout added to the page stylesheet
+const SLIDES_STYLESHEET: &str = "\
+html, body { height: 100%; overflow: hidden; background: #111; }
+.slide { display: none; position: absolute; inset: 0; max-width: none; margin: 0;
+  padding: 6vh 8vw; box-sizing: border-box; overflow: auto; background: #fff;
+  font-size: 3.2vmin; }
+.slide.current { display: flex; flex-direction: column; justify-content: center; }
+.slide.lead { text-align: center; }
+.slide .notes { display: none; }
+body.show-notes .slide .notes { display: block; position: absolute; left: 0; right: 0;
+  bottom: 0; max-height: 35%; overflow: auto; padding: 1em 8vw; font-size: 70%;
+  background: #fffbe6; border-top: 1px solid #d1d9e0; }
+.progress { position: fixed; left: 0; bottom: 0; height: 4px; background: #0969da; }
+";
+
+/// Keyboard navigation: arrows, space and page keys move between slides,
+/// Home/End jump, `n` toggles speaker notes and `f` goes full screen
+const SCRIPT: &str = "\
+(function () {
+  var slides = document.querySelectorAll('.slide');
+  var progress = document.querySelector('.progress');
+  var current = 0;
+  function show(n) {
+    current = Math.max(0, Math.min(slides.length - 1, n));
+    slides.forEach(function (slide, i) { slide.classList.toggle('current', i === current); });
+    progress.style.width = ((current + 1) / slides.length * 100) + '%';
+    history.replaceState(null, '', '#' + (current + 1));
+  }
+  document.addEventListener('keydown', function (e) {
+    switch (e.key) {
+      case 'ArrowRight': case 'ArrowDown': case 'PageDown': case ' ': show(current + 1); break;
+      case 'ArrowLeft': case 'ArrowUp': case 'PageUp': show(current - 1); break;
+      case 'Home': show(0); break;
+      case 'End': show(slides.length - 1); break;
+      case 'n': document.body.classList.toggle('show-notes'); break;
+      case 'f': document.documentElement.requestFullscreen(); break;
+      default: return;
+    }
+    e.preventDefault();
+  });
+  show((parseInt(location.hash.slice(1), 10) || 1) - 1);
+})();
+";
+
+/// Where one slide ends and the next begins
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlideSplit {
+    /// On `---` thematic breaks only
+    Rule,
+    /// Also before every heading of this level or higher
+    Heading(u8),
+}
+
+/// Options for slide export
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlideOptions {
+    /// Split rule; a `slide_split` front matter key (`rule`, `h1`...`h6`)
+    /// in the document takes precedence
+    pub split: SlideSplit,
+    /// Deck title; defaults to the front matter `title`, then the first heading
+    pub title: Option<String>,
+    /// CSS replacing the built-in stylesheet
+    pub stylesheet: Option<String>,
+}
+
+impl Default for SlideOptions {
+    fn default() -> Self {
+        Self {
+            split: SlideSplit::Rule,
+            title: None,
+            stylesheet: None,
+        }
+    }
+}
+
+/// A slide cut from the document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slide {
+    /// Byte range of the slide in the document, without its separator
+    pub range: Range<usize>,
+    /// Slide content without speaker notes
+    pub markdown: String,
+    /// Speaker notes from a `Note:` paragraph onwards and HTML comments
+    pub notes: String,
+    /// Options from the slide's own front matter block, such as `class`
+    /// and `background`
+    pub options: Vec<(String, String)>,
+}
+
+/// Split a document into slides. A slide may start with its own front
+/// matter block right after a `---` separator:
+///
+/// ```text
+/// ---
+/// class: lead
+/// ---
+/// # Title slide
+/// ```
+pub fn slides(text: &str, split: SlideSplit) -> Vec<Slide> {
+    let tree = MarkdownTree::parse(text);
+    let front_matter = parse_front_matter(text).ok().flatten();
+    let split = front_matter
+        .as_ref()
+        .and_then(|fm| fm.get("slide_split"))
+        .and_then(|entry| entry.value.as_str())
+        .and_then(parse_split)
+        .unwrap_or(split);
+    let body_start = front_matter.map_or(0, |fm| fm.range.end);
+
+    let mut breaks = Vec::new();
+    tree.visit(&mut |node| {
+        if node.kind() == "thematic_break"
+            && node
+                .parent()
+                .is_some_and(|p| matches!(p.kind(), "section" | "document"))
+            && node.start_byte() >= body_start
+        {
+            breaks.push(node.byte_range());
+        }
+    });
+    let headings: Vec<usize> = match split {
+        SlideSplit::Rule => Vec::new(),
+        SlideSplit::Heading(level) => outline(&tree, text)
+            .into_iter()
+            .filter(|h| h.level <= level)
+            .map(|h| h.range.start)
+            .collect(),
+    };
+    let code = tree.code_ranges();
+
+    let mut segments = Vec::new();
+    let mut start = body_start;
+    for rule in breaks {
+        segments.push(start..rule.start);
+        start = rule.end;
+    }
+    segments.push(start..text.len());
+
+    let mut slides = Vec::new();
+    for segment in segments {
+        let (mut options, content_start) = slide_front_matter(text, segment.clone());
+        let mut start = content_start;
+        let inside = headings
+            .iter()
+            .filter(|&&h| h > content_start && h < segment.end);
+        for &heading in inside {
+            if !text[start..heading].trim().is_empty() {
+                slides.push(slide(
+                    text,
+                    &tree,
+                    &code,
+                    start..heading,
+                    std::mem::take(&mut options),
+                ));
+            }
+            start = heading;
+        }
+        if !text[start..segment.end].trim().is_empty() || !options.is_empty() {
+            slides.push(slide(text, &tree, &code, start..segment.end, options));
+        }
+    }
+    slides
+}
+
+/// Render a document as a self-contained HTML slide deck
+pub fn render_slides(text: &str, options: &SlideOptions) -> String {
+    let tree = MarkdownTree::parse(text);
+    let title = options
+        .title
+        .clone()
+        .or_else(|| {
+            parse_front_matter(text).ok().flatten().and_then(|fm| {
+                fm.get("title")
+                    .and_then(|e| e.value.as_str().map(String::from))
+            })
+        })
+        .or_else(|| outline(&tree, text).first().map(|h| h.text.clone()))
+        .unwrap_or_else(|| "Slides".to_string());
+
+    // Reference definitions anywhere in the document apply to every slide
+    let references: String = definitions(&tree, text)
+        .iter()
+        .map(|d| format!("{}\n", text[d.range.clone()].trim_end()))
+        .collect();
+    let render = |markdown: &str| {
+        html::render_html(
+            &format!("{markdown}\n\n{references}"),
+            &HtmlOptions::default(),
+        )
+    };
+
+    let mut body = String::from("<div class=\"deck\">\n");
+    for (i, slide) in slides(text, options.split).iter().enumerate() {
+        let mut class = String::from("slide markdown-body");
+        let mut attributes = String::new();
+        for (key, value) in &slide.options {
+            match key.as_str() {
+                "class" => {
+                    class.push(' ');
+                    class.push_str(value);
+                }
+                "background" => {
+                    let _ = write!(attributes, " style=\"background: {}\"", escape(value));
+                }
+                _ => {
+                    let _ = write!(attributes, " data-{}=\"{}\"", escape(key), escape(value));
+                }
+            }
+        }
+        let _ = writeln!(
+            body,
+            "<section class=\"{}\" id=\"slide-{}\"{attributes}>",
+            escape(&class),
+            i + 1
+        );
+        body.push_str(&render(&slide.markdown));
+        if !slide.notes.trim().is_empty() {
+            let _ = write!(
+                body,
+                "<aside class=\"notes\">\n{}</aside>\n",
+                render(&slide.notes)
+            );
+        }
+        body.push_str("</section>\n");
+    }
+    let _ = write!(
+        body,
+        "</div>\n<div class=\"progress\"></div>\n<script>\n{SCRIPT}</script>\n"
+    );
+
+    let stylesheet = match &options.stylesheet {
+        Some(stylesheet) => stylesheet.clone(),
+        None => format!("{}{SLIDES_STYLESHEET}", html::STYLESHEET),
+    };
+    standalone_page(&title, &stylesheet, &body)
+}
+
+fn parse_split(value: &str) -> Option<SlideSplit> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "rule" | "---" => Some(SlideSplit::Rule),
+        heading => heading
+            .strip_prefix('h')
+            .and_then(|level| level.parse().ok())
+            .filter(|level| (1..=6).contains(level))
+            .map(SlideSplit::Heading),
+    }
+}
+
+/// Options of a `key: value` block closed by `---` at the start of a
+/// slide, and where the slide content begins after it
+fn slide_front_matter(text: &str, segment: Range<usize>) -> (Vec<(String, String)>, usize) {
+    let none = (Vec::new(), segment.start);
+    let mut block = String::new();
+    let mut pos = segment.start;
+    for line in text[segment.clone()].split_inclusive('\n') {
+        pos += line.len();
+        if line.trim().is_empty() && block.is_empty() {
+            continue;
+        }
+        if line.trim_end() == "---" {
+            if block.is_empty() {
+                return none;
+            }
+            let Ok(Some(front_matter)) = parse_front_matter(&format!("---\n{block}---\n")) else {
+                return none;
+            };
+            let options = front_matter
+                .entries
+                .into_iter()
+                .map(|entry| (entry.key, entry.value.to_string()))
+                .collect();
+            return (options, pos);
+        }
+        let is_option = line.split_once(':').is_some_and(|(key, _)| {
+            !key.is_empty()
+                && key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        });
+        if !is_option {
+            return none;
+        }
+        block.push_str(line);
+    }
+    none
+}
+
+/// Cut speaker notes out of a slide: everything from a `Note:` line on,
+/// and HTML comments
+fn slide(
+    text: &str,
+    tree: &MarkdownTree,
+    code: &[Range<usize>],
+    range: Range<usize>,
+    options: Vec<(String, String)>,
+) -> Slide {
+    let in_code = |pos: usize| code.iter().any(|r| r.contains(&pos));
+    let mut notes_start = range.end;
+    let mut pos = range.start;
+    for line in text[range.clone()].split_inclusive('\n') {
+        if (line.starts_with("Note:") || line.starts_with("Notes:")) && !in_code(pos) {
+            notes_start = pos;
+            break;
+        }
+        pos += line.len();
+    }
+
+    let mut comments = Vec::new();
+    tree.visit(&mut |node| {
+        let source = text[node.byte_range()].trim();
+        if node.kind() == "html_block"
+            && range.contains(&node.start_byte())
+            && node.start_byte() < notes_start
+            && source.starts_with("<!--")
+            && source.ends_with("-->")
+        {
+            comments.push(node.byte_range());
+        }
+    });
+
+    let mut markdown = String::new();
+    let mut notes = Vec::new();
+    let mut pos = range.start;
+    for comment in &comments {
+        markdown.push_str(&text[pos..comment.start]);
+        let source = text[comment.clone()].trim();
+        notes.push(source[4..source.len() - 3].trim().to_string());
+        pos = comment.end;
+    }
+    markdown.push_str(&text[pos..notes_start]);
+    if notes_start < range.end {
+        let note = &text[notes_start..range.end];
+        let note = note.split_once(':').map_or(note, |(_, rest)| rest);
+        notes.push(note.trim().to_string());
+    }
+
+    Slide {
+        range,
+        markdown: markdown.trim().to_string(),
+        notes: notes.join("\n\n"),
+        options,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TALK: &str = "---\ntitle: Talk\n---\n# Hello\n\nSee [docs].\n\nNote: say *hi*\n\n\
+                        ---\nclass: lead\nbackground: black\n---\n## Second\n\n<!-- breathe -->\n\n\
+                        ```\n---\n```\n\n[docs]: https://example.com\n";
+
+    #[test]
+    fn test_split_on_rules() {
+        let slides = slides(TALK, SlideSplit::Rule);
+        assert_eq!(slides.len(), 2);
+        assert_eq!(slides[0].markdown, "# Hello\n\nSee [docs].");
+        assert_eq!(slides[0].notes, "say *hi*");
+        assert!(slides[0].options.is_empty());
+        assert_eq!(
+            slides[1].options,
+            vec![
+                ("class".to_string(), "lead".to_string()),
+                ("background".to_string(), "black".to_string())
+            ]
+        );
+        assert!(slides[1].markdown.starts_with("## Second\n"));
+        assert!(slides[1].markdown.contains("```\n---\n```"));
+        assert!(!slides[1].markdown.contains("breathe"));
+        assert_eq!(slides[1].notes, "breathe");
+    }
+
+    #[test]
+    fn test_split_on_headings() {
+        let text = "# A\n\ntext\n\n## B\n\nmore\n\n### C\n\n---\n\nD\n";
+        let markdown: Vec<String> = slides(text, SlideSplit::Heading(2))
+            .into_iter()
+            .map(|s| s.markdown)
+            .collect();
+        assert_eq!(markdown, ["# A\n\ntext", "## B\n\nmore\n\n### C", "D"]);
+
+        let text = "---\nslide_split: h1\n---\n# A\n\n## B\n\n# C\n";
+        assert_eq!(slides(text, SlideSplit::Rule).len(), 2);
+    }
+
+    #[test]
+    fn test_render_deck() {
+        let deck = render_slides(TALK, &SlideOptions::default());
+        assert!(deck.contains("<title>Talk</title>"));
+        assert!(
+            deck.contains(
+                "<section class=\"slide markdown-body\" id=\"slide-1\">\n<h1 id=\"hello\">"
+            )
+        );
+        assert!(deck.contains("<a href=\"https://example.com\">docs</a>"));
+        assert!(deck.contains("<aside class=\"notes\">\n<p>say <em>hi</em></p>\n</aside>"));
+        assert!(deck.contains(
+            "<section class=\"slide markdown-body lead\" id=\"slide-2\" style=\"background: black\">"
+        ));
+        assert!(deck.contains("document.addEventListener('keydown'"));
+    }
+}
@@ -0,0 +1,74 @@
+//! GitHub-compatible heading anchor slugs
+
+use std::collections::HashMap;
+
+use crate::outline::Heading;
+
+/// Convert heading text to an anchor slug the way GitHub does: lowercase,
+/// drop punctuation and symbols, turn each space into `-`
+pub fn slugify(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Generates unique slugs, suffixing repeats with `-1`, `-2`, ...
+#[derive(Debug, Default)]
+pub struct Slugger {
+    seen: HashMap<String, usize>,
+}
+
+impl Slugger {
+    /// Create a slugger with no slugs seen yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Slug for the next heading with this text
+    pub fn slug(&mut self, text: &str) -> String {
+        let base = slugify(text);
+        let mut slug = base.clone();
+        while self.seen.contains_key(&slug) {
+            let count = self.seen.entry(base.clone()).or_insert(0);
+            *count += 1;
+            slug = format!("{base}-{count}");
+        }
+        self.seen.insert(slug.clone(), 0);
+        slug
+    }
+}
+
+/// Unique anchor slug for each heading, in order
+pub fn heading_slugs(headings: &[Heading]) -> Vec<String> {
+    let mut slugger = Slugger::new();
+    headings.iter().map(|h| slugger.slug(&h.text)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello World"), "hello-world");
+        assert_eq!(slugify("What's new in v2.0?"), "whats-new-in-v20");
+        assert_eq!(slugify("foo_bar - baz"), "foo_bar---baz");
+        assert_eq!(slugify("Über Straße"), "über-straße");
+    }
+
+    #[test]
+    fn test_duplicate_suffixes() {
+        let mut slugger = Slugger::new();
+        assert_eq!(slugger.slug("Intro"), "intro");
+        assert_eq!(slugger.slug("Intro"), "intro-1");
+        assert_eq!(slugger.slug("Intro 1"), "intro-1-1");
+        assert_eq!(slugger.slug("Intro"), "intro-2");
+    }
+}
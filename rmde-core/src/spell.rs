@@ -0,0 +1,442 @@
+//! Spell checking of prose with Hunspell dictionaries, a personal word list
+//! and per-document ignore lists
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::error::Result;
+use crate::front_matter::{FrontMatter, FrontMatterEntry, FrontMatterValue, parse_front_matter};
+use crate::hunspell::Dictionary;
+use crate::syntax::MarkdownTree;
+use crate::wiki::parse_wiki_links;
+
+/// Suggestions offered per misspelled word
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Front matter key listing words a document accepts
+pub(crate) const IGNORE_KEY: &str = "spell_ignore";
+
+/// A fresh generation number, so cached results of a different checker
+/// state are never reused
+fn next_generation() -> u64 {
+    static GENERATION: AtomicU64 = AtomicU64::new(1);
+    GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// A word the dictionary doesn't know. Suggestions are looked up
+/// separately, as they cost far more than the check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Misspelling {
+    pub range: Range<usize>,
+    pub word: String,
+}
+
+impl From<&Misspelling> for Diagnostic {
+    fn from(m: &Misspelling) -> Self {
+        let message = format!("Unknown word '{}'", m.word);
+        Diagnostic::new(m.range.clone(), Severity::Info, "spelling", message)
+    }
+}
+
+/// A dictionary plus the user's personal word list
+#[derive(Debug)]
+pub struct SpellChecker {
+    dictionary: Option<Dictionary>,
+    personal: BTreeSet<String>,
+    /// File the personal word list is kept in, one word per line
+    personal_path: Option<PathBuf>,
+    /// Suggestions already computed, by word
+    suggestions: HashMap<String, Vec<String>>,
+    generation: u64,
+}
+
+impl Default for SpellChecker {
+    fn default() -> Self {
+        Self {
+            dictionary: None,
+            personal: BTreeSet::new(),
+            personal_path: None,
+            suggestions: HashMap::new(),
+            generation: next_generation(),
+        }
+    }
+}
+
+impl SpellChecker {
+    /// Create a checker with a dictionary
+    pub fn new(dictionary: Dictionary) -> Self {
+        Self {
+            dictionary: Some(dictionary),
+            ..Self::default()
+        }
+    }
+
+    /// Whether a dictionary is loaded. Without one nothing is reported.
+    pub fn has_dictionary(&self) -> bool {
+        self.dictionary.is_some()
+    }
+
+    /// Replace the dictionary, keeping the personal word list
+    pub fn set_dictionary(&mut self, dictionary: Dictionary) {
+        self.dictionary = Some(dictionary);
+        self.suggestions.clear();
+        self.generation = next_generation();
+    }
+
+    /// Use a personal word list file, loading the words it has. A missing
+    /// file is created when the first word is added.
+    pub fn load_personal_words(&mut self, path: impl Into<PathBuf>) -> Result<()> {
+        let path = path.into();
+        self.personal = if path.exists() {
+            std::fs::read_to_string(&path)?
+                .lines()
+                .map(str::trim)
+                .filter(|w| !w.is_empty())
+                .map(str::to_string)
+                .collect()
+        } else {
+            BTreeSet::new()
+        };
+        self.personal_path = Some(path);
+        self.generation = next_generation();
+        Ok(())
+    }
+
+    /// Words in the personal word list, sorted
+    pub fn personal_words(&self) -> impl Iterator<Item = &str> {
+        self.personal.iter().map(String::as_str)
+    }
+
+    /// Add a word to the personal word list, saving it if the list has a file
+    pub fn add_personal_word(&mut self, word: &str) -> Result<()> {
+        if self.personal.insert(word.trim().to_string()) {
+            self.generation = next_generation();
+            self.save_personal_words()?;
+        }
+        Ok(())
+    }
+
+    /// Remove a word from the personal word list. Returns false if it
+    /// wasn't there.
+    pub fn remove_personal_word(&mut self, word: &str) -> Result<bool> {
+        if !self.personal.remove(word.trim()) {
+            return Ok(false);
+        }
+        self.generation = next_generation();
+        self.save_personal_words()?;
+        Ok(true)
+    }
+
+    fn save_personal_words(&self) -> Result<()> {
+        let Some(path) = &self.personal_path else {
+            return Ok(());
+        };
+        let mut out = String::new();
+        for word in &self.personal {
+            out.push_str(word);
+            out.push('\n');
+        }
+        std::fs::write(path, out)?;
+        Ok(())
+    }
+
+    /// Whether a word is spelled correctly. Personal words match in the
+    /// case they were added, or capitalized/upper-case if added lower-case.
+    pub fn is_correct(&self, word: &str) -> bool {
+        if self.personal.contains(word) || self.personal.contains(&word.to_lowercase()) {
+            return true;
+        }
+        self.dictionary.as_ref().is_none_or(|d| d.check(word))
+    }
+
+    /// Ranked replacements for a misspelled word, remembered per word
+    pub fn suggest(&mut self, word: &str) -> Vec<String> {
+        let Some(dictionary) = &self.dictionary else {
+            return Vec::new();
+        };
+        self.suggestions
+            .entry(word.to_string())
+            .or_insert_with(|| dictionary.suggest(word, MAX_SUGGESTIONS))
+            .clone()
+    }
+}
+
+/// Misspellings of each checked block by the hash of its text, kept for
+/// one checker generation so unchanged paragraphs aren't checked again
+#[derive(Debug, Default)]
+pub(crate) struct SpellCache {
+    generation: u64,
+    /// Misspellings with ranges relative to the block start
+    blocks: HashMap<u64, Vec<Misspelling>>,
+}
+
+/// Hash of a block's text and the skipped ranges inside it
+fn hash(text: &str, block: &Range<usize>, skip: &[Range<usize>]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text[block.clone()].hash(&mut hasher);
+    for range in skip
+        .iter()
+        .filter(|r| r.start < block.end && r.end > block.start)
+    {
+        let start = range.start.saturating_sub(block.start);
+        let end = range.end.min(block.end) - block.start;
+        (start, end).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Find misspelled words in the prose of a document: paragraphs, headings,
+/// table cells and front matter values. Words in `ignore` are accepted.
+pub(crate) fn check(
+    text: &str,
+    checker: &SpellChecker,
+    ignore: &HashSet<String>,
+    cache: &mut SpellCache,
+) -> Vec<Misspelling> {
+    if !checker.has_dictionary() {
+        return Vec::new();
+    }
+    if cache.generation != checker.generation {
+        cache.blocks.clear();
+        cache.generation = checker.generation;
+    }
+
+    let tree = MarkdownTree::parse(text);
+    let front_matter = parse_front_matter(text).ok().flatten();
+    let skip = skipped_ranges(&tree, text, front_matter.as_ref());
+    let mut blocks = HashMap::new();
+    let mut out = Vec::new();
+    for block in prose_blocks(&tree, front_matter.as_ref()) {
+        let key = hash(text, &block, &skip);
+        let found = match cache
+            .blocks
+            .remove(&key)
+            .or_else(|| blocks.get(&key).cloned())
+        {
+            Some(found) => found,
+            None => check_block(text, block.clone(), &skip, checker),
+        };
+        out.extend(found.iter().map(|m| Misspelling {
+            range: m.range.start + block.start..m.range.end + block.start,
+            ..m.clone()
+        }));
+        blocks.insert(key, found);
+    }
+    // Blocks no longer in the document are dropped
+    cache.blocks = blocks;
+
+    let ignored = |word: &str| ignore.contains(word) || ignore.contains(&word.to_lowercase());
+    out.retain(|m| !ignored(&m.word));
+    out
+}
+
+/// Ranges of prose to check: the inline content of paragraphs, headings
+/// and table cells, and front matter string values
+fn prose_blocks(tree: &MarkdownTree, front_matter: Option<&FrontMatter>) -> Vec<Range<usize>> {
+    let mut blocks: Vec<Range<usize>> = tree
+        .nodes_of_kind("inline")
+        .into_iter()
+        .map(|node| node.byte_range())
+        .collect();
+    if let Some(front_matter) = front_matter {
+        front_matter_values(&front_matter.entries, &mut blocks);
+    }
+    blocks.sort_by_key(|r| (r.start, r.end));
+    blocks.dedup();
+    blocks
+}
+
+fn front_matter_values(entries: &[FrontMatterEntry], out: &mut Vec<Range<usize>>) {
+    for entry in entries {
+        match &entry.value {
+            FrontMatterValue::String(_) => out.push(entry.value_range.clone()),
+            FrontMatterValue::List(items)
+                if items
+                    .iter()
+                    .all(|i| matches!(i, FrontMatterValue::String(_))) =>
+            {
+                out.push(entry.value_range.clone())
+            }
+            FrontMatterValue::Map(entries) => front_matter_values(entries, out),
+            _ => {}
+        }
+    }
+}
+
+/// Ranges inside prose that aren't words: code, HTML, link destinations
+/// and labels, footnote labels and wiki-links. The given front matter block
+/// isn't skipped, so its values can be checked.
+pub(crate) fn skipped_ranges(
+    tree: &MarkdownTree,
+    text: &str,
+    front_matter: Option<&FrontMatter>,
+) -> Vec<Range<usize>> {
+    let mut skip = tree.code_ranges();
+    skip.retain(|r| front_matter.is_none_or(|f| f.range != *r));
+    tree.visit(&mut |node| {
+        let footnote = node.kind() == "shortcut_link" && text[node.byte_range()].starts_with("[^");
+        if footnote || node.kind() == "link_label" {
+            skip.push(node.byte_range());
+        }
+    });
+    skip.extend(parse_wiki_links(text).into_iter().map(|link| link.range));
+    skip.sort_by_key(|r| r.start);
+    skip
+}
+
+/// Check the words of one block; ranges are relative to its start
+fn check_block(
+    text: &str,
+    block: Range<usize>,
+    skip: &[Range<usize>],
+    checker: &SpellChecker,
+) -> Vec<Misspelling> {
+    words(text, block.clone(), skip)
+        .into_iter()
+        .filter(|range| !checker.is_correct(&text[range.clone()]))
+        .map(|range| Misspelling {
+            word: text[range.clone()].to_string(),
+            range: range.start - block.start..range.end - block.start,
+        })
+        .collect()
+}
+
+/// Word ranges in a block: runs of letters with inner apostrophes, outside
+/// skipped ranges. Tokens that look like URLs or e-mail addresses and words
+/// containing digits are left out.
+fn words(text: &str, block: Range<usize>, skip: &[Range<usize>]) -> Vec<Range<usize>> {
+    let skipped = |pos: usize| skip.iter().any(|r| r.contains(&pos));
+    let mut out = Vec::new();
+    let source = &text[block.clone()];
+
+    let mut token_start = 0;
+    for token in source.split_inclusive(char::is_whitespace) {
+        let start = block.start + token_start;
+        token_start += token.len();
+        let token = token.trim_end();
+        if token.contains("://") || token.starts_with("www.") || token.contains('@') {
+            continue;
+        }
+
+        let mut word: Option<Range<usize>> = None;
+        let mut has_digit = false;
+        let mut chars = token.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            let pos = start + i;
+            let next_is_letter = chars.peek().is_some_and(|(_, n)| n.is_alphabetic());
+            let in_word = !skipped(pos)
+                && (c.is_alphabetic()
+                    || c.is_ascii_digit()
+                    || (matches!(c, '\'' | '’') && word.is_some() && next_is_letter));
+            if in_word {
+                has_digit |= c.is_ascii_digit();
+                let end = pos + c.len_utf8();
+                word = Some(word.map_or(pos..end, |w| w.start..end));
+            } else if let Some(done) = word.take() {
+                if !has_digit {
+                    out.push(done);
+                }
+                has_digit = false;
+            }
+        }
+        if let Some(done) = word
+            && !has_digit
+        {
+            out.push(done);
+        }
+    }
+    out
+}
+
+/// Words listed under `spell_ignore` in a document's front matter
+pub(crate) fn front_matter_ignores(text: &str) -> Vec<String> {
+    let Ok(Some(front_matter)) = parse_front_matter(text) else {
+        return Vec::new();
+    };
+    match front_matter.get(IGNORE_KEY).map(|e| &e.value) {
+        Some(FrontMatterValue::List(items)) => items
+            .iter()
+            .filter_map(|i| i.as_str().map(str::to_string))
+            .collect(),
+        Some(FrontMatterValue::String(words)) => words
+            .split([',', ' '])
+            .filter(|w| !w.is_empty())
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checker() -> SpellChecker {
+        let aff = "SET UTF-8\nSFX S Y 1\nSFX S 0 s .\n";
+        let dic =
+            "a\nthe\nand\nor\nquick\nfox/S\njump/S\nover\nlazy\ndog/S\nit's\ntitle\nlink\nsee\n";
+        SpellChecker::new(Dictionary::parse(aff, dic).unwrap())
+    }
+
+    fn misspelled(text: &str, checker: &SpellChecker) -> Vec<String> {
+        check(text, checker, &HashSet::new(), &mut SpellCache::default())
+            .into_iter()
+            .map(|m| m.word)
+            .collect()
+    }
+
+    #[test]
+    fn test_words_skip_code_and_links() {
+        let text = "---\ntitle: The quik fox\nslug: teh-fox\n---\n# The quik fox\n\n\
+            It's `teh` dog, see [link](http://teh.example) and [[Teh Note]] or \
+            https://teh.example[^fn1].\n\n```\nteh\n```\n\n| dogz | fox2 |\n|---|---|\n";
+        assert_eq!(
+            misspelled(text, &checker()),
+            vec!["quik", "teh", "quik", "dogz"]
+        );
+    }
+
+    #[test]
+    fn test_personal_words_and_ignores() {
+        let mut checker = checker();
+        let text = "The quik fox jumps over the lazy dgo.\n";
+        assert_eq!(misspelled(text, &checker), vec!["quik", "dgo"]);
+
+        checker.add_personal_word("quik").unwrap();
+        assert_eq!(misspelled(text, &checker), vec!["dgo"]);
+        assert!(checker.is_correct("Quik"));
+
+        let ignore: HashSet<String> = ["dgo".to_string()].into();
+        assert!(check(text, &checker, &ignore, &mut SpellCache::default()).is_empty());
+        assert_eq!(
+            front_matter_ignores("---\nspell_ignore: [dgo, quik]\n---\n"),
+            vec!["dgo", "quik"]
+        );
+    }
+
+    #[test]
+    fn test_cache_rechecks_changed_blocks() {
+        let mut checker = checker();
+        let mut cache = SpellCache::default();
+        let first = check(
+            "The dgo.\n\nA foxx.\n",
+            &checker,
+            &HashSet::new(),
+            &mut cache,
+        );
+        assert_eq!(first[0].word, "dgo");
+        assert_eq!(cache.blocks.len(), 2);
+        assert_eq!(checker.suggest("dgo")[0], "dog");
+        assert!(checker.suggestions.contains_key("dgo"));
+
+        let text = "The dgo.\n\nNew foxx here.\n\nA foxx.\n";
+        let found = check(text, &checker, &HashSet::new(), &mut cache);
+        let ranges: Vec<_> = found.iter().map(|m| &text[m.range.clone()]).collect();
+        assert_eq!(ranges, vec!["dgo", "New", "foxx", "here", "foxx"]);
+        assert_eq!(cache.blocks.len(), 3);
+    }
+}
@@ -0,0 +1,410 @@
+//! Document statistics: words, characters, sentences, paragraphs and
+//! reading time for the whole document, each section and the selections
+
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::Range;
+
+use crate::outline::{Heading, outline};
+use crate::selection::Selection;
+use crate::syntax::{MarkdownTree, child_of_kind, plain_text};
+
+/// Word, character, sentence and paragraph counts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextStats {
+    pub words: usize,
+    /// Characters, counting each run of whitespace as one space
+    pub characters: usize,
+    pub characters_no_spaces: usize,
+    pub sentences: usize,
+    pub paragraphs: usize,
+}
+
+impl TextStats {
+    /// Estimated reading time in seconds, rounded up
+    pub fn reading_time_seconds(&self, words_per_minute: u32) -> u64 {
+        let wpm = u64::from(words_per_minute.max(1));
+        (self.words as u64 * 60).div_ceil(wpm)
+    }
+
+    fn add(&mut self, other: &TextStats) {
+        self.words += other.words;
+        self.characters += other.characters;
+        self.characters_no_spaces += other.characters_no_spaces;
+        self.sentences += other.sentences;
+        self.paragraphs += other.paragraphs;
+    }
+}
+
+/// What to count
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatsOptions {
+    /// Skip fenced and indented code blocks
+    pub exclude_code: bool,
+    /// Skip the YAML/TOML front matter block
+    pub exclude_front_matter: bool,
+    /// Reading speed for reading time estimates
+    pub words_per_minute: u32,
+}
+
+impl Default for StatsOptions {
+    fn default() -> Self {
+        Self {
+            exclude_code: true,
+            exclude_front_matter: true,
+            words_per_minute: 200,
+        }
+    }
+}
+
+/// Counts for one outline section: a heading and everything up to the next
+/// heading of the same or a higher level, subsections included
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionStats {
+    pub heading: Heading,
+    /// Byte range of the section
+    pub range: Range<usize>,
+    pub stats: TextStats,
+}
+
+/// Counts for a whole document
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentStats {
+    pub total: TextStats,
+    pub sections: Vec<SectionStats>,
+    /// Estimated reading time of the whole document in seconds
+    pub reading_time_seconds: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum BlockKind {
+    Paragraph,
+    Heading,
+    TableCell,
+    Code,
+    FrontMatter,
+}
+
+/// A counted piece of the document
+#[derive(Debug, Clone)]
+struct Block {
+    kind: BlockKind,
+    range: Range<usize>,
+    stats: TextStats,
+}
+
+/// Stats of the last computed revision, with per-block counts reused
+/// across edits so only changed blocks are counted again
+#[derive(Debug, Default)]
+pub(crate) struct StatsCache {
+    key: Option<(u64, StatsOptions)>,
+    blocks: Vec<Block>,
+    stats: DocumentStats,
+    /// Block counts by kind and source text hash
+    counts: HashMap<(BlockKind, u64), TextStats>,
+}
+
+impl StatsCache {
+    /// Stats for a text at a revision, recounting only blocks whose source
+    /// changed since the last call
+    pub(crate) fn get(
+        &mut self,
+        text: &str,
+        revision: u64,
+        options: StatsOptions,
+    ) -> &DocumentStats {
+        if self.key == Some((revision, options)) {
+            return &self.stats;
+        }
+
+        let tree = MarkdownTree::parse(text);
+        let mut counts = HashMap::new();
+        self.blocks = blocks(&tree, text, options)
+            .into_iter()
+            .map(|(kind, range)| {
+                let key = (kind, hash(&text[range.clone()]));
+                let stats = match self.counts.get(&key) {
+                    Some(stats) => *stats,
+                    None => count_block(&tree, text, kind, &range),
+                };
+                counts.insert(key, stats);
+                Block { kind, range, stats }
+            })
+            .collect();
+        self.counts = counts;
+        self.stats = summarize(&tree, text, &self.blocks, options);
+        self.key = Some((revision, options));
+        &self.stats
+    }
+
+    /// Stats of the selected text, using the blocks of the last `get`.
+    /// Blocks cut by a selection are counted from their source text.
+    pub(crate) fn selection_stats(&self, text: &str, selections: &[Selection]) -> TextStats {
+        let mut total = TextStats::default();
+        for sel in selections.iter().filter(|s| !s.is_empty()) {
+            for block in &self.blocks {
+                let start = block.range.start.max(sel.start());
+                let end = block.range.end.min(sel.end());
+                if start >= end {
+                    continue;
+                }
+                if start == block.range.start && end == block.range.end {
+                    total.add(&block.stats);
+                } else {
+                    total.add(&count(block.kind, &text[start..end]));
+                }
+            }
+        }
+        total
+    }
+}
+
+fn hash(source: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Counted blocks in document order. Headings and paragraphs use their
+/// inline content; code blocks and front matter only their body lines.
+fn blocks(
+    tree: &MarkdownTree,
+    text: &str,
+    options: StatsOptions,
+) -> Vec<(BlockKind, Range<usize>)> {
+    let mut out = Vec::new();
+    tree.visit(&mut |node| {
+        let block = match node.kind() {
+            "paragraph" => {
+                let in_setext = node.parent().is_some_and(|p| p.kind() == "setext_heading");
+                let kind = if in_setext {
+                    BlockKind::Heading
+                } else {
+                    BlockKind::Paragraph
+                };
+                child_of_kind(node, "inline").map(|inline| (kind, inline.byte_range()))
+            }
+            "atx_heading" => child_of_kind(node, "inline")
+                .map(|inline| (BlockKind::Heading, inline.byte_range())),
+            "pipe_table_cell" => Some((BlockKind::TableCell, node.byte_range())),
+            "fenced_code_block" if !options.exclude_code => {
+                child_of_kind(node, "code_fence_content").map(|c| (BlockKind::Code, c.byte_range()))
+            }
+            "indented_code_block" if !options.exclude_code => {
+                Some((BlockKind::Code, node.byte_range()))
+            }
+            "minus_metadata" | "plus_metadata" if !options.exclude_front_matter => {
+                let range = node.byte_range();
+                let source = &text[range.clone()];
+                let body_start = source.find('\n').map_or(source.len(), |i| i + 1);
+                let body_end = source.trim_end().rfind('\n').map_or(body_start, |i| i + 1);
+                Some((
+                    BlockKind::FrontMatter,
+                    range.start + body_start..range.start + body_end.max(body_start),
+                ))
+            }
+            _ => None,
+        };
+        out.extend(block);
+    });
+    out.sort_by_key(|(_, range)| range.start);
+    out
+}
+
+fn count_block(
+    tree: &MarkdownTree,
+    text: &str,
+    kind: BlockKind,
+    range: &Range<usize>,
+) -> TextStats {
+    match kind {
+        BlockKind::Paragraph | BlockKind::Heading | BlockKind::TableCell => {
+            let node = tree
+                .root()
+                .descendant_for_byte_range(range.start, range.end)
+                .filter(|n| n.byte_range() == *range);
+            match node {
+                Some(node) => count(kind, &plain_text(tree, node, text)),
+                None => count(kind, &text[range.clone()]),
+            }
+        }
+        BlockKind::Code | BlockKind::FrontMatter => count(kind, &text[range.clone()]),
+    }
+}
+
+/// Count a block's text. Only paragraphs have sentences.
+fn count(kind: BlockKind, source: &str) -> TextStats {
+    let mut stats = TextStats::default();
+    let mut tokens: usize = 0;
+    let mut open_sentence = false;
+    for line in source.lines() {
+        // Block quote markers on continuation lines are not text
+        let line = match kind {
+            BlockKind::Paragraph => line.trim_start().trim_start_matches(['>', ' ', '\t']),
+            _ => line,
+        };
+        for token in line.split_whitespace() {
+            tokens += 1;
+            stats.characters_no_spaces += token.chars().count();
+            if !token.chars().any(char::is_alphanumeric) {
+                continue;
+            }
+            stats.words += 1;
+            open_sentence = true;
+            let end = token.trim_end_matches(['"', '\'', ')', ']', '*', '_', '”', '’']);
+            if end.ends_with(['.', '!', '?', '…']) {
+                stats.sentences += 1;
+                open_sentence = false;
+            }
+        }
+    }
+    stats.characters = stats.characters_no_spaces + tokens.saturating_sub(1);
+    if kind == BlockKind::Paragraph && stats.words > 0 {
+        stats.sentences += usize::from(open_sentence);
+        stats.paragraphs = 1;
+    } else {
+        stats.sentences = 0;
+    }
+    stats
+}
+
+fn summarize(
+    tree: &MarkdownTree,
+    text: &str,
+    blocks: &[Block],
+    options: StatsOptions,
+) -> DocumentStats {
+    let mut total = TextStats::default();
+    for block in blocks {
+        total.add(&block.stats);
+    }
+
+    let headings = outline(tree, text);
+    let sections = headings
+        .iter()
+        .enumerate()
+        .map(|(i, heading)| {
+            let end = headings[i + 1..]
+                .iter()
+                .find(|h| h.level <= heading.level)
+                .map_or(text.len(), |h| h.range.start);
+            let range = heading.range.start..end;
+            let mut stats = TextStats::default();
+            for block in blocks.iter().filter(|b| range.contains(&b.range.start)) {
+                stats.add(&block.stats);
+            }
+            SectionStats {
+                heading: heading.clone(),
+                range,
+                stats,
+            }
+        })
+        .collect();
+
+    DocumentStats {
+        total,
+        sections,
+        reading_time_seconds: total.reading_time_seconds(options.words_per_minute),
+    }
+}
+
+/// Words (whitespace-separated runs with a letter or digit) in text split
+/// into chunks, such as a rope's
+pub(crate) fn words<'a>(chunks: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for chunk in chunks {
+        for c in chunk.chars() {
+            if c.is_whitespace() {
+                push_word(&mut words, &mut word);
+            } else {
+                word.push(c);
+            }
+        }
+    }
+    push_word(&mut words, &mut word);
+    words
+}
+
+fn push_word(words: &mut Vec<String>, word: &mut String) {
+    if word.chars().any(char::is_alphanumeric) {
+        words.push(std::mem::take(word));
+    } else {
+        word.clear();
+    }
+}
+
+/// Compute stats for a text in one go
+pub fn document_stats(text: &str, options: StatsOptions) -> DocumentStats {
+    StatsCache::default().get(text, 0, options).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "---\ntitle: Draft notes\n---\n# Intro *here*\n\nOne sentence. Another one!\nAnd a third\n\n> quoted words.\n> more\n\n## Details\n\n| a b | c |\n|---|---|\n| d | e |\n\n```\nlet x = 1;\n```\n";
+
+    #[test]
+    fn test_count() {
+        let stats = count(BlockKind::Paragraph, "Hello, world.  It's *fine* -- ok");
+        assert_eq!(stats.words, 5);
+        assert_eq!(stats.sentences, 2);
+        assert_eq!(stats.characters, 31);
+        assert_eq!(stats.characters_no_spaces, 26);
+        assert_eq!(stats.paragraphs, 1);
+    }
+
+    #[test]
+    fn test_words_across_chunks() {
+        assert_eq!(
+            words(["Hel", "lo wo", "rld -- ", "ok"]),
+            ["Hello", "world", "ok"]
+        );
+        assert!(words([""]).is_empty());
+    }
+
+    #[test]
+    fn test_document_stats() {
+        let stats = document_stats(TEXT, StatsOptions::default());
+        // Intro here / One sentence Another one And a third / quoted words more
+        // / Details / a b c d e
+        assert_eq!(stats.total.words, 2 + 7 + 3 + 1 + 5);
+        assert_eq!(stats.total.paragraphs, 2);
+        assert_eq!(stats.total.sentences, 5);
+        assert_eq!(stats.reading_time_seconds, 6);
+
+        let sections: Vec<_> = stats
+            .sections
+            .iter()
+            .map(|s| (s.heading.text.as_str(), s.stats.words))
+            .collect();
+        assert_eq!(sections, vec![("Intro here", 18), ("Details", 6)]);
+
+        let all = StatsOptions {
+            exclude_code: false,
+            exclude_front_matter: false,
+            ..StatsOptions::default()
+        };
+        // title Draft notes / let x 1
+        assert_eq!(document_stats(TEXT, all).total.words, 18 + 3 + 3);
+    }
+
+    #[test]
+    fn test_cache_reuses_unchanged_blocks() {
+        let mut cache = StatsCache::default();
+        let options = StatsOptions::default();
+        cache.get("First para.\n\nSecond para.\n", 1, options);
+        assert_eq!(cache.counts.len(), 2);
+
+        let stats = cache.get("First para.\n\nSecond para, longer.\n", 2, options);
+        assert_eq!(stats.total.words, 5);
+        assert_eq!(cache.counts.len(), 2);
+
+        let text = "First para.\n\nSecond para, longer.\n";
+        let sel = [Selection::new(0, 5), Selection::new(13, text.len())];
+        let selected = cache.selection_stats(text, &sel);
+        assert_eq!(selected.words, 4);
+        assert_eq!(selected.paragraphs, 2);
+    }
+}
@@ -0,0 +1,254 @@
+//! Markdown parse tree built on tree-sitter-md's block and inline grammars
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use tree_sitter::{Node, Parser, Range as TsRange, Tree};
+
+/// A parsed Markdown document: one block tree plus an inline tree for every
+/// `inline` and `pipe_table_cell` node in it
+pub struct MarkdownTree {
+    block: Tree,
+    inlines: Vec<Tree>,
+    inline_index: HashMap<usize, usize>,
+}
+
+impl MarkdownTree {
+    /// Parse Markdown source text
+    pub fn parse(text: &str) -> Self {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&tree_sitter_md::LANGUAGE.into())
+            .expect("block grammar is compatible with tree-sitter");
+        let block = parser
+            .parse(text, None)
+            .expect("parsing without timeout always yields a tree");
+
+        parser
+            .set_language(&tree_sitter_md::INLINE_LANGUAGE.into())
+            .expect("inline grammar is compatible with tree-sitter");
+
+        let mut inlines = Vec::new();
+        let mut inline_index = HashMap::new();
+        let mut stack = vec![block.root_node()];
+        while let Some(node) = stack.pop() {
+            if matches!(node.kind(), "inline" | "pipe_table_cell") {
+                let ranges = inline_ranges(node);
+                if parser.set_included_ranges(&ranges).is_ok()
+                    && let Some(tree) = parser.parse(text, None)
+                {
+                    inline_index.insert(node.id(), inlines.len());
+                    inlines.push(tree);
+                }
+                continue;
+            }
+            let mut cursor = node.walk();
+            stack.extend(node.children(&mut cursor));
+        }
+
+        Self {
+            block,
+            inlines,
+            inline_index,
+        }
+    }
+
+    /// Root node of the block tree
+    pub fn root(&self) -> Node<'_> {
+        self.block.root_node()
+    }
+
+    /// Inline tree root for an `inline` or `pipe_table_cell` block node
+    pub fn inline_root(&self, node: &Node) -> Option<Node<'_>> {
+        let idx = *self.inline_index.get(&node.id())?;
+        Some(self.inlines[idx].root_node())
+    }
+
+    /// Visit every node in document order, descending from block nodes into
+    /// their inline trees
+    pub fn visit<'t>(&'t self, f: &mut impl FnMut(Node<'t>)) {
+        self.visit_node(self.root(), f);
+    }
+
+    fn visit_node<'t>(&'t self, node: Node<'t>, f: &mut impl FnMut(Node<'t>)) {
+        f(node);
+        if let Some(inline) = self.inline_root(&node) {
+            self.visit_node(inline, f);
+            return;
+        }
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            self.visit_node(child, f);
+        }
+    }
+
+    /// All nodes of the given kind, in document order
+    pub fn nodes_of_kind<'t>(&'t self, kind: &str) -> Vec<Node<'t>> {
+        let mut out = Vec::new();
+        self.visit(&mut |node| {
+            if node.kind() == kind {
+                out.push(node);
+            }
+        });
+        out
+    }
+
+    /// Byte ranges of nodes whose text is not prose: code, HTML, front matter,
+    /// link destinations and autolinks
+    pub fn code_ranges(&self) -> Vec<Range<usize>> {
+        let mut out = Vec::new();
+        self.visit(&mut |node| {
+            if matches!(
+                node.kind(),
+                "fenced_code_block"
+                    | "indented_code_block"
+                    | "code_span"
+                    | "html_block"
+                    | "html_tag"
+                    | "minus_metadata"
+                    | "plus_metadata"
+                    | "link_destination"
+                    | "uri_autolink"
+                    | "email_autolink"
+                    | "latex_block"
+            ) {
+                out.push(node.byte_range());
+            }
+        });
+        out.sort_by_key(|r| r.start);
+        out
+    }
+}
+
+/// Rendered text of an inline node: emphasis and code delimiters, link
+/// destinations and HTML tags are dropped, escapes are resolved
+pub fn plain_text(tree: &MarkdownTree, node: Node, text: &str) -> String {
+    let mut out = String::new();
+    match tree.inline_root(&node) {
+        Some(inline) => push_plain_text(inline, text, &mut out),
+        None => push_plain_text(node, text, &mut out),
+    }
+    out
+}
+
+fn push_plain_text(node: Node, text: &str, out: &mut String) {
+    match node.kind() {
+        "emphasis_delimiter"
+        | "code_span_delimiter"
+        | "latex_span_delimiter"
+        | "html_tag"
+        | "link_destination"
+        | "link_title"
+        | "link_label" => {}
+        "backslash_escape" => out.push_str(&text[node.start_byte() + 1..node.end_byte()]),
+        "inline_link"
+        | "full_reference_link"
+        | "collapsed_reference_link"
+        | "shortcut_link"
+        | "image" => {
+            let mut cursor = node.walk();
+            for child in node.children(&mut cursor) {
+                if matches!(child.kind(), "link_text" | "image_description") {
+                    push_plain_text(child, text, out);
+                }
+            }
+        }
+        "uri_autolink" | "email_autolink" => out.push_str(
+            text[node.byte_range()]
+                .trim_start_matches('<')
+                .trim_end_matches('>'),
+        ),
+        _ => {
+            let mut pos = node.start_byte();
+            let mut cursor = node.walk();
+            for child in node.children(&mut cursor) {
+                out.push_str(&text[pos..child.start_byte().max(pos)]);
+                push_plain_text(child, text, out);
+                pos = child.end_byte();
+            }
+            if pos < node.end_byte() {
+                out.push_str(&text[pos..node.end_byte()]);
+            }
+        }
+    }
+}
+
+/// Find the closest ancestor (or the node itself) with the given kind
+pub fn ancestor_of_kind<'t>(node: Node<'t>, kind: &str) -> Option<Node<'t>> {
+    let mut current = Some(node);
+    while let Some(n) = current {
+        if n.kind() == kind {
+            return Some(n);
+        }
+        current = n.parent();
+    }
+    None
+}
+
+/// First direct child with the given kind
+pub fn child_of_kind<'t>(node: Node<'t>, kind: &str) -> Option<Node<'t>> {
+    let mut cursor = node.walk();
+    node.children(&mut cursor).find(|c| c.kind() == kind)
+}
+
+/// Ranges covered by an inline node, minus its named children (block
+/// continuations such as `> ` inside a block quote)
+fn inline_ranges(node: Node) -> Vec<TsRange> {
+    let mut range = node.range();
+    let mut ranges = Vec::new();
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        let child_range = child.range();
+        ranges.push(TsRange {
+            start_byte: range.start_byte,
+            start_point: range.start_point,
+            end_byte: child_range.start_byte,
+            end_point: child_range.start_point,
+        });
+        range.start_byte = child_range.end_byte;
+        range.start_point = child_range.end_point;
+    }
+    ranges.push(range);
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_block_and_inline() {
+        let tree = MarkdownTree::parse("# Title\n\nSome [link](a.md) text.\n");
+        assert_eq!(tree.nodes_of_kind("atx_heading").len(), 1);
+        let links = tree.nodes_of_kind("inline_link");
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].byte_range(), 14..26);
+    }
+
+    #[test]
+    fn test_code_ranges() {
+        let text = "Use `code` here.\n\n```\nfn x() {}\n```\n";
+        let tree = MarkdownTree::parse(text);
+        let ranges = tree.code_ranges();
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(&text[ranges[0].clone()], "`code`");
+        assert!(text[ranges[1].clone()].starts_with("```"));
+    }
+
+    #[test]
+    fn test_plain_text() {
+        let text = "Some *em* `code` [link](x.md) \\* end\n";
+        let tree = MarkdownTree::parse(text);
+        let inline = tree.nodes_of_kind("inline")[0];
+        assert_eq!(plain_text(&tree, inline, text), "Some em code link * end");
+    }
+
+    #[test]
+    fn test_ancestor_of_kind() {
+        let tree = MarkdownTree::parse("- item\n  - nested\n");
+        let items = tree.nodes_of_kind("list_item");
+        assert_eq!(items.len(), 2);
+        let parent = ancestor_of_kind(items[1].parent().unwrap(), "list_item").unwrap();
+        assert_eq!(parent.id(), items[0].id());
+    }
+}
@@ -0,0 +1,313 @@
+//! GFM task items: toggling and workspace-wide aggregation
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::date::Date;
+use crate::edit::TextEdit;
+use crate::encoding;
+use crate::error::Result;
+use crate::outline::{heading_path, outline};
+use crate::selection::Selection;
+use crate::syntax::MarkdownTree;
+use crate::text::{line_of, line_starts, touched_lines};
+use crate::{DocumentId, workspace};
+
+/// Completion state of a task item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TaskState {
+    Open,
+    Done,
+}
+
+/// A GFM task list item (`- [ ] text`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task {
+    /// Line of the item (0-indexed)
+    pub line: usize,
+    /// Titles of the enclosing headings, outermost first
+    pub heading_path: Vec<String>,
+    pub state: TaskState,
+    /// First line of the item's text
+    pub text: String,
+    /// Date from a `due:YYYY-MM-DD` annotation
+    pub due: Option<Date>,
+    /// Names from `@tag` annotations, without the `@`
+    pub tags: Vec<String>,
+    /// Byte range of the `[ ]` / `[x]` marker
+    pub marker: Range<usize>,
+}
+
+/// Find all task items in Markdown text
+pub fn parse_tasks(text: &str) -> Vec<Task> {
+    let tree = MarkdownTree::parse(text);
+    let headings = outline(&tree, text);
+    let starts = line_starts(text);
+    let mut tasks = Vec::new();
+
+    for item in tree.nodes_of_kind("list_item") {
+        let mut cursor = item.walk();
+        let Some(marker) = item.children(&mut cursor).find(|c| {
+            matches!(
+                c.kind(),
+                "task_list_marker_checked" | "task_list_marker_unchecked"
+            )
+        }) else {
+            continue;
+        };
+        let state = if marker.kind() == "task_list_marker_checked" {
+            TaskState::Done
+        } else {
+            TaskState::Open
+        };
+        let rest = &text[marker.end_byte()..item.end_byte()];
+        let item_text = rest
+            .split(['\n', '\r'])
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_string();
+
+        tasks.push(Task {
+            line: line_of(&starts, marker.start_byte()),
+            heading_path: heading_path(&headings, item.start_byte()),
+            state,
+            due: parse_due(&item_text),
+            tags: parse_tags(&item_text),
+            text: item_text,
+            marker: marker.byte_range(),
+        });
+    }
+    tasks
+}
+
+/// Edits toggling every task item on lines touched by the selections
+pub(crate) fn toggle_edits(text: &str, selections: &[Selection]) -> Vec<TextEdit> {
+    let lines = touched_lines(&line_starts(text), selections);
+    parse_tasks(text)
+        .into_iter()
+        .filter(|task| lines.binary_search(&task.line).is_ok())
+        .map(|task| {
+            let marker = match task.state {
+                TaskState::Open => "[x]",
+                TaskState::Done => "[ ]",
+            };
+            TextEdit::new(task.marker, marker)
+        })
+        .collect()
+}
+
+fn parse_due(text: &str) -> Option<Date> {
+    text.split_whitespace()
+        .find_map(|word| word.strip_prefix("due:"))
+        .and_then(|date| Date::parse(date.get(..10)?))
+}
+
+fn parse_tags(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .filter_map(|word| word.strip_prefix('@'))
+        .map(|tag| {
+            tag.trim_end_matches(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '/')))
+        })
+        .filter(|tag| !tag.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Task completion counts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaskProgress {
+    pub done: usize,
+    pub total: usize,
+}
+
+impl TaskProgress {
+    /// Fraction of tasks done, 0.0 when there are no tasks
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.done as f64 / self.total as f64
+        }
+    }
+
+    /// Count the done and total tasks in a list
+    pub fn of(tasks: &[Task]) -> Self {
+        Self {
+            done: tasks.iter().filter(|t| t.state == TaskState::Done).count(),
+            total: tasks.len(),
+        }
+    }
+}
+
+/// Where indexed tasks came from
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TaskSource {
+    /// A file on disk (or an open document saved there)
+    File(PathBuf),
+    /// An open document that has never been saved
+    Untitled(DocumentId),
+}
+
+/// Filter for task queries. Unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct TaskQuery {
+    pub state: Option<TaskState>,
+    /// Only tasks due on or before this date
+    pub due_before: Option<Date>,
+    /// Only tasks due on or after this date
+    pub due_after: Option<Date>,
+    /// Only tasks carrying this tag (without `@`)
+    pub tag: Option<String>,
+}
+
+impl TaskQuery {
+    /// Check whether a task matches this query
+    pub fn matches(&self, task: &Task) -> bool {
+        if self.state.is_some_and(|s| s != task.state) {
+            return false;
+        }
+        if let Some(before) = self.due_before
+            && task.due.is_none_or(|d| d > before)
+        {
+            return false;
+        }
+        if let Some(after) = self.due_after
+            && task.due.is_none_or(|d| d < after)
+        {
+            return false;
+        }
+        if let Some(tag) = &self.tag
+            && !task.tags.iter().any(|t| t == tag)
+        {
+            return false;
+        }
+        true
+    }
+}
+
+/// Task items gathered across documents and workspace files
+#[derive(Debug, Default)]
+pub struct TaskIndex {
+    sources: BTreeMap<TaskSource, Vec<Task>>,
+}
+
+impl TaskIndex {
+    /// Create an empty index
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index (or re-index) the tasks in some text
+    pub fn update(&mut self, source: TaskSource, text: &str) {
+        self.sources.insert(source, parse_tasks(text));
+    }
+
+    /// Remove a source from the index
+    pub fn remove(&mut self, source: &TaskSource) {
+        self.sources.remove(source);
+    }
+
+    /// Index every Markdown file under a folder, returning the count of
+    /// files indexed. Files that can't be read are skipped.
+    pub fn index_folder(&mut self, root: &Path) -> Result<usize> {
+        let mut count = 0;
+        for file in workspace::markdown_files(root)? {
+            let Ok((text, _)) = encoding::read_file(&file) else {
+                continue;
+            };
+            self.update(TaskSource::File(file), &text.to_string());
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// All tasks matching a query, ordered by source and line
+    pub fn query(&self, query: &TaskQuery) -> Vec<(&TaskSource, &Task)> {
+        self.sources
+            .iter()
+            .flat_map(|(source, tasks)| tasks.iter().map(move |t| (source, t)))
+            .filter(|(_, task)| query.matches(task))
+            .collect()
+    }
+
+    /// Completion progress of one source
+    pub fn progress(&self, source: &TaskSource) -> TaskProgress {
+        self.sources
+            .get(source)
+            .map(|tasks| TaskProgress::of(tasks))
+            .unwrap_or_default()
+    }
+
+    /// Completion progress of every source that has tasks
+    pub fn progress_by_source(&self) -> Vec<(&TaskSource, TaskProgress)> {
+        self.sources
+            .iter()
+            .filter(|(_, tasks)| !tasks.is_empty())
+            .map(|(source, tasks)| (source, TaskProgress::of(tasks)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE: &str = "# Project\n\n## Next\n\n- [ ] write spec due:2024-03-01 @work\n- [x] book room @work\n- plain item\n\n## Later\n\n- [ ] relax @home\n";
+
+    #[test]
+    fn test_parse_tasks() {
+        let tasks = parse_tasks(NOTE);
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(tasks[0].line, 4);
+        assert_eq!(tasks[0].heading_path, vec!["Project", "Next"]);
+        assert_eq!(tasks[0].state, TaskState::Open);
+        assert_eq!(tasks[0].due, Date::parse("2024-03-01"));
+        assert_eq!(tasks[0].tags, vec!["work"]);
+        assert_eq!(tasks[1].state, TaskState::Done);
+        assert_eq!(tasks[2].heading_path, vec!["Project", "Later"]);
+
+        let tasks = parse_tasks("# A\r\r- [ ] one\r\r## B\r\r- [ ] two\r");
+        let lines: Vec<_> = tasks.iter().map(|t| t.line).collect();
+        assert_eq!(lines, vec![2, 6]);
+        assert_eq!(tasks[0].text, "one");
+    }
+
+    #[test]
+    fn test_toggle_edits() {
+        let text = "- [ ] a\n- [x] b\n- [ ] c\n";
+        let sel = [Selection::new(2, 10)];
+        let edits = toggle_edits(text, &sel);
+        assert_eq!(
+            edits,
+            vec![TextEdit::new(2..5, "[x]"), TextEdit::new(10..13, "[ ]")]
+        );
+    }
+
+    #[test]
+    fn test_query_and_progress() {
+        let mut index = TaskIndex::new();
+        let source = TaskSource::File(PathBuf::from("note.md"));
+        index.update(source.clone(), NOTE);
+
+        let work = TaskQuery {
+            tag: Some("work".into()),
+            state: Some(TaskState::Open),
+            ..Default::default()
+        };
+        let found = index.query(&work);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].1.text, "write spec due:2024-03-01 @work");
+
+        let due = TaskQuery {
+            due_before: Date::parse("2024-12-31"),
+            ..Default::default()
+        };
+        assert_eq!(index.query(&due).len(), 1);
+
+        let progress = index.progress(&source);
+        assert_eq!(progress, TaskProgress { done: 1, total: 3 });
+        assert!((progress.fraction() - 1.0 / 3.0).abs() < 1e-9);
+    }
+}
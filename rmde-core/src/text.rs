@@ -0,0 +1,85 @@
+//! Line and word helpers for plain text
+
+use crate::line_ending::line_breaks;
+use crate::selection::Selection;
+
+/// Byte offset of the start of every line, after `\n`, `\r\n` or `\r`
+pub(crate) fn line_starts(text: &str) -> Vec<usize> {
+    let mut starts = vec![0];
+    starts.extend(line_breaks(text).map(|(_, end)| end));
+    starts
+}
+
+/// Line containing a byte offset
+pub(crate) fn line_of(starts: &[usize], pos: usize) -> usize {
+    starts.partition_point(|&s| s <= pos).saturating_sub(1)
+}
+
+/// Text of a line without its line break
+pub(crate) fn line_text<'a>(text: &'a str, starts: &[usize], line: usize) -> &'a str {
+    let end = starts.get(line + 1).copied().unwrap_or(text.len());
+    text[starts[line]..end].trim_end_matches(['\n', '\r'])
+}
+
+/// Display width of leading text, expanding tabs to tab stops
+pub(crate) fn columns(text: &str, tab_width: usize) -> usize {
+    text.chars().fold(0, |col, c| match c {
+        '\t' => col + tab_width - col % tab_width,
+        _ => col + 1,
+    })
+}
+
+/// A word with its first letter in upper case
+pub(crate) fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Lines touched by selections, sorted and deduplicated. A selection ending
+/// at the start of a line does not touch that line.
+pub(crate) fn touched_lines(starts: &[usize], selections: &[Selection]) -> Vec<usize> {
+    let mut lines = Vec::new();
+    for sel in selections {
+        let first = line_of(starts, sel.start());
+        let mut last = line_of(starts, sel.end());
+        if last > first && starts[last] == sel.end() {
+            last -= 1;
+        }
+        lines.extend(first..=last);
+    }
+    lines.sort_unstable();
+    lines.dedup();
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_of() {
+        let starts = line_starts("ab\ncd\n");
+        assert_eq!(starts, vec![0, 3, 6]);
+        assert_eq!(line_of(&starts, 0), 0);
+        assert_eq!(line_of(&starts, 2), 0);
+        assert_eq!(line_of(&starts, 3), 1);
+        assert_eq!(line_of(&starts, 6), 2);
+
+        let text = "ab\r\ncd\ref";
+        let starts = line_starts(text);
+        assert_eq!(starts, vec![0, 4, 7]);
+        assert_eq!(line_of(&starts, 3), 0);
+        assert_eq!(line_text(text, &starts, 0), "ab");
+        assert_eq!(line_text(text, &starts, 1), "cd");
+    }
+
+    #[test]
+    fn test_touched_lines() {
+        let starts = line_starts("a\nb\nc\nd\n");
+        let sels = [Selection::new(0, 4), Selection::cursor(6)];
+        assert_eq!(touched_lines(&starts, &sels), vec![0, 1, 3]);
+    }
+}
@@ -0,0 +1,236 @@
+//! Table of contents: a linked list of headings kept between
+//! `<!-- toc -->` and `<!-- tocstop -->` markers
+
+use std::ops::Range;
+
+use crate::edit::TextEdit;
+use crate::line_ending::{LineEnding, line_breaks, normalize, trailing_breaks};
+use crate::outline::outline;
+use crate::slug::heading_slugs;
+use crate::syntax::MarkdownTree;
+
+/// Deepest heading level listed when a marker doesn't say
+const DEFAULT_TOC_DEPTH: u8 = 3;
+
+const TOC_END: &str = "<!-- tocstop -->";
+
+/// A heading listed in the table of contents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TocEntry {
+    /// Heading level, 1-6
+    pub level: u8,
+    /// Nesting depth in the list, 0 for the shallowest listed level
+    pub depth: usize,
+    pub text: String,
+    /// Anchor slug, unique in the document
+    pub slug: String,
+    /// Line the heading starts on (0-indexed)
+    pub line: usize,
+}
+
+/// Headings up to a level, with their anchors and list nesting
+pub fn toc_entries(text: &str, max_level: u8) -> Vec<TocEntry> {
+    let tree = MarkdownTree::parse(text);
+    let headings = outline(&tree, text);
+    let slugs = heading_slugs(&headings);
+    let listed: Vec<_> = headings
+        .into_iter()
+        .zip(slugs)
+        .filter(|(h, _)| h.level <= max_level)
+        .collect();
+    let top = listed.iter().map(|(h, _)| h.level).min().unwrap_or(1);
+    listed
+        .into_iter()
+        .map(|(heading, slug)| TocEntry {
+            level: heading.level,
+            depth: usize::from(heading.level - top),
+            text: heading.text,
+            slug,
+            line: heading.line,
+        })
+        .collect()
+}
+
+/// Edit inserting a table of contents at a position, separated from the
+/// surrounding text by blank lines
+pub(crate) fn insert_edit(text: &str, pos: usize, max_level: u8) -> TextEdit {
+    let before = &text[..pos];
+    let prefix = match trailing_breaks(before) {
+        _ if before.is_empty() => "",
+        0 => "\n\n",
+        1 => "\n",
+        _ => "",
+    };
+    let after = &text[pos..];
+    let suffix = if after.is_empty() || after.starts_with(['\n', '\r']) {
+        ""
+    } else {
+        "\n"
+    };
+    let block = format!(
+        "{prefix}<!-- toc depth={max_level} -->\n{}{TOC_END}\n{suffix}",
+        list(&toc_entries(text, max_level))
+    );
+    TextEdit::insert(pos, block)
+}
+
+/// Edits regenerating every table of contents between its markers. The
+/// list is built with `\n` line breaks, so existing ones are compared in
+/// that style.
+pub(crate) fn update_edits(text: &str) -> Vec<TextEdit> {
+    blocks(text)
+        .into_iter()
+        .filter_map(|(range, max_level)| {
+            let list = list(&toc_entries(text, max_level));
+            let current = normalize(&text[range.clone()], LineEnding::Lf);
+            (current != list).then(|| TextEdit::new(range, list))
+        })
+        .collect()
+}
+
+/// Content between each start marker line and its end marker, with the
+/// depth the start marker asks for
+fn blocks(text: &str) -> Vec<(Range<usize>, u8)> {
+    let tree = MarkdownTree::parse(text);
+    let mut markers = Vec::new();
+    tree.visit(&mut |node| {
+        if node.kind() == "html_block" {
+            markers.push(node.byte_range());
+        }
+    });
+
+    let mut blocks = Vec::new();
+    let mut open: Option<(usize, u8)> = None;
+    for range in markers {
+        let block = text[range.clone()].trim();
+        if let Some(max_level) = start_marker(block) {
+            let end = line_breaks(&text[range.clone()])
+                .next()
+                .map_or(range.end, |(_, end)| range.start + end);
+            open = Some((end, max_level));
+        } else if block == TOC_END
+            && let Some((start, max_level)) = open.take()
+        {
+            blocks.push((start..range.start, max_level));
+        }
+    }
+    blocks
+}
+
+/// Depth of a `<!-- toc -->` or `<!-- toc depth=N -->` marker
+fn start_marker(block: &str) -> Option<u8> {
+    let inner = block.strip_prefix("<!--")?.strip_suffix("-->")?;
+    let mut words = inner.split_whitespace();
+    if words.next()? != "toc" {
+        return None;
+    }
+    match words.next() {
+        None => Some(DEFAULT_TOC_DEPTH),
+        Some(word) => {
+            let depth = word.strip_prefix("depth=")?.parse().ok()?;
+            (words.next().is_none() && (1..=6).contains(&depth)).then_some(depth)
+        }
+    }
+}
+
+/// Nested Markdown list of links, surrounded by blank lines
+fn list(entries: &[TocEntry]) -> String {
+    if entries.is_empty() {
+        return "\n".to_string();
+    }
+    let mut out = String::from("\n");
+    for entry in entries {
+        out.push_str(&"  ".repeat(entry.depth));
+        out.push_str("- [");
+        for c in entry.text.chars() {
+            if matches!(c, '\\' | '[' | ']' | '*' | '_' | '`') {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out.push_str("](#");
+        out.push_str(&entry.slug);
+        out.push_str(")\n");
+    }
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit::edited;
+
+    const DOC: &str =
+        "# Guide\n\n## Setup\n\n### Linux\n\n#### Deep\n\n## Setup\n\n## *Use* [it]\n";
+
+    #[test]
+    fn test_entries() {
+        let entries = toc_entries(DOC, 3);
+        let rows: Vec<_> = entries
+            .iter()
+            .map(|e| (e.depth, e.slug.as_str(), e.line))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (0, "guide", 0),
+                (1, "setup", 2),
+                (2, "linux", 4),
+                (1, "setup-1", 8),
+                (1, "use-it", 10),
+            ]
+        );
+        assert_eq!(toc_entries(DOC, 2)[1].depth, 1);
+        assert!(toc_entries("No headings.\n", 3).is_empty());
+    }
+
+    #[test]
+    fn test_insert() {
+        let text = "# Title\nIntro.\n## Part\n";
+        let out = edited(text, vec![insert_edit(text, 15, 2)]);
+        assert_eq!(
+            out,
+            "# Title\nIntro.\n\n<!-- toc depth=2 -->\n\n- [Title](#title)\n  - [Part](#part)\n\n\
+             <!-- tocstop -->\n\n## Part\n"
+        );
+        assert!(update_edits(&out).is_empty());
+
+        let out = edited(DOC, vec![insert_edit(DOC, DOC.len(), 1)]);
+        assert!(
+            out.ends_with(
+                "[it]\n\n<!-- toc depth=1 -->\n\n- [Guide](#guide)\n\n<!-- tocstop -->\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_other_line_endings() {
+        let text = "# Title\nIntro.\n\n## Part\n";
+        let expected = edited(text, vec![insert_edit(text, 16, 2)]);
+        for ending in [LineEnding::Crlf, LineEnding::Cr] {
+            let text = normalize(text, ending);
+            let pos = text.find("## Part").unwrap();
+            let out = edited(&text, vec![insert_edit(&text, pos, 2)]);
+            let out = normalize(&out, ending);
+            assert_eq!(out, normalize(&expected, ending));
+            assert!(update_edits(&out).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_update_in_place() {
+        let text = "# A\n\n<!-- toc -->\n- [Old](#old)\n<!-- tocstop -->\n\n## B \\*\n\n\
+                    ```\n<!-- toc -->\n<!-- tocstop -->\n```\n";
+        let out = edited(text, update_edits(text));
+        assert_eq!(
+            out,
+            "# A\n\n<!-- toc -->\n\n- [A](#a)\n  - [B \\*](#b-)\n\n<!-- tocstop -->\n\n## B \\*\n\n\
+             ```\n<!-- toc -->\n<!-- tocstop -->\n```\n"
+        );
+        assert!(update_edits(&out).is_empty());
+        assert_eq!(start_marker("<!-- toc depth=2 -->"), Some(2));
+        assert_eq!(start_marker("<!-- toc depth=9 -->"), None);
+        assert_eq!(start_marker("<!-- tocstop -->"), None);
+    }
+}
@@ -0,0 +1,312 @@
+//! Unicode hygiene: text outside a normalization form and invisible
+//! characters such as zero-width spaces and bidi controls
+
+use std::ops::Range;
+
+use unicode_normalization::char::canonical_combining_class;
+use unicode_normalization::{IsNormalized, UnicodeNormalization, is_nfc_quick, is_nfd_quick};
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::edit::TextEdit;
+use crate::selection::Selection;
+
+/// Characters that render as nothing or as an ordinary space, with a name
+/// and a visible replacement
+const INVISIBLE: &[(char, &str, &str)] = &[
+    ('\u{00A0}', "No-break space", " "),
+    ('\u{00AD}', "Soft hyphen", ""),
+    ('\u{061C}', "Arabic letter mark", ""),
+    ('\u{180E}', "Mongolian vowel separator", ""),
+    ('\u{2007}', "Figure space", " "),
+    ('\u{200B}', "Zero width space", ""),
+    ('\u{200C}', "Zero width non-joiner", ""),
+    ('\u{200D}', "Zero width joiner", ""),
+    ('\u{200E}', "Left-to-right mark", ""),
+    ('\u{200F}', "Right-to-left mark", ""),
+    ('\u{202A}', "Left-to-right embedding", ""),
+    ('\u{202B}', "Right-to-left embedding", ""),
+    ('\u{202C}', "Pop directional formatting", ""),
+    ('\u{202D}', "Left-to-right override", ""),
+    ('\u{202E}', "Right-to-left override", ""),
+    ('\u{202F}', "Narrow no-break space", " "),
+    ('\u{2060}', "Word joiner", ""),
+    ('\u{2061}', "Function application", ""),
+    ('\u{2062}', "Invisible times", ""),
+    ('\u{2063}', "Invisible separator", ""),
+    ('\u{2064}', "Invisible plus", ""),
+    ('\u{2066}', "Left-to-right isolate", ""),
+    ('\u{2067}', "Right-to-left isolate", ""),
+    ('\u{2068}', "First strong isolate", ""),
+    ('\u{2069}', "Pop directional isolate", ""),
+    ('\u{FEFF}', "Zero width no-break space", ""),
+];
+
+/// A Unicode normalization form
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NormalizationForm {
+    /// Canonical composition, as most platforms store text
+    Nfc,
+    /// Canonical decomposition, as macOS file names use
+    Nfd,
+}
+
+impl NormalizationForm {
+    /// Display name: `NFC` or `NFD`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Nfc => "NFC",
+            Self::Nfd => "NFD",
+        }
+    }
+
+    /// Look up a form by display name, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Nfc, Self::Nfd]
+            .into_iter()
+            .find(|form| form.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    fn quick_check(self, chars: impl Iterator<Item = char>) -> IsNormalized {
+        match self {
+            Self::Nfc => is_nfc_quick(chars),
+            Self::Nfd => is_nfd_quick(chars),
+        }
+    }
+
+    fn apply(self, text: &str) -> String {
+        match self {
+            Self::Nfc => text.nfc().collect(),
+            Self::Nfd => text.nfd().collect(),
+        }
+    }
+}
+
+/// An invisible character found in the text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvisibleChar {
+    /// Byte range of the character
+    pub range: Range<usize>,
+    pub ch: char,
+    /// Unicode name in sentence case, e.g. "Zero width space"
+    pub name: &'static str,
+}
+
+impl InvisibleChar {
+    /// Code point in `U+XXXX` notation
+    pub fn code_point(&self) -> String {
+        code_point(self.ch)
+    }
+}
+
+/// Invisible characters in text. Joiners between non-ASCII characters are
+/// left out, as emoji sequences and many scripts need them.
+pub fn invisible_chars(text: &str) -> Vec<InvisibleChar> {
+    let mut found = Vec::new();
+    let mut prev = None;
+    for (i, c) in text.char_indices() {
+        if let Some(&(_, name, _)) = INVISIBLE.iter().find(|(ch, _, _)| *ch == c) {
+            let joins = matches!(c, '\u{200C}' | '\u{200D}')
+                && prev.is_some_and(|p: char| !p.is_ascii() && !p.is_whitespace());
+            if !joins {
+                found.push(InvisibleChar {
+                    range: i..i + c.len_utf8(),
+                    ch: c,
+                    name,
+                });
+            }
+        }
+        prev = Some(c);
+    }
+    found
+}
+
+/// Edits bringing the text in each range into a normalization form
+pub(crate) fn normalize_edits(
+    text: &str,
+    ranges: &[Range<usize>],
+    form: NormalizationForm,
+) -> Vec<TextEdit> {
+    let mut edits = Vec::new();
+    for range in ranges {
+        let part = &text[range.clone()];
+        if form.quick_check(part.chars()) == IsNormalized::Yes {
+            continue;
+        }
+        for segment in segments(part, form) {
+            let normalized = form.apply(&part[segment.clone()]);
+            if normalized != part[segment.clone()] {
+                let start = range.start + segment.start;
+                edits.push(TextEdit::new(start..range.start + segment.end, normalized));
+            }
+        }
+    }
+    edits
+}
+
+/// Edits removing the invisible characters in each range, turning the
+/// space-like ones into ordinary spaces
+pub(crate) fn invisible_edits(text: &str, ranges: &[Range<usize>]) -> Vec<TextEdit> {
+    invisible_chars(text)
+        .into_iter()
+        .filter(|c| {
+            ranges
+                .iter()
+                .any(|r| r.start <= c.range.start && c.range.end <= r.end)
+        })
+        .map(|c| {
+            let replacement = INVISIBLE
+                .iter()
+                .find(|(ch, _, _)| *ch == c.ch)
+                .map_or("", |&(_, _, replacement)| replacement);
+            TextEdit::new(c.range, replacement)
+        })
+        .collect()
+}
+
+/// Warnings for invisible characters and notes for text not in NFC
+pub(crate) fn diagnostics(text: &str) -> Vec<Diagnostic> {
+    let invisible = invisible_chars(text).into_iter().map(|c| {
+        let message = format!("Invisible character: {} ({})", c.name, c.code_point());
+        Diagnostic::new(c.range, Severity::Warning, "invisible-character", message)
+    });
+    let all = 0..text.len();
+    let unnormalized = normalize_edits(text, &[all], NormalizationForm::Nfc)
+        .into_iter()
+        .map(|edit| {
+            let points: Vec<_> = text[edit.range.clone()].chars().map(code_point).collect();
+            let message = format!("Text is not in NFC form: {}", points.join(" "));
+            Diagnostic::new(edit.range, Severity::Info, "unicode-normalization", message)
+        });
+    let mut diagnostics: Vec<_> = invisible.chain(unnormalized).collect();
+    diagnostics.sort_by_key(|d| d.range.start);
+    diagnostics
+}
+
+/// Ranges covered by selections, or the whole text if nothing is selected
+pub(crate) fn selected_ranges(text: &str, selections: &[Selection]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<_> = selections
+        .iter()
+        .filter(|s| !s.is_empty())
+        .map(|s| s.start()..s.end())
+        .collect();
+    if ranges.is_empty() {
+        ranges.push(0..text.len());
+        return ranges;
+    }
+    ranges.sort_by_key(|r| r.start);
+    let mut merged: Vec<Range<usize>> = Vec::new();
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Split text before each character normalization can't combine with what
+/// precedes it, so segments normalize independently
+fn segments(text: &str, form: NormalizationForm) -> Vec<Range<usize>> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for (i, c) in text.char_indices().skip(1) {
+        if canonical_combining_class(c) == 0
+            && form.quick_check(std::iter::once(c)) == IsNormalized::Yes
+        {
+            segments.push(start..i);
+            start = i;
+        }
+    }
+    segments.push(start..text.len());
+    segments
+}
+
+fn code_point(c: char) -> String {
+    format!("U+{:04X}", u32::from(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit::edited;
+
+    #[test]
+    fn test_normalize() {
+        let nfd = "Cafe\u{301} and re\u{301}sume\u{301}";
+        let nfc = "Caf\u{E9} and r\u{E9}sum\u{E9}";
+        let all = selected_ranges(nfd, &[Selection::cursor(0)]);
+        assert_eq!(
+            edited(nfd, normalize_edits(nfd, &all, NormalizationForm::Nfc)),
+            nfc
+        );
+        let all = selected_ranges(nfc, &[Selection::cursor(0)]);
+        assert_eq!(
+            edited(nfc, normalize_edits(nfc, &all, NormalizationForm::Nfd)),
+            nfd
+        );
+        assert!(normalize_edits(nfc, &all, NormalizationForm::Nfc).is_empty());
+
+        // Only the selected word changes
+        let selections = [Selection::new(nfd.len(), 12), Selection::new(10, 14)];
+        let ranges = selected_ranges(nfd, &selections);
+        assert_eq!(ranges, vec![10..nfd.len()]);
+        let edits = normalize_edits(nfd, &ranges, NormalizationForm::Nfc);
+        assert_eq!(edited(nfd, edits), "Cafe\u{301} and r\u{E9}sum\u{E9}");
+        assert_eq!(
+            NormalizationForm::from_name("nfd"),
+            Some(NormalizationForm::Nfd)
+        );
+    }
+
+    #[test]
+    fn test_invisible_chars() {
+        let text = "[a\u{200B}b](#x\u{202E}y) 10\u{A0}kg 👩\u{200D}💻 a\u{200D}b";
+        let found: Vec<_> = invisible_chars(text)
+            .iter()
+            .map(|c| (c.code_point(), c.name))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("U+200B".to_string(), "Zero width space"),
+                ("U+202E".to_string(), "Right-to-left override"),
+                ("U+00A0".to_string(), "No-break space"),
+                ("U+200D".to_string(), "Zero width joiner"),
+            ]
+        );
+
+        let all = selected_ranges(text, &[Selection::cursor(0)]);
+        assert_eq!(
+            edited(text, invisible_edits(text, &all)),
+            "[ab](#xy) 10 kg 👩\u{200D}💻 ab"
+        );
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let text = "# Re\u{301}sume\u{301}\n\nA\u{200B}B\n";
+        let diagnostics = diagnostics(text);
+        let rows: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.code, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (
+                    "unicode-normalization",
+                    "Text is not in NFC form: U+0065 U+0301"
+                ),
+                (
+                    "unicode-normalization",
+                    "Text is not in NFC form: U+0065 U+0301"
+                ),
+                (
+                    "invisible-character",
+                    "Invisible character: Zero width space (U+200B)"
+                ),
+            ]
+        );
+        assert_eq!(diagnostics[0].range, 3..6);
+    }
+}
@@ -0,0 +1,522 @@
+//! Wiki-links (`[[Note#Heading|alias]]`) and the workspace backlinks index
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::encoding;
+use crate::error::Result;
+use crate::syntax::MarkdownTree;
+use crate::text::{line_of, line_starts, line_text};
+use crate::workspace;
+
+/// A `[[...]]` link to another note
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WikiLink {
+    /// Byte range including the brackets
+    pub range: Range<usize>,
+    /// Note name or path; empty for links within the same note (`[[#Heading]]`)
+    pub target: String,
+    /// Heading text after `#`
+    pub heading: Option<String>,
+    /// Display text after `|`
+    pub alias: Option<String>,
+}
+
+/// Find wiki-links outside code spans, code blocks and HTML
+pub fn parse_wiki_links(text: &str) -> Vec<WikiLink> {
+    let code = MarkdownTree::parse(text).code_ranges();
+    let in_code = |pos: usize| code.iter().any(|r| r.contains(&pos));
+
+    let mut links = Vec::new();
+    let mut search = 0;
+    while let Some(offset) = text[search..].find("[[") {
+        let start = search + offset;
+        let Some(len) = text[start + 2..].find("]]") else {
+            break;
+        };
+        let inner = &text[start + 2..start + 2 + len];
+        let end = start + 2 + len + 2;
+        if inner.contains('\n') || inner.contains("[[") || in_code(start) {
+            search = start + 2;
+            continue;
+        }
+
+        let (target, alias) = match inner.split_once('|') {
+            Some((t, a)) => (t, Some(a.trim().to_string())),
+            None => (inner, None),
+        };
+        let (target, heading) = match target.split_once('#') {
+            Some((t, h)) => (t, Some(h.trim().to_string())),
+            None => (target, None),
+        };
+        if !target.trim().is_empty() || heading.is_some() {
+            links.push(WikiLink {
+                range: start..end,
+                target: target.trim().to_string(),
+                heading,
+                alias,
+            });
+        }
+        search = end;
+    }
+    links
+}
+
+/// Prefix typed after an unclosed `[[` before a cursor, for note completion
+pub fn completion_prefix(text: &str, pos: usize) -> Option<&str> {
+    let line_start = text[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let before = &text[line_start..pos];
+    let open = before.rfind("[[")?;
+    let prefix = &before[open + 2..];
+    if prefix.contains("]]") || prefix.contains('|') || prefix.contains('#') {
+        return None;
+    }
+    Some(prefix)
+}
+
+/// A wiki-link pointing at a note, seen from the linking side
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backlink {
+    /// Note containing the link
+    pub source: PathBuf,
+    /// Line of the link in the source note (0-indexed)
+    pub line: usize,
+    /// Byte range of the link in the source note
+    pub range: Range<usize>,
+    /// Text of the line containing the link
+    pub context: String,
+}
+
+/// Wiki-links of one indexed note
+#[derive(Debug, Clone)]
+struct NoteEntry {
+    links: Vec<(WikiLink, usize, String)>,
+    /// Modification time when read from disk; `None` for in-memory content
+    modified: Option<SystemTime>,
+    /// Notes the links resolve to
+    targets: BTreeSet<PathBuf>,
+}
+
+impl NoteEntry {
+    fn new(text: &str, modified: Option<SystemTime>) -> Self {
+        Self {
+            links: scan(text),
+            modified,
+            targets: BTreeSet::new(),
+        }
+    }
+
+    /// Read a note from disk in whatever encoding it is in
+    fn read(path: &Path) -> Result<Self> {
+        let (text, _) = encoding::read_file(path)?;
+        let modified = std::fs::metadata(path)?.modified().ok();
+        Ok(Self::new(&text.to_string(), modified))
+    }
+}
+
+/// Index of the notes in a workspace folder and the wiki-links between them.
+/// Notes are re-scanned individually as they change.
+#[derive(Debug, Clone)]
+pub struct NoteIndex {
+    root: PathBuf,
+    notes: BTreeMap<PathBuf, NoteEntry>,
+    /// Notes by lowercase file stem, so resolving a name doesn't scan them all
+    by_name: HashMap<String, Vec<PathBuf>>,
+    /// Notes linking to each note
+    linked_from: HashMap<PathBuf, BTreeSet<PathBuf>>,
+}
+
+impl NoteIndex {
+    /// Scan every Markdown file under a workspace folder. Files that can't
+    /// be read are left out.
+    pub fn build(root: impl Into<PathBuf>) -> Result<Self> {
+        let mut index = Self {
+            root: root.into(),
+            notes: BTreeMap::new(),
+            by_name: HashMap::new(),
+            linked_from: HashMap::new(),
+        };
+        index.refresh()?;
+        Ok(index)
+    }
+
+    /// Workspace folder this index covers
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Paths of all indexed notes, sorted
+    pub fn notes(&self) -> impl Iterator<Item = &Path> {
+        self.notes.keys().map(|p| p.as_path())
+    }
+
+    /// Re-index a note from in-memory content (an open, possibly unsaved
+    /// document). Disk refreshes leave it alone until it is reloaded.
+    pub fn update_text(&mut self, path: &Path, text: &str) {
+        if self.insert(path, NoteEntry::new(text, None)) {
+            self.relink_all();
+        } else {
+            self.relink(path);
+        }
+    }
+
+    /// Re-read a note from disk, or drop it if it no longer exists
+    pub fn reload(&mut self, path: &Path) -> Result<()> {
+        if !path.exists() {
+            if self.remove(path) {
+                self.relink_all();
+            }
+            return Ok(());
+        }
+        if self.insert(path, NoteEntry::read(path)?) {
+            self.relink_all();
+        } else {
+            self.relink(path);
+        }
+        Ok(())
+    }
+
+    /// Pick up files added, changed or removed on disk since the last scan.
+    /// Returns the paths that were re-indexed or dropped; files that can't
+    /// be read are dropped.
+    pub fn refresh(&mut self) -> Result<Vec<PathBuf>> {
+        let files = workspace::markdown_files(&self.root)?;
+        let mut changed = Vec::new();
+        let mut names_changed = false;
+
+        let removed: Vec<PathBuf> = self
+            .notes
+            .iter()
+            .filter(|(path, entry)| entry.modified.is_some() && files.binary_search(path).is_err())
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in removed {
+            names_changed |= self.remove(&path);
+            changed.push(path);
+        }
+
+        for file in files {
+            let modified = std::fs::metadata(&file).and_then(|m| m.modified()).ok();
+            let stale = match self.notes.get(&file) {
+                Some(entry) => entry.modified.is_some() && entry.modified != modified,
+                None => true,
+            };
+            if !stale {
+                continue;
+            }
+            match NoteEntry::read(&file) {
+                Ok(entry) => names_changed |= self.insert(&file, entry),
+                Err(_) => names_changed |= self.remove(&file),
+            }
+            changed.push(file);
+        }
+
+        if names_changed {
+            self.relink_all();
+        } else {
+            for path in &changed {
+                self.relink(path);
+            }
+        }
+        changed.sort();
+        Ok(changed)
+    }
+
+    /// Set a note's links. Returns true if the note is new.
+    fn insert(&mut self, path: &Path, entry: NoteEntry) -> bool {
+        match self.notes.insert(path.to_path_buf(), entry) {
+            Some(old) => {
+                self.notes.get_mut(path).unwrap().targets = old.targets;
+                false
+            }
+            None => {
+                if let Some(name) = name_key(path) {
+                    self.by_name
+                        .entry(name)
+                        .or_default()
+                        .push(path.to_path_buf());
+                }
+                true
+            }
+        }
+    }
+
+    /// Drop a note. Returns true if it was indexed.
+    fn remove(&mut self, path: &Path) -> bool {
+        if self.notes.remove(path).is_none() {
+            return false;
+        }
+        if let Some(name) = name_key(path)
+            && let Some(paths) = self.by_name.get_mut(&name)
+        {
+            paths.retain(|p| p != path);
+            if paths.is_empty() {
+                self.by_name.remove(&name);
+            }
+        }
+        true
+    }
+
+    /// Update the reverse links of one note after its links changed
+    fn relink(&mut self, source: &Path) {
+        let Some(entry) = self.notes.get(source) else {
+            return;
+        };
+        let targets: BTreeSet<PathBuf> = entry
+            .links
+            .iter()
+            .filter(|(link, _, _)| !link.target.is_empty())
+            .filter_map(|(link, _, _)| self.resolve(&link.target, Some(source)))
+            .collect();
+        let entry = self.notes.get_mut(source).unwrap();
+        for old in std::mem::take(&mut entry.targets) {
+            if let Some(sources) = self.linked_from.get_mut(&old) {
+                sources.remove(source);
+            }
+        }
+        for target in &targets {
+            self.linked_from
+                .entry(target.clone())
+                .or_default()
+                .insert(source.to_path_buf());
+        }
+        entry.targets = targets;
+    }
+
+    /// Rebuild all reverse links, after notes were added or removed and
+    /// names may resolve differently
+    fn relink_all(&mut self) {
+        self.linked_from.clear();
+        let sources: Vec<PathBuf> = self.notes.keys().cloned().collect();
+        for source in sources {
+            self.notes.get_mut(&source).unwrap().targets.clear();
+            self.relink(&source);
+        }
+    }
+
+    /// Resolve a wiki-link target to a note. Names match file stems
+    /// case-insensitively; targets with `/` match the end of the path.
+    /// Ambiguous names prefer notes in the linking note's folder, then the
+    /// shortest path.
+    pub fn resolve(&self, target: &str, from: Option<&Path>) -> Option<PathBuf> {
+        let target = target.trim();
+        let target = strip_markdown_extension(target).to_lowercase();
+        if target.is_empty() {
+            return None;
+        }
+        let from_dir = from.and_then(|p| p.parent());
+        let name = target.rsplit('/').next().unwrap_or(&target);
+
+        self.by_name
+            .get(name)?
+            .iter()
+            .filter(|path| {
+                let relative = path.strip_prefix(&self.root).unwrap_or(path);
+                let relative = relative.with_extension("");
+                let relative = relative.to_string_lossy().replace('\\', "/").to_lowercase();
+                relative == target || relative.ends_with(&format!("/{target}"))
+            })
+            .min_by_key(|path| {
+                let same_dir = from_dir.is_some_and(|d| path.parent() == Some(d));
+                (!same_dir, path.components().count(), (*path).clone())
+            })
+            .cloned()
+    }
+
+    /// Note names starting with (or else containing) a prefix, for completion
+    pub fn complete(&self, prefix: &str) -> Vec<String> {
+        let prefix = prefix.trim().to_lowercase();
+        let mut starts = Vec::new();
+        let mut contains = Vec::new();
+        for path in self.notes.keys() {
+            let Some(name) = note_name(path) else {
+                continue;
+            };
+            let lower = name.to_lowercase();
+            if lower.starts_with(&prefix) {
+                starts.push(name);
+            } else if lower.contains(&prefix) {
+                contains.push(name);
+            }
+        }
+        starts.sort_by_key(|n| n.to_lowercase());
+        contains.sort_by_key(|n| n.to_lowercase());
+        starts.dedup();
+        contains.dedup();
+        starts.extend(contains);
+        starts
+    }
+
+    /// Links from other notes that resolve to `path`
+    pub fn backlinks(&self, path: &Path) -> Vec<Backlink> {
+        let mut out = Vec::new();
+        let Some(sources) = self.linked_from.get(path) else {
+            return out;
+        };
+        for source in sources {
+            let Some(entry) = self.notes.get(source) else {
+                continue;
+            };
+            for (link, line, context) in &entry.links {
+                if link.target.is_empty() {
+                    continue;
+                }
+                if self.resolve(&link.target, Some(source)).as_deref() == Some(path) {
+                    out.push(Backlink {
+                        source: source.clone(),
+                        line: *line,
+                        range: link.range.clone(),
+                        context: context.clone(),
+                    });
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Display name of a note: its file stem
+pub fn note_name(path: &Path) -> Option<String> {
+    path.file_stem().map(|s| s.to_string_lossy().into_owned())
+}
+
+/// Key of a note in the name index: its lowercase file stem
+fn name_key(path: &Path) -> Option<String> {
+    note_name(path).map(|name| name.to_lowercase())
+}
+
+fn strip_markdown_extension(target: &str) -> &str {
+    let path = Path::new(target);
+    if workspace::is_markdown_file(path) {
+        &target[..target.len() - path.extension().map_or(0, |e| e.len() + 1)]
+    } else {
+        target
+    }
+}
+
+/// Wiki-links with their line and line text
+fn scan(text: &str) -> Vec<(WikiLink, usize, String)> {
+    let starts = line_starts(text);
+    parse_wiki_links(text)
+        .into_iter()
+        .map(|link| {
+            let line = line_of(&starts, link.range.start);
+            let context = line_text(text, &starts, line).trim().to_string();
+            (link, line, context)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wiki_links() {
+        let text =
+            "See [[Note Name]], [[Other#Goals|the goals]] and [[#Local]].\n\n`[[not a link]]`\n";
+        let links = parse_wiki_links(text);
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[0].target, "Note Name");
+        assert_eq!(&text[links[0].range.clone()], "[[Note Name]]");
+        assert_eq!(links[1].target, "Other");
+        assert_eq!(links[1].heading.as_deref(), Some("Goals"));
+        assert_eq!(links[1].alias.as_deref(), Some("the goals"));
+        assert_eq!(links[2].target, "");
+        assert_eq!(links[2].heading.as_deref(), Some("Local"));
+    }
+
+    #[test]
+    fn test_completion_prefix() {
+        let text = "Link to [[Pro";
+        assert_eq!(completion_prefix(text, text.len()), Some("Pro"));
+        assert_eq!(completion_prefix("[[Done]] x", 10), None);
+    }
+
+    fn temp_workspace(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("rmde-wiki-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("projects")).unwrap();
+        std::fs::write(
+            root.join("Index.md"),
+            "Start at [[Roadmap]] or [[projects/roadmap|the plan]].\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("projects/Roadmap.md"),
+            "# Roadmap\n\nBack to [[index]].\n",
+        )
+        .unwrap();
+        root
+    }
+
+    #[test]
+    fn test_resolve_and_backlinks() {
+        let root = temp_workspace("resolve");
+        let index = NoteIndex::build(&root).unwrap();
+        let roadmap = root.join("projects/Roadmap.md");
+
+        assert_eq!(index.resolve("roadmap", None), Some(roadmap.clone()));
+        assert_eq!(index.resolve("Roadmap.md", None), Some(roadmap.clone()));
+        assert_eq!(index.resolve("missing", None), None);
+
+        let backlinks = index.backlinks(&roadmap);
+        assert_eq!(backlinks.len(), 2);
+        assert_eq!(backlinks[0].source, root.join("Index.md"));
+        assert_eq!(backlinks[0].line, 0);
+
+        assert_eq!(index.complete("road"), vec!["Roadmap"]);
+        assert_eq!(index.complete("dex"), vec!["Index"]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_incremental_updates() {
+        let root = temp_workspace("incremental");
+        let mut index = NoteIndex::build(&root).unwrap();
+        let index_note = root.join("Index.md");
+
+        // Edited in memory
+        index.update_text(&root.join("projects/Roadmap.md"), "No links now.\n");
+        assert!(index.backlinks(&index_note).is_empty());
+
+        // Added on disk
+        std::fs::write(root.join("Journal.md"), "Today: [[Index]]\n").unwrap();
+        let changed = index.refresh().unwrap();
+        assert_eq!(changed, vec![root.join("Journal.md")]);
+        assert_eq!(index.backlinks(&index_note).len(), 1);
+
+        // Removed on disk
+        std::fs::remove_file(root.join("Journal.md")).unwrap();
+        index.refresh().unwrap();
+        assert!(index.backlinks(&index_note).is_empty());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_backlinks_follow_new_notes() {
+        let root = temp_workspace("relink");
+        // Not UTF-8, but still indexed
+        std::fs::write(root.join("projects/Notes.md"), b"Caf\xe9 [[Roadmap]]\n").unwrap();
+        let mut index = NoteIndex::build(&root).unwrap();
+        let roadmap = root.join("projects/Roadmap.md");
+        assert_eq!(index.backlinks(&roadmap).len(), 3);
+
+        // A closer note of the same name takes over the link from Index.md
+        let local = root.join("Roadmap.md");
+        index.update_text(&local, "# Local roadmap\n");
+        let sources: Vec<_> = index
+            .backlinks(&local)
+            .into_iter()
+            .map(|b| b.source)
+            .collect();
+        assert_eq!(sources, vec![root.join("Index.md")]);
+        assert_eq!(index.backlinks(&roadmap).len(), 2);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
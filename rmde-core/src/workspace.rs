@@ -0,0 +1,69 @@
+//! Workspace folder scanning
+
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+
+/// File extensions treated as Markdown notes
+const MARKDOWN_EXTENSIONS: &[&str] = &["md", "markdown", "mdown", "mkd"];
+
+/// Check whether a path has a Markdown file extension
+pub fn is_markdown_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| MARKDOWN_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Recursively list Markdown files under a folder, sorted by path.
+/// Hidden files and folders (starting with `.`) are skipped.
+pub fn markdown_files(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if hidden {
+                continue;
+            }
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                dirs.push(path);
+            } else if is_markdown_file(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_markdown_file() {
+        assert!(is_markdown_file(Path::new("notes/a.md")));
+        assert!(is_markdown_file(Path::new("README.MD")));
+        assert!(!is_markdown_file(Path::new("image.png")));
+        assert!(!is_markdown_file(Path::new("md")));
+    }
+
+    #[test]
+    fn test_markdown_files() {
+        let root = std::env::temp_dir().join(format!("rmde-ws-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join("a.md"), "a").unwrap();
+        std::fs::write(root.join("sub/b.markdown"), "b").unwrap();
+        std::fs::write(root.join("sub/c.txt"), "c").unwrap();
+        std::fs::write(root.join(".git/d.md"), "d").unwrap();
+
+        let files = markdown_files(&root).unwrap();
+        assert_eq!(files, vec![root.join("a.md"), root.join("sub/b.markdown")]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,597 @@
+//! Word-count goals, writing session statistics and the daily writing history
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use ropey::Rope;
+
+use crate::date::Date;
+use crate::edit::TextEdit;
+use crate::error::{Error, Result};
+use crate::front_matter::{FrontMatterValue, parse_front_matter};
+use crate::stats;
+
+/// Front matter key holding a document's word goal
+pub(crate) const GOAL_KEY: &str = "word_goal";
+
+/// Sidecar file holding the word goals of the documents in its folder
+const GOALS_FILE: &str = ".rmde-goals";
+
+/// Gaps between edits longer than this don't count as writing time
+const IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Changes within this span of the first one share an activity entry
+const ACTIVITY_SPAN: Duration = Duration::from_secs(60);
+
+/// Where a document's word goal is kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GoalStorage {
+    /// A `word_goal` key in the document's front matter
+    FrontMatter,
+    /// A `.rmde-goals` file next to the document
+    Sidecar,
+}
+
+/// Progress towards a word goal
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GoalProgress {
+    pub goal: usize,
+    pub words: usize,
+}
+
+impl GoalProgress {
+    /// Fraction of the goal written, capped at 1.0
+    pub fn fraction(&self) -> f64 {
+        if self.goal == 0 {
+            1.0
+        } else {
+            (self.words as f64 / self.goal as f64).min(1.0)
+        }
+    }
+
+    /// Whether the goal has been reached
+    pub fn is_reached(&self) -> bool {
+        self.words >= self.goal
+    }
+}
+
+/// Word goal set in a document's front matter
+pub(crate) fn front_matter_goal(text: &str) -> Option<usize> {
+    let front_matter = parse_front_matter(text).ok()??;
+    match &front_matter.get(GOAL_KEY)?.value {
+        FrontMatterValue::Integer(goal) if *goal > 0 => Some(*goal as usize),
+        FrontMatterValue::String(goal) => goal.trim().parse().ok().filter(|g| *g > 0),
+        _ => None,
+    }
+}
+
+/// Word goal of a document from the sidecar file in its folder
+pub(crate) fn sidecar_goal(path: &Path) -> Result<Option<usize>> {
+    let Some(name) = file_name(path) else {
+        return Ok(None);
+    };
+    Ok(read_goals(&goals_file(path))?.get(&name).copied())
+}
+
+/// Set or clear a document's goal in the sidecar file in its folder. The
+/// file is removed once it holds no goals.
+pub(crate) fn set_sidecar_goal(path: &Path, goal: Option<usize>) -> Result<()> {
+    let name = file_name(path).ok_or_else(|| {
+        Error::Io(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Document has no file name",
+        ))
+    })?;
+    let file = goals_file(path);
+    let mut goals = read_goals(&file)?;
+    match goal {
+        Some(goal) => goals.insert(name, goal),
+        None => goals.remove(&name),
+    };
+
+    if goals.is_empty() {
+        if file.exists() {
+            std::fs::remove_file(&file)?;
+        }
+        return Ok(());
+    }
+    let mut out = String::new();
+    for (name, goal) in &goals {
+        out.push_str(&format!("{name}\t{goal}\n"));
+    }
+    std::fs::write(&file, out)?;
+    Ok(())
+}
+
+fn file_name(path: &Path) -> Option<String> {
+    path.file_name().map(|n| n.to_string_lossy().into_owned())
+}
+
+fn goals_file(path: &Path) -> PathBuf {
+    path.with_file_name(GOALS_FILE)
+}
+
+/// Goals by file name; lines that aren't `name<TAB>words` are skipped
+fn read_goals(file: &Path) -> Result<BTreeMap<String, usize>> {
+    if !file.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let text = std::fs::read_to_string(file)?;
+    Ok(text
+        .lines()
+        .filter_map(|line| {
+            let (name, goal) = line.rsplit_once('\t')?;
+            Some((name.to_string(), goal.trim().parse().ok()?))
+        })
+        .collect())
+}
+
+/// Words added and removed by the changes to a document within a minute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct WordActivity {
+    /// Time of the first change
+    pub started: SystemTime,
+    /// Time of the last change
+    pub at: SystemTime,
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// Add word changes to an activity log, merging them into the last entry
+/// if it started less than a minute before
+pub(crate) fn log_activity(
+    log: &mut Vec<WordActivity>,
+    at: SystemTime,
+    (added, removed): (usize, usize),
+) {
+    match log.last_mut() {
+        Some(last)
+            if at
+                .duration_since(last.started)
+                .is_ok_and(|d| d < ACTIVITY_SPAN) =>
+        {
+            last.at = at;
+            last.added += added;
+            last.removed += removed;
+        }
+        _ => log.push(WordActivity {
+            started: at,
+            at,
+            added,
+            removed,
+        }),
+    }
+}
+
+/// Words added and removed by edits, found by comparing only the words
+/// around each edit. A word that grows or shrinks at either end is the same
+/// word, so typing or erasing one letter at a time counts it once; a word
+/// replaced by another counts as one added and one removed.
+pub(crate) fn word_changes(before: &Rope, after: &Rope, edits: &[TextEdit]) -> (usize, usize) {
+    // Ranges around the edits widened to whole words, merged, with the
+    // length change of the edits in each
+    let mut windows: Vec<(Range<usize>, isize)> = Vec::new();
+    for edit in edits {
+        let window = whole_words(before, edit.range.clone());
+        match windows.last_mut() {
+            Some((last, delta)) if window.start <= last.end => {
+                last.end = last.end.max(window.end);
+                *delta += edit.delta();
+            }
+            _ => windows.push((window, edit.delta())),
+        }
+    }
+
+    let (mut added, mut removed) = (0, 0);
+    let mut shift = 0;
+    for (window, delta) in windows {
+        let start = window.start.saturating_add_signed(shift);
+        let end = window.end.saturating_add_signed(shift + delta);
+        let old = stats::words(before.byte_slice(window).chunks());
+        let new = stats::words(after.byte_slice(start..end).chunks());
+        let (a, r) = compare_words(old, new);
+        added += a;
+        removed += r;
+        shift += delta;
+    }
+    (added, removed)
+}
+
+/// Extend a byte range to the whitespace on either side
+fn whole_words(text: &Rope, range: Range<usize>) -> Range<usize> {
+    let mut start = range.start;
+    while start > 0 && !text.byte(start - 1).is_ascii_whitespace() {
+        start -= 1;
+    }
+    let mut end = range.end;
+    while end < text.len_bytes() && !text.byte(end).is_ascii_whitespace() {
+        end += 1;
+    }
+    start..end
+}
+
+/// Words only in `new` and words only in `old`, pairing up words where one
+/// starts or ends with the other
+fn compare_words(mut old: Vec<String>, mut new: Vec<String>) -> (usize, usize) {
+    old.sort_unstable();
+    new.sort_unstable();
+    let mut unmatched_old = Vec::new();
+    let mut unmatched_new = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        match old[i].cmp(&new[j]) {
+            std::cmp::Ordering::Equal => {
+                i += 1;
+                j += 1;
+            }
+            std::cmp::Ordering::Less => {
+                unmatched_old.push(&old[i]);
+                i += 1;
+            }
+            std::cmp::Ordering::Greater => {
+                unmatched_new.push(&new[j]);
+                j += 1;
+            }
+        }
+    }
+    unmatched_old.extend(&old[i..]);
+    unmatched_new.extend(&new[j..]);
+
+    let mut added = 0;
+    for word in unmatched_new {
+        let related = |w: &&String| {
+            word.starts_with(w.as_str())
+                || word.ends_with(w.as_str())
+                || w.starts_with(word.as_str())
+                || w.ends_with(word.as_str())
+        };
+        match unmatched_old.iter().position(related) {
+            Some(pos) => {
+                unmatched_old.swap_remove(pos);
+            }
+            None => added += 1,
+        }
+    }
+    (added, unmatched_old.len())
+}
+
+/// Words written and time spent writing, for a session or a day
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WritingStats {
+    pub words_added: usize,
+    pub words_removed: usize,
+    /// Time between edits, leaving out pauses of more than two minutes
+    pub active_time: Duration,
+}
+
+impl WritingStats {
+    /// Words added per minute of active writing
+    pub fn words_per_minute(&self) -> f64 {
+        let minutes = self.active_time.as_secs_f64() / 60.0;
+        if minutes == 0.0 {
+            0.0
+        } else {
+            self.words_added as f64 / minutes
+        }
+    }
+
+    fn add(&mut self, other: &WritingStats) {
+        self.words_added += other.words_added;
+        self.words_removed += other.words_removed;
+        self.active_time += other.active_time;
+    }
+}
+
+/// Statistics of the edits made since the editor started
+#[derive(Debug, Clone)]
+pub struct WritingSession {
+    started: SystemTime,
+    stats: WritingStats,
+    last_activity: Option<SystemTime>,
+}
+
+impl WritingSession {
+    /// Start a session at a point in time
+    pub fn new(started: SystemTime) -> Self {
+        Self {
+            started,
+            stats: WritingStats::default(),
+            last_activity: None,
+        }
+    }
+
+    /// When the session started
+    pub fn started(&self) -> SystemTime {
+        self.started
+    }
+
+    /// Totals so far
+    pub fn stats(&self) -> WritingStats {
+        self.stats
+    }
+
+    /// Add a change to the session. Returns the date it happened on and
+    /// what it added to the totals, for the daily history.
+    pub(crate) fn record(&mut self, activity: &WordActivity) -> (Date, WritingStats) {
+        let gap = self
+            .last_activity
+            .and_then(|last| activity.started.duration_since(last).ok())
+            .filter(|gap| *gap <= IDLE_TIMEOUT)
+            .unwrap_or_default();
+        let span = activity
+            .at
+            .duration_since(activity.started)
+            .unwrap_or_default();
+        let active_time = gap + span;
+        self.last_activity = Some(
+            self.last_activity
+                .map_or(activity.at, |l| l.max(activity.at)),
+        );
+
+        let delta = WritingStats {
+            words_added: activity.added,
+            words_removed: activity.removed,
+            active_time,
+        };
+        self.stats.add(&delta);
+        (Date::local(activity.at), delta)
+    }
+}
+
+impl Default for WritingSession {
+    fn default() -> Self {
+        Self::new(SystemTime::now())
+    }
+}
+
+/// Writing statistics per day, kept in a local file with one line per day:
+/// `YYYY-MM-DD<TAB>added<TAB>removed<TAB>active seconds`
+#[derive(Debug, Clone, Default)]
+pub struct WritingHistory {
+    path: Option<PathBuf>,
+    days: BTreeMap<Date, WritingStats>,
+}
+
+impl WritingHistory {
+    /// Load the history from a file; a missing file is an empty history
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let mut days = BTreeMap::new();
+        if path.exists() {
+            let text = std::fs::read_to_string(&path)?;
+            for (i, line) in text.lines().enumerate() {
+                if line.trim().is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let (date, stats) = parse_day(line).ok_or_else(|| {
+                    Error::ParseError(format!("writing history line {}: {line}", i + 1))
+                })?;
+                days.insert(date, stats);
+            }
+        }
+        Ok(Self {
+            path: Some(path),
+            days,
+        })
+    }
+
+    /// File the history is saved to, if any
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Write the history to its file. Does nothing for an in-memory history.
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut out = String::from("# date\tadded\tremoved\tactive seconds\n");
+        for (date, stats) in &self.days {
+            out.push_str(&format!(
+                "{date}\t{}\t{}\t{}\n",
+                stats.words_added,
+                stats.words_removed,
+                stats.active_time.as_secs()
+            ));
+        }
+        std::fs::write(path, out)?;
+        Ok(())
+    }
+
+    /// Add statistics to a day
+    pub fn add(&mut self, date: Date, stats: &WritingStats) {
+        self.days.entry(date).or_default().add(stats);
+    }
+
+    /// Add every day of another history to this one
+    pub(crate) fn merge(&mut self, other: &WritingHistory) {
+        for (date, stats) in &other.days {
+            self.add(*date, stats);
+        }
+    }
+
+    /// Statistics of one day
+    pub fn day(&self, date: Date) -> WritingStats {
+        self.days.get(&date).copied().unwrap_or_default()
+    }
+
+    /// Days with recorded writing between two dates, inclusive
+    pub fn days(&self, from: Date, to: Date) -> Vec<(Date, WritingStats)> {
+        if from > to {
+            return Vec::new();
+        }
+        self.days
+            .range(from..=to)
+            .map(|(date, stats)| (*date, *stats))
+            .collect()
+    }
+
+    /// Totals between two dates, inclusive
+    pub fn total(&self, from: Date, to: Date) -> WritingStats {
+        let mut total = WritingStats::default();
+        for (_, stats) in self.days(from, to) {
+            total.add(&stats);
+        }
+        total
+    }
+}
+
+fn parse_day(line: &str) -> Option<(Date, WritingStats)> {
+    let mut fields = line.split('\t');
+    let date = Date::parse(fields.next()?.trim())?;
+    let mut number = || fields.next()?.trim().parse::<u64>().ok();
+    let stats = WritingStats {
+        words_added: number()? as usize,
+        words_removed: number()? as usize,
+        active_time: Duration::from_secs(number()?),
+    };
+    Some((date, stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_700_000_000 + secs)
+    }
+
+    #[test]
+    fn test_front_matter_goal() {
+        assert_eq!(front_matter_goal("---\nword_goal: 1500\n---\n"), Some(1500));
+        assert_eq!(
+            front_matter_goal("+++\nword_goal = \"800\"\n+++\n"),
+            Some(800)
+        );
+        assert_eq!(front_matter_goal("---\nword_goal: 0\n---\n"), None);
+        assert_eq!(front_matter_goal("No front matter\n"), None);
+    }
+
+    #[test]
+    fn test_sidecar_goal() {
+        let dir = std::env::temp_dir().join(format!("rmde-goals-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let a = dir.join("a.md");
+        let b = dir.join("b.md");
+
+        set_sidecar_goal(&a, Some(500)).unwrap();
+        set_sidecar_goal(&b, Some(2000)).unwrap();
+        assert_eq!(sidecar_goal(&a).unwrap(), Some(500));
+        assert_eq!(
+            std::fs::read_to_string(dir.join(GOALS_FILE)).unwrap(),
+            "a.md\t500\nb.md\t2000\n"
+        );
+
+        set_sidecar_goal(&a, None).unwrap();
+        set_sidecar_goal(&b, None).unwrap();
+        assert_eq!(sidecar_goal(&b).unwrap(), None);
+        assert!(!dir.join(GOALS_FILE).exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_session_active_time() {
+        let mut session = WritingSession::new(at(0));
+        let edit = |started, secs, added, removed| WordActivity {
+            started: at(started),
+            at: at(secs),
+            added,
+            removed,
+        };
+        session.record(&edit(10, 10, 1, 0));
+        session.record(&edit(40, 50, 5, 0));
+        // A long pause isn't writing time
+        session.record(&edit(1000, 1000, 4, 2));
+        let (_, delta) = session.record(&edit(1060, 1080, 10, 0));
+        assert_eq!(delta.active_time, Duration::from_secs(80));
+
+        let stats = session.stats();
+        assert_eq!(stats.words_added, 20);
+        assert_eq!(stats.words_removed, 2);
+        assert_eq!(stats.active_time, Duration::from_secs(120));
+        assert!((stats.words_per_minute() - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_word_changes() {
+        // Apply edits one at a time, as typing does
+        let changes = |text: &str, steps: &[(Range<usize>, &str)]| {
+            let mut rope = Rope::from_str(text);
+            let mut total = (0, 0);
+            for (range, insert) in steps {
+                let before = rope.clone();
+                let start = rope.byte_to_char(range.start);
+                rope.remove(start..rope.byte_to_char(range.end));
+                rope.insert(start, insert);
+                let edit = TextEdit::new(range.clone(), *insert);
+                let (added, removed) = word_changes(&before, &rope, &[edit]);
+                total = (total.0 + added, total.1 + removed);
+            }
+            total
+        };
+        let typed = [(4..4, "c"), (5..5, "a"), (6..6, "t"), (7..7, " ")];
+        assert_eq!(changes("the  sat", &typed), (1, 0));
+        // Replacing a word with one of the same length still counts
+        assert_eq!(changes("the cat sat", &[(4..7, "dog")]), (1, 1));
+        let erased = [(6..7, ""), (5..6, ""), (4..5, "")];
+        assert_eq!(changes("the cat sat", &erased), (0, 1));
+
+        let before = Rope::from_str("one two three four");
+        let after = Rope::from_str("one 2 three four five");
+        let edits = [TextEdit::new(4..7, "2"), TextEdit::new(18..18, " five")];
+        assert_eq!(word_changes(&before, &after, &edits), (2, 1));
+    }
+
+    #[test]
+    fn test_log_activity() {
+        let mut log = Vec::new();
+        log_activity(&mut log, at(0), (1, 0));
+        log_activity(&mut log, at(30), (2, 1));
+        log_activity(&mut log, at(61), (1, 0));
+        assert_eq!(log.len(), 2);
+        assert_eq!((log[0].started, log[0].at), (at(0), at(30)));
+        assert_eq!((log[0].added, log[0].removed), (3, 1));
+    }
+
+    #[test]
+    fn test_history_round_trip() {
+        let path = std::env::temp_dir().join(format!("rmde-history-{}.tsv", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let day = |s| Date::parse(s).unwrap();
+        let stats = WritingStats {
+            words_added: 300,
+            words_removed: 20,
+            active_time: Duration::from_secs(900),
+        };
+
+        let mut history = WritingHistory::load(&path).unwrap();
+        history.add(day("2024-03-01"), &stats);
+        history.add(day("2024-03-01"), &stats);
+        history.add(day("2024-03-04"), &stats);
+        history.save().unwrap();
+
+        let history = WritingHistory::load(&path).unwrap();
+        assert_eq!(history.day(day("2024-03-01")).words_added, 600);
+        assert_eq!(history.days(day("2024-03-02"), day("2024-03-31")).len(), 1);
+        assert_eq!(
+            history
+                .total(day("2024-03-01"), day("2024-03-04"))
+                .active_time,
+            Duration::from_secs(2700)
+        );
+
+        std::fs::write(&path, "2024-03-01\tmany\n").unwrap();
+        assert!(WritingHistory::load(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}